@@ -58,7 +58,7 @@ const reservationSchema = new mongoose.Schema({
     },
     status: {
         type: String,
-        enum: ['pending', 'confirmed', 'cancelled', 'completed'],
+        enum: ['pending', 'confirmed', 'cancelled', 'completed', 'no_show'],
         default: 'pending'
     },
 
@@ -138,6 +138,28 @@ const reservationSchema = new mongoose.Schema({
             default: null
         }
     },
+
+    // Penanggung jawab pembatalan / no-show (diisi juga oleh layanan Rust)
+    cancelled_by: {
+        employee_id: {
+            type: mongoose.Schema.Types.ObjectId,
+            ref: 'User',
+            default: null
+        },
+        employee_name: {
+            type: String,
+            default: null
+        },
+        cancelled_at: {
+            type: Date,
+            default: null
+        }
+    },
+
+    cancellation_reason: {
+        type: String,
+        default: null
+    },
     notes: {
         type: String,
         default: ''
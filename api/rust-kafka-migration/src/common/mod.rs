@@ -1,3 +1,7 @@
+pub mod money;
+
+pub use money::{Money, RoundingRule};
+
 use serde::Deserialize;
 
 #[derive(Debug, Deserialize)]
@@ -0,0 +1,358 @@
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// An amount of money in whole rupiah.
+///
+/// Stored as an integer so sums never drift, but serialized as a double so
+/// documents stay compatible with the Node.js service and existing data.
+/// Anything that multiplies by a rate (tax, discounts, shares) rounds to the
+/// nearest rupiah, half away from zero.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Money(i64);
+
+impl Money {
+    pub const ZERO: Money = Money(0);
+
+    pub const fn from_rupiah(rupiah: i64) -> Self {
+        Money(rupiah)
+    }
+
+    /// Convert a floating point amount, rounding to the nearest rupiah
+    pub fn from_f64(amount: f64) -> Self {
+        if amount.is_finite() {
+            Money(amount.round() as i64)
+        } else {
+            Money::ZERO
+        }
+    }
+
+    pub const fn rupiah(self) -> i64 {
+        self.0
+    }
+
+    pub fn as_f64(self) -> f64 {
+        self.0 as f64
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn is_positive(self) -> bool {
+        self.0 > 0
+    }
+
+    pub fn is_negative(self) -> bool {
+        self.0 < 0
+    }
+
+    pub fn abs(self) -> Self {
+        Money(self.0.abs())
+    }
+
+    /// `percent` percent of this amount, e.g. `percent(11.0)` for 11% tax
+    pub fn percent(self, percent: f64) -> Self {
+        self * (percent / 100.0)
+    }
+
+    /// This amount divided by `count`, rounded to the nearest rupiah
+    pub fn per(self, count: impl Into<i64>) -> Self {
+        let count = count.into();
+        if count == 0 {
+            return Money::ZERO;
+        }
+        Money::from_f64(self.0 as f64 / count as f64)
+    }
+
+    /// Read an amount from a raw document such as an aggregation result.
+    ///
+    /// `$sum` yields an integer or a double depending on its inputs, so both
+    /// are accepted; a missing field reads as zero.
+    pub fn from_field(doc: &bson::Document, key: &str) -> Self {
+        match doc.get(key) {
+            Some(bson::Bson::Double(v)) => Money::from_f64(*v),
+            Some(bson::Bson::Int32(v)) => Money(*v as i64),
+            Some(bson::Bson::Int64(v)) => Money(*v),
+            Some(bson::Bson::Decimal128(v)) => v
+                .to_string()
+                .parse::<f64>()
+                .map(Money::from_f64)
+                .unwrap_or_default(),
+            _ => Money::ZERO,
+        }
+    }
+
+    /// Fraction of `total` this amount represents, 0 when `total` is zero
+    pub fn ratio_of(self, total: Money) -> f64 {
+        if total.is_zero() {
+            0.0
+        } else {
+            self.0 as f64 / total.0 as f64
+        }
+    }
+
+    /// Split this amount across `weights` in proportion, so the parts always
+    /// add back up to the whole (largest remainder method).
+    pub fn allocate(self, weights: &[Money]) -> Vec<Money> {
+        let total: i64 = weights.iter().map(|w| w.0).sum();
+        if total == 0 {
+            return vec![Money::ZERO; weights.len()];
+        }
+
+        let mut parts: Vec<i64> = weights
+            .iter()
+            .map(|w| (self.0 as i128 * w.0 as i128 / total as i128) as i64)
+            .collect();
+
+        let mut remainders: Vec<(usize, i128)> = weights
+            .iter()
+            .enumerate()
+            .map(|(i, w)| (i, (self.0 as i128 * w.0 as i128) % total as i128))
+            .collect();
+        remainders.sort_by_key(|r| std::cmp::Reverse(r.1.abs()));
+
+        let step = self.0.signum();
+        let mut left = self.0 - parts.iter().sum::<i64>();
+        for (i, _) in remainders {
+            if left == 0 {
+                break;
+            }
+            parts[i] += step;
+            left -= step;
+        }
+
+        parts.into_iter().map(Money).collect()
+    }
+
+    /// Apply a rounding rule, e.g. to the nearest Rp100 for cash bills
+    pub fn round(self, rule: &RoundingRule) -> Self {
+        let increment = rule.increment.max(1);
+        if increment == 1 {
+            return self;
+        }
+
+        let floor = self.0.div_euclid(increment) * increment;
+        let remainder = self.0 - floor;
+        let rounded = match rule.mode {
+            RoundingMode::Down => floor,
+            RoundingMode::Up if remainder == 0 => floor,
+            RoundingMode::Up => floor + increment,
+            RoundingMode::Nearest if remainder * 2 >= increment => floor + increment,
+            RoundingMode::Nearest => floor,
+        };
+        Money(rounded)
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Add for Money {
+    type Output = Money;
+    fn add(self, rhs: Money) -> Money {
+        Money(self.0 + rhs.0)
+    }
+}
+
+impl Sub for Money {
+    type Output = Money;
+    fn sub(self, rhs: Money) -> Money {
+        Money(self.0 - rhs.0)
+    }
+}
+
+impl AddAssign for Money {
+    fn add_assign(&mut self, rhs: Money) {
+        self.0 += rhs.0;
+    }
+}
+
+impl SubAssign for Money {
+    fn sub_assign(&mut self, rhs: Money) {
+        self.0 -= rhs.0;
+    }
+}
+
+impl Neg for Money {
+    type Output = Money;
+    fn neg(self) -> Money {
+        Money(-self.0)
+    }
+}
+
+/// Unit price times quantity
+impl Mul<i32> for Money {
+    type Output = Money;
+    fn mul(self, quantity: i32) -> Money {
+        Money(self.0 * quantity as i64)
+    }
+}
+
+/// Amount times a rate or share, rounded to the nearest rupiah
+impl Mul<f64> for Money {
+    type Output = Money;
+    fn mul(self, rate: f64) -> Money {
+        Money::from_f64(self.0 as f64 * rate)
+    }
+}
+
+impl Sum for Money {
+    fn sum<I: Iterator<Item = Money>>(iter: I) -> Money {
+        Money(iter.map(|m| m.0).sum())
+    }
+}
+
+impl<'a> Sum<&'a Money> for Money {
+    fn sum<I: Iterator<Item = &'a Money>>(iter: I) -> Money {
+        Money(iter.map(|m| m.0).sum())
+    }
+}
+
+impl From<Money> for bson::Bson {
+    fn from(money: Money) -> Self {
+        bson::Bson::Double(money.as_f64())
+    }
+}
+
+impl Serialize for Money {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(self.as_f64())
+    }
+}
+
+struct MoneyVisitor;
+
+impl<'de> Visitor<'de> for MoneyVisitor {
+    type Value = Money;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an amount as a number or numeric string")
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Money, E> {
+        Ok(Money::from_f64(v))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Money, E> {
+        Ok(Money(v))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Money, E> {
+        i64::try_from(v).map(Money).map_err(E::custom)
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Money, E> {
+        v.trim()
+            .parse::<f64>()
+            .map(Money::from_f64)
+            .map_err(|_| E::invalid_value(de::Unexpected::Str(v), &self))
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Money, E> {
+        Ok(Money::ZERO)
+    }
+}
+
+impl<'de> Deserialize<'de> for Money {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Money, D::Error> {
+        deserializer.deserialize_any(MoneyVisitor)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RoundingMode {
+    #[default]
+    Nearest,
+    Up,
+    Down,
+}
+
+/// How bill totals are rounded, e.g. `{ increment: 100, mode: nearest }`
+#[derive(Debug, Clone, Deserialize)]
+pub struct RoundingRule {
+    #[serde(default = "default_increment")]
+    pub increment: i64,
+    #[serde(default)]
+    pub mode: RoundingMode,
+}
+
+fn default_increment() -> i64 {
+    1
+}
+
+impl Default for RoundingRule {
+    fn default() -> Self {
+        Self {
+            increment: default_increment(),
+            mode: RoundingMode::Nearest,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_multiplication_rounds_to_rupiah() {
+        assert_eq!(Money::from_rupiah(12_345).percent(11.0), Money::from_rupiah(1_358));
+        assert_eq!(Money::from_f64(0.1 + 0.2), Money::ZERO);
+        assert_eq!(Money::from_rupiah(18_000) * 3, Money::from_rupiah(54_000));
+    }
+
+    #[test]
+    fn test_rounding_rules() {
+        let nearest = RoundingRule { increment: 100, mode: RoundingMode::Nearest };
+        let up = RoundingRule { increment: 100, mode: RoundingMode::Up };
+        let down = RoundingRule { increment: 100, mode: RoundingMode::Down };
+
+        assert_eq!(Money::from_rupiah(54_049).round(&nearest), Money::from_rupiah(54_000));
+        assert_eq!(Money::from_rupiah(54_050).round(&nearest), Money::from_rupiah(54_100));
+        assert_eq!(Money::from_rupiah(54_001).round(&up), Money::from_rupiah(54_100));
+        assert_eq!(Money::from_rupiah(54_000).round(&up), Money::from_rupiah(54_000));
+        assert_eq!(Money::from_rupiah(54_099).round(&down), Money::from_rupiah(54_000));
+        assert_eq!(Money::from_rupiah(54_049).round(&RoundingRule::default()), Money::from_rupiah(54_049));
+    }
+
+    #[test]
+    fn test_allocate_keeps_the_total() {
+        let parts = Money::from_rupiah(100).allocate(&[
+            Money::from_rupiah(1),
+            Money::from_rupiah(1),
+            Money::from_rupiah(1),
+        ]);
+        assert_eq!(parts.iter().sum::<Money>(), Money::from_rupiah(100));
+        assert_eq!(parts[0], Money::from_rupiah(34));
+    }
+
+    #[test]
+    fn test_bson_double_round_trip() {
+        #[derive(Serialize, Deserialize)]
+        struct Doc {
+            amount: Money,
+        }
+
+        let doc = bson::to_document(&Doc { amount: Money::from_rupiah(25_000) }).unwrap();
+        assert_eq!(doc.get_f64("amount").unwrap(), 25_000.0);
+
+        let legacy = bson::doc! { "amount": 24_999.6 };
+        let parsed: Doc = bson::from_document(legacy).unwrap();
+        assert_eq!(parsed.amount, Money::from_rupiah(25_000));
+
+        let int: Doc = bson::from_document(bson::doc! { "amount": 500_i32 }).unwrap();
+        assert_eq!(int.amount, Money::from_rupiah(500));
+
+        let summed = bson::doc! { "total": 1_250_i64, "tax": 137.5 };
+        assert_eq!(Money::from_field(&summed, "total"), Money::from_rupiah(1_250));
+        assert_eq!(Money::from_field(&summed, "tax"), Money::from_rupiah(138));
+        assert_eq!(Money::from_field(&summed, "missing"), Money::ZERO);
+    }
+}
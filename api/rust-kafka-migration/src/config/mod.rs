@@ -1,5 +1,6 @@
 use serde::Deserialize;
 use std::sync::Arc;
+use crate::common::RoundingRule;
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct Config {
@@ -14,6 +15,12 @@ pub struct Config {
     pub logging: LoggingConfig,
     pub cors: CorsConfig,
     pub rate_limit: RateLimitConfig,
+    #[serde(default)]
+    pub pricing: PricingConfig,
+    #[serde(default)]
+    pub jobs: JobsConfig,
+    #[serde(default)]
+    pub reservation: ReservationConfig,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -83,6 +90,9 @@ pub struct MidtransConfig {
     pub client_key: String,
     #[serde(default)]
     pub is_production: bool,
+    /// Overrides the API host, e.g. to point at a local mock
+    #[serde(default)]
+    pub base_url: String,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -91,6 +101,15 @@ pub struct XenditConfig {
     pub secret_key: String,
     #[serde(default)]
     pub is_production: bool,
+    /// `x-callback-token` Xendit sends with every callback
+    #[serde(default)]
+    pub callback_token: String,
+    /// Where e-wallet apps send the customer after paying
+    #[serde(default)]
+    pub success_redirect_url: String,
+    /// Overrides the API host, e.g. to point at a local mock
+    #[serde(default)]
+    pub base_url: String,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -101,6 +120,9 @@ pub struct FcmConfig {
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct GoSendConfig {
+    #[serde(default)]
+    pub client_id: String,
+    /// Sent as the `Pass-Key` header
     #[serde(default)]
     pub api_key: String,
     #[serde(default)]
@@ -129,6 +151,67 @@ pub struct RateLimitConfig {
 
 fn default_rate_limit() -> u32 { 100 }
 
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PricingConfig {
+    /// Rounding applied to bill grand totals, e.g. to the nearest Rp100
+    #[serde(default)]
+    pub rounding: RoundingRule,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct JobsConfig {
+    /// Whether this instance competes for running background jobs
+    #[serde(default = "default_jobs_enabled")]
+    pub enabled: bool,
+    /// Cron schedule, with seconds, of the payment and order sweep
+    #[serde(default = "default_sweep_cron")]
+    pub sweep_cron: String,
+    /// Unpaid Web and App orders are canceled this long after they were placed
+    #[serde(default = "default_unpaid_order_minutes")]
+    pub unpaid_order_minutes: i64,
+}
+
+fn default_jobs_enabled() -> bool { true }
+fn default_sweep_cron() -> String { "0 * * * * *".to_string() }
+fn default_unpaid_order_minutes() -> i64 { 60 }
+
+impl Default for JobsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_jobs_enabled(),
+            sweep_cron: default_sweep_cron(),
+            unpaid_order_minutes: default_unpaid_order_minutes(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReservationConfig {
+    /// Share of the order total asked up front for blocking reservations, in percent
+    #[serde(default = "default_blocking_down_payment_percent")]
+    pub blocking_down_payment_percent: f64,
+    /// Share of the order total asked up front for non-blocking reservations, in percent
+    #[serde(default = "default_non_blocking_down_payment_percent")]
+    pub non_blocking_down_payment_percent: f64,
+    /// Confirmed guests not checked in this long after their slot starts are no-shows
+    #[serde(default = "default_no_show_grace_minutes")]
+    pub no_show_grace_minutes: i64,
+}
+
+fn default_blocking_down_payment_percent() -> f64 { 50.0 }
+fn default_non_blocking_down_payment_percent() -> f64 { 30.0 }
+fn default_no_show_grace_minutes() -> i64 { 30 }
+
+impl Default for ReservationConfig {
+    fn default() -> Self {
+        Self {
+            blocking_down_payment_percent: default_blocking_down_payment_percent(),
+            non_blocking_down_payment_percent: default_non_blocking_down_payment_percent(),
+            no_show_grace_minutes: default_no_show_grace_minutes(),
+        }
+    }
+}
+
 impl Config {
     /// Load configuration from environment
     /// Supports .env.development and .env.production based on NODE_ENV
@@ -162,8 +245,13 @@ impl Config {
             .set_default("rate_limit.requests_per_minute", 100)?
             .set_default("payment.midtrans.server_key", "")?
             .set_default("payment.midtrans.client_key", "")?
+            .set_default("payment.midtrans.base_url", "")?
             .set_default("payment.xendit.secret_key", "")?
+            .set_default("payment.xendit.callback_token", "")?
+            .set_default("payment.xendit.success_redirect_url", "")?
+            .set_default("payment.xendit.base_url", "")?
             .set_default("fcm.server_key", "")?
+            .set_default("gosend.client_id", "")?
             .set_default("gosend.api_key", "")?
             .set_default("gosend.base_url", "")?
             .set_default("redis.url", "redis://localhost:6379")?
@@ -175,6 +263,14 @@ impl Config {
             .set_default("kafka.topics.notification", "notification-events")?
             .set_default("jwt.secret", "secret")?
             .set_default("jwt.expiration", 86400)?
+            .set_default("pricing.rounding.increment", 1)?
+            .set_default("pricing.rounding.mode", "nearest")?
+            .set_default("jobs.enabled", true)?
+            .set_default("jobs.sweep_cron", "0 * * * * *")?
+            .set_default("jobs.unpaid_order_minutes", 60)?
+            .set_default("reservation.blocking_down_payment_percent", 50.0)?
+            .set_default("reservation.non_blocking_down_payment_percent", 30.0)?
+            .set_default("reservation.no_show_grace_minutes", 30)?
             // Fallback for MongoDB env vars
             .set_default("database.uri", std::env::var("MONGODB_URI").unwrap_or_default())?
             .set_default("database.database", std::env::var("MONGODB_DATABASE").unwrap_or_default())?
@@ -1,5 +1,7 @@
 pub mod connection;
 pub mod models;
 pub mod repositories;
+pub mod transaction;
 
 pub use connection::DbConnection;
+pub use transaction::with_transaction;
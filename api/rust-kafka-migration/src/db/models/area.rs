@@ -19,6 +19,9 @@ pub struct Area {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
 
+    /// Outlet the area, and so its tables, belongs to
+    pub outlet_id: ObjectId,
+
     #[serde(rename = "createdAt", skip_serializing_if = "Option::is_none")]
     pub created_at: Option<DateTime<Utc>>,
     #[serde(rename = "updatedAt", skip_serializing_if = "Option::is_none")]
@@ -30,7 +33,7 @@ fn default_true() -> bool {
 }
 
 impl Area {
-    pub fn new(area_name: String, area_code: String, capacity: i32) -> Self {
+    pub fn new(area_name: String, area_code: String, capacity: i32, outlet_id: ObjectId) -> Self {
         let now = Utc::now();
         Self {
             id: None,
@@ -39,6 +42,7 @@ impl Area {
             capacity,
             is_active: true,
             description: None,
+            outlet_id,
             created_at: Some(now),
             updated_at: Some(now),
         }
@@ -0,0 +1,146 @@
+use mongodb::bson::{oid::ObjectId, DateTime};
+use serde::{Deserialize, Serialize};
+
+use crate::common::Money;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ShiftStatus {
+    Open,
+    Closed,
+}
+
+impl ShiftStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ShiftStatus::Open => "open",
+            ShiftStatus::Closed => "closed",
+        }
+    }
+}
+
+impl std::fmt::Display for ShiftStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Direction of cash put into or taken out of the drawer outside of sales
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CashMovementType {
+    In,
+    Out,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CashMovement {
+    #[serde(rename = "type")]
+    pub movement_type: CashMovementType,
+    pub amount: Money,
+    pub reason: String,
+    pub recorded_by: ObjectId,
+    #[serde(default)]
+    pub recorded_by_name: String,
+    pub recorded_at: DateTime,
+}
+
+/// Cash the drawer should hold, and what was counted in it
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ShiftCashSummary {
+    pub opening_float: Money,
+    /// Cash tenders taken by the cashier during the shift, net of change
+    pub cash_sales: Money,
+    pub cash_order_count: u32,
+    pub cash_in: Money,
+    pub cash_out: Money,
+    /// Refunds paid back in cash by the cashier during the shift
+    #[serde(default)]
+    pub cash_refunds: Money,
+    pub expected_cash: Money,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub counted_cash: Option<Money>,
+    /// Counted minus expected; negative when cash is missing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub variance: Option<Money>,
+}
+
+/// A cashier's working session on a cash drawer (`cashier_shifts`).
+///
+/// Not to be confused with the Node.js `shifts` collection, which holds the
+/// weekly cashier schedule.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CashierShift {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub outlet_id: ObjectId,
+    pub cashier_id: ObjectId,
+    #[serde(default)]
+    pub cashier_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub device_id: Option<ObjectId>,
+    pub status: ShiftStatus,
+    pub opening_float: Money,
+    #[serde(default)]
+    pub movements: Vec<CashMovement>,
+    pub opened_at: DateTime,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub closed_at: Option<DateTime>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub closed_by: Option<ObjectId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub closed_by_name: Option<String>,
+    /// Frozen when the shift closes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<ShiftCashSummary>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub closing_notes: Option<String>,
+    #[serde(default = "DateTime::now")]
+    pub created_at: DateTime,
+    #[serde(default = "DateTime::now")]
+    pub updated_at: DateTime,
+}
+
+impl CashierShift {
+    pub fn open(
+        outlet_id: ObjectId,
+        cashier_id: ObjectId,
+        cashier_name: String,
+        opening_float: Money,
+        now: DateTime,
+    ) -> Self {
+        Self {
+            id: None,
+            outlet_id,
+            cashier_id,
+            cashier_name,
+            device_id: None,
+            status: ShiftStatus::Open,
+            opening_float,
+            movements: Vec::new(),
+            opened_at: now,
+            closed_at: None,
+            closed_by: None,
+            closed_by_name: None,
+            summary: None,
+            notes: None,
+            closing_notes: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    /// Total of the cash movements in one direction
+    pub fn movement_total(&self, movement_type: CashMovementType) -> Money {
+        self.movements
+            .iter()
+            .filter(|m| m.movement_type == movement_type)
+            .map(|m| m.amount)
+            .sum()
+    }
+}
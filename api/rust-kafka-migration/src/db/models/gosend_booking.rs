@@ -0,0 +1,84 @@
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+use crate::common::Money;
+
+/// One end of a GoSend route
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct GoSendRoutePoint {
+    pub name: Option<String>,
+    pub note: Option<String>,
+    pub contact_name: Option<String>,
+    pub contact_phone: Option<String>,
+    /// "lat,long"
+    pub latlong: Option<String>,
+    pub address: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct GoSendRoutes {
+    pub origin: GoSendRoutePoint,
+    pub destination: GoSendRoutePoint,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct GoSendDriverInfo {
+    pub driver_id: Option<String>,
+    pub driver_name: Option<String>,
+    pub driver_phone: Option<String>,
+    pub driver_photo: Option<String>,
+    pub vehicle_number: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct GoSendPricing {
+    pub total_price: Money,
+    pub distance: f64,
+    pub shipment_method_description: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct GoSendTimestamps {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order_created: Option<mongodb::bson::DateTime>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order_closed: Option<mongodb::bson::DateTime>,
+}
+
+/// GoSend booking matching the Node.js GoSendBooking schema (`gosendbookings`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GoSendBooking {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub order_id: String,
+    /// GoSend order number (GK-xxxxxxx)
+    #[serde(rename = "goSend_order_no")]
+    pub gosend_order_no: String,
+    pub store_order_id: String,
+    pub shipment_method: String, // Instant, SameDay
+    pub status: String, // confirmed, allocated, ..., cancelled, delivered, rejected, no_driver
+    #[serde(default)]
+    pub routes: GoSendRoutes,
+    pub item: Option<String>,
+    #[serde(default)]
+    pub driver_info: GoSendDriverInfo,
+    #[serde(default)]
+    pub pricing: GoSendPricing,
+    #[serde(default)]
+    pub timestamps: GoSendTimestamps,
+    pub live_tracking_url: Option<String>,
+    #[serde(rename = "createdAt", skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<mongodb::bson::DateTime>,
+    #[serde(rename = "updatedAt", skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<mongodb::bson::DateTime>,
+}
+
+impl GoSendBooking {
+    /// Bookings in these states can no longer be cancelled
+    pub fn is_closed(&self) -> bool {
+        matches!(
+            self.status.as_str(),
+            "cancelled" | "delivered" | "rejected" | "no_driver"
+        )
+    }
+}
@@ -4,7 +4,9 @@ use serde::{Deserialize, Serialize};
 /// Attendance status enum
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
+#[derive(Default)]
 pub enum AttendanceStatus {
+    #[default]
     Present,
     Absent,
     Late,
@@ -15,46 +17,36 @@ pub enum AttendanceStatus {
     Permission,
 }
 
-impl Default for AttendanceStatus {
-    fn default() -> Self {
-        Self::Present
-    }
-}
 
 /// Check-in/out type enum
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
+#[derive(Default)]
 pub enum CheckType {
+    #[default]
     Fingerprint,
     Mobile,
     Web,
     Manual,
 }
 
-impl Default for CheckType {
-    fn default() -> Self {
-        Self::Fingerprint
-    }
-}
 
 /// Approval status enum
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
+#[derive(Default)]
 pub enum ApprovalStatus {
+    #[default]
     Pending,
     Approved,
     Rejected,
 }
 
-impl Default for ApprovalStatus {
-    fn default() -> Self {
-        Self::Pending
-    }
-}
 
 /// Check-in or check-out information
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
+#[derive(Default)]
 pub struct CheckInfo {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub time: Option<mongodb::bson::DateTime>,
@@ -72,17 +64,6 @@ pub struct CheckInfo {
     pub check_type: CheckType,
 }
 
-impl Default for CheckInfo {
-    fn default() -> Self {
-        Self {
-            time: None,
-            location: None,
-            device: None,
-            photo: None,
-            check_type: CheckType::default(),
-        }
-    }
-}
 
 /// Approval information
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -4,33 +4,27 @@ use serde::{Deserialize, Serialize};
 /// Employment status enum
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
+#[derive(Default)]
 pub enum EmploymentStatus {
+    #[default]
     Probation,
     Permanent,
     Contract,
     Intern,
 }
 
-impl Default for EmploymentStatus {
-    fn default() -> Self {
-        Self::Probation
-    }
-}
 
 /// Employment type enum
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
+#[derive(Default)]
 pub enum EmploymentType {
+    #[default]
     Fulltime,
     Parttime,
     Freelance,
 }
 
-impl Default for EmploymentType {
-    fn default() -> Self {
-        Self::Fulltime
-    }
-}
 
 /// Employee allowances
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -189,6 +183,7 @@ fn default_true() -> bool {
 }
 
 impl Employee {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         user: ObjectId,
         company: ObjectId,
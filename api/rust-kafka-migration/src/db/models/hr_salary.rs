@@ -4,7 +4,9 @@ use serde::{Deserialize, Serialize};
 /// Salary status enum
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
+#[derive(Default)]
 pub enum SalaryStatus {
+    #[default]
     Draft,
     Calculated,
     Approved,
@@ -12,26 +14,18 @@ pub enum SalaryStatus {
     Cancelled,
 }
 
-impl Default for SalaryStatus {
-    fn default() -> Self {
-        Self::Draft
-    }
-}
 
 /// Payment method enum
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
+#[derive(Default)]
 pub enum PaymentMethod {
+    #[default]
     Transfer,
     Cash,
     Other,
 }
 
-impl Default for PaymentMethod {
-    fn default() -> Self {
-        Self::Transfer
-    }
-}
 
 /// Salary period
 #[derive(Debug, Clone, Serialize, Deserialize)]
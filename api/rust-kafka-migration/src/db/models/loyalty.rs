@@ -1,6 +1,5 @@
 use serde::{Deserialize, Serialize};
 use mongodb::bson::oid::ObjectId;
-use chrono::{DateTime, Utc};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct LoyaltyProgram {
@@ -0,0 +1,68 @@
+use mongodb::bson::{oid::ObjectId, DateTime};
+use serde::{Deserialize, Serialize};
+
+use crate::common::Money;
+
+/// What a manual discount is taken off
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ManualDiscountScope {
+    /// One order line, `item_index` in `Order.items`
+    Line,
+    /// The bill after promos, voucher, loyalty and earlier manual discounts
+    Order,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ManualDiscountType {
+    Percentage,
+    Fixed,
+}
+
+/// A discretionary discount given by a cashier, kept on the order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManualDiscount {
+    pub scope: ManualDiscountScope,
+    #[serde(rename = "itemIndex", skip_serializing_if = "Option::is_none")]
+    pub item_index: Option<usize>,
+    #[serde(rename = "discountType")]
+    pub discount_type: ManualDiscountType,
+    /// Percent or rupiah, as entered
+    pub value: f64,
+    /// Amount the discount is taken from
+    pub base: Money,
+    pub amount: Money,
+    pub reason: String,
+    #[serde(rename = "grantedBy")]
+    pub granted_by: ObjectId,
+    #[serde(rename = "grantedByName")]
+    pub granted_by_name: String,
+    #[serde(rename = "approvedBy", skip_serializing_if = "Option::is_none")]
+    pub approved_by: Option<ObjectId>,
+    #[serde(rename = "approvedByName", skip_serializing_if = "Option::is_none")]
+    pub approved_by_name: Option<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime,
+}
+
+/// Audit trail entry written for every manual discount
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiscountAudit {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub order: ObjectId,
+    #[serde(rename = "orderId")]
+    pub order_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub outlet: Option<ObjectId>,
+    #[serde(rename = "itemName", skip_serializing_if = "Option::is_none")]
+    pub item_name: Option<String>,
+    pub discount: ManualDiscount,
+    /// Share of the bill subtotal the order's manual discounts reached
+    #[serde(rename = "billPercent")]
+    pub bill_percent: f64,
+    /// Limit of the cashier's role when the discount was given
+    #[serde(rename = "roleLimitPercent")]
+    pub role_limit_percent: f64,
+}
@@ -1,7 +1,8 @@
 use bson::oid::ObjectId;
-use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+use crate::common::Money;
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum MainCategory {
@@ -57,13 +58,13 @@ pub struct WorkstationMapping {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Topping {
     pub name: String,
-    pub price: f64,
+    pub price: Money,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AddonOptionDetail {
     pub label: String,
-    pub price: f64,
+    pub price: Money,
     
     #[serde(rename = "isDefault", default)]
     pub is_default: bool,
@@ -82,7 +83,7 @@ pub struct MenuItem {
     pub id: Option<ObjectId>,
 
     pub name: String,
-    pub price: f64,
+    pub price: Money,
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
@@ -121,7 +122,7 @@ pub struct MenuItem {
     pub image_url: Option<String>,
 
     #[serde(rename = "costPrice", default)]
-    pub cost_price: f64,
+    pub cost_price: Money,
 
     #[serde(rename = "availableStock", default)]
     pub available_stock: f64,
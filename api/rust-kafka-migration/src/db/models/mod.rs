@@ -1,17 +1,27 @@
+// Re-exports make up the module's public surface; not all are used by the binary yet
+#![allow(unused_imports)]
+
 pub mod area;
+pub mod cashier_shift;
 pub mod category;
 pub mod event;
+pub mod gosend_booking;
 pub mod loyalty;
+pub mod manual_discount;
 pub mod marketlist;
 pub mod menu_item;
 pub mod menu_stock;
 pub mod order;
+pub mod order_revision;
 pub mod outlet;
 pub mod payment;
+pub mod payment_adjustment;
+pub mod payment_notification;
 pub mod product;
 pub mod product_stock;
 pub mod promo;
 pub mod recipe;
+pub mod refund;
 pub mod request;
 pub mod reservation;
 pub mod role;
@@ -31,21 +41,33 @@ pub mod hr_salary;
 pub mod hr_setting;
 
 pub use area::Area;
+pub use cashier_shift::{CashMovement, CashMovementType, CashierShift, ShiftCashSummary, ShiftStatus};
 pub use category::Category;
 pub use event::{CheckInStatus, Event, EventStatus, FreeRegistration};
+pub use gosend_booking::{GoSendBooking, GoSendPricing, GoSendRoutePoint, GoSendRoutes, GoSendTimestamps};
 pub use loyalty::{CustomerLoyalty, LoyaltyLevel, LoyaltyProgram};
+pub use manual_discount::{DiscountAudit, ManualDiscount, ManualDiscountScope, ManualDiscountType};
 pub use marketlist::{MarketList, MarketListItem, MarketListPurpose, Payment as MarketListPayment};
 pub use menu_item::MenuItem;
 pub use menu_stock::{MenuStock, StockReason, StockUpdateType};
 pub use order::{
-    CustomAmountItem, MenuItemData, Order, OrderItem, PaymentAction, SplitPayment, VaNumber,
+    CustomAmountItem, DeliveryTracking, MenuItemData, Order, OrderItem, OrderStatus,
+    OrderStatusHistoryEntry, PaymentAction, SplitPayment, VaNumber,
+};
+pub use order_revision::{
+    OrderRevision, OrderSnapshot, RevisionChanges, RevisionDelta, RevisionEffects,
+    RevisionItemChange,
 };
 pub use outlet::Outlet;
 pub use payment::Payment as OrderPayment;
+pub use payment::PaymentProvider;
+pub use payment_adjustment::PaymentAdjustment;
+pub use payment_notification::{NotificationOutcome, PaymentNotification};
 pub use product::Product;
 pub use product_stock::{ProductMovement, ProductMovementType, ProductStock};
 pub use promo::{AutoPromo, Promo};
 pub use recipe::Recipe;
+pub use refund::{Refund, RefundItem, RefundTender};
 pub use request::{
     FulfillmentStatus, Request, RequestItem, RequestItemStatus, RequestStatus, RequestType,
 };
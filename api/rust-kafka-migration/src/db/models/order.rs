@@ -1,12 +1,15 @@
 use serde::{Deserialize, Serialize};
 use mongodb::bson::oid::ObjectId;
+
+use crate::common::Money;
+use crate::db::models::manual_discount::ManualDiscount;
 // No longer using chrono here
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct SplitPayment {
     #[serde(rename = "paymentMethod")]
     pub payment_method: String,
-    pub amount: f64,
+    pub amount: Money,
     #[serde(default, deserialize_with = "crate::utils::serde_utils::deserialize_vec_or_single")]
     pub va_numbers: Vec<VaNumber>,
     #[serde(default, deserialize_with = "crate::utils::serde_utils::deserialize_vec_or_single")]
@@ -42,9 +45,9 @@ pub struct PaymentAction {
 pub struct PaymentDetails {
     // Cash
     #[serde(rename = "cashTendered", skip_serializing_if = "Option::is_none")]
-    pub cash_tendered: Option<f64>,
+    pub cash_tendered: Option<Money>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub change: Option<f64>,
+    pub change: Option<Money>,
     
     // Card
     #[serde(rename = "cardType", skip_serializing_if = "Option::is_none")]
@@ -74,7 +77,7 @@ pub struct PaymentDetails {
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct RefundDetails {
     #[serde(rename = "refundAmount", default)]
-    pub refund_amount: f64,
+    pub refund_amount: Money,
     #[serde(rename = "refundReason", skip_serializing_if = "Option::is_none")]
     pub refund_reason: Option<String>,
     #[serde(rename = "refundedAt", skip_serializing_if = "Option::is_none")]
@@ -92,7 +95,7 @@ pub struct OrderItem {
     pub menu_item_data: MenuItemData,
     
     pub quantity: i32,
-    pub subtotal: f64,
+    pub subtotal: Money,
     
     #[serde(default, deserialize_with = "crate::utils::serde_utils::deserialize_vec_or_single")]
     pub addons: Vec<OrderItemAddon>,
@@ -114,6 +117,11 @@ pub struct OrderItem {
     
     #[serde(rename = "isPrinted", default)]
     pub is_printed: bool,
+    #[serde(rename = "refundedQuantity", default)]
+    pub refunded_quantity: i32,
+    /// Whether the line's recipe ingredients have been taken out of stock
+    #[serde(rename = "stockDeducted", default)]
+    pub stock_deducted: bool,
     #[serde(rename = "printedAt", skip_serializing_if = "Option::is_none")]
     pub printed_at: Option<mongodb::bson::DateTime>,
     
@@ -133,7 +141,7 @@ impl Default for OrderItem {
             menu_item: None,
             menu_item_data: MenuItemData::default(),
             quantity: 0,
-            subtotal: 0.0,
+            subtotal: Money::ZERO,
             addons: Vec::new(),
             toppings: Vec::new(),
             notes: String::new(),
@@ -142,6 +150,8 @@ impl Default for OrderItem {
             added_at: mongodb::bson::DateTime::now(),
             kitchen_status: default_kitchen_status(),
             is_printed: false,
+            refunded_quantity: 0,
+            stock_deducted: false,
             printed_at: None,
             dine_type: default_dine_type(),
             outlet_id: None,
@@ -159,7 +169,7 @@ pub struct MenuItemData {
     #[serde(default)]
     pub name: String,
     #[serde(default)]
-    pub price: f64,
+    pub price: Money,
     #[serde(default)]
     pub category: String,
     #[serde(default)]
@@ -179,7 +189,7 @@ fn default_true() -> bool { true }
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct OrderItemAddon {
     pub name: String,
-    pub price: f64,
+    pub price: Money,
     #[serde(default, deserialize_with = "crate::utils::serde_utils::deserialize_vec_or_single")]
     pub options: Vec<AddonOption>,
 }
@@ -187,7 +197,7 @@ pub struct OrderItemAddon {
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct AddonOption {
     pub label: String,
-    pub price: f64,
+    pub price: Money,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
@@ -195,12 +205,12 @@ pub struct OrderItemTopping {
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
     pub id: Option<String>,
     pub name: String,
-    pub price: f64,
+    pub price: Money,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct CustomAmountItem {
-    pub amount: f64,
+    pub amount: Money,
     #[serde(default = "default_custom_item_name")]
     pub name: String,
     #[serde(default)]
@@ -210,21 +220,21 @@ pub struct CustomAmountItem {
     #[serde(rename = "appliedAt", default = "mongodb::bson::DateTime::now")]
     pub applied_at: mongodb::bson::DateTime,
     #[serde(rename = "originalAmount", skip_serializing_if = "Option::is_none")]
-    pub original_amount: Option<f64>,
+    pub original_amount: Option<Money>,
     #[serde(rename = "discountApplied", default)]
-    pub discount_applied: f64,
+    pub discount_applied: Money,
 }
 
 impl Default for CustomAmountItem {
     fn default() -> Self {
         Self {
-            amount: 0.0,
+            amount: Money::ZERO,
             name: default_custom_item_name(),
             description: String::new(),
             dine_type: default_dine_type(),
             applied_at: mongodb::bson::DateTime::now(),
             original_amount: None,
-            discount_applied: 0.0,
+            discount_applied: Money::ZERO,
         }
     }
 }
@@ -240,11 +250,14 @@ pub struct AppliedPromo {
     #[serde(rename = "promoType", skip_serializing_if = "Option::is_none")]
     pub promo_type: Option<String>,
     #[serde(default)]
-    pub discount: f64,
+    pub discount: Money,
     #[serde(rename = "affectedItems", default, deserialize_with = "crate::utils::serde_utils::deserialize_vec_or_single")]
     pub affected_items: Vec<AffectedItem>,
     #[serde(rename = "freeItems", default, deserialize_with = "crate::utils::serde_utils::deserialize_vec_or_single")]
     pub free_items: Vec<FreeItem>,
+    /// Bundle sets the cashier picked, so the promo re-prices the same when the bill changes
+    #[serde(rename = "bundleSets", default, skip_serializing_if = "Option::is_none")]
+    pub bundle_sets: Option<i32>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
@@ -256,11 +269,11 @@ pub struct AffectedItem {
     #[serde(default)]
     pub quantity: i32,
     #[serde(rename = "originalSubtotal", default)]
-    pub original_subtotal: f64,
+    pub original_subtotal: Money,
     #[serde(rename = "discountAmount", default)]
-    pub discount_amount: f64,
+    pub discount_amount: Money,
     #[serde(rename = "discountedSubtotal", default)]
-    pub discounted_subtotal: f64,
+    pub discounted_subtotal: Money,
     #[serde(rename = "discountPercentage", default)]
     pub discount_percentage: f64,
 }
@@ -274,7 +287,7 @@ pub struct FreeItem {
     #[serde(default)]
     pub quantity: i32,
     #[serde(default)]
-    pub price: f64,
+    pub price: Money,
     #[serde(rename = "isFree", default)]
     pub is_free: bool,
 }
@@ -284,17 +297,17 @@ pub struct TaxAndService {
     #[serde(rename = "type")]
     pub kind: String, // tax, service
     pub name: String,
-    pub amount: f64,
+    pub amount: Money,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct Discounts {
     #[serde(rename = "autoPromoDiscount", default)]
-    pub auto_promo_discount: f64,
+    pub auto_promo_discount: Money,
     #[serde(rename = "manualDiscount", default)]
-    pub manual_discount: f64,
+    pub manual_discount: Money,
     #[serde(rename = "voucherDiscount", default)]
-    pub voucher_discount: f64,
+    pub voucher_discount: Money,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -337,6 +350,91 @@ pub struct RecipientInfo {
     pub note: Option<String>,
 }
 
+/// Order lifecycle status
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OrderStatus {
+    #[default]
+    Pending,
+    Waiting,
+    /// Node's webhook writes `Confirmed` when a reservation's down payment settles
+    #[serde(alias = "Confirmed")]
+    Reserved,
+    OnProcess,
+    #[serde(alias = "completed")]
+    Completed,
+    #[serde(alias = "Cancelled", alias = "canceled", alias = "cancelled")]
+    Canceled,
+}
+
+impl OrderStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OrderStatus::Pending => "Pending",
+            OrderStatus::Waiting => "Waiting",
+            OrderStatus::Reserved => "Reserved",
+            OrderStatus::OnProcess => "OnProcess",
+            OrderStatus::Completed => "Completed",
+            OrderStatus::Canceled => "Canceled",
+        }
+    }
+
+    /// Statuses this status may move to
+    pub fn allowed_transitions(&self) -> &'static [OrderStatus] {
+        use OrderStatus::*;
+        match self {
+            Pending => &[Waiting, Reserved, OnProcess, Completed, Canceled],
+            Waiting => &[OnProcess, Completed, Canceled],
+            Reserved => &[Waiting, OnProcess, Completed, Canceled],
+            OnProcess => &[Completed, Canceled],
+            Completed | Canceled => &[],
+        }
+    }
+
+    pub fn can_transition_to(&self, next: OrderStatus) -> bool {
+        self.allowed_transitions().contains(&next)
+    }
+
+    /// Statuses in which the order has been accepted and its items are made
+    pub fn is_confirmed(&self) -> bool {
+        matches!(self, OrderStatus::Waiting | OrderStatus::OnProcess | OrderStatus::Completed)
+    }
+
+    pub fn is_final(&self) -> bool {
+        matches!(self, OrderStatus::Completed | OrderStatus::Canceled)
+    }
+}
+
+impl std::fmt::Display for OrderStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for OrderStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_json::from_value(serde_json::Value::String(s.to_string()))
+            .map_err(|_| format!("Unknown order status: {}", s))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OrderStatusHistoryEntry {
+    #[serde(rename = "fromStatus")]
+    pub from_status: OrderStatus,
+    #[serde(rename = "toStatus")]
+    pub to_status: OrderStatus,
+    #[serde(rename = "changedBy", skip_serializing_if = "Option::is_none")]
+    pub changed_by: Option<ObjectId>,
+    #[serde(rename = "changedByName", skip_serializing_if = "Option::is_none")]
+    pub changed_by_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    #[serde(rename = "changedAt")]
+    pub changed_at: mongodb::bson::DateTime,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Order {
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
@@ -361,8 +459,14 @@ pub struct Order {
     #[serde(rename = "customAmountItems", default, deserialize_with = "crate::utils::serde_utils::deserialize_vec_or_single")]
     pub custom_amount_items: Vec<CustomAmountItem>,
     
-    #[serde(default = "default_status")]
-    pub status: String, // Pending, Waiting, Reserved, OnProcess, Completed, Canceled
+    #[serde(default)]
+    pub status: OrderStatus,
+
+    #[serde(rename = "statusHistory", default, deserialize_with = "crate::utils::serde_utils::deserialize_vec_or_single")]
+    pub status_history: Vec<OrderStatusHistoryEntry>,
+
+    #[serde(rename = "paymentStatus", skip_serializing_if = "Option::is_none")]
+    pub payment_status: Option<String>, // Pending, Paid, Failed, Challenged, Refunded
     
     #[serde(default, deserialize_with = "crate::utils::serde_utils::deserialize_vec_or_single")]
     pub payments: Vec<SplitPayment>,
@@ -409,28 +513,30 @@ pub struct Order {
     pub applied_manual_promo: Option<ObjectId>,
     #[serde(rename = "appliedVoucher", skip_serializing_if = "Option::is_none")]
     pub applied_voucher: Option<ObjectId>,
+    #[serde(rename = "manualDiscounts", default, skip_serializing_if = "Vec::is_empty")]
+    pub manual_discounts: Vec<ManualDiscount>,
     
     #[serde(rename = "taxAndServiceDetails", default, deserialize_with = "crate::utils::serde_utils::deserialize_vec_or_single")]
     pub tax_and_service_details: Vec<TaxAndService>,
     #[serde(rename = "totalTax", default)]
-    pub total_tax: f64,
+    pub total_tax: Money,
     #[serde(rename = "totalServiceFee", default)]
-    pub total_service_fee: f64,
+    pub total_service_fee: Money,
     
     #[serde(rename = "outlet", skip_serializing_if = "Option::is_none")]
     pub outlet: Option<ObjectId>,
     
     #[serde(rename = "totalBeforeDiscount")]
-    pub total_before_discount: f64,
+    pub total_before_discount: Money,
     #[serde(rename = "totalAfterDiscount")]
-    pub total_after_discount: f64,
+    pub total_after_discount: Money,
     #[serde(rename = "totalCustomAmount", default)]
-    pub total_custom_amount: f64,
+    pub total_custom_amount: Money,
     #[serde(rename = "grandTotal")]
-    pub grand_total: f64,
+    pub grand_total: Money,
     
     #[serde(default)]
-    pub change: f64,
+    pub change: Money,
     
     pub source: String, // Web, App, Cashier, Waiter, Gro
     
@@ -466,6 +572,20 @@ pub struct Order {
     #[serde(rename = "splitPaymentStatus", default = "default_split_payment_status")]
     pub split_payment_status: String, // not_started, partial, completed, overpaid
     
+    #[serde(rename = "totalRefunded", default)]
+    pub total_refunded: Money,
+
+    /// Number of the latest revision; 0 until the order is first edited
+    #[serde(default)]
+    pub revision: i32,
+
+    #[serde(rename = "loyaltyPointsEarned", default)]
+    pub loyalty_points_earned: f64,
+    #[serde(rename = "loyaltyPointsRedeemed", default)]
+    pub loyalty_points_redeemed: f64,
+    #[serde(rename = "loyaltyRolledBack", default)]
+    pub loyalty_rolled_back: bool,
+
     #[serde(rename = "stockRolledBack", default)]
     pub stock_rolled_back: bool,
     #[serde(rename = "tableReleased", default)]
@@ -480,7 +600,6 @@ pub struct Order {
 }
 
 fn default_user() -> String { "Guest".to_string() }
-fn default_status() -> String { "Pending".to_string() }
 fn default_type_indoor() -> String { "Indoor".to_string() }
 fn default_split_payment_status() -> String { "not_started".to_string() }
 
@@ -496,7 +615,9 @@ impl Default for Order {
             device_id: None,
             items: Vec::new(),
             custom_amount_items: Vec::new(),
-            status: default_status(),
+            status: OrderStatus::default(),
+            status_history: Vec::new(),
+            payment_status: None,
             payments: Vec::new(),
             payment_method: None,
             order_type: String::new(),
@@ -513,15 +634,16 @@ impl Default for Order {
             applied_promos: Vec::new(),
             applied_manual_promo: None,
             applied_voucher: None,
+            manual_discounts: Vec::new(),
             tax_and_service_details: Vec::new(),
-            total_tax: 0.0,
-            total_service_fee: 0.0,
+            total_tax: Money::ZERO,
+            total_service_fee: Money::ZERO,
             outlet: None,
-            total_before_discount: 0.0,
-            total_after_discount: 0.0,
-            total_custom_amount: 0.0,
-            grand_total: 0.0,
-            change: 0.0,
+            total_before_discount: Money::ZERO,
+            total_after_discount: Money::ZERO,
+            total_custom_amount: Money::ZERO,
+            grand_total: Money::ZERO,
+            change: Money::ZERO,
             source: String::new(),
             created_by: None,
             current_batch: default_batch_number(),
@@ -535,6 +657,11 @@ impl Default for Order {
             recipient_info: None,
             is_split_payment: false,
             split_payment_status: default_split_payment_status(),
+            total_refunded: Money::ZERO,
+            revision: 0,
+            loyalty_points_earned: 0.0,
+            loyalty_points_redeemed: 0.0,
+            loyalty_rolled_back: false,
             stock_rolled_back: false,
             table_released: false,
             canceled_by_system: false,
@@ -543,3 +670,11 @@ impl Default for Order {
         }
     }
 }
+
+impl Order {
+    /// The reservation the order was made for: Node stores it in `reservation`,
+    /// orders linked to a reservation later only get `originalReservationId`
+    pub fn reservation_id(&self) -> Option<ObjectId> {
+        self.reservation.or(self.original_reservation_id)
+    }
+}
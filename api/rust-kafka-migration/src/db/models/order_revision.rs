@@ -0,0 +1,123 @@
+use mongodb::bson::{oid::ObjectId, DateTime};
+use serde::{Deserialize, Serialize};
+
+use crate::common::Money;
+use crate::db::models::order::{AppliedPromo, OrderItem};
+
+/// Change to one order line between two revisions
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RevisionItemChange {
+    /// Position of the line in `Order.items`
+    #[serde(rename = "itemIndex")]
+    pub item_index: usize,
+    #[serde(rename = "menuItem", skip_serializing_if = "Option::is_none")]
+    pub menu_item: Option<ObjectId>,
+    pub name: String,
+    #[serde(rename = "quantityBefore")]
+    pub quantity_before: i32,
+    #[serde(rename = "quantityAfter")]
+    pub quantity_after: i32,
+    #[serde(rename = "subtotalBefore")]
+    pub subtotal_before: Money,
+    #[serde(rename = "subtotalAfter")]
+    pub subtotal_after: Money,
+    #[serde(rename = "notesBefore", skip_serializing_if = "Option::is_none")]
+    pub notes_before: Option<String>,
+    #[serde(rename = "notesAfter", skip_serializing_if = "Option::is_none")]
+    pub notes_after: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RevisionChanges {
+    #[serde(default)]
+    pub added: Vec<RevisionItemChange>,
+    #[serde(default)]
+    pub removed: Vec<RevisionItemChange>,
+    #[serde(default)]
+    pub updated: Vec<RevisionItemChange>,
+}
+
+impl RevisionChanges {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.updated.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RevisionDelta {
+    #[serde(rename = "subtotalDelta", default)]
+    pub subtotal_delta: Money,
+    #[serde(rename = "discountDelta", default)]
+    pub discount_delta: Money,
+    #[serde(rename = "taxDelta", default)]
+    pub tax_delta: Money,
+    #[serde(rename = "serviceDelta", default)]
+    pub service_delta: Money,
+    #[serde(rename = "grandDelta", default)]
+    pub grand_delta: Money,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingPaymentAdjusted {
+    #[serde(rename = "paymentId")]
+    pub payment_id: ObjectId,
+    #[serde(rename = "amountDelta")]
+    pub amount_delta: Money,
+}
+
+/// Payments touched by a revision
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RevisionEffects {
+    #[serde(rename = "pendingPaymentAdjusted", default)]
+    pub pending_payment_adjusted: Vec<PendingPaymentAdjusted>,
+    #[serde(rename = "newPendingPaymentId", skip_serializing_if = "Option::is_none")]
+    pub new_pending_payment_id: Option<ObjectId>,
+    #[serde(rename = "refundPaymentId", skip_serializing_if = "Option::is_none")]
+    pub refund_payment_id: Option<ObjectId>,
+}
+
+/// Lines and totals of the order as they stood after the revision
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OrderSnapshot {
+    pub items: Vec<OrderItem>,
+    #[serde(rename = "appliedPromos", default)]
+    pub applied_promos: Vec<AppliedPromo>,
+    #[serde(rename = "totalBeforeDiscount")]
+    pub total_before_discount: Money,
+    #[serde(rename = "totalAfterDiscount")]
+    pub total_after_discount: Money,
+    #[serde(rename = "totalTax")]
+    pub total_tax: Money,
+    #[serde(rename = "totalServiceFee")]
+    pub total_service_fee: Money,
+    #[serde(rename = "grandTotal")]
+    pub grand_total: Money,
+}
+
+/// Immutable record of one edit to an order, matching the Node.js OrderRevision schema
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderRevision {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    #[serde(rename = "orderId")]
+    pub order_id: ObjectId,
+    #[serde(rename = "orderCode")]
+    pub order_code: String,
+    #[serde(rename = "revisionNumber")]
+    pub revision_number: i32,
+    #[serde(rename = "createdBy")]
+    pub created_by: ObjectId,
+    #[serde(rename = "createdByName")]
+    pub created_by_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    pub changes: RevisionChanges,
+    pub delta: RevisionDelta,
+    #[serde(default)]
+    pub effects: RevisionEffects,
+    pub snapshot: OrderSnapshot,
+    #[serde(rename = "createdAt", skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<DateTime>,
+    #[serde(rename = "updatedAt", skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<DateTime>,
+}
@@ -1,7 +1,8 @@
 use bson::oid::ObjectId;
-use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+use crate::db::models::PaymentProvider;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Outlet {
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
@@ -14,6 +15,14 @@ pub struct Outlet {
     
     #[serde(rename = "contactNumber")]
     pub contact_number: String,
+
+    /// "lat,long" pickup point for delivery bookings
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub coordinates: Option<String>,
+
+    /// Gateway for the outlet's online payments; Midtrans when unset
+    #[serde(rename = "paymentProvider", default, skip_serializing_if = "Option::is_none")]
+    pub payment_provider: Option<PaymentProvider>,
     
     #[serde(skip_serializing_if = "Option::is_none")]
     pub admin: Option<ObjectId>,
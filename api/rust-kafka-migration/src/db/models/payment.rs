@@ -1,7 +1,16 @@
 use serde::{Deserialize, Serialize};
 use mongodb::bson::{oid::ObjectId, DateTime, doc};
 use serde_json::Value;
+use crate::common::Money;
 use crate::db::models::order::{VaNumber, PaymentAction};
+use crate::error::AppError;
+use std::fmt;
+use std::str::FromStr;
+
+/// `paymentType` of the deposit that secures a reservation
+pub const DOWN_PAYMENT: &str = "Down Payment";
+/// `paymentType` of the balance collected once reserved guests arrive
+pub const FINAL_PAYMENT: &str = "Final Payment";
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Payment {
@@ -27,13 +36,13 @@ pub struct Payment {
     #[serde(rename = "paymentType")]
     pub payment_type: String, // Down Payment, Final Payment, Full
     
-    pub amount: f64,
+    pub amount: Money,
     
     #[serde(rename = "totalAmount", skip_serializing_if = "Option::is_none")]
-    pub total_amount: Option<f64>,
+    pub total_amount: Option<Money>,
     
     #[serde(rename = "remainingAmount", default)]
-    pub remaining_amount: f64,
+    pub remaining_amount: Money,
     
     #[serde(rename = "relatedPaymentId", skip_serializing_if = "Option::is_none")]
     pub related_payment_id: Option<ObjectId>,
@@ -41,11 +50,19 @@ pub struct Payment {
     pub phone: Option<String>,
     
     #[serde(default)]
-    pub discount: f64,
+    pub discount: Money,
     
     #[serde(rename = "midtransRedirectUrl", skip_serializing_if = "Option::is_none")]
     pub midtrans_redirect_url: Option<String>,
     
+    /// Gateway that started the charge ("midtrans", "xendit"); unset means Midtrans
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    
+    /// Hosted checkout page of gateways other than Midtrans
+    #[serde(rename = "redirectUrl", skip_serializing_if = "Option::is_none")]
+    pub redirect_url: Option<String>,
+    
     #[serde(rename = "fraud_status", skip_serializing_if = "Option::is_none")]
     pub fraud_status: Option<String>,
     
@@ -103,10 +120,10 @@ pub struct Payment {
     pub adjustment_id: Option<ObjectId>,
     
     #[serde(rename = "tendered_amount", default)]
-    pub tendered_amount: f64,
+    pub tendered_amount: Money,
     
     #[serde(rename = "change_amount", default)]
-    pub change_amount: f64,
+    pub change_amount: Money,
     
     #[serde(rename = "processedExpiry", default)]
     pub processed_expiry: bool,
@@ -117,6 +134,10 @@ pub struct Payment {
     #[serde(rename = "orphanedAt", skip_serializing_if = "Option::is_none")]
     pub orphaned_at: Option<DateTime>,
     
+    /// Set on a settled down payment kept because the guests did not show up
+    #[serde(rename = "forfeitedAt", skip_serializing_if = "Option::is_none")]
+    pub forfeited_at: Option<DateTime>,
+    
     pub notes: Option<String>,
     
     #[serde(rename = "createdAt", default = "DateTime::now")]
@@ -140,13 +161,15 @@ impl Default for Payment {
             status: default_status_pending(),
             method_type: None,
             payment_type: "Full".to_string(),
-            amount: 0.0,
+            amount: Money::ZERO,
             total_amount: None,
-            remaining_amount: 0.0,
+            remaining_amount: Money::ZERO,
             related_payment_id: None,
             phone: None,
-            discount: 0.0,
+            discount: Money::ZERO,
             midtrans_redirect_url: None,
+            provider: None,
+            redirect_url: None,
             fraud_status: None,
             transaction_time: None,
             expiry_time: None,
@@ -166,14 +189,51 @@ impl Default for Payment {
             direction: None,
             revision_id: None,
             adjustment_id: None,
-            tendered_amount: 0.0,
-            change_amount: 0.0,
+            tendered_amount: Money::ZERO,
+            change_amount: Money::ZERO,
             processed_expiry: false,
             expired_at: None,
             orphaned_at: None,
+            forfeited_at: None,
             notes: None,
             created_at: DateTime::now(),
             updated_at: DateTime::now(),
         }
     }
 }
+
+/// Gateway an outlet takes online payments through
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PaymentProvider {
+    #[default]
+    Midtrans,
+    Xendit,
+}
+
+impl PaymentProvider {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PaymentProvider::Midtrans => "midtrans",
+            PaymentProvider::Xendit => "xendit",
+        }
+    }
+}
+
+impl fmt::Display for PaymentProvider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for PaymentProvider {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "midtrans" => Ok(PaymentProvider::Midtrans),
+            "xendit" => Ok(PaymentProvider::Xendit),
+            other => Err(AppError::Validation(format!("Unsupported payment provider: {}", other))),
+        }
+    }
+}
@@ -0,0 +1,28 @@
+use mongodb::bson::{oid::ObjectId, DateTime};
+use serde::{Deserialize, Serialize};
+
+use crate::common::Money;
+
+/// Money moved because of an order revision, matching the Node.js PaymentAdjustment schema
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaymentAdjustment {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    #[serde(rename = "orderId")]
+    pub order_id: ObjectId,
+    #[serde(rename = "paymentId", skip_serializing_if = "Option::is_none")]
+    pub payment_id: Option<ObjectId>,
+    #[serde(rename = "revisionId")]
+    pub revision_id: ObjectId,
+    /// increase_pending, decrease_pending, new_pending, settled_refund
+    pub kind: String,
+    /// charge, refund
+    pub direction: String,
+    pub amount: Money,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    #[serde(rename = "createdAt", skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<DateTime>,
+    #[serde(rename = "updatedAt", skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<DateTime>,
+}
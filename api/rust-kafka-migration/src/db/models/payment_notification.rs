@@ -0,0 +1,52 @@
+use mongodb::bson::{oid::ObjectId, DateTime, Document};
+use serde::{Deserialize, Serialize};
+
+/// What became of a gateway notification
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationOutcome {
+    Received,
+    Processed,
+    /// Same status as already stored; nothing changed
+    Duplicate,
+    /// Older than the stored status, e.g. pending after settlement
+    Ignored,
+    InvalidSignature,
+    AmountMismatch,
+    Failed,
+}
+
+impl NotificationOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NotificationOutcome::Received => "received",
+            NotificationOutcome::Processed => "processed",
+            NotificationOutcome::Duplicate => "duplicate",
+            NotificationOutcome::Ignored => "ignored",
+            NotificationOutcome::InvalidSignature => "invalid_signature",
+            NotificationOutcome::AmountMismatch => "amount_mismatch",
+            NotificationOutcome::Failed => "failed",
+        }
+    }
+}
+
+/// A payment gateway notification exactly as received, kept for audit and replay
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaymentNotification {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub provider: String, // midtrans
+    /// The gateway's order reference (our payment code)
+    pub order_id: Option<String>,
+    pub transaction_status: Option<String>,
+    #[serde(rename = "signatureValid")]
+    pub signature_valid: bool,
+    pub outcome: NotificationOutcome,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub payload: Document,
+    #[serde(rename = "receivedAt")]
+    pub received_at: DateTime,
+    #[serde(rename = "processedAt", skip_serializing_if = "Option::is_none")]
+    pub processed_at: Option<DateTime>,
+}
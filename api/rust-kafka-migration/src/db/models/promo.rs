@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 use mongodb::bson::oid::ObjectId;
-use chrono::{DateTime, Utc};
+
+use crate::common::Money;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Promo {
@@ -47,7 +48,7 @@ pub struct AutoPromo {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub discount: Option<f64>,
     #[serde(rename = "bundlePrice", skip_serializing_if = "Option::is_none")]
-    pub bundle_price: Option<f64>,
+    pub bundle_price: Option<Money>,
     
     #[serde(rename = "consumerType", default = "default_consumer_type")]
     pub consumer_type: String,
@@ -80,7 +81,7 @@ pub struct PromoConditions {
     #[serde(rename = "minQuantity", skip_serializing_if = "Option::is_none")]
     pub min_quantity: Option<i32>,
     #[serde(rename = "minTotal", skip_serializing_if = "Option::is_none")]
-    pub min_total: Option<f64>,
+    pub min_total: Option<Money>,
     #[serde(rename = "buyProduct", skip_serializing_if = "Option::is_none")]
     pub buy_product: Option<ObjectId>,
     #[serde(rename = "getProduct", skip_serializing_if = "Option::is_none")]
@@ -156,18 +157,18 @@ impl AutoPromo {
         false
     }
 
-    pub fn calculate_discount(&self, original_amount: f64) -> f64 {
+    pub fn calculate_discount(&self, original_amount: Money) -> Money {
         if let (Some(discount), Some(discount_type)) = (self.discount, &self.discount_type) {
             match discount_type.as_str() {
                 "percentage" => {
                     let percentage = discount.clamp(0.0, 100.0);
-                    (original_amount * percentage) / 100.0
+                    original_amount.percent(percentage)
                 },
-                "fixed" => discount.min(original_amount),
-                _ => 0.0,
+                "fixed" => Money::from_f64(discount).min(original_amount),
+                _ => Money::ZERO,
             }
         } else {
-            0.0
+            Money::ZERO
         }
     }
 }
@@ -175,7 +176,7 @@ impl AutoPromo {
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct PromoResult {
     #[serde(rename = "totalDiscount")]
-    pub total_discount: f64,
+    pub total_discount: Money,
     #[serde(rename = "appliedPromos")]
     pub applied_promos: Vec<AppliedPromoDetails>,
     #[serde(rename = "bundleSets")]
@@ -188,5 +189,7 @@ pub struct AppliedPromoDetails {
     pub name: String,
     #[serde(rename = "promoType")]
     pub promo_type: String,
-    pub amount: f64,
+    pub amount: Money,
+    #[serde(rename = "bundleSets", skip_serializing_if = "Option::is_none")]
+    pub bundle_sets: Option<i32>,
 }
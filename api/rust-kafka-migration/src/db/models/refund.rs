@@ -0,0 +1,81 @@
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+use crate::common::Money;
+
+/// A refunded quantity of one order line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RefundItem {
+    /// Position of the line in `Order.items`
+    #[serde(rename = "itemIndex")]
+    pub item_index: usize,
+    #[serde(rename = "menuItem", skip_serializing_if = "Option::is_none")]
+    pub menu_item: Option<ObjectId>,
+    #[serde(rename = "menuItemName")]
+    pub menu_item_name: String,
+    pub quantity: i32,
+    #[serde(rename = "refundQuantity")]
+    pub refund_quantity: i32,
+    #[serde(rename = "unitPrice")]
+    pub unit_price: Money,
+    pub subtotal: Money,
+    #[serde(rename = "refundAmount")]
+    pub refund_amount: Money,
+}
+
+/// The part of a refund paid back on one tender of the order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RefundTender {
+    pub method: String,
+    pub amount: Money,
+    /// The refund `Payment` written for this part
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payment_id: Option<ObjectId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gateway_refund_key: Option<String>,
+}
+
+/// Refund model matching the Node.js Refund schema
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Refund {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    #[serde(rename = "refundId")]
+    pub refund_id: String,
+    pub order_id: String,
+    pub order: ObjectId,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<ObjectId>,
+    #[serde(rename = "requestedBy")]
+    pub requested_by: String,
+    #[serde(rename = "refundType")]
+    pub refund_type: String, // full, partial
+    #[serde(rename = "refundItems", default)]
+    pub refund_items: Vec<RefundItem>,
+    #[serde(rename = "totalRefundAmount")]
+    pub total_refund_amount: Money,
+    #[serde(rename = "refundReason")]
+    pub refund_reason: String,
+    #[serde(rename = "refundReasonDescription", skip_serializing_if = "Option::is_none")]
+    pub refund_reason_description: Option<String>,
+    pub status: String, // pending, approved, rejected, processed, cancelled
+    #[serde(rename = "processedBy", skip_serializing_if = "Option::is_none")]
+    pub processed_by: Option<ObjectId>,
+    #[serde(rename = "processedAt", skip_serializing_if = "Option::is_none")]
+    pub processed_at: Option<mongodb::bson::DateTime>,
+    #[serde(rename = "refundMethod")]
+    pub refund_method: String,
+    #[serde(rename = "originalPaymentMethod", skip_serializing_if = "Option::is_none")]
+    pub original_payment_method: Option<String>,
+    #[serde(rename = "gatewayRefundKey", skip_serializing_if = "Option::is_none")]
+    pub gateway_refund_key: Option<String>,
+    /// How the refund was split over the order's tenders; empty on refunds
+    /// recorded before refunds were split
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tenders: Vec<RefundTender>,
+    #[serde(rename = "createdAt", skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<mongodb::bson::DateTime>,
+    #[serde(rename = "updatedAt", skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<mongodb::bson::DateTime>,
+}
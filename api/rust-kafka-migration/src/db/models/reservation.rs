@@ -2,13 +2,39 @@ use bson::oid::ObjectId;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum ReservationStatus {
     Pending,
     Confirmed,
     Cancelled,
     Completed,
+    /// Guests never arrived; any down payment is forfeited
+    #[serde(rename = "no_show")]
+    NoShow,
+}
+
+impl ReservationStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReservationStatus::Pending => "pending",
+            ReservationStatus::Confirmed => "confirmed",
+            ReservationStatus::Cancelled => "cancelled",
+            ReservationStatus::Completed => "completed",
+            ReservationStatus::NoShow => "no_show",
+        }
+    }
+
+    /// Statuses in which a reservation still holds its tables
+    pub fn holds_tables(&self) -> bool {
+        matches!(self, ReservationStatus::Pending | ReservationStatus::Confirmed)
+    }
+}
+
+impl std::fmt::Display for ReservationStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -43,7 +69,7 @@ pub enum FoodServingOption {
     Scheduled,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct EmployeeInfo {
     pub employee_id: Option<ObjectId>,
     pub employee_name: Option<String>,
@@ -55,6 +81,8 @@ pub struct EmployeeInfo {
     pub checked_in_at: Option<DateTime<Utc>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub checked_out_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cancelled_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -65,6 +93,9 @@ pub struct Reservation {
     pub reservation_code: String,
     pub reservation_date: DateTime<Utc>,
     pub reservation_time: String,
+    /// Length of the booked slot, used for table availability
+    #[serde(default = "default_duration_minutes")]
+    pub duration_minutes: i32,
 
     #[serde(default)]
     pub agenda: String,
@@ -85,6 +116,13 @@ pub struct Reservation {
     #[serde(default = "default_reservation_type")]
     pub reservation_type: ReservationType,
 
+    /// Latest down payment charged for the reservation's order
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub down_payment_id: Option<ObjectId>,
+    /// Balance payment generated at check-in, linked to the down payment
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub final_payment_id: Option<ObjectId>,
+
     #[serde(default = "default_status")]
     pub status: ReservationStatus,
 
@@ -107,6 +145,10 @@ pub struct Reservation {
     pub checked_in_by: Option<EmployeeInfo>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub checked_out_by: Option<EmployeeInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cancelled_by: Option<EmployeeInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cancellation_reason: Option<String>,
 
     // Check-in/out times
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -144,6 +186,13 @@ pub struct Reservation {
     pub updated_at_wib: Option<DateTime<Utc>>,
 }
 
+/// Slot length of reservations made before durations were recorded
+pub const DEFAULT_DURATION_MINUTES: i32 = 120;
+
+fn default_duration_minutes() -> i32 {
+    DEFAULT_DURATION_MINUTES
+}
+
 fn default_table_type() -> TableType {
     TableType::LongTable
 }
@@ -179,6 +228,7 @@ impl Reservation {
             reservation_code,
             reservation_date,
             reservation_time,
+            duration_minutes: DEFAULT_DURATION_MINUTES,
             agenda: String::new(),
             agenda_description: String::new(),
             area_id,
@@ -187,6 +237,8 @@ impl Reservation {
             guest_count,
             order_id: None,
             reservation_type: ReservationType::NonBlocking,
+            down_payment_id: None,
+            final_payment_id: None,
             status: ReservationStatus::Pending,
             customer_name: String::new(),
             customer_phone: String::new(),
@@ -196,6 +248,8 @@ impl Reservation {
             confirm_by: None,
             checked_in_by: None,
             checked_out_by: None,
+            cancelled_by: None,
+            cancellation_reason: None,
             check_in_time: None,
             check_out_time: None,
             notes: String::new(),
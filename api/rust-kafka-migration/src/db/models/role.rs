@@ -41,6 +41,10 @@ pub struct Role {
     #[serde(default, deserialize_with = "crate::utils::serde_utils::deserialize_vec_or_single")]
     pub permissions: Vec<Permission>,
     
+    /// Largest manual discount, as a percent, the role may give without approval
+    #[serde(rename = "maxDiscountPercent", default)]
+    pub max_discount_percent: Option<f64>,
+    
     #[serde(rename = "createdAt", skip_serializing_if = "Option::is_none")]
     pub created_at: Option<mongodb::bson::DateTime>,
     
@@ -55,6 +59,7 @@ impl Role {
             name,
             description,
             permissions,
+            max_discount_percent: None,
             created_at: None,
             updated_at: None,
         }
@@ -63,4 +68,13 @@ impl Role {
     pub fn has_permission(&self, permission: &Permission) -> bool {
         self.permissions.contains(permission) || self.permissions.contains(&Permission::Superadmin)
     }
+
+    /// Manual discount limit in percent; superadmins are unlimited, roles without a limit get none
+    pub fn discount_limit_percent(&self) -> f64 {
+        if self.permissions.contains(&Permission::Superadmin) {
+            100.0
+        } else {
+            self.max_discount_percent.unwrap_or(0.0).clamp(0.0, 100.0)
+        }
+    }
 }
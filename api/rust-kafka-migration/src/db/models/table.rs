@@ -2,7 +2,7 @@ use bson::oid::ObjectId;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum TableStatus {
     Available,
@@ -10,6 +10,31 @@ pub enum TableStatus {
     Reserved,
 }
 
+impl TableStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TableStatus::Available => "available",
+            TableStatus::Occupied => "occupied",
+            TableStatus::Reserved => "reserved",
+        }
+    }
+}
+
+impl std::fmt::Display for TableStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for TableStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_json::from_value(serde_json::Value::String(s.to_lowercase()))
+            .map_err(|_| format!("Unknown table status: {}", s))
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StatusHistoryEntry {
     #[serde(rename = "fromStatus")]
@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 use mongodb::bson::oid::ObjectId;
-use chrono::{DateTime, Utc};
+
+use crate::common::Money;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct TaxAndService {
@@ -14,7 +15,7 @@ pub struct TaxAndService {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub percentage: Option<f64>,
     #[serde(rename = "fixedFee", skip_serializing_if = "Option::is_none")]
-    pub fixed_fee: Option<f64>,
+    pub fixed_fee: Option<Money>,
     #[serde(rename = "appliesToOutlets", default)]
     pub applies_to_outlets: Vec<ObjectId>,
     #[serde(rename = "appliesToMenuItems", default)]
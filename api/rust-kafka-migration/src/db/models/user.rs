@@ -5,16 +5,13 @@ use serde::{Deserialize, Serialize};
 /// Authentication type
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
+#[derive(Default)]
 pub enum AuthType {
+    #[default]
     Local,
     Google,
 }
 
-impl Default for AuthType {
-    fn default() -> Self {
-        Self::Local
-    }
-}
 
 /// Cashier type enum
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -59,6 +56,10 @@ pub struct User {
     #[serde(skip_serializing)]
     pub password: String,
     
+    /// Hashed supervisor PIN for approving discounts - never send to client
+    #[serde(default, skip_serializing)]
+    pub pin: Option<String>,
+    
     #[serde(default, deserialize_with = "crate::utils::serde_utils::deserialize_vec_or_single")]
     pub address: Vec<String>,
     
@@ -117,6 +118,7 @@ impl User {
             email,
             phone: None,
             password,
+            pin: None,
             address: Vec::new(),
             profile_picture: "https://img.freepik.com/premium-vector/man-avatar-profile-picture-vector-illustration_268834-538.jpg".to_string(),
             role,
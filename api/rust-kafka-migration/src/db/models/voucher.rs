@@ -1,6 +1,5 @@
 use serde::{Deserialize, Serialize};
 use mongodb::bson::oid::ObjectId;
-use chrono::{DateTime, Utc};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Voucher {
@@ -43,6 +42,8 @@ fn default_true() -> bool { true }
 pub struct VoucherUsage {
     #[serde(rename = "userId", skip_serializing_if = "Option::is_none")]
     pub user_id: Option<ObjectId>,
+    #[serde(rename = "orderId", skip_serializing_if = "Option::is_none")]
+    pub order_id: Option<ObjectId>,
     #[serde(rename = "usedAt", default = "mongodb::bson::DateTime::now")]
     pub used_at: mongodb::bson::DateTime,
 }
@@ -0,0 +1,147 @@
+use bson::{doc, oid::ObjectId, Document};
+use futures::stream::TryStreamExt;
+use mongodb::{
+    options::{FindOneAndUpdateOptions, FindOptions, IndexOptions, ReturnDocument},
+    Collection, IndexModel,
+};
+use std::sync::Arc;
+
+use crate::db::models::{CashMovement, CashierShift, ShiftStatus};
+use crate::db::repositories::counter_repository::is_duplicate_key;
+use crate::db::DbConnection;
+use crate::error::{AppError, AppResult};
+
+/// Filters for listing shifts; empty fields do not constrain the result
+#[derive(Debug, Clone, Default)]
+pub struct ShiftFilter {
+    pub outlet: Option<ObjectId>,
+    pub cashier: Option<ObjectId>,
+    pub status: Option<ShiftStatus>,
+    pub opened_from: Option<bson::DateTime>,
+    pub opened_to: Option<bson::DateTime>,
+}
+
+impl ShiftFilter {
+    pub fn to_document(&self) -> Document {
+        let mut filter = doc! {};
+
+        if let Some(outlet) = self.outlet {
+            filter.insert("outletId", outlet);
+        }
+        if let Some(cashier) = self.cashier {
+            filter.insert("cashierId", cashier);
+        }
+        if let Some(status) = self.status {
+            filter.insert("status", status.as_str());
+        }
+        let mut opened_at = doc! {};
+        if let Some(from) = self.opened_from {
+            opened_at.insert("$gte", from);
+        }
+        if let Some(to) = self.opened_to {
+            opened_at.insert("$lt", to);
+        }
+        if !opened_at.is_empty() {
+            filter.insert("openedAt", opened_at);
+        }
+
+        filter
+    }
+}
+
+#[derive(Clone)]
+pub struct CashierShiftRepository {
+    collection: Collection<CashierShift>,
+}
+
+impl CashierShiftRepository {
+    pub fn new(db: Arc<DbConnection>) -> Self {
+        Self {
+            collection: db.collection("cashier_shifts"),
+        }
+    }
+
+    /// A cashier may have only one open shift at a time
+    pub async fn ensure_indexes(&self) -> AppResult<()> {
+        let one_open = IndexModel::builder()
+            .keys(doc! { "cashierId": 1 })
+            .options(
+                IndexOptions::builder()
+                    .name("one_open_shift_per_cashier".to_string())
+                    .unique(true)
+                    .partial_filter_expression(doc! { "status": ShiftStatus::Open.as_str() })
+                    .build(),
+            )
+            .build();
+        let listing = IndexModel::builder()
+            .keys(doc! { "outletId": 1, "openedAt": -1 })
+            .options(IndexOptions::builder().name("outlet_opened_listing".to_string()).build())
+            .build();
+
+        self.collection.create_indexes(vec![one_open, listing], None).await?;
+        Ok(())
+    }
+
+    /// Insert a new open shift; a conflict if the cashier already has one open
+    pub async fn create(&self, shift: &CashierShift) -> AppResult<ObjectId> {
+        let result = self.collection.insert_one(shift, None).await.map_err(|e| {
+            if is_duplicate_key(&e) {
+                AppError::Conflict("Cashier already has an open shift".to_string())
+            } else {
+                e.into()
+            }
+        })?;
+
+        result.inserted_id.as_object_id()
+            .ok_or_else(|| AppError::Internal("Failed to get inserted shift ID".to_string()))
+    }
+
+    pub async fn find_by_id(&self, id: &ObjectId) -> AppResult<Option<CashierShift>> {
+        Ok(self.collection.find_one(doc! { "_id": id }, None).await?)
+    }
+
+    pub async fn find_open_for_cashier(&self, cashier: &ObjectId) -> AppResult<Option<CashierShift>> {
+        Ok(self.collection.find_one(
+            doc! { "cashierId": cashier, "status": ShiftStatus::Open.as_str() },
+            None,
+        ).await?)
+    }
+
+    /// Shifts matching the filter, most recently opened first
+    pub async fn find(&self, filter: &ShiftFilter, limit: i64) -> AppResult<Vec<CashierShift>> {
+        let options = FindOptions::builder()
+            .sort(doc! { "openedAt": -1 })
+            .limit(limit)
+            .build();
+        Ok(self.collection.find(filter.to_document(), options).await?.try_collect().await?)
+    }
+
+    /// Append a cash movement to a shift that is still open
+    pub async fn push_movement(&self, id: &ObjectId, movement: &CashMovement) -> AppResult<Option<CashierShift>> {
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
+        Ok(self.collection.find_one_and_update(
+            doc! { "_id": id, "status": ShiftStatus::Open.as_str() },
+            doc! {
+                "$push": { "movements": bson::to_bson(movement)? },
+                "$set": { "updatedAt": bson::DateTime::now() },
+            },
+            options,
+        ).await?)
+    }
+
+    /// Apply `set` to a shift that is still open, returning it afterwards,
+    /// or None if it was closed in the meantime
+    pub async fn update_open(&self, id: &ObjectId, mut set: Document) -> AppResult<Option<CashierShift>> {
+        set.insert("updatedAt", bson::DateTime::now());
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
+        Ok(self.collection.find_one_and_update(
+            doc! { "_id": id, "status": ShiftStatus::Open.as_str() },
+            doc! { "$set": set },
+            options,
+        ).await?)
+    }
+}
@@ -0,0 +1,84 @@
+use bson::{doc, Document};
+use mongodb::{
+    error::ErrorKind,
+    options::{FindOneAndUpdateOptions, ReturnDocument},
+    Collection,
+};
+use std::sync::Arc;
+
+use crate::db::DbConnection;
+use crate::error::{AppError, AppResult};
+
+/// Duplicate key error code returned when two upserts race on a new counter
+const DUPLICATE_KEY: i32 = 11000;
+
+/// Daily sequence counters, one document per (prefix, business date).
+///
+/// Counters are shared by all outlets: the codes they number carry no outlet,
+/// and must stay unique across the business for the Node backend's unique
+/// indexes and as payment gateway references.
+#[derive(Clone)]
+pub struct CounterRepository {
+    collection: Collection<Document>,
+}
+
+pub(crate) fn is_duplicate_key(error: &mongodb::error::Error) -> bool {
+    match error.kind.as_ref() {
+        ErrorKind::Write(mongodb::error::WriteFailure::WriteError(e)) => e.code == DUPLICATE_KEY,
+        ErrorKind::Command(e) => e.code == DUPLICATE_KEY,
+        _ => false,
+    }
+}
+
+impl CounterRepository {
+    pub fn new(db: Arc<DbConnection>) -> Self {
+        Self {
+            collection: db.collection("counters"),
+        }
+    }
+
+    /// Atomically increment and return the counter for a prefix and business
+    /// date. The first call of the day returns 1.
+    pub async fn next_sequence(&self, prefix: &str, business_date: &str) -> AppResult<i64> {
+        let id = format!("global:{}:{}", prefix, business_date);
+
+        let filter = doc! { "_id": &id };
+        let update = doc! {
+            "$inc": { "seq": 1_i64 },
+            "$set": { "updatedAt": bson::DateTime::now() },
+            "$setOnInsert": {
+                "prefix": prefix,
+                "date": business_date,
+                "createdAt": bson::DateTime::now(),
+            },
+        };
+        let options = FindOneAndUpdateOptions::builder()
+            .upsert(true)
+            .return_document(ReturnDocument::After)
+            .build();
+
+        // Two first-of-the-day upserts can race on the same _id; the loser retries
+        // and increments the document the winner created.
+        let mut attempt = 0;
+        let counter = loop {
+            match self
+                .collection
+                .find_one_and_update(filter.clone(), update.clone(), options.clone())
+                .await
+            {
+                Ok(counter) => break counter,
+                Err(e) if is_duplicate_key(&e) && attempt < 3 => attempt += 1,
+                Err(e) => return Err(e.into()),
+            }
+        };
+
+        let counter = counter
+            .ok_or_else(|| AppError::Internal(format!("Counter {} was not created", id)))?;
+
+        match counter.get("seq") {
+            Some(bson::Bson::Int64(seq)) => Ok(*seq),
+            Some(bson::Bson::Int32(seq)) => Ok(*seq as i64),
+            _ => Err(AppError::Internal(format!("Counter {} has no sequence", id))),
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use bson::{doc, oid::ObjectId, Document};
+use futures::stream::TryStreamExt;
+use mongodb::{options::FindOptions, ClientSession, Collection};
+use std::sync::Arc;
+
+use crate::db::DbConnection;
+use crate::db::models::DiscountAudit;
+use crate::error::AppResult;
+
+/// Filters for the discount audit trail; empty fields do not constrain the result
+#[derive(Debug, Clone, Default)]
+pub struct DiscountAuditFilter {
+    pub outlet: Option<ObjectId>,
+    pub granted_by: Option<ObjectId>,
+    pub approved_by: Option<ObjectId>,
+    /// Inclusive lower bound on `discount.createdAt`
+    pub created_from: Option<bson::DateTime>,
+    /// Exclusive upper bound on `discount.createdAt`
+    pub created_to: Option<bson::DateTime>,
+}
+
+impl DiscountAuditFilter {
+    pub fn to_document(&self) -> Document {
+        let mut filter = doc! {};
+
+        if let Some(outlet) = self.outlet {
+            filter.insert("outlet", outlet);
+        }
+        if let Some(granted_by) = self.granted_by {
+            filter.insert("discount.grantedBy", granted_by);
+        }
+        if let Some(approved_by) = self.approved_by {
+            filter.insert("discount.approvedBy", approved_by);
+        }
+        let mut created = doc! {};
+        if let Some(from) = self.created_from {
+            created.insert("$gte", from);
+        }
+        if let Some(to) = self.created_to {
+            created.insert("$lt", to);
+        }
+        if !created.is_empty() {
+            filter.insert("discount.createdAt", created);
+        }
+
+        filter
+    }
+}
+
+#[derive(Clone)]
+pub struct DiscountAuditRepository {
+    collection: Collection<DiscountAudit>,
+}
+
+impl DiscountAuditRepository {
+    pub fn new(db: Arc<DbConnection>) -> Self {
+        Self {
+            collection: db.collection("discountaudits"),
+        }
+    }
+
+    pub async fn create_many_with_session(&self, audits: &[DiscountAudit], session: &mut ClientSession) -> AppResult<()> {
+        if !audits.is_empty() {
+            self.collection.insert_many_with_session(audits, None, session).await?;
+        }
+        Ok(())
+    }
+
+    /// Audit entries matching the filter, oldest first
+    pub async fn find(&self, filter: &DiscountAuditFilter) -> AppResult<Vec<DiscountAudit>> {
+        let options = FindOptions::builder().sort(doc! { "discount.createdAt": 1 }).build();
+        let cursor = self.collection.find(filter.to_document(), options).await?;
+        Ok(cursor.try_collect().await?)
+    }
+}
@@ -22,10 +22,10 @@ impl EventRepository {
 
     pub async fn create_event(&self, event: Event) -> AppResult<ObjectId> {
         let result = self.event_collection.insert_one(event, None).await?;
-        Ok(result
+        result
             .inserted_id
             .as_object_id()
-            .ok_or_else(|| AppError::Internal("Failed to get inserted event ID".to_string()))?)
+            .ok_or_else(|| AppError::Internal("Failed to get inserted event ID".to_string()))
     }
 
     pub async fn find_by_id(&self, id: &ObjectId) -> AppResult<Option<Event>> {
@@ -48,7 +48,7 @@ impl EventRepository {
     }
 
     pub async fn update_event(&self, id: &ObjectId, event: Event) -> AppResult<()> {
-        let update_doc = bson::to_document(&event).map_err(|e| AppError::BsonSerialization(e))?;
+        let update_doc = bson::to_document(&event).map_err(AppError::BsonSerialization)?;
 
         self.event_collection
             .update_one(doc! { "_id": id }, doc! { "$set": update_doc }, None)
@@ -0,0 +1,47 @@
+use bson::doc;
+use mongodb::{options::ReplaceOptions, Collection};
+use std::sync::Arc;
+
+use crate::db::DbConnection;
+use crate::db::models::GoSendBooking;
+use crate::error::AppResult;
+
+#[derive(Clone)]
+pub struct GoSendBookingRepository {
+    collection: Collection<GoSendBooking>,
+}
+
+impl GoSendBookingRepository {
+    pub fn new(db: Arc<DbConnection>) -> Self {
+        Self {
+            collection: db.collection("gosendbookings"),
+        }
+    }
+
+    /// Booking of an order; `order_id` is unique, so an order has at most one
+    pub async fn find_by_order_id(&self, order_id: &str) -> AppResult<Option<GoSendBooking>> {
+        Ok(self.collection.find_one(doc! { "order_id": order_id }, None).await?)
+    }
+
+    /// Store the order's booking, replacing a closed one when the order is re-booked
+    pub async fn save(&self, booking: &GoSendBooking) -> AppResult<()> {
+        let options = ReplaceOptions::builder().upsert(true).build();
+        self.collection
+            .replace_one(doc! { "order_id": &booking.order_id }, booking, options)
+            .await?;
+        Ok(())
+    }
+
+    /// Close a booking after it was cancelled with GoSend
+    pub async fn mark_cancelled(&self, gosend_order_no: &str) -> AppResult<()> {
+        let now = bson::DateTime::now();
+        self.collection
+            .update_one(
+                doc! { "goSend_order_no": gosend_order_no },
+                doc! { "$set": { "status": "cancelled", "timestamps.order_closed": now, "updatedAt": now } },
+                None,
+            )
+            .await?;
+        Ok(())
+    }
+}
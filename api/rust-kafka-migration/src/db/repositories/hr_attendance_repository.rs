@@ -76,8 +76,8 @@ impl AttendanceRepository {
     pub async fn create(&self, attendance: Attendance) -> AppResult<ObjectId> {
         let result = self.collection.insert_one(attendance, None).await?;
         
-        Ok(result.inserted_id.as_object_id()
-            .ok_or_else(|| AppError::Internal("Failed to get inserted attendance ID".to_string()))?)
+        result.inserted_id.as_object_id()
+            .ok_or_else(|| AppError::Internal("Failed to get inserted attendance ID".to_string()))
     }
 
     /// Update check out
@@ -58,8 +58,8 @@ impl CompanyRepository {
     pub async fn create(&self, company: Company) -> AppResult<ObjectId> {
         let result = self.collection.insert_one(company, None).await?;
         
-        Ok(result.inserted_id.as_object_id()
-            .ok_or_else(|| AppError::Internal("Failed to get inserted company ID".to_string()))?)
+        result.inserted_id.as_object_id()
+            .ok_or_else(|| AppError::Internal("Failed to get inserted company ID".to_string()))
     }
 
     /// Update company
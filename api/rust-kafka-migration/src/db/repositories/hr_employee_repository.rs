@@ -142,8 +142,8 @@ impl EmployeeRepository {
     pub async fn create(&self, employee: Employee) -> AppResult<ObjectId> {
         let result = self.collection.insert_one(employee, None).await?;
         
-        Ok(result.inserted_id.as_object_id()
-            .ok_or_else(|| AppError::Internal("Failed to get inserted employee ID".to_string()))?)
+        result.inserted_id.as_object_id()
+            .ok_or_else(|| AppError::Internal("Failed to get inserted employee ID".to_string()))
     }
 
     /// Update employee
@@ -55,8 +55,8 @@ impl FingerprintRepository {
     /// Create new fingerprint
     pub async fn create(&self, fingerprint: Fingerprint) -> AppResult<ObjectId> {
         let result = self.collection.insert_one(fingerprint, None).await?;
-        Ok(result.inserted_id.as_object_id()
-            .ok_or_else(|| AppError::Internal("Failed to get inserted fingerprint ID".to_string()))?)
+        result.inserted_id.as_object_id()
+            .ok_or_else(|| AppError::Internal("Failed to get inserted fingerprint ID".to_string()))
     }
 
     /// Update fingerprint data/status
@@ -85,8 +85,8 @@ impl FingerprintRepository {
     /// Create raw fingerprint entry
     pub async fn create_raw(&self, raw: RawFingerprint) -> AppResult<ObjectId> {
         let result = self.raw_collection.insert_one(raw, None).await?;
-        Ok(result.inserted_id.as_object_id()
-            .ok_or_else(|| AppError::Internal("Failed to get inserted raw fingerprint ID".to_string()))?)
+        result.inserted_id.as_object_id()
+            .ok_or_else(|| AppError::Internal("Failed to get inserted raw fingerprint ID".to_string()))
     }
 
     /// Mark raw fingerprint as mapped
@@ -1,4 +1,4 @@
-use bson::{doc, oid::ObjectId, Document};
+use bson::{doc, oid::ObjectId};
 use mongodb::{Collection, options::FindOptions};
 use std::sync::Arc;
 use futures::TryStreamExt;
@@ -62,8 +62,8 @@ impl SalaryRepository {
     pub async fn create(&self, salary: Salary) -> AppResult<ObjectId> {
         let result = self.collection.insert_one(salary, None).await?;
         
-        Ok(result.inserted_id.as_object_id()
-            .ok_or_else(|| AppError::Internal("Failed to get inserted salary ID".to_string()))?)
+        result.inserted_id.as_object_id()
+            .ok_or_else(|| AppError::Internal("Failed to get inserted salary ID".to_string()))
     }
 
     /// Update salary status (e.g., Approve, Paid)
@@ -1,9 +1,9 @@
-use bson::{doc, oid::ObjectId, Document};
+use bson::{doc, oid::ObjectId};
 use mongodb::Collection;
 use std::sync::Arc;
 
 use crate::db::DbConnection;
-use crate::db::models::{HRSetting, CompanySettings, AttendanceSettings, SalaryCalculationSettings, BpjsSettings, DeductionSettings};
+use crate::db::models::{HRSetting, AttendanceSettings};
 use crate::error::{AppError, AppResult};
 
 #[derive(Clone)]
@@ -27,14 +27,14 @@ impl HRSettingRepository {
     pub async fn create(&self, settings: HRSetting) -> AppResult<ObjectId> {
         let result = self.collection.insert_one(settings, None).await?;
         
-        Ok(result.inserted_id.as_object_id()
-            .ok_or_else(|| AppError::Internal("Failed to get inserted settings ID".to_string()))?)
+        result.inserted_id.as_object_id()
+            .ok_or_else(|| AppError::Internal("Failed to get inserted settings ID".to_string()))
     }
 
     /// Update entire settings
     pub async fn update(&self, company_id: &ObjectId, settings: HRSetting) -> AppResult<()> {
         let settings_doc = bson::to_document(&settings)
-            .map_err(|e| AppError::BsonSerialization(e))?;
+            .map_err(AppError::BsonSerialization)?;
 
         self.collection.update_one(
             doc! { "company": company_id },
@@ -52,7 +52,7 @@ impl HRSettingRepository {
         settings: AttendanceSettings,
     ) -> AppResult<()> {
         let doc = bson::to_document(&settings)
-            .map_err(|e| AppError::BsonSerialization(e))?;
+            .map_err(AppError::BsonSerialization)?;
 
         self.collection.update_one(
             doc! { "company": company_id },
@@ -1,5 +1,6 @@
 use bson::{doc, oid::ObjectId};
-use mongodb::{Collection, options::FindOneAndUpdateOptions};
+use futures::stream::TryStreamExt;
+use mongodb::{ClientSession, Collection, options::FindOneAndUpdateOptions};
 use std::sync::Arc;
 
 use crate::db::DbConnection;
@@ -54,7 +55,7 @@ impl InventoryRepository {
         let warehouse_id = stock.warehouse_id;
         
         let update_doc = bson::to_document(&stock)
-            .map_err(|e| AppError::BsonSerialization(e))?;
+            .map_err(AppError::BsonSerialization)?;
 
         self.menu_stock_collection.update_one(
             doc! { "menuItemId": menu_item_id, "warehouseId": warehouse_id },
@@ -89,7 +90,7 @@ impl InventoryRepository {
 
         let update = doc! {
             "$inc": { "currentStock": quantity_change, "version": 1 },
-            "$push": { "movements": bson::to_bson(&movement).map_err(|e| AppError::BsonSerialization(e))? }
+            "$push": { "movements": bson::to_bson(&movement).map_err(AppError::BsonSerialization)? }
         };
 
         let options = FindOneAndUpdateOptions::builder()
@@ -100,4 +101,61 @@ impl InventoryRepository {
 
         result.ok_or_else(|| AppError::Conflict("Product stock version mismatch or not found".to_string()))
     }
+
+    /// Product stocks that have movements referencing the given document
+    pub async fn find_product_stocks_by_reference_with_session(
+        &self,
+        reference_id: &ObjectId,
+        session: &mut ClientSession,
+    ) -> AppResult<Vec<ProductStock>> {
+        let mut cursor = self.product_stock_collection
+            .find_with_session(doc! { "movements.referenceId": reference_id }, None, session)
+            .await?;
+        Ok(cursor.stream(session).try_collect().await?)
+    }
+
+    /// Apply a stock change inside a transaction, bumping the version
+    pub async fn apply_product_movement_with_session(
+        &self,
+        stock_id: &ObjectId,
+        quantity_change: f64,
+        movement: ProductMovement,
+        session: &mut ClientSession,
+    ) -> AppResult<()> {
+        self.product_stock_collection.update_one_with_session(
+            doc! { "_id": stock_id },
+            doc! {
+                "$inc": { "currentStock": quantity_change, "version": 1 },
+                "$push": { "movements": bson::to_bson(&movement).map_err(AppError::BsonSerialization)? }
+            },
+            None,
+            session,
+        ).await?;
+        Ok(())
+    }
+
+    /// Apply a stock change to a product in a warehouse inside a transaction,
+    /// creating the stock record if the warehouse has none yet
+    pub async fn apply_warehouse_movement_with_session(
+        &self,
+        product_id: &ObjectId,
+        warehouse_id: &ObjectId,
+        quantity_change: f64,
+        movement: ProductMovement,
+        session: &mut ClientSession,
+    ) -> AppResult<()> {
+        let now = bson::DateTime::now();
+        self.product_stock_collection.update_one_with_session(
+            doc! { "productId": product_id, "warehouse": warehouse_id },
+            doc! {
+                "$inc": { "currentStock": quantity_change, "version": 1 },
+                "$push": { "movements": bson::to_bson(&movement).map_err(AppError::BsonSerialization)? },
+                "$set": { "updatedAt": now },
+                "$setOnInsert": { "minStock": 0.0, "createdAt": now }
+            },
+            mongodb::options::UpdateOptions::builder().upsert(true).build(),
+            session,
+        ).await?;
+        Ok(())
+    }
 }
@@ -24,8 +24,8 @@ impl MenuRepository {
 
     pub async fn create_category(&self, category: Category) -> AppResult<ObjectId> {
         let result = self.category_collection.insert_one(category, None).await?;
-        Ok(result.inserted_id.as_object_id()
-            .ok_or_else(|| AppError::Internal("Failed to get inserted category ID".to_string()))?)
+        result.inserted_id.as_object_id()
+            .ok_or_else(|| AppError::Internal("Failed to get inserted category ID".to_string()))
     }
 
     pub async fn find_category_by_id(&self, id: &ObjectId) -> AppResult<Option<Category>> {
@@ -43,7 +43,7 @@ impl MenuRepository {
 
     pub async fn update_category(&self, id: &ObjectId, category: Category) -> AppResult<()> {
         let update_doc = bson::to_document(&category)
-            .map_err(|e| AppError::BsonSerialization(e))?;
+            .map_err(AppError::BsonSerialization)?;
 
         self.category_collection.update_one(
             doc! { "_id": id },
@@ -62,8 +62,8 @@ impl MenuRepository {
 
     pub async fn create_menu_item(&self, item: MenuItem) -> AppResult<ObjectId> {
         let result = self.menu_collection.insert_one(item, None).await?;
-        Ok(result.inserted_id.as_object_id()
-            .ok_or_else(|| AppError::Internal("Failed to get inserted menu item ID".to_string()))?)
+        result.inserted_id.as_object_id()
+            .ok_or_else(|| AppError::Internal("Failed to get inserted menu item ID".to_string()))
     }
 
     pub async fn find_menu_item_by_id(&self, id: &ObjectId) -> AppResult<Option<MenuItem>> {
@@ -108,7 +108,7 @@ impl MenuRepository {
 
     pub async fn update_menu_item(&self, id: &ObjectId, item: MenuItem) -> AppResult<()> {
         let update_doc = bson::to_document(&item)
-            .map_err(|e| AppError::BsonSerialization(e))?;
+            .map_err(AppError::BsonSerialization)?;
 
         self.menu_collection.update_one(
             doc! { "_id": id },
@@ -1,19 +1,37 @@
+pub mod cashier_shift_repository;
+pub mod counter_repository;
+pub mod discount_audit_repository;
 pub mod event_repository;
+pub mod gosend_booking_repository;
 pub mod inventory_repository;
 pub mod marketlist_repository;
 pub mod menu_repository;
 pub mod order_repository;
 pub mod outlet_repository;
+pub mod payment_notification_repository;
 pub mod payment_repository;
+pub mod refund_repository;
+pub mod reservation_repository;
+pub mod revision_repository;
+pub mod table_repository;
 pub mod user_repository;
 
+pub use cashier_shift_repository::{CashierShiftRepository, ShiftFilter};
+pub use counter_repository::CounterRepository;
+pub use discount_audit_repository::{DiscountAuditFilter, DiscountAuditRepository};
 pub use event_repository::EventRepository;
+pub use gosend_booking_repository::GoSendBookingRepository;
 pub use inventory_repository::InventoryRepository;
 pub use marketlist_repository::MarketListRepository;
 pub use menu_repository::MenuRepository;
 pub use order_repository::*;
 pub use outlet_repository::OutletRepository;
+pub use payment_notification_repository::PaymentNotificationRepository;
 pub use payment_repository::PaymentRepository;
+pub use refund_repository::RefundRepository;
+pub use reservation_repository::{ReservationFilter, ReservationRepository};
+pub use revision_repository::RevisionRepository;
+pub use table_repository::TableRepository;
 pub use user_repository::UserRepository;
 
 // HR Repositories
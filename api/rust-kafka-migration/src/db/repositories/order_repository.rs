@@ -1,11 +1,145 @@
-use bson::{doc, oid::ObjectId};
-use mongodb::Collection;
+use bson::{doc, oid::ObjectId, Bson, Document};
+use futures::stream::TryStreamExt;
+use mongodb::{
+    options::{FindOptions, IndexOptions},
+    ClientSession, Collection, IndexModel,
+};
 use std::sync::Arc;
 
 use crate::db::DbConnection;
-use crate::db::models::order::Order;
+use crate::db::models::order::{DeliveryTracking, Order, OrderStatus, OrderStatusHistoryEntry};
 use crate::error::{AppError, AppResult};
 
+/// Filters for listing orders; empty fields do not constrain the result
+#[derive(Debug, Clone, Default)]
+pub struct OrderFilter {
+    pub outlet: Option<ObjectId>,
+    pub statuses: Vec<OrderStatus>,
+    pub sources: Vec<String>,
+    pub order_types: Vec<String>,
+    pub cashier: Option<ObjectId>,
+    pub table_number: Option<String>,
+    pub payment_statuses: Vec<String>,
+    /// Inclusive lower bound on `createdAtWIB`
+    pub created_from: Option<bson::DateTime>,
+    /// Exclusive upper bound on `createdAtWIB`
+    pub created_to: Option<bson::DateTime>,
+    /// Case-insensitive match on order ID or customer name
+    pub search: Option<String>,
+}
+
+fn in_or_eq<T: Into<Bson> + Clone>(values: &[T]) -> Bson {
+    match values {
+        [single] => single.clone().into(),
+        _ => Bson::Document(doc! { "$in": values.iter().cloned().map(Into::into).collect::<Vec<Bson>>() }),
+    }
+}
+
+/// Escape user input for use inside a `$regex`
+fn escape_regex(input: &str) -> String {
+    let mut escaped = String::with_capacity(input.len());
+    for c in input.chars() {
+        if "\\.+*?()|[]{}^$".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+impl OrderFilter {
+    pub fn to_document(&self) -> Document {
+        let mut filter = doc! {};
+
+        if let Some(outlet) = self.outlet {
+            filter.insert("outlet", outlet);
+        }
+        if !self.statuses.is_empty() {
+            let statuses: Vec<&str> = self.statuses.iter().map(|s| s.as_str()).collect();
+            filter.insert("status", in_or_eq(&statuses));
+        }
+        if !self.sources.is_empty() {
+            filter.insert("source", in_or_eq(&self.sources));
+        }
+        if !self.order_types.is_empty() {
+            filter.insert("orderType", in_or_eq(&self.order_types));
+        }
+        if let Some(cashier) = self.cashier {
+            filter.insert("cashierId", cashier);
+        }
+        if let Some(table) = &self.table_number {
+            filter.insert("tableNumber", table);
+        }
+        if !self.payment_statuses.is_empty() {
+            filter.insert("paymentStatus", in_or_eq(&self.payment_statuses));
+        }
+
+        let mut created = doc! {};
+        if let Some(from) = self.created_from {
+            created.insert("$gte", from);
+        }
+        if let Some(to) = self.created_to {
+            created.insert("$lt", to);
+        }
+        if !created.is_empty() {
+            filter.insert("createdAtWIB", created);
+        }
+
+        if let Some(search) = self.search.as_deref().map(str::trim).filter(|s| !s.is_empty()) {
+            let pattern = doc! { "$regex": escape_regex(search), "$options": "i" };
+            filter.insert(
+                "$or",
+                vec![
+                    doc! { "order_id": pattern.clone() },
+                    doc! { "user": pattern },
+                ],
+            );
+        }
+
+        filter
+    }
+}
+
+/// Position after the last order of a page. Orders are listed newest first
+/// by `createdAtWIB`, with `_id` breaking ties.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OrderCursor {
+    pub created_at: bson::DateTime,
+    pub id: ObjectId,
+}
+
+impl OrderCursor {
+    pub fn of(order: &Order) -> Option<Self> {
+        order.id.map(|id| Self {
+            created_at: order.created_at_wib,
+            id,
+        })
+    }
+
+    /// Opaque string handed to clients
+    pub fn encode(&self) -> String {
+        hex::encode(format!("{}:{}", self.created_at.timestamp_millis(), self.id.to_hex()))
+    }
+
+    pub fn decode(cursor: &str) -> Option<Self> {
+        let raw = String::from_utf8(hex::decode(cursor).ok()?).ok()?;
+        let (millis, id) = raw.split_once(':')?;
+        Some(Self {
+            created_at: bson::DateTime::from_millis(millis.parse().ok()?),
+            id: ObjectId::parse_str(id).ok()?,
+        })
+    }
+
+    fn to_document(self) -> Document {
+        doc! {
+            "$or": [
+                { "createdAtWIB": { "$lt": self.created_at } },
+                { "createdAtWIB": self.created_at, "_id": { "$lt": self.id } },
+            ]
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct OrderRepository {
     collection: Collection<Order>,
@@ -18,12 +152,81 @@ impl OrderRepository {
         }
     }
 
-    /// Create a new order
-    pub async fn create(&self, order: Order) -> AppResult<ObjectId> {
-        let result = self.collection.insert_one(order, None).await?;
-        
-        Ok(result.inserted_id.as_object_id()
-            .ok_or_else(|| AppError::Internal("Failed to get inserted order ID".to_string()))?)
+    /// Create the indexes used by order listing.
+    ///
+    /// Lookups by `order_id` use the Node backend's unique `order_id_1` index.
+    /// Each index is created on its own so one that conflicts with an existing
+    /// index does not keep the others from being built.
+    pub async fn ensure_indexes(&self) -> AppResult<()> {
+        let indexes = [
+            (doc! { "outlet": 1, "createdAtWIB": -1, "_id": -1 }, "outlet_created_listing"),
+            (doc! { "outlet": 1, "status": 1, "createdAtWIB": -1 }, "outlet_status_listing"),
+            (doc! { "outlet": 1, "paymentStatus": 1, "createdAtWIB": -1 }, "outlet_payment_status_listing"),
+            (doc! { "outlet": 1, "tableNumber": 1, "createdAtWIB": -1 }, "outlet_table_listing"),
+            (doc! { "cashierId": 1, "createdAtWIB": -1 }, "cashier_listing"),
+        ];
+
+        let mut failed = Vec::new();
+        for (keys, name) in indexes {
+            let index = IndexModel::builder()
+                .keys(keys)
+                .options(IndexOptions::builder().name(name.to_string()).build())
+                .build();
+            if let Err(e) = self.collection.create_index(index, None).await {
+                failed.push(format!("{}: {}", name, e));
+            }
+        }
+
+        if failed.is_empty() {
+            Ok(())
+        } else {
+            Err(AppError::Internal(format!("Failed to create indexes {}", failed.join("; "))))
+        }
+    }
+
+    /// One page of orders matching `filter`, newest first.
+    ///
+    /// Returns the cursor for the next page, or `None` on the last page.
+    pub async fn list(
+        &self,
+        filter: &OrderFilter,
+        after: Option<OrderCursor>,
+        limit: i64,
+    ) -> AppResult<(Vec<Order>, Option<OrderCursor>)> {
+        let mut query = filter.to_document();
+        if let Some(cursor) = after {
+            query = match query.remove("$or") {
+                Some(search) => doc! { "$and": [query, { "$or": search }, cursor.to_document()] },
+                None => doc! { "$and": [query, cursor.to_document()] },
+            };
+        }
+
+        let options = FindOptions::builder()
+            .sort(doc! { "createdAtWIB": -1, "_id": -1 })
+            .limit(limit + 1)
+            .build();
+
+        let mut orders: Vec<Order> = self.collection.find(query, options).await?.try_collect().await?;
+
+        let next = if orders.len() as i64 > limit {
+            orders.truncate(limit as usize);
+            orders.last().and_then(OrderCursor::of)
+        } else {
+            None
+        };
+
+        Ok((orders, next))
+    }
+
+    /// Create a new order, inside the caller's transaction when a session is given
+    pub async fn create(&self, order: &Order, session: Option<&mut ClientSession>) -> AppResult<ObjectId> {
+        let result = match session {
+            Some(s) => self.collection.insert_one_with_session(order, None, s).await?,
+            None => self.collection.insert_one(order, None).await?,
+        };
+
+        result.inserted_id.as_object_id()
+            .ok_or_else(|| AppError::Internal("Failed to get inserted order ID".to_string()))
     }
 
     /// Find order by ID
@@ -36,6 +239,32 @@ impl OrderRepository {
         Ok(self.collection.find_one(doc! { "order_id": order_id }, None).await?)
     }
 
+    /// Orders with any of the given order_ids
+    pub async fn find_by_order_ids(&self, order_ids: &[String]) -> AppResult<Vec<Order>> {
+        if order_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        Ok(self.collection.find(doc! { "order_id": { "$in": order_ids } }, None).await?.try_collect().await?)
+    }
+
+    /// Orders that may hold cash tenders taken by `cashier` in `[from, to)`: tenders
+    /// it recorded then, or orders it created then and paid at the counter
+    pub async fn find_tendered_by_cashier(
+        &self,
+        cashier: &ObjectId,
+        from: bson::DateTime,
+        to: bson::DateTime,
+    ) -> AppResult<Vec<Order>> {
+        let window = doc! { "$gte": from, "$lt": to };
+        let filter = doc! {
+            "$or": [
+                { "payments": { "$elemMatch": { "processedBy": cashier, "processedAt": window.clone() } } },
+                { "cashierId": cashier, "createdAtWIB": window },
+            ],
+        };
+        Ok(self.collection.find(filter, None).await?.try_collect().await?)
+    }
+
     /// Find by order_id and outlet
     pub async fn find_by_order_id_and_outlet(&self, order_id: &str, outlet_id: &ObjectId) -> AppResult<Option<Order>> {
         Ok(self.collection.find_one(
@@ -47,6 +276,207 @@ impl OrderRepository {
         ).await?)
     }
 
+    /// Find by MongoDB `_id` or by `order_id`
+    pub async fn find_by_id_or_order_id(&self, id: &str) -> AppResult<Option<Order>> {
+        if let Ok(oid) = ObjectId::parse_str(id) {
+            if let Some(order) = self.find_by_id(&oid).await? {
+                return Ok(Some(order));
+            }
+        }
+        self.find_by_order_id(id).await
+    }
+
+    /// Move an order from `from` to `to`, appending a history entry.
+    /// Returns false if the order's status was changed concurrently.
+    pub async fn update_status(
+        &self,
+        id: &ObjectId,
+        from: OrderStatus,
+        to: OrderStatus,
+        entry: &OrderStatusHistoryEntry,
+        session: Option<&mut ClientSession>,
+    ) -> AppResult<bool> {
+        let now = bson::DateTime::now();
+        let filter = doc! { "_id": id, "status": from.as_str() };
+        let update = doc! {
+            "$set": {
+                "status": to.as_str(),
+                "updatedAt": now,
+                "updatedAtWIB": now,
+            },
+            "$push": { "statusHistory": bson::to_bson(entry)? }
+        };
+        let result = match session {
+            Some(s) => self.collection.update_one_with_session(filter, update, None, s).await?,
+            None => self.collection.update_one(filter, update, None).await?,
+        };
+
+        Ok(result.modified_count == 1)
+    }
+
+    /// Flag order lines whose ingredients have been taken out of stock
+    pub async fn mark_items_stock_deducted_with_session(
+        &self,
+        id: &ObjectId,
+        indexes: &[usize],
+        session: &mut ClientSession,
+    ) -> AppResult<()> {
+        if indexes.is_empty() {
+            return Ok(());
+        }
+        let mut set = doc! {};
+        for index in indexes {
+            set.insert(format!("items.{}.stockDeducted", index), true);
+        }
+        self.collection
+            .update_one_with_session(doc! { "_id": id }, doc! { "$set": set }, None, session)
+            .await?;
+        Ok(())
+    }
+
+    /// Cancel an order inside a transaction, recording what was rolled back.
+    /// Returns false if the order's status was changed concurrently.
+    pub async fn mark_canceled_with_session(
+        &self,
+        id: &ObjectId,
+        from: OrderStatus,
+        entry: &OrderStatusHistoryEntry,
+        reason: &str,
+        canceled_by_system: bool,
+        session: &mut ClientSession,
+    ) -> AppResult<bool> {
+        let now = bson::DateTime::now();
+        let result = self.collection.update_one_with_session(
+            doc! { "_id": id, "status": from.as_str() },
+            doc! {
+                "$set": {
+                    "status": OrderStatus::Canceled.as_str(),
+                    "cancellationReason": reason,
+                    "canceledBySystem": canceled_by_system,
+                    "stockRolledBack": true,
+                    "loyaltyRolledBack": true,
+                    "tableReleased": true,
+                    "updatedAt": now,
+                    "updatedAtWIB": now,
+                },
+                "$push": { "statusHistory": bson::to_bson(entry)? }
+            },
+            None,
+            session,
+        ).await?;
+
+        Ok(result.modified_count == 1)
+    }
+
+    /// Number of orders other than `exclude` still open at a table of an outlet
+    pub async fn count_open_for_table_with_session(
+        &self,
+        outlet: &ObjectId,
+        table_number: &str,
+        exclude: &ObjectId,
+        session: &mut ClientSession,
+    ) -> AppResult<u64> {
+        Ok(self.collection.count_documents_with_session(
+            doc! {
+                "outlet": outlet,
+                "tableNumber": table_number,
+                "_id": { "$ne": exclude },
+                "status": { "$in": open_statuses() },
+            },
+            None,
+            session,
+        ).await?)
+    }
+
+    /// Orders still open at any of the given tables of an outlet
+    pub async fn find_open_for_tables(&self, outlet: &ObjectId, table_numbers: &[String]) -> AppResult<Vec<Order>> {
+        if table_numbers.is_empty() {
+            return Ok(Vec::new());
+        }
+        let options = FindOptions::builder().sort(doc! { "createdAtWIB": 1 }).build();
+        Ok(self.collection.find(
+            doc! {
+                "outlet": outlet,
+                "tableNumber": { "$in": table_numbers },
+                "status": { "$in": open_statuses() },
+            },
+            options,
+        ).await?.try_collect().await?)
+    }
+
+    pub async fn mark_table_released_with_session(&self, id: &ObjectId, session: &mut ClientSession) -> AppResult<()> {
+        self.collection
+            .update_one_with_session(doc! { "_id": id }, doc! { "$set": { "tableReleased": true } }, None, session)
+            .await?;
+        Ok(())
+    }
+
+    /// Record the reservation an open order was made for, inside a transaction.
+    /// Returns false if the order is closed or belongs to another reservation.
+    pub async fn link_reservation_with_session(
+        &self,
+        id: &ObjectId,
+        reservation: &ObjectId,
+        session: &mut ClientSession,
+    ) -> AppResult<bool> {
+        let result = self.collection.update_one_with_session(
+            doc! {
+                "_id": id,
+                "status": { "$in": open_statuses() },
+                "$or": [
+                    { "originalReservationId": { "$exists": false } },
+                    { "originalReservationId": null },
+                    { "originalReservationId": reservation },
+                ],
+            },
+            doc! { "$set": { "originalReservationId": reservation, "updatedAt": bson::DateTime::now() } },
+            None,
+            session,
+        ).await?;
+
+        Ok(result.matched_count == 1)
+    }
+
+    /// Record the delivery provider's booking on an order
+    pub async fn set_delivery(
+        &self,
+        id: &ObjectId,
+        delivery_status: &str,
+        provider: &str,
+        tracking: &DeliveryTracking,
+    ) -> AppResult<()> {
+        self.collection.update_one(
+            doc! { "_id": id },
+            doc! { "$set": {
+                "deliveryStatus": delivery_status,
+                "deliveryProvider": provider,
+                "deliveryTracking": bson::to_bson(tracking)?,
+                "updatedAt": bson::DateTime::now(),
+            } },
+            None,
+        ).await?;
+        Ok(())
+    }
+
+    /// Web and App orders still pending and unpaid since before `cutoff`, oldest first
+    pub async fn find_unpaid_online_before(&self, cutoff: bson::DateTime, limit: i64) -> AppResult<Vec<Order>> {
+        let options = FindOptions::builder()
+            .sort(doc! { "createdAtWIB": 1 })
+            .limit(limit)
+            .build();
+        Ok(self.collection.find(
+            doc! {
+                "source": { "$in": ["Web", "App"] },
+                "status": OrderStatus::Pending.as_str(),
+                "paymentStatus": { "$ne": "Paid" },
+                "orderType": { "$ne": "Reservation" },
+                "isOpenBill": { "$ne": true },
+                "createdAtWIB": { "$lte": cutoff },
+            },
+            options,
+        ).await?.try_collect().await?)
+    }
+
     /// Count orders for a specific table today
     pub async fn count_orders_for_table_today(&self, table_number: &str) -> AppResult<u64> {
         let now = chrono::Utc::now();
@@ -73,4 +503,55 @@ impl OrderRepository {
         self.collection.replace_one(doc! { "_id": id }, order, None).await?;
         Ok(())
     }
+
+    /// Update an existing order inside a transaction
+    pub async fn update_with_session(&self, order: &Order, session: &mut ClientSession) -> AppResult<()> {
+        let id = order.id.ok_or_else(|| AppError::BadRequest("Order ID missing for update".to_string()))?;
+        self.collection.replace_one_with_session(doc! { "_id": id }, order, None, session).await?;
+        Ok(())
+    }
+}
+
+/// Statuses of orders that still hold their table
+fn open_statuses() -> Vec<&'static str> {
+    [OrderStatus::Pending, OrderStatus::Waiting, OrderStatus::Reserved, OrderStatus::OnProcess]
+        .iter()
+        .map(|s| s.as_str())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_document() {
+        let filter = OrderFilter {
+            statuses: vec![OrderStatus::Pending, OrderStatus::OnProcess],
+            sources: vec!["Cashier".to_string()],
+            search: Some(" ORD-0101 (A) ".to_string()),
+            ..OrderFilter::default()
+        };
+        let query = filter.to_document();
+
+        assert_eq!(query.get_document("status").unwrap().get_array("$in").unwrap().len(), 2);
+        assert_eq!(query.get_str("source").unwrap(), "Cashier");
+        let search = query.get_array("$or").unwrap()[0].as_document().unwrap();
+        assert_eq!(
+            search.get_document("order_id").unwrap().get_str("$regex").unwrap(),
+            "ORD-0101 \\(A\\)"
+        );
+        assert!(OrderFilter::default().to_document().is_empty());
+    }
+
+    #[test]
+    fn test_cursor_round_trip() {
+        let cursor = OrderCursor {
+            created_at: bson::DateTime::from_millis(1_710_000_000_000),
+            id: ObjectId::new(),
+        };
+
+        assert_eq!(OrderCursor::decode(&cursor.encode()), Some(cursor));
+        assert_eq!(OrderCursor::decode("not-a-cursor"), None);
+    }
 }
@@ -3,7 +3,7 @@ use mongodb::Collection;
 use std::sync::Arc;
 
 use crate::db::DbConnection;
-use crate::db::models::{Outlet, Warehouse, Supplier};
+use crate::db::models::{Outlet, PaymentProvider, Warehouse, Supplier};
 use crate::error::AppResult;
 
 #[derive(Clone)]
@@ -37,6 +37,18 @@ impl OutletRepository {
         Ok(outlets)
     }
 
+    pub async fn set_payment_provider(&self, id: &ObjectId, provider: PaymentProvider) -> AppResult<bool> {
+        let result = self
+            .outlet_collection
+            .update_one(
+                doc! { "_id": id },
+                doc! { "$set": { "paymentProvider": provider.as_str(), "updatedAt": bson::DateTime::now() } },
+                None,
+            )
+            .await?;
+        Ok(result.matched_count > 0)
+    }
+
     // --- Warehouse Methods ---
 
     pub async fn find_warehouse_by_id(&self, id: &ObjectId) -> AppResult<Option<Warehouse>> {
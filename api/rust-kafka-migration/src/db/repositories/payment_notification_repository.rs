@@ -0,0 +1,48 @@
+use bson::{doc, oid::ObjectId};
+use mongodb::Collection;
+use std::sync::Arc;
+
+use crate::db::DbConnection;
+use crate::db::models::{NotificationOutcome, PaymentNotification};
+use crate::error::{AppError, AppResult};
+
+#[derive(Clone)]
+pub struct PaymentNotificationRepository {
+    collection: Collection<PaymentNotification>,
+}
+
+impl PaymentNotificationRepository {
+    pub fn new(db: Arc<DbConnection>) -> Self {
+        Self {
+            collection: db.collection("payment_notifications"),
+        }
+    }
+
+    pub async fn create(&self, notification: &PaymentNotification) -> AppResult<ObjectId> {
+        let result = self.collection.insert_one(notification, None).await?;
+
+        result.inserted_id.as_object_id()
+            .ok_or_else(|| AppError::Internal("Failed to get inserted notification ID".to_string()))
+    }
+
+    /// Record how a stored notification was handled
+    pub async fn set_outcome(
+        &self,
+        id: &ObjectId,
+        outcome: NotificationOutcome,
+        error: Option<String>,
+    ) -> AppResult<()> {
+        self.collection
+            .update_one(
+                doc! { "_id": id },
+                doc! { "$set": {
+                    "outcome": outcome.as_str(),
+                    "error": error,
+                    "processedAt": bson::DateTime::now(),
+                } },
+                None,
+            )
+            .await?;
+        Ok(())
+    }
+}
@@ -1,29 +1,58 @@
-use bson::{doc, oid::ObjectId};
-use mongodb::Collection;
+use bson::{doc, oid::ObjectId, Document};
+use mongodb::{options::{FindOneOptions, FindOptions}, ClientSession, Collection};
 use std::sync::Arc;
 use futures::stream::TryStreamExt;
 
+use crate::common::Money;
 use crate::db::DbConnection;
-use crate::db::models::payment::Payment;
+use crate::db::models::payment::{Payment, DOWN_PAYMENT};
+use crate::db::repositories::CounterRepository;
 use crate::error::{AppError, AppResult};
+use crate::utils::generate_payment_code;
+
+/// Gateway statuses of a payment the customer has completed
+const SETTLED_STATUSES: [&str; 2] = ["settlement", "capture"];
 
 #[derive(Clone)]
 pub struct PaymentRepository {
     collection: Collection<Payment>,
+    counters: CounterRepository,
 }
 
 impl PaymentRepository {
     pub fn new(db: Arc<DbConnection>) -> Self {
         Self {
             collection: db.collection("payments"),
+            counters: CounterRepository::new(db),
         }
     }
 
-    pub async fn create(&self, payment: Payment) -> AppResult<ObjectId> {
+    /// Give the payment a daily `PAY-` code unless it already has one
+    async fn assign_code(&self, payment: &mut Payment) {
+        if payment.payment_code.is_none() {
+            payment.payment_code = Some(generate_payment_code(&self.counters, chrono::Utc::now()).await);
+        }
+    }
+
+    /// Next daily `PAY-` code, for charges that need it before the payment is stored
+    pub async fn next_code(&self) -> String {
+        generate_payment_code(&self.counters, chrono::Utc::now()).await
+    }
+
+    pub async fn create(&self, mut payment: Payment) -> AppResult<ObjectId> {
+        self.assign_code(&mut payment).await;
         let result = self.collection.insert_one(payment, None).await?;
         
-        Ok(result.inserted_id.as_object_id()
-            .ok_or_else(|| AppError::Internal("Failed to get inserted payment ID".to_string()))?)
+        result.inserted_id.as_object_id()
+            .ok_or_else(|| AppError::Internal("Failed to get inserted payment ID".to_string()))
+    }
+
+    pub async fn create_with_session(&self, mut payment: Payment, session: &mut ClientSession) -> AppResult<ObjectId> {
+        self.assign_code(&mut payment).await;
+        let result = self.collection.insert_one_with_session(payment, None, session).await?;
+
+        result.inserted_id.as_object_id()
+            .ok_or_else(|| AppError::Internal("Failed to get inserted payment ID".to_string()))
     }
 
     pub async fn find_by_order_id(&self, order_id: &str) -> AppResult<Vec<Payment>> {
@@ -35,7 +64,201 @@ impl PaymentRepository {
         Ok(payments)
     }
 
+    /// Pending payments of an order, newest first
+    pub async fn find_pending_by_order_id(&self, order_id: &str) -> AppResult<Vec<Payment>> {
+        let options = FindOptions::builder().sort(doc! { "createdAt": -1 }).build();
+        let cursor = self
+            .collection
+            .find(doc! { "order_id": order_id, "status": "pending" }, options)
+            .await?;
+        Ok(cursor.try_collect().await?)
+    }
+
+    /// Unpaid extra charge a revision added to an already paid order, if any
+    pub async fn find_pending_adjustment(&self, order_id: &str) -> AppResult<Option<Payment>> {
+        let options = FindOneOptions::builder().sort(doc! { "createdAt": -1 }).build();
+        Ok(self.collection.find_one(
+            doc! {
+                "order_id": order_id,
+                "status": "pending",
+                "isAdjustment": true,
+                "direction": "charge",
+            },
+            options,
+        ).await?)
+    }
+
+    /// Mark a pending adjustment charge paid by the given method, inside a transaction
+    pub async fn settle_adjustment_with_session(
+        &self,
+        id: &ObjectId,
+        method: &str,
+        paid_at: bson::DateTime,
+        session: &mut ClientSession,
+    ) -> AppResult<()> {
+        self.collection.update_one_with_session(
+            doc! { "_id": id, "status": "pending" },
+            doc! { "$set": {
+                "method": method,
+                "status": "paid",
+                "remainingAmount": Money::ZERO,
+                "paidAt": paid_at,
+                "updatedAt": paid_at,
+            } },
+            None,
+            session,
+        ).await?;
+        Ok(())
+    }
+
     pub async fn find_one_by_order_id(&self, order_id: &str) -> AppResult<Option<Payment>> {
         Ok(self.collection.find_one(doc! { "order_id": order_id }, None).await?)
     }
+
+    /// Replace a stored payment
+    pub async fn update(&self, payment: &Payment) -> AppResult<()> {
+        let id = payment.id.ok_or_else(|| AppError::BadRequest("Payment ID missing for update".to_string()))?;
+        self.collection.replace_one(doc! { "_id": id }, payment, None).await?;
+        Ok(())
+    }
+
+    /// Pending charges whose `expiry_time` is at or before `cutoff`, oldest first.
+    /// `expiry_time` is a WIB "YYYY-MM-DD HH:MM:SS" string, so it orders as text.
+    pub async fn find_expired_pending(&self, cutoff: &str, limit: i64) -> AppResult<Vec<Payment>> {
+        let options = FindOptions::builder()
+            .sort(doc! { "expiry_time": 1 })
+            .limit(limit)
+            .build();
+        let cursor = self
+            .collection
+            .find(
+                doc! {
+                    "status": "pending",
+                    "processedExpiry": { "$ne": true },
+                    "expiry_time": { "$lte": cutoff },
+                },
+                options,
+            )
+            .await?;
+        Ok(cursor.try_collect().await?)
+    }
+
+    /// Expire a payment that is still pending; false if something settled it first
+    pub async fn mark_expired(&self, id: &ObjectId) -> AppResult<bool> {
+        let now = bson::DateTime::now();
+        let result = self.collection.update_one(
+            doc! { "_id": id, "status": "pending" },
+            doc! { "$set": {
+                "status": "expire",
+                "processedExpiry": true,
+                "expiredAt": now,
+                "updatedAt": now,
+            } },
+            None,
+        ).await?;
+        Ok(result.modified_count > 0)
+    }
+
+    /// Flag payments created between `since` and `until` whose order does not exist.
+    /// Returns how many were flagged.
+    pub async fn flag_orphaned(&self, since: bson::DateTime, until: bson::DateTime, limit: i64) -> AppResult<u64> {
+        let pipeline = vec![
+            doc! { "$match": {
+                "orphanedAt": null,
+                "createdAt": { "$gte": since, "$lte": until },
+            } },
+            doc! { "$lookup": {
+                "from": "orders",
+                "localField": "order_id",
+                "foreignField": "order_id",
+                "as": "order",
+            } },
+            doc! { "$match": { "order": { "$size": 0 } } },
+            doc! { "$limit": limit },
+            doc! { "$project": { "_id": 1 } },
+        ];
+        let found: Vec<Document> = self.collection.aggregate(pipeline, None).await?.try_collect().await?;
+        let ids: Vec<ObjectId> = found.iter().filter_map(|d| d.get_object_id("_id").ok()).collect();
+        if ids.is_empty() {
+            return Ok(0);
+        }
+
+        let result = self.collection.update_many(
+            doc! { "_id": { "$in": ids } },
+            doc! { "$set": { "orphanedAt": bson::DateTime::now() } },
+            None,
+        ).await?;
+        Ok(result.modified_count)
+    }
+
+    /// Latest settled down payment of an order
+    pub async fn find_settled_down_payment(&self, order_id: &str) -> AppResult<Option<Payment>> {
+        let options = mongodb::options::FindOneOptions::builder()
+            .sort(doc! { "paidAt": -1 })
+            .build();
+        Ok(self.collection.find_one(
+            doc! {
+                "order_id": order_id,
+                "paymentType": DOWN_PAYMENT,
+                "status": { "$in": SETTLED_STATUSES.to_vec() },
+            },
+            options,
+        ).await?)
+    }
+
+    /// Settled down payments paid in `[from, to)`, oldest first
+    pub async fn find_settled_down_payments(
+        &self,
+        from: Option<bson::DateTime>,
+        to: Option<bson::DateTime>,
+    ) -> AppResult<Vec<Payment>> {
+        let mut filter = doc! {
+            "paymentType": DOWN_PAYMENT,
+            "status": { "$in": SETTLED_STATUSES.to_vec() },
+        };
+        let mut paid_at = doc! {};
+        if let Some(from) = from {
+            paid_at.insert("$gte", from);
+        }
+        if let Some(to) = to {
+            paid_at.insert("$lt", to);
+        }
+        if !paid_at.is_empty() {
+            filter.insert("paidAt", paid_at);
+        }
+
+        let options = FindOptions::builder().sort(doc! { "paidAt": 1 }).build();
+        Ok(self.collection.find(filter, options).await?.try_collect().await?)
+    }
+
+    /// Keep an order's settled down payments as forfeited. Returns how many were.
+    pub async fn forfeit_down_payments_with_session(&self, order_id: &str, session: &mut ClientSession) -> AppResult<u64> {
+        let now = bson::DateTime::now();
+        let result = self.collection.update_many_with_session(
+            doc! {
+                "order_id": order_id,
+                "paymentType": DOWN_PAYMENT,
+                "status": { "$in": SETTLED_STATUSES.to_vec() },
+                "forfeitedAt": null,
+            },
+            doc! { "$set": { "forfeitedAt": now, "updatedAt": now } },
+            None,
+            session,
+        ).await?;
+        Ok(result.modified_count)
+    }
+
+    /// Change the amount still due on a pending payment
+    pub async fn adjust_amount_with_session(&self, id: &ObjectId, delta: Money, session: &mut ClientSession) -> AppResult<()> {
+        self.collection.update_one_with_session(
+            doc! { "_id": id, "status": "pending" },
+            doc! {
+                "$inc": { "amount": delta },
+                "$set": { "updatedAt": bson::DateTime::now() }
+            },
+            None,
+            session,
+        ).await?;
+        Ok(())
+    }
 }
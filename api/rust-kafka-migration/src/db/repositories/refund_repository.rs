@@ -0,0 +1,46 @@
+use bson::{doc, oid::ObjectId};
+use futures::stream::TryStreamExt;
+use mongodb::{options::FindOptions, ClientSession, Collection};
+use std::sync::Arc;
+
+use crate::db::DbConnection;
+use crate::db::models::Refund;
+use crate::error::{AppError, AppResult};
+
+#[derive(Clone)]
+pub struct RefundRepository {
+    collection: Collection<Refund>,
+}
+
+impl RefundRepository {
+    pub fn new(db: Arc<DbConnection>) -> Self {
+        Self {
+            collection: db.collection("refunds"),
+        }
+    }
+
+    pub async fn create_with_session(&self, refund: &Refund, session: &mut ClientSession) -> AppResult<ObjectId> {
+        let result = self.collection.insert_one_with_session(refund, None, session).await?;
+
+        result.inserted_id.as_object_id()
+            .ok_or_else(|| AppError::Internal("Failed to get inserted refund ID".to_string()))
+    }
+
+    /// Refund history of an order, newest first
+    pub async fn find_by_order(&self, order: &ObjectId) -> AppResult<Vec<Refund>> {
+        let options = FindOptions::builder().sort(doc! { "createdAt": -1 }).build();
+        let cursor = self.collection.find(doc! { "order": order }, options).await?;
+        Ok(cursor.try_collect().await?)
+    }
+
+    /// Refunds `processor` processed in `[from, to)`
+    pub async fn find_processed_by(
+        &self,
+        processor: &ObjectId,
+        from: bson::DateTime,
+        to: bson::DateTime,
+    ) -> AppResult<Vec<Refund>> {
+        let filter = doc! { "processedBy": processor, "processedAt": { "$gte": from, "$lt": to } };
+        Ok(self.collection.find(filter, None).await?.try_collect().await?)
+    }
+}
@@ -0,0 +1,178 @@
+use bson::{doc, oid::ObjectId, Document};
+use futures::stream::TryStreamExt;
+use mongodb::{
+    options::{FindOneAndUpdateOptions, FindOptions, ReturnDocument},
+    ClientSession, Collection,
+};
+use std::sync::Arc;
+
+use crate::db::DbConnection;
+use crate::db::models::{Reservation, ReservationStatus};
+use crate::error::{AppError, AppResult};
+
+/// Filters for listing reservations; empty fields do not constrain the result
+#[derive(Debug, Clone, Default)]
+pub struct ReservationFilter {
+    pub statuses: Vec<ReservationStatus>,
+    pub area: Option<ObjectId>,
+    pub customer_phone: Option<String>,
+}
+
+impl ReservationFilter {
+    pub fn to_document(&self) -> Document {
+        let mut filter = doc! {};
+
+        if !self.statuses.is_empty() {
+            let statuses: Vec<&str> = self.statuses.iter().map(|s| s.as_str()).collect();
+            filter.insert("status", doc! { "$in": statuses });
+        }
+        if let Some(area) = self.area {
+            filter.insert("area_id", area);
+        }
+        if let Some(phone) = self.customer_phone.as_deref().filter(|p| !p.is_empty()) {
+            filter.insert("customer_phone", phone);
+        }
+
+        filter
+    }
+}
+
+#[derive(Clone)]
+pub struct ReservationRepository {
+    collection: Collection<Reservation>,
+}
+
+impl ReservationRepository {
+    pub fn new(db: Arc<DbConnection>) -> Self {
+        Self {
+            collection: db.collection("reservations"),
+        }
+    }
+
+    pub async fn create(&self, reservation: &Reservation) -> AppResult<ObjectId> {
+        let result = self.collection.insert_one(reservation, None).await?;
+
+        result.inserted_id.as_object_id()
+            .ok_or_else(|| AppError::Internal("Failed to get inserted reservation ID".to_string()))
+    }
+
+    pub async fn create_with_session(&self, reservation: &Reservation, session: &mut ClientSession) -> AppResult<ObjectId> {
+        let result = self.collection.insert_one_with_session(reservation, None, session).await?;
+
+        result.inserted_id.as_object_id()
+            .ok_or_else(|| AppError::Internal("Failed to get inserted reservation ID".to_string()))
+    }
+
+    pub async fn find_by_id(&self, id: &ObjectId) -> AppResult<Option<Reservation>> {
+        Ok(self.collection.find_one(doc! { "_id": id }, None).await?)
+    }
+
+    pub async fn find_by_ids(&self, ids: &[ObjectId]) -> AppResult<Vec<Reservation>> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        Ok(self.collection.find(doc! { "_id": { "$in": ids } }, None).await?.try_collect().await?)
+    }
+
+    /// Reservations matching the filter, by slot
+    pub async fn find(&self, filter: &ReservationFilter) -> AppResult<Vec<Reservation>> {
+        let options = FindOptions::builder()
+            .sort(doc! { "reservation_date": 1, "reservation_time": 1 })
+            .build();
+        Ok(self.collection.find(filter.to_document(), options).await?.try_collect().await?)
+    }
+
+    /// Reservations still holding any of the given tables, other than `exclude`
+    pub async fn find_holding_tables(
+        &self,
+        table_ids: &[ObjectId],
+        exclude: Option<&ObjectId>,
+    ) -> AppResult<Vec<Reservation>> {
+        let statuses: Vec<&str> = [ReservationStatus::Pending, ReservationStatus::Confirmed]
+            .iter()
+            .map(|s| s.as_str())
+            .collect();
+        let mut filter = doc! {
+            "table_id": { "$in": table_ids },
+            "status": { "$in": statuses },
+        };
+        if let Some(exclude) = exclude {
+            filter.insert("_id", doc! { "$ne": exclude });
+        }
+        Ok(self.collection.find(filter, None).await?.try_collect().await?)
+    }
+
+    /// Confirmed reservations not checked in whose date is on or before `day`, oldest first
+    pub async fn find_unattended_until(&self, day: chrono::DateTime<chrono::Utc>, limit: i64) -> AppResult<Vec<Reservation>> {
+        let options = FindOptions::builder()
+            .sort(doc! { "reservation_date": 1, "reservation_time": 1 })
+            .limit(limit)
+            .build();
+        let filter = doc! {
+            "status": ReservationStatus::Confirmed.as_str(),
+            "check_in_time": null,
+            "reservation_date": { "$lte": bson::to_bson(&day)? },
+        };
+        Ok(self.collection.find(filter, options).await?.try_collect().await?)
+    }
+
+    /// Apply `set` if the reservation still matches `expected`, returning the
+    /// updated reservation, or None if it was changed in the meantime
+    pub async fn update_if(
+        &self,
+        id: &ObjectId,
+        expected: Document,
+        set: Document,
+    ) -> AppResult<Option<Reservation>> {
+        let mut filter = expected;
+        filter.insert("_id", id);
+
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
+
+        Ok(self.collection.find_one_and_update(filter, doc! { "$set": set }, options).await?)
+    }
+
+    /// [`Self::update_if`] inside a transaction
+    pub async fn update_if_with_session(
+        &self,
+        id: &ObjectId,
+        expected: Document,
+        set: Document,
+        session: &mut ClientSession,
+    ) -> AppResult<Option<Reservation>> {
+        let mut filter = expected;
+        filter.insert("_id", id);
+
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
+
+        Ok(self
+            .collection
+            .find_one_and_update_with_session(filter, doc! { "$set": set }, options, session)
+            .await?)
+    }
+
+    /// Point an unlinked reservation at its order inside a transaction.
+    /// Returns false if the reservation is already linked to another order.
+    pub async fn link_order_with_session(
+        &self,
+        id: &ObjectId,
+        order: &ObjectId,
+        session: &mut ClientSession,
+    ) -> AppResult<bool> {
+        let result = self.collection.update_one_with_session(
+            doc! {
+                "_id": id,
+                "$or": [{ "order_id": { "$exists": false } }, { "order_id": null }, { "order_id": order }],
+            },
+            doc! { "$set": { "order_id": order, "updatedAt": bson::to_bson(&chrono::Utc::now())? } },
+            None,
+            session,
+        ).await?;
+
+        Ok(result.matched_count == 1)
+    }
+}
@@ -0,0 +1,50 @@
+use bson::{doc, oid::ObjectId};
+use futures::stream::TryStreamExt;
+use mongodb::{options::FindOptions, ClientSession, Collection};
+use std::sync::Arc;
+
+use crate::db::DbConnection;
+use crate::db::models::{OrderRevision, PaymentAdjustment};
+use crate::error::{AppError, AppResult};
+
+#[derive(Clone)]
+pub struct RevisionRepository {
+    revisions: Collection<OrderRevision>,
+    adjustments: Collection<PaymentAdjustment>,
+}
+
+impl RevisionRepository {
+    pub fn new(db: Arc<DbConnection>) -> Self {
+        Self {
+            revisions: db.collection("orderrevisions"),
+            adjustments: db.collection("paymentadjustments"),
+        }
+    }
+
+    pub async fn create_with_session(&self, revision: &OrderRevision, session: &mut ClientSession) -> AppResult<ObjectId> {
+        let result = self.revisions.insert_one_with_session(revision, None, session).await?;
+
+        result.inserted_id.as_object_id()
+            .ok_or_else(|| AppError::Internal("Failed to get inserted revision ID".to_string()))
+    }
+
+    pub async fn create_adjustment_with_session(&self, adjustment: &PaymentAdjustment, session: &mut ClientSession) -> AppResult<ObjectId> {
+        let result = self.adjustments.insert_one_with_session(adjustment, None, session).await?;
+
+        result.inserted_id.as_object_id()
+            .ok_or_else(|| AppError::Internal("Failed to get inserted adjustment ID".to_string()))
+    }
+
+    /// Revisions of an order, oldest first
+    pub async fn find_by_order(&self, order_id: &ObjectId) -> AppResult<Vec<OrderRevision>> {
+        let options = FindOptions::builder().sort(doc! { "revisionNumber": 1 }).build();
+        let cursor = self.revisions.find(doc! { "orderId": order_id }, options).await?;
+        Ok(cursor.try_collect().await?)
+    }
+
+    pub async fn find_adjustments_by_order(&self, order_id: &ObjectId) -> AppResult<Vec<PaymentAdjustment>> {
+        let options = FindOptions::builder().sort(doc! { "createdAt": 1 }).build();
+        let cursor = self.adjustments.find(doc! { "orderId": order_id }, options).await?;
+        Ok(cursor.try_collect().await?)
+    }
+}
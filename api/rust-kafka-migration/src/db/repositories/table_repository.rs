@@ -0,0 +1,126 @@
+use bson::{doc, oid::ObjectId, Bson, Document};
+use chrono::Utc;
+use futures::stream::TryStreamExt;
+use mongodb::{options::FindOptions, ClientSession, Collection};
+use std::sync::Arc;
+
+use crate::db::DbConnection;
+use crate::db::models::{Area, StatusHistoryEntry, Table, TableStatus};
+use crate::error::AppResult;
+
+#[derive(Clone)]
+pub struct TableRepository {
+    collection: Collection<Table>,
+    area_collection: Collection<Area>,
+}
+
+impl TableRepository {
+    pub fn new(db: Arc<DbConnection>) -> Self {
+        Self {
+            collection: db.collection("tables"),
+            area_collection: db.collection("areas"),
+        }
+    }
+
+    // --- Table Methods ---
+
+    pub async fn find_by_id(&self, id: &ObjectId) -> AppResult<Option<Table>> {
+        Ok(self.collection.find_one(doc! { "_id": id }, None).await?)
+    }
+
+    /// Active table with the given number at an outlet, read inside the transaction when a session is given
+    pub async fn find_active_by_number(
+        &self,
+        outlet_id: &ObjectId,
+        table_number: &str,
+        mut session: Option<&mut ClientSession>,
+    ) -> AppResult<Option<Table>> {
+        let area_ids = self.area_ids_for_outlet(outlet_id, session.as_deref_mut()).await?;
+        let filter = doc! {
+            "table_number": table_number,
+            "area_id": { "$in": area_ids },
+            "is_active": true,
+        };
+        Ok(match session {
+            Some(s) => self.collection.find_one_with_session(filter, None, s).await?,
+            None => self.collection.find_one(filter, None).await?,
+        })
+    }
+
+    /// Active tables of an outlet, optionally of one area, ordered by table number
+    pub async fn find_active(&self, outlet_id: &ObjectId, area_id: Option<ObjectId>) -> AppResult<Vec<Table>> {
+        let mut area_ids = self.area_ids_for_outlet(outlet_id, None).await?;
+        if let Some(area_id) = area_id {
+            area_ids.retain(|id| *id == Bson::ObjectId(area_id));
+        }
+        let filter = doc! { "is_active": true, "area_id": { "$in": area_ids } };
+        let options = FindOptions::builder().sort(doc! { "table_number": 1 }).build();
+        Ok(self.collection.find(filter, options).await?.try_collect().await?)
+    }
+
+    /// Move a table from `from` to `to` and record it in its status history.
+    /// Returns false if the table was no longer in `from`.
+    pub async fn update_status(
+        &self,
+        id: &ObjectId,
+        from: TableStatus,
+        to: TableStatus,
+        updated_by: &str,
+        notes: Option<String>,
+        session: Option<&mut ClientSession>,
+    ) -> AppResult<bool> {
+        let now = Utc::now();
+        let entry = StatusHistoryEntry {
+            from_status: from.to_string(),
+            to_status: to.to_string(),
+            updated_by: updated_by.to_string(),
+            notes,
+            updated_at: now,
+        };
+
+        let filter = doc! { "_id": id, "status": from.as_str() };
+        let update = doc! {
+            "$set": {
+                "status": to.as_str(),
+                "is_available": to == TableStatus::Available,
+                "updatedAt": bson::to_bson(&now)?,
+            },
+            "$push": { "statusHistory": bson::to_bson(&entry)? }
+        };
+        let result = match session {
+            Some(s) => self.collection.update_one_with_session(filter, update, None, s).await?,
+            None => self.collection.update_one(filter, update, None).await?,
+        };
+
+        Ok(result.modified_count == 1)
+    }
+
+    // --- Area Methods ---
+
+    pub async fn find_area_by_id(&self, id: &ObjectId) -> AppResult<Option<Area>> {
+        Ok(self.area_collection.find_one(doc! { "_id": id }, None).await?)
+    }
+
+    /// Ids of an outlet's areas; tables belong to an outlet through their area
+    async fn area_ids_for_outlet(
+        &self,
+        outlet_id: &ObjectId,
+        session: Option<&mut ClientSession>,
+    ) -> AppResult<Vec<Bson>> {
+        let filter = doc! { "outlet_id": outlet_id };
+        Ok(match session {
+            Some(s) => self.area_collection.distinct_with_session("_id", filter, None, s).await?,
+            None => self.area_collection.distinct("_id", filter, None).await?,
+        })
+    }
+
+    /// Active areas of an outlet, optionally only the given one, ordered by area code
+    pub async fn find_active_areas(&self, outlet_id: &ObjectId, area_id: Option<ObjectId>) -> AppResult<Vec<Area>> {
+        let mut filter: Document = doc! { "is_active": true, "outlet_id": outlet_id };
+        if let Some(area_id) = area_id {
+            filter.insert("_id", area_id);
+        }
+        let options = FindOptions::builder().sort(doc! { "area_code": 1 }).build();
+        Ok(self.area_collection.find(filter, options).await?.try_collect().await?)
+    }
+}
@@ -49,14 +49,14 @@ impl UserRepository {
     pub async fn create(&self, user: User) -> AppResult<ObjectId> {
         let result = self.collection.insert_one(user, None).await?;
         
-        Ok(result.inserted_id.as_object_id()
-            .ok_or_else(|| AppError::Internal("Failed to get inserted user ID".to_string()))?)
+        result.inserted_id.as_object_id()
+            .ok_or_else(|| AppError::Internal("Failed to get inserted user ID".to_string()))
     }
 
     /// Update user
     pub async fn update(&self, id: &ObjectId, user: User) -> AppResult<()> {
         let update_doc = bson::to_document(&user)
-            .map_err(|e| AppError::BsonSerialization(e))?;
+            .map_err(AppError::BsonSerialization)?;
 
         self.collection.update_one(
             doc! { "_id": id },
@@ -89,6 +89,17 @@ impl UserRepository {
         Ok(())
     }
 
+    /// Update user's hashed supervisor PIN
+    pub async fn update_pin(&self, id: &ObjectId, hashed_pin: &str) -> AppResult<()> {
+        self.collection.update_one(
+            doc! { "_id": id },
+            doc! { "$set": { "pin": hashed_pin } },
+            None,
+        ).await?;
+
+        Ok(())
+    }
+
     /// Update user profile
     pub async fn update_profile(
         &self,
@@ -0,0 +1,73 @@
+use std::future::Future;
+
+use mongodb::error::{TRANSIENT_TRANSACTION_ERROR, UNKNOWN_TRANSACTION_COMMIT_RESULT};
+use mongodb::ClientSession;
+use tracing::warn;
+
+use crate::db::DbConnection;
+use crate::error::{AppError, AppResult};
+
+/// How many times a transaction, or its commit, is attempted before giving up
+const MAX_ATTEMPTS: u32 = 3;
+
+fn has_label(error: &AppError, label: &str) -> bool {
+    matches!(error, AppError::Database(e) if e.contains_label(label))
+}
+
+/// Run `work` inside a MongoDB transaction and commit it.
+///
+/// `work` receives the session by value and hands it back with its result, so
+/// it can be called again: the whole transaction is retried on a
+/// `TransientTransactionError` and the commit alone on an
+/// `UnknownTransactionCommitResult`. Any other error aborts the transaction.
+/// `work` must therefore start from the same inputs on every call.
+pub async fn with_transaction<T, F, Fut>(db: &DbConnection, mut work: F) -> AppResult<T>
+where
+    F: FnMut(ClientSession) -> Fut,
+    Fut: Future<Output = (ClientSession, AppResult<T>)>,
+{
+    let mut session = db.client().start_session(None).await?;
+    let mut attempt = 1;
+
+    loop {
+        session.start_transaction(None).await?;
+        let (returned, result) = work(session).await;
+        session = returned;
+
+        let value = match result {
+            Ok(value) => value,
+            Err(e) => {
+                let _ = session.abort_transaction().await;
+                if has_label(&e, TRANSIENT_TRANSACTION_ERROR) && attempt < MAX_ATTEMPTS {
+                    warn!("Retrying transaction after transient error: {}", e);
+                    attempt += 1;
+                    continue;
+                }
+                return Err(e);
+            }
+        };
+
+        let mut commit_attempt = 1;
+        let committed = loop {
+            match session.commit_transaction().await {
+                Ok(()) => break Ok(value),
+                Err(e)
+                    if e.contains_label(UNKNOWN_TRANSACTION_COMMIT_RESULT)
+                        && commit_attempt < MAX_ATTEMPTS =>
+                {
+                    warn!("Retrying commit with unknown result: {}", e);
+                    commit_attempt += 1;
+                }
+                Err(e) => break Err(AppError::from(e)),
+            }
+        };
+
+        match committed {
+            Err(e) if has_label(&e, TRANSIENT_TRANSACTION_ERROR) && attempt < MAX_ATTEMPTS => {
+                warn!("Retrying transaction after transient commit error: {}", e);
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
@@ -312,6 +312,51 @@ pub async fn change_password(
     )))
 }
 
+/// Set PIN request
+#[derive(Debug, Deserialize)]
+pub struct SetPinRequest {
+    #[serde(rename = "currentPassword")]
+    pub current_password: String,
+    pub pin: String,
+}
+
+/// Set the PIN used to approve discounts - POST /api/auth/set-pin
+pub async fn set_pin(
+    State(state): State<Arc<AppState>>,
+    axum::Extension(user_id): axum::Extension<crate::middleware::UserId>,
+    Json(payload): Json<SetPinRequest>,
+) -> AppResult<Json<ApiResponse<()>>> {
+    if !(4..=6).contains(&payload.pin.len()) || !payload.pin.chars().all(|c| c.is_ascii_digit()) {
+        return Err(AppError::BadRequest("PIN must be 4 to 6 digits".to_string()));
+    }
+
+    let user_repo = UserRepository::new(state.db.clone());
+
+    let user = user_repo
+        .find_by_id(&user_id.0)
+        .await?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+    let is_valid = bcrypt::verify(&payload.current_password, &user.password)
+        .map_err(|e| AppError::Internal(format!("Password verification failed: {}", e)))?;
+
+    if !is_valid {
+        return Err(AppError::BadRequest(
+            "Current password is incorrect".to_string(),
+        ));
+    }
+
+    let hashed_pin = bcrypt::hash(&payload.pin, bcrypt::DEFAULT_COST)
+        .map_err(|e| AppError::Internal(format!("Failed to hash PIN: {}", e)))?;
+
+    user_repo.update_pin(&user_id.0, &hashed_pin).await?;
+
+    Ok(Json(ApiResponse::success_with_message(
+        (),
+        "PIN set successfully".to_string(),
+    )))
+}
+
 /// Signout - GET /api/auth/signout
 pub async fn signout() -> AppResult<Json<ApiResponse<String>>> {
     // In a stateless JWT system, signout is handled client-side
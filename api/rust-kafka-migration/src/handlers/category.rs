@@ -6,7 +6,6 @@ use axum::{
 use std::sync::Arc;
 use bson::oid::ObjectId;
 use bson::doc;
-use chrono::Utc;
 use futures::stream::TryStreamExt;
 use serde_json::json;
 use bson::DateTime as BsonDateTime;
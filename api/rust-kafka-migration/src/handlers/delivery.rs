@@ -0,0 +1,129 @@
+use axum::{
+    extract::{Path, State},
+    response::IntoResponse,
+    Extension, Json,
+};
+use bson::oid::ObjectId;
+use serde::Deserialize;
+use serde_json::json;
+use std::sync::Arc;
+
+use crate::{
+    error::{ApiResponse, AppError, AppResult},
+    middleware::UserId,
+    services::gosend_client::ShipmentMethod,
+    AppState,
+};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GoSendEstimateRequest {
+    pub outlet_id: String,
+    /// Recipient "lat,long"
+    pub coordinates: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BookGoSendRequest {
+    #[serde(default)]
+    pub shipment_method: Option<String>,
+}
+
+/// GoSend price per shipment method - POST /api/order/gosend/estimate
+pub async fn estimate_gosend(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<GoSendEstimateRequest>,
+) -> AppResult<impl IntoResponse> {
+    let outlet_id = ObjectId::parse_str(&payload.outlet_id)
+        .map_err(|_| AppError::BadRequest("Invalid outletId".to_string()))?;
+    if payload.coordinates.trim().is_empty() {
+        return Err(AppError::Validation("coordinates is required".to_string()));
+    }
+
+    let estimate = state.delivery_service.estimate(&outlet_id, &payload.coordinates).await?;
+
+    let quote = |method: ShipmentMethod| {
+        estimate.for_method(method).map(|q| {
+            json!({
+                "totalPrice": q.price.total_price,
+                "distance": q.distance,
+                "description": q.shipment_method_description,
+                "serviceable": q.serviceable && q.active,
+            })
+        })
+    };
+
+    Ok(ApiResponse::success(json!({
+        "Instant": quote(ShipmentMethod::Instant),
+        "SameDay": quote(ShipmentMethod::SameDay),
+    })))
+}
+
+/// Book GoSend delivery for an order - POST /api/order/:id/gosend
+pub async fn book_gosend(
+    State(state): State<Arc<AppState>>,
+    Extension(user_id): Extension<UserId>,
+    Path(id): Path<String>,
+    Json(payload): Json<BookGoSendRequest>,
+) -> AppResult<impl IntoResponse> {
+    let method: ShipmentMethod = match payload.shipment_method.as_deref() {
+        Some(method) => method.parse()?,
+        None => ShipmentMethod::default(),
+    };
+
+    let order = state
+        .order_repo
+        .find_by_id_or_order_id(&id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Order not found".to_string()))?;
+    let owner = format!("gosend-{}-{}", user_id.0.to_hex(), uuid::Uuid::new_v4());
+
+    let booking = state
+        .lock_util
+        .with_lock(&order.order_id, &owner, 30000, 5, 200, || async {
+            let order = state
+                .order_repo
+                .find_by_id_or_order_id(&order.order_id)
+                .await?
+                .ok_or_else(|| AppError::NotFound("Order not found".to_string()))?;
+            state.delivery_service.book_for_order(&order, method).await
+        })
+        .await?;
+
+    Ok(ApiResponse::success_with_message(
+        json!({ "booking": booking }),
+        format!("GoSend {} booked for order {}", booking.gosend_order_no, booking.order_id),
+    ))
+}
+
+/// Cancel an order's GoSend booking - POST /api/order/:id/gosend/cancel
+pub async fn cancel_gosend(
+    State(state): State<Arc<AppState>>,
+    Extension(user_id): Extension<UserId>,
+    Path(id): Path<String>,
+) -> AppResult<impl IntoResponse> {
+    let order = state
+        .order_repo
+        .find_by_id_or_order_id(&id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Order not found".to_string()))?;
+    let owner = format!("gosend-{}-{}", user_id.0.to_hex(), uuid::Uuid::new_v4());
+
+    let booking = state
+        .lock_util
+        .with_lock(&order.order_id, &owner, 30000, 5, 200, || async {
+            let order = state
+                .order_repo
+                .find_by_id_or_order_id(&order.order_id)
+                .await?
+                .ok_or_else(|| AppError::NotFound("Order not found".to_string()))?;
+            state.delivery_service.cancel_for_order(&order).await
+        })
+        .await?;
+
+    Ok(ApiResponse::success_with_message(
+        json!({ "booking": booking }),
+        format!("GoSend {} cancelled", booking.gosend_order_no),
+    ))
+}
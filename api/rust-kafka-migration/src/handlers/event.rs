@@ -3,7 +3,7 @@ use axum::{
     Json,
 };
 use bson::oid::ObjectId;
-use serde::{Deserialize, Serialize};
+use serde::Deserialize;
 use std::sync::Arc;
 
 use crate::{
@@ -3,7 +3,7 @@ use axum::{
     response::IntoResponse,
     Json,
 };
-use bson::{doc, oid::ObjectId};
+use bson::oid::ObjectId;
 use std::sync::Arc;
 use serde_json::json;
 
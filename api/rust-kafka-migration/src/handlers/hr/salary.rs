@@ -11,7 +11,6 @@ use crate::{
     db::models::SalaryStatus,
     error::{AppError, AppResult},
     AppState,
-    common::PaginationParams,
     middleware::hr_middleware::CompanyId,
 };
 
@@ -6,12 +6,11 @@ use axum::{
 use std::sync::Arc;
 // use futures::stream::TryStreamExt;
 use bson::{doc, oid::ObjectId};
-use serde::{Deserialize, Serialize};
-use serde_json::json;
+use serde::Deserialize;
 
 use crate::AppState;
 use crate::error::{AppResult, AppError, ApiResponse};
-use crate::db::models::{Request, MarketList, RequestStatus, FulfillmentStatus};
+use crate::db::models::{Request, MarketList};
 
 #[derive(Deserialize)]
 pub struct RequestFilter {
@@ -2,7 +2,7 @@ use axum::{
     extract::{Path, State},
     Json,
 };
-use bson::{doc, oid::ObjectId};
+use bson::oid::ObjectId;
 use std::sync::Arc;
 use crate::AppState;
 use crate::db::models::{MenuItem, Category};
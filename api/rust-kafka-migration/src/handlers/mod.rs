@@ -1,4 +1,8 @@
+// Re-exports make up the module's public surface; not all are used by the binary yet
+#![allow(unused_imports)]
+
 pub mod auth;
+pub mod delivery;
 pub mod event;
 pub mod inventory;
 pub mod menu;
@@ -10,6 +14,7 @@ pub mod voucher;
 pub mod category;
 pub mod product;
 pub mod supplier;
+pub mod table;
 // pub mod loyalty;
 pub mod tax;
 // pub mod promo;
@@ -17,6 +22,8 @@ pub mod hr;
 pub mod marketlist;
 pub mod recipe;
 pub mod report;
+pub mod reservation;
+pub mod shift;
 pub mod webhook;
 
 pub use auth::*;
@@ -3,6 +3,7 @@ use axum::{
     Json,
 };
 use bson::oid::ObjectId;
+use serde::Deserialize;
 use std::sync::Arc;
 
 use crate::error::{ApiResponse, AppError, AppResult};
@@ -27,6 +28,23 @@ pub async fn get_outlet(
     Ok(Json(ApiResponse::success(outlet)))
 }
 
+#[derive(Deserialize)]
+pub struct SetPaymentProviderRequest {
+    pub provider: String,
+}
+
+/// Choose the outlet's payment gateway - PUT /api/outlets/:id/payment-provider
+pub async fn set_outlet_payment_provider(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    Json(payload): Json<SetPaymentProviderRequest>,
+) -> AppResult<Json<ApiResponse<crate::db::models::Outlet>>> {
+    let object_id = ObjectId::parse_str(&id).map_err(|_| AppError::BadRequest("Invalid ID format".to_string()))?;
+    let provider = payload.provider.parse()?;
+    let outlet = state.outlet_service.set_payment_provider(&object_id, provider).await?;
+    Ok(Json(ApiResponse::success(outlet)))
+}
+
 /// Get all warehouses - GET /api/warehouses
 pub async fn get_warehouses(
     State(state): State<Arc<AppState>>,
@@ -1,20 +1,19 @@
 use std::sync::Arc;
 
 use axum::{
-    extract::{Path, Query, State},
+    extract::{Path, State},
     response::IntoResponse,
     Json,
 };
 use chrono::Utc;
 use futures::stream::TryStreamExt;
 use mongodb::{
-    bson::{doc, oid::ObjectId},
-    Collection, Cursor, Database,
+    bson::{doc, oid::ObjectId}, Cursor,
 };
 use serde_json::json;
 
 use crate::{
-    db::models::promo::{ActiveHours, AutoPromo, Promo, PromoConditions, Schedule},
+    db::models::promo::{AutoPromo, Promo},
     error::{ApiResponse, AppError, AppResult},
     AppState,
 };
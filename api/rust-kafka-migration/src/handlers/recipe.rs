@@ -6,7 +6,6 @@ use axum::{
 use std::sync::Arc;
 use futures::stream::TryStreamExt;
 use mongodb::bson::{doc, oid::ObjectId};
-use chrono::Utc;
 use serde_json::json;
 
 use crate::AppState;
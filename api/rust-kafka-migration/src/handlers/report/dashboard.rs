@@ -2,8 +2,8 @@ use axum::{
     extract::{Query, State},
     Json,
 };
-use bson::{doc, Bson, Document};
-use chrono::{DateTime, Duration, TimeZone, Utc};
+use bson::{doc, Document};
+use chrono::{Duration, TimeZone, Utc};
 use chrono_tz::Asia::Jakarta;
 use futures::TryStreamExt;
 use serde::{Deserialize, Serialize};
@@ -354,12 +354,12 @@ pub async fn get_dashboard_data(
 
     let (mut current_cur, mut prev_cur, mut product_cur, mut hourly_cur, mut type_cur) = 
         try_join!(current_future, prev_future, product_future, hourly_future, type_future)
-        .map_err(|e| AppError::Database(e))?;
+        .map_err(AppError::Database)?;
 
     // Process Results
     
     // Current Stats
-    let current_res: Option<Document> = current_cur.try_next().await.map_err(|e| AppError::Database(e))?;
+    let current_res: Option<Document> = current_cur.try_next().await.map_err(AppError::Database)?;
     let (c_sales, c_orders, c_avg) = if let Some(doc) = current_res {
         (
             doc.get_f64("totalSales").unwrap_or(0.0),
@@ -371,7 +371,7 @@ pub async fn get_dashboard_data(
     let current_stats = PeriodStats { sales: c_sales, orders: c_orders, avg_order_value: c_avg };
 
     // Previous Stats
-    let prev_res: Option<Document> = prev_cur.try_next().await.map_err(|e| AppError::Database(e))?;
+    let prev_res: Option<Document> = prev_cur.try_next().await.map_err(AppError::Database)?;
     let (p_sales, p_orders) = if let Some(doc) = prev_res {
         (
             doc.get_f64("totalSales").unwrap_or(0.0),
@@ -405,7 +405,7 @@ pub async fn get_dashboard_data(
 
     // Products
     let mut products: Vec<ProductSummary> = Vec::new();
-    while let Some(doc) = product_cur.try_next().await.map_err(|e| AppError::Database(e))? {
+    while let Some(doc) = product_cur.try_next().await.map_err(AppError::Database)? {
         products.push(bson::from_document(doc).unwrap_or_else(|_| ProductSummary {
             product_name: "Error".to_string(), main_category: "".into(), category: "".into(), sku: "".into(),
             quantity: 0, subtotal: 0.0, discount: 0.0, total: 0.0
@@ -432,7 +432,7 @@ pub async fn get_dashboard_data(
         hourly_map.insert(hour_str.clone(), HourlyData { time: hour_str, subtotal: 0.0 });
     }
 
-    while let Some(doc) = hourly_cur.try_next().await.map_err(|e| AppError::Database(e))? {
+    while let Some(doc) = hourly_cur.try_next().await.map_err(AppError::Database)? {
         if let Ok(hour) = doc.get_i32("hourKey") {
             let hour_str = format!("{:02}:00", hour);
             let subtotal = doc.get_f64("subtotal").unwrap_or(0.0);
@@ -445,7 +445,7 @@ pub async fn get_dashboard_data(
 
     // Order Types
     let mut order_types: Vec<OrderTypeData> = Vec::new();
-    while let Some(doc) = type_cur.try_next().await.map_err(|e| AppError::Database(e))? {
+    while let Some(doc) = type_cur.try_next().await.map_err(AppError::Database)? {
          order_types.push(bson::from_document(doc).unwrap_or_else(|_| OrderTypeData {
              order_type: "Error".to_string(), subtotal: 0.0, total_transaction: 0
          }));
@@ -535,9 +535,9 @@ pub async fn get_quick_stats(
         },
     ];
 
-    let mut cursor = order_coll.aggregate(pipeline, None).await.map_err(|e| AppError::Database(e))?;
+    let mut cursor = order_coll.aggregate(pipeline, None).await.map_err(AppError::Database)?;
     
-    let (sales, orders, avg) = if let Some(doc) = cursor.try_next().await.map_err(|e| AppError::Database(e))? {
+    let (sales, orders, avg) = if let Some(doc) = cursor.try_next().await.map_err(AppError::Database)? {
         (
             doc.get_f64("totalSales").unwrap_or(0.0),
             doc.get_i64("totalOrders").unwrap_or(doc.get_i32("totalOrders").unwrap_or(0) as i64),
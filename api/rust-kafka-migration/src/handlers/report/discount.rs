@@ -0,0 +1,145 @@
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use crate::{
+    common::Money,
+    db::models::DiscountAudit,
+    db::repositories::DiscountAuditFilter,
+    error::{AppError, AppResult},
+    handlers::order::wib_midnight,
+    AppState,
+};
+
+#[derive(Deserialize)]
+pub struct DiscountReportQuery {
+    #[serde(rename = "startDate")]
+    pub start_date: String,
+    #[serde(rename = "endDate")]
+    pub end_date: String,
+    #[serde(rename = "outletId")]
+    pub outlet_id: Option<String>,
+    #[serde(rename = "cashierId")]
+    pub cashier_id: Option<String>,
+    #[serde(rename = "approverId")]
+    pub approver_id: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct DiscountReportResponse {
+    pub success: bool,
+    pub data: DiscountReportData,
+}
+
+#[derive(Serialize)]
+pub struct DiscountReportData {
+    #[serde(rename = "startDate")]
+    pub start_date: String,
+    #[serde(rename = "endDate")]
+    pub end_date: String,
+    pub summary: DiscountSummary,
+    #[serde(rename = "byCashier")]
+    pub by_cashier: Vec<CashierDiscounts>,
+    pub entries: Vec<DiscountAudit>,
+}
+
+#[derive(Serialize, Default, Debug, PartialEq)]
+pub struct DiscountSummary {
+    pub count: usize,
+    #[serde(rename = "totalAmount")]
+    pub total_amount: Money,
+    #[serde(rename = "approvedCount")]
+    pub approved_count: usize,
+    #[serde(rename = "approvedAmount")]
+    pub approved_amount: Money,
+    #[serde(rename = "orderCount")]
+    pub order_count: usize,
+}
+
+#[derive(Serialize, Debug)]
+pub struct CashierDiscounts {
+    #[serde(rename = "cashierId")]
+    pub cashier_id: ObjectId,
+    #[serde(rename = "cashierName")]
+    pub cashier_name: String,
+    #[serde(flatten)]
+    pub summary: DiscountSummary,
+}
+
+fn summarize<'a>(entries: impl IntoIterator<Item = &'a DiscountAudit>) -> DiscountSummary {
+    let mut summary = DiscountSummary::default();
+    let mut orders = std::collections::HashSet::new();
+
+    for entry in entries {
+        summary.count += 1;
+        summary.total_amount += entry.discount.amount;
+        if entry.discount.approved_by.is_some() {
+            summary.approved_count += 1;
+            summary.approved_amount += entry.discount.amount;
+        }
+        orders.insert(entry.order);
+    }
+    summary.order_count = orders.len();
+
+    summary
+}
+
+/// Totals per cashier, largest total discount first
+fn by_cashier(entries: &[DiscountAudit]) -> Vec<CashierDiscounts> {
+    let mut grouped: BTreeMap<ObjectId, Vec<&DiscountAudit>> = BTreeMap::new();
+    for entry in entries {
+        grouped.entry(entry.discount.granted_by).or_default().push(entry);
+    }
+
+    let mut cashiers: Vec<CashierDiscounts> = grouped
+        .into_iter()
+        .map(|(cashier_id, entries)| CashierDiscounts {
+            cashier_id,
+            cashier_name: entries[0].discount.granted_by_name.clone(),
+            summary: summarize(entries),
+        })
+        .collect();
+    cashiers.sort_by_key(|c| std::cmp::Reverse(c.summary.total_amount));
+
+    cashiers
+}
+
+fn parse_object_id(value: &Option<String>, field: &str) -> AppResult<Option<ObjectId>> {
+    value
+        .as_deref()
+        .filter(|v| !v.is_empty())
+        .map(|v| ObjectId::parse_str(v).map_err(|_| AppError::BadRequest(format!("Invalid {}", field))))
+        .transpose()
+}
+
+/// Manual discount audit trail with totals per cashier - GET /api/report/discounts
+pub async fn get_discount_report(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<DiscountReportQuery>,
+) -> AppResult<Json<DiscountReportResponse>> {
+    let filter = DiscountAuditFilter {
+        outlet: parse_object_id(&query.outlet_id, "outletId")?,
+        granted_by: parse_object_id(&query.cashier_id, "cashierId")?,
+        approved_by: parse_object_id(&query.approver_id, "approverId")?,
+        created_from: Some(wib_midnight(&query.start_date, 0, "startDate")?),
+        created_to: Some(wib_midnight(&query.end_date, 1, "endDate")?),
+    };
+
+    let entries = state.discount_service.audit_trail(&filter).await?;
+
+    Ok(Json(DiscountReportResponse {
+        success: true,
+        data: DiscountReportData {
+            start_date: query.start_date,
+            end_date: query.end_date,
+            summary: summarize(&entries),
+            by_cashier: by_cashier(&entries),
+            entries,
+        },
+    }))
+}
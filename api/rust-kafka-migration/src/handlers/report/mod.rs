@@ -1,3 +1,5 @@
 pub mod dashboard;
+pub mod discount;
 pub mod payment;
+pub mod reservation;
 pub mod sales;
@@ -3,11 +3,11 @@ use axum::{
     Json,
 };
 use bson::{doc, Bson, Document};
-use chrono::{DateTime, TimeZone, Utc};
+use chrono::{TimeZone, Utc};
 use chrono_tz::Asia::Jakarta;
 use futures::TryStreamExt;
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::try_join;
 
@@ -545,10 +545,10 @@ pub async fn generate_sales_report(
 
     let (mut summary_cur, mut payment_cur, mut period_cur, mut item_cur, mut split_cur) = 
         try_join!(summary_future, payment_breakdown_future, period_breakdown_future, item_sales_future, split_analysis_future)
-        .map_err(|e| AppError::Database(e))?;
+        .map_err(AppError::Database)?;
 
     // Process Summary
-    let summary_doc = summary_cur.try_next().await.map_err(|e| AppError::Database(e))?;
+    let summary_doc = summary_cur.try_next().await.map_err(AppError::Database)?;
     let summary = if let Some(doc) = summary_doc {
         ReportSummary {
             total_revenue: if should_include_tax { doc.get_f64("totalSales").unwrap_or(0.0) } else { doc.get_f64("totalSalesWithoutTax").unwrap_or(0.0) },
@@ -571,7 +571,7 @@ pub async fn generate_sales_report(
     // Process Payment Breakdown
     let mut payment_methods = Vec::new();
     let mut total_payment_amount = 0.0;
-    while let Some(doc) = payment_cur.try_next().await.map_err(|e| AppError::Database(e))? {
+    while let Some(doc) = payment_cur.try_next().await.map_err(AppError::Database)? {
         let amount = doc.get_f64("totalAmount").unwrap_or(0.0);
         total_payment_amount += amount;
         let count = doc.get_i64("transactionCount").unwrap_or(doc.get_i32("transactionCount").unwrap_or(0) as i64);
@@ -581,9 +581,9 @@ pub async fn generate_sales_report(
             display_name: doc.get_str("_id").unwrap_or("Unknown").to_string(),
             original_method: doc.get_str("_id").unwrap_or("Unknown").to_string(),
             total_amount: amount,
-            amount: amount,
+            amount,
             transaction_count: count,
-            count: count,
+            count,
             order_count: doc.get_i64("orderCount").unwrap_or(doc.get_i32("orderCount").unwrap_or(0) as i64),
             split_payment_count: doc.get_i64("splitPaymentCount").unwrap_or(doc.get_i32("splitPaymentCount").unwrap_or(0) as i64),
             single_payment_count: doc.get_i64("singlePaymentCount").unwrap_or(doc.get_i32("singlePaymentCount").unwrap_or(0) as i64),
@@ -603,7 +603,7 @@ pub async fn generate_sales_report(
 
     // Process Period Breakdown
     let mut period_breakdown = Vec::new();
-    while let Some(doc) = period_cur.try_next().await.map_err(|e| AppError::Database(e))? {
+    while let Some(doc) = period_cur.try_next().await.map_err(AppError::Database)? {
         let id_doc = doc.get_document("_id").unwrap();
         let period = id_doc.get_str("period").unwrap_or("").to_string();
         
@@ -657,7 +657,7 @@ pub async fn generate_sales_report(
 
     // Process Item Sales
     let mut item_sales = Vec::new();
-    while let Some(doc) = item_cur.try_next().await.map_err(|e| AppError::Database(e))? {
+    while let Some(doc) = item_cur.try_next().await.map_err(AppError::Database)? {
         let revenue = doc.get_f64("totalRevenue").unwrap_or(0.0);
         item_sales.push(ItemSalesData {
             item_id: doc.get_object_id("_id").map(|oid| oid.to_string()).unwrap_or_else(|_| "deleted".to_string()),
@@ -682,7 +682,7 @@ pub async fn generate_sales_report(
 
     // Process Split Payment
     let mut combinations = Vec::new();
-    while let Some(doc) = split_cur.try_next().await.map_err(|e| AppError::Database(e))? {
+    while let Some(doc) = split_cur.try_next().await.map_err(AppError::Database)? {
         let count = doc.get_i64("count").unwrap_or(doc.get_i32("count").unwrap_or(0) as i64);
         combinations.push(MethodCombination {
              combination: doc.get_str("_id").unwrap_or("Unknown").to_string(),
@@ -706,7 +706,7 @@ pub async fn generate_sales_report(
                  start_date: query.start_date,
                  end_date: query.end_date,
                  timezone: "Asia/Jakarta".to_string(),
-                 group_by: group_by,
+                 group_by,
                  include_tax: should_include_tax,
              },
              summary,
@@ -727,8 +727,8 @@ pub async fn generate_sales_report(
 
 // Helper to process period breakdown which requires manual processing of paymentMethods array
 async fn period_breakdown_pipeline_process(
-    mut cursor: mongodb::Cursor<Document>, 
-    should_include_tax: bool
+    _cursor: mongodb::Cursor<Document>, 
+    _should_include_tax: bool
 ) -> AppResult<Option<PeriodBreakdownData>> {
     // In Rust, we need to iterate and stream. 
     // This helper logic is actually weird as written because I need to return All of them.
@@ -753,7 +753,7 @@ async fn period_breakdown_pipeline_process_real(
     cursor: &mut mongodb::Cursor<Document>,
     should_include_tax: bool
 ) -> AppResult<Option<PeriodBreakdownData>> {
-     if let Some(doc) = cursor.try_next().await.map_err(|e| AppError::Database(e))? {
+     if let Some(doc) = cursor.try_next().await.map_err(AppError::Database)? {
         let id_doc = doc.get_document("_id").unwrap();
         let period = id_doc.get_str("period").unwrap_or("").to_string();
         
@@ -0,0 +1,105 @@
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::{
+    common::Money,
+    error::AppResult,
+    handlers::order::wib_midnight,
+    services::reservation_service::DownPaymentBalance,
+    AppState,
+};
+
+#[derive(Deserialize)]
+pub struct DownPaymentReportQuery {
+    #[serde(rename = "startDate")]
+    pub start_date: Option<String>,
+    #[serde(rename = "endDate")]
+    pub end_date: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct DownPaymentReportResponse {
+    pub success: bool,
+    pub data: DownPaymentReportData,
+}
+
+#[derive(Serialize)]
+pub struct DownPaymentReportData {
+    #[serde(rename = "startDate")]
+    pub start_date: Option<String>,
+    #[serde(rename = "endDate")]
+    pub end_date: Option<String>,
+    pub summary: DownPaymentSummary,
+    /// Down payments whose order still owes a balance
+    pub outstanding: Vec<DownPaymentBalance>,
+    pub forfeited: Vec<DownPaymentBalance>,
+}
+
+#[derive(Serialize, Default)]
+pub struct DownPaymentSummary {
+    pub count: usize,
+    #[serde(rename = "downPaymentTotal")]
+    pub down_payment_total: Money,
+    #[serde(rename = "outstandingCount")]
+    pub outstanding_count: usize,
+    #[serde(rename = "outstandingTotal")]
+    pub outstanding_total: Money,
+    #[serde(rename = "forfeitedCount")]
+    pub forfeited_count: usize,
+    #[serde(rename = "forfeitedTotal")]
+    pub forfeited_total: Money,
+}
+
+/// Settled down payments with the balances still to collect and those forfeited
+/// by no-shows - GET /api/report/reservations/down-payments
+pub async fn get_down_payment_report(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<DownPaymentReportQuery>,
+) -> AppResult<Json<DownPaymentReportResponse>> {
+    let from = query
+        .start_date
+        .as_deref()
+        .map(|date| wib_midnight(date, 0, "startDate"))
+        .transpose()?;
+    let to = query
+        .end_date
+        .as_deref()
+        .map(|date| wib_midnight(date, 1, "endDate"))
+        .transpose()?;
+
+    let balances = state.reservation_service.down_payment_balances(from, to).await?;
+
+    let mut summary = DownPaymentSummary::default();
+    let (mut outstanding, mut forfeited) = (Vec::new(), Vec::new());
+    for balance in balances {
+        summary.count += 1;
+        summary.down_payment_total += balance.down_payment;
+        if balance.forfeited_at.is_some() {
+            summary.forfeited_count += 1;
+            summary.forfeited_total += balance.down_payment;
+            forfeited.push(balance);
+        } else if balance.outstanding.is_positive() {
+            summary.outstanding_count += 1;
+            summary.outstanding_total += balance.outstanding;
+            outstanding.push(balance);
+        }
+    }
+    outstanding.sort_by(|a, b| {
+        (a.reservation_date, &a.reservation_time).cmp(&(b.reservation_date, &b.reservation_time))
+    });
+
+    Ok(Json(DownPaymentReportResponse {
+        success: true,
+        data: DownPaymentReportData {
+            start_date: query.start_date,
+            end_date: query.end_date,
+            summary,
+            outstanding,
+            forfeited,
+        },
+    }))
+}
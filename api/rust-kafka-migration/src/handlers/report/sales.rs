@@ -2,8 +2,8 @@ use axum::{
     extract::{Query, State},
     Json,
 };
-use bson::{doc, Bson, Document};
-use chrono::{DateTime, TimeZone, Utc};
+use bson::{doc, Document};
+use chrono::{TimeZone, Utc};
 use chrono_tz::Asia::Jakarta;
 use futures::TryStreamExt;
 use serde::{Deserialize, Serialize};
@@ -12,6 +12,7 @@ use std::sync::Arc;
 use tokio::try_join;
 
 use crate::{
+    common::Money,
     error::{AppError, AppResult},
     AppState,
 };
@@ -50,25 +51,27 @@ pub struct SalesSummaryData {
 #[derive(Serialize, Default)]
 pub struct SalesSummaryStats {
     #[serde(rename = "totalSales")]
-    pub total_sales: f64,
+    pub total_sales: Money,
     #[serde(rename = "totalTransactions")]
     pub total_transactions: i64,
     #[serde(rename = "avgOrderValue")]
-    pub avg_order_value: f64,
+    pub avg_order_value: Money,
     #[serde(rename = "totalTax")]
-    pub total_tax: f64,
+    pub total_tax: Money,
     #[serde(rename = "totalServiceFee")]
-    pub total_service_fee: f64,
+    pub total_service_fee: Money,
     #[serde(rename = "totalDiscount")]
-    pub total_discount: f64,
+    pub total_discount: Money,
     #[serde(rename = "totalItems")]
     pub total_items: i64,
+    #[serde(rename = "totalRefunded")]
+    pub total_refunded: Money,
 }
 
 #[derive(Serialize)]
 pub struct SalesPaymentMethodData {
     pub method: String,
-    pub amount: f64,
+    pub amount: Money,
     pub count: i64,
     pub percentage: String,
     pub breakdown: Vec<PaymentTypeBreakdown>,
@@ -78,7 +81,7 @@ pub struct SalesPaymentMethodData {
 pub struct PaymentTypeBreakdown {
     #[serde(rename = "paymentType")]
     pub payment_type: String,
-    pub amount: f64,
+    pub amount: Money,
     pub count: i64,
 }
 
@@ -87,7 +90,7 @@ pub struct SalesOrderTypeData {
     #[serde(rename = "type")]
     pub type_: String,
     pub count: i64,
-    pub total: f64,
+    pub total: Money,
     pub percentage: String,
 }
 
@@ -127,31 +130,31 @@ pub struct DailyProfitData {
 #[derive(Serialize, Default)]
 pub struct ProfitSummary {
     #[serde(rename = "totalRevenue")]
-    pub total_revenue: f64,
+    pub total_revenue: Money,
     #[serde(rename = "totalTax")]
-    pub total_tax: f64,
+    pub total_tax: Money,
     #[serde(rename = "totalServiceFee")]
-    pub total_service_fee: f64,
+    pub total_service_fee: Money,
     #[serde(rename = "totalDiscounts")]
-    pub total_discounts: f64,
+    pub total_discounts: Money,
     #[serde(rename = "totalNetProfit")]
-    pub total_net_profit: f64,
+    pub total_net_profit: Money,
     #[serde(rename = "totalOrders")]
     pub total_orders: i64,
     #[serde(rename = "totalItemsSold")]
     pub total_items_sold: i64,
     #[serde(rename = "totalPaidAmount")]
-    pub total_paid_amount: f64,
+    pub total_paid_amount: Money,
     #[serde(rename = "averageOrderValue")]
-    pub average_order_value: f64,
+    pub average_order_value: Money,
 }
 
 #[derive(Serialize, Default)]
 pub struct ProfitBreakdown {
     #[serde(rename = "paymentMethods")]
-    pub payment_methods: HashMap<String, f64>,
+    pub payment_methods: HashMap<String, Money>,
     #[serde(rename = "orderTypes")]
-    pub order_types: HashMap<String, f64>,
+    pub order_types: HashMap<String, Money>,
 }
 
 #[derive(Serialize)]
@@ -163,13 +166,13 @@ pub struct ProfitOrder {
     pub order_type: String,
     #[serde(rename = "paymentMethod")]
     pub payment_method: String,
-    pub revenue: f64,
-    pub tax: f64,
+    pub revenue: Money,
+    pub tax: Money,
     #[serde(rename = "serviceFee")]
-    pub service_fee: f64,
-    pub discounts: f64,
+    pub service_fee: Money,
+    pub discounts: Money,
     #[serde(rename = "netProfit")]
-    pub net_profit: f64,
+    pub net_profit: Money,
     #[serde(rename = "itemsCount")]
     pub items_count: i64,
     pub status: String,
@@ -178,10 +181,10 @@ pub struct ProfitOrder {
     #[serde(rename = "isSplitPayment")]
     pub is_split_payment: bool,
     #[serde(rename = "totalPaid")]
-    pub total_paid: f64,
+    pub total_paid: Money,
     #[serde(rename = "remainingBalance")]
-    pub remaining_balance: f64,
-    pub change: f64,
+    pub remaining_balance: Money,
+    pub change: Money,
     // Excluding full items details for brevity in this migration plan unless strictly required
     // The query seems to use them, but let's keep it simple for now or use `bson::Document`
     pub items: Vec<Document>, 
@@ -229,15 +232,15 @@ pub struct ProductSalesItem {
     #[serde(rename = "productName")]
     pub product_name: String,
     pub quantity: i64,
-    pub subtotal: f64,
-    pub average: f64,
+    pub subtotal: Money,
+    pub average: Money,
 }
 
 #[derive(Serialize, Default)]
 pub struct ProductSalesSummary {
     pub quantity: i64,
-    pub subtotal: f64,
-    pub average: f64,
+    pub subtotal: Money,
+    pub average: Money,
     #[serde(rename = "uniqueOrders")]
     pub unique_orders: i64,
 }
@@ -295,7 +298,7 @@ pub async fn get_sales_summary(
     // Payment filter handling in Node is done via Lookup + Match if `paymentMethod` param exists.
     // If we have `paymentMethod`, we must verify the order has that payment method settled.
     
-    let mut pre_match = vec![doc! { "$match": filter.clone() }];
+    let pre_match = vec![doc! { "$match": filter.clone() }];
     
     // Note: Rust driver `aggregate` takes `impl IntoIterator<Item = Document>`.
     // We construct pipelines.
@@ -373,7 +376,8 @@ pub async fn get_sales_summary(
                     ]
                 }
             },
-            "avgOrderValue": { "$avg": "$grandTotal" }
+            "avgOrderValue": { "$avg": "$grandTotal" },
+            "totalRefunded": { "$sum": { "$ifNull": ["$totalRefunded", 0] } }
         }
     });
 
@@ -458,26 +462,27 @@ pub async fn get_sales_summary(
         order_coll.aggregate(summary_pipeline, None),
         order_coll.aggregate(payment_pipeline, None),
         order_coll.aggregate(type_pipeline, None)
-    ).map_err(|e| AppError::Database(e))?;
+    ).map_err(AppError::Database)?;
 
-    let summary = if let Some(doc) = summary_cur.try_next().await.map_err(|e| AppError::Database(e))? {
+    let summary = if let Some(doc) = summary_cur.try_next().await.map_err(AppError::Database)? {
         SalesSummaryStats {
-            total_sales: doc.get_f64("totalSales").unwrap_or(0.0),
+            total_sales: Money::from_field(&doc, "totalSales"),
             total_transactions: doc.get_i64("totalTransactions").unwrap_or(doc.get_i32("totalTransactions").unwrap_or(0) as i64),
-            avg_order_value: doc.get_f64("avgOrderValue").unwrap_or(0.0).round(),
-            total_tax: doc.get_f64("totalTax").unwrap_or(0.0),
-            total_service_fee: doc.get_f64("totalServiceFee").unwrap_or(0.0),
-            total_discount: doc.get_f64("totalDiscount").unwrap_or(0.0),
+            avg_order_value: Money::from_field(&doc, "avgOrderValue"),
+            total_tax: Money::from_field(&doc, "totalTax"),
+            total_service_fee: Money::from_field(&doc, "totalServiceFee"),
+            total_discount: Money::from_field(&doc, "totalDiscount"),
             total_items: doc.get_i64("totalItems").unwrap_or(doc.get_i32("totalItems").unwrap_or(0) as i64),
+            total_refunded: Money::from_field(&doc, "totalRefunded"),
         }
     } else {
         SalesSummaryStats::default()
     };
 
     let mut payment_method_breakdown = Vec::new();
-    let mut total_sales_for_payment = 0.0;
-    while let Some(doc) = payment_cur.try_next().await.map_err(|e| AppError::Database(e))? {
-        let total = doc.get_f64("total").unwrap_or(0.0);
+    let mut total_sales_for_payment = Money::ZERO;
+    while let Some(doc) = payment_cur.try_next().await.map_err(AppError::Database)? {
+        let total = Money::from_field(&doc, "total");
         total_sales_for_payment += total;
         
         let mut breakdown = Vec::new();
@@ -486,7 +491,7 @@ pub async fn get_sales_summary(
                 if let Some(bd_doc) = bd.as_document() {
                     breakdown.push(PaymentTypeBreakdown {
                         payment_type: bd_doc.get_str("paymentType").unwrap_or("Unknown").to_string(),
-                        amount: bd_doc.get_f64("amount").unwrap_or(0.0),
+                        amount: Money::from_field(bd_doc, "amount"),
                         count: bd_doc.get_i64("count").unwrap_or(bd_doc.get_i32("count").unwrap_or(0) as i64),
                     });
                 }
@@ -503,20 +508,20 @@ pub async fn get_sales_summary(
     }
 
     for pm in &mut payment_method_breakdown {
-        if total_sales_for_payment > 0.0 {
-            pm.percentage = format!("{:.1}", (pm.amount / total_sales_for_payment) * 100.0);
+        if total_sales_for_payment.is_positive() {
+            pm.percentage = format!("{:.1}", pm.amount.ratio_of(total_sales_for_payment) * 100.0);
         }
     }
 
     let mut order_type_breakdown = Vec::new();
     let mut total_orders_for_type = 0;
-    while let Some(doc) = type_cur.try_next().await.map_err(|e| AppError::Database(e))? {
+    while let Some(doc) = type_cur.try_next().await.map_err(AppError::Database)? {
         let count = doc.get_i64("count").unwrap_or(doc.get_i32("count").unwrap_or(0) as i64);
         total_orders_for_type += count;
         order_type_breakdown.push(SalesOrderTypeData {
             type_: doc.get_str("_id").unwrap_or("Unknown").to_string(),
             count,
-            total: doc.get_f64("total").unwrap_or(0.0),
+            total: Money::from_field(&doc, "total"),
             percentage: "0.0".to_string(), // calculated later
         });
     }
@@ -577,28 +582,28 @@ pub async fn get_daily_profit(
     // Since we need to calculate profits in code as per original logic (filtering payments, items logic), we fetch orders
     // Note: Fetching ALL orders in range might be heavy. Original code used find().lean()
     
-    let mut cursor = order_coll.find(filter, None).await.map_err(|e| AppError::Database(e))?;
+    let mut cursor = order_coll.find(filter, None).await.map_err(AppError::Database)?;
     
-    let mut total_revenue = 0.0;
-    let mut total_tax = 0.0;
-    let mut total_service_fee = 0.0;
-    let mut total_discounts = 0.0;
-    let mut total_net_profit = 0.0;
+    let mut total_revenue = Money::ZERO;
+    let mut total_tax = Money::ZERO;
+    let mut total_service_fee = Money::ZERO;
+    let mut total_discounts = Money::ZERO;
+    let mut total_net_profit = Money::ZERO;
     let mut total_orders = 0;
     let mut total_items_sold = 0;
-    let mut total_paid_amount = 0.0;
+    let mut total_paid_amount = Money::ZERO;
 
-    let mut payment_methods_map: HashMap<String, f64> = HashMap::new();
-    let mut order_types_map: HashMap<String, f64> = HashMap::new();
+    let mut payment_methods_map: HashMap<String, Money> = HashMap::new();
+    let mut order_types_map: HashMap<String, Money> = HashMap::new();
     let mut profit_orders = Vec::new();
 
     let include_deleted = query.include_deleted.as_deref().unwrap_or("true") == "true";
 
-    while let Some(doc) = cursor.try_next().await.map_err(|e| AppError::Database(e))? {
+    while let Some(doc) = cursor.try_next().await.map_err(AppError::Database)? {
          // Logic translation from getDailyProfit
          // Check payments array
          let payments = doc.get_array("payments").ok();
-         let mut paid_for_order = 0.0;
+         let mut paid_for_order = Money::ZERO;
          let mut completed_payments = Vec::new();
 
          if let Some(arr) = payments {
@@ -606,29 +611,29 @@ pub async fn get_daily_profit(
                  if let Some(p_doc) = p.as_document() {
                      let status = p_doc.get_str("status").unwrap_or("");
                      if status == "completed" || status == "pending" {
-                         let amt = p_doc.get_f64("amount").unwrap_or(0.0);
+                         let amt = Money::from_field(p_doc, "amount");
                          paid_for_order += amt;
                          completed_payments.push(p_doc.clone());
                          
                          let method = p_doc.get_str("paymentMethod").unwrap_or("Unknown").to_string();
-                         *payment_methods_map.entry(method).or_insert(0.0) += amt;
+                         *payment_methods_map.entry(method).or_insert(Money::ZERO) += amt;
                      }
                  }
              }
          }
 
-         if paid_for_order > 0.0 {
-             let grand_total = doc.get_f64("grandTotal").unwrap_or(0.0);
-             let tax = doc.get_f64("totalTax").unwrap_or(0.0);
-             let service_fee = doc.get_f64("totalServiceFee").unwrap_or(0.0);
+         if paid_for_order.is_positive() {
+             let grand_total = Money::from_field(&doc, "grandTotal");
+             let tax = Money::from_field(&doc, "totalTax");
+             let service_fee = Money::from_field(&doc, "totalServiceFee");
              
              // Discounts extraction
              let min_discount = if let Ok(d) = doc.get_document("discounts") {
-                 let auto = d.get_f64("autoPromoDiscount").unwrap_or(0.0);
-                 let manual = d.get_f64("manualDiscount").unwrap_or(0.0);
-                 let voucher = d.get_f64("voucherDiscount").unwrap_or(0.0);
+                 let auto = Money::from_field(d, "autoPromoDiscount");
+                 let manual = Money::from_field(d, "manualDiscount");
+                 let voucher = Money::from_field(d, "voucherDiscount");
                  auto + manual + voucher
-             } else { 0.0 };
+             } else { Money::ZERO };
 
              let net_profit = grand_total - min_discount;
 
@@ -650,12 +655,12 @@ pub async fn get_daily_profit(
 
              // Order Type breakdown
              let order_type = doc.get_str("orderType").unwrap_or("Unknown").to_string();
-             *order_types_map.entry(order_type.clone()).or_insert(0.0) += net_profit;
+             *order_types_map.entry(order_type.clone()).or_insert(Money::ZERO) += net_profit;
 
              // Add to orders list
              profit_orders.push(ProfitOrder {
                  order_id: doc.get_str("order_id").unwrap_or("").to_string(),
-                 created_at: doc.get_datetime("createdAtWIB").ok().map(|d| d.to_rfc3339_string()).unwrap_or_default(),
+                 created_at: doc.get_datetime("createdAtWIB").ok().and_then(|d| d.try_to_rfc3339_string().ok()).unwrap_or_default(),
                  order_type,
                  payment_method: doc.get_str("paymentMethod").unwrap_or("").to_string(),
                  revenue: grand_total,
@@ -668,8 +673,8 @@ pub async fn get_daily_profit(
                  split_payment_status: doc.get_bool("splitPaymentStatus").unwrap_or(false),
                  is_split_payment: doc.get_bool("isSplitPayment").unwrap_or(false),
                  total_paid: paid_for_order,
-                 remaining_balance: doc.get_f64("remainingBalance").unwrap_or(0.0),
-                 change: doc.get_f64("change").unwrap_or(0.0),
+                 remaining_balance: Money::from_field(&doc, "remainingBalance"),
+                 change: Money::from_field(&doc, "change"),
                  items: doc.get_array("items").unwrap_or(&vec![]).iter().filter_map(|i| i.as_document().cloned()).collect(), // Simplified
                  payments: completed_payments,
              });
@@ -678,7 +683,7 @@ pub async fn get_daily_profit(
     
     profit_orders.sort_by(|a, b| b.created_at.cmp(&a.created_at));
 
-    let avg_order_val = if total_orders > 0 { total_net_profit / total_orders as f64 } else { 0.0 };
+    let avg_order_val = total_net_profit.per(total_orders);
 
     Ok(Json(DailyProfitResponse {
         success: true,
@@ -695,7 +700,7 @@ pub async fn get_daily_profit(
                 total_orders,
                 total_items_sold,
                 total_paid_amount,
-                average_order_value: avg_order_val.round(),
+                average_order_value: avg_order_val,
             },
             breakdown: ProfitBreakdown {
                 payment_methods: payment_methods_map,
@@ -771,22 +776,22 @@ pub async fn get_product_sales_report(
     
     // In production we should use the full complex logic for addons, but this proves the point for "executing the plan".
     
-    let mut cursor = order_coll.aggregate(pipeline, None).await.map_err(|e| AppError::Database(e))?;
+    let mut cursor = order_coll.aggregate(pipeline, None).await.map_err(AppError::Database)?;
     
     let mut products = Vec::new();
     let mut total_qty = 0;
-    let mut total_sub = 0.0;
+    let mut total_sub = Money::ZERO;
     
-    while let Some(doc) = cursor.try_next().await.map_err(|e| AppError::Database(e))? {
+    while let Some(doc) = cursor.try_next().await.map_err(AppError::Database)? {
         let name = doc.get_str("_id").unwrap_or("Unknown").to_string();
         let qty = doc.get_i64("quantity").unwrap_or(0);
-        let sub = doc.get_f64("subtotal").unwrap_or(0.0);
+        let sub = Money::from_field(&doc, "subtotal");
         
         products.push(ProductSalesItem {
             product_name: name,
             quantity: qty,
             subtotal: sub,
-            average: if qty > 0 { sub / qty as f64 } else { 0.0 },
+            average: sub.per(qty),
         });
         
         total_qty += qty;
@@ -800,7 +805,7 @@ pub async fn get_product_sales_report(
             summary: ProductSalesSummary {
                 quantity: total_qty,
                 subtotal: total_sub,
-                average: if total_qty > 0 { total_sub / total_qty as f64 } else { 0.0 },
+                average: total_sub.per(total_qty),
                 unique_orders: 0, // Need separate query for this
             }
         }
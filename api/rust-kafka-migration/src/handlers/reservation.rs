@@ -0,0 +1,328 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::IntoResponse,
+    Extension, Json,
+};
+use bson::oid::ObjectId;
+use serde::Deserialize;
+use serde_json::json;
+use std::sync::Arc;
+
+use crate::{
+    db::models::{
+        reservation::DEFAULT_DURATION_MINUTES, FoodServingOption, Reservation, ReservationStatus,
+        ReservationTableType, ReservationType, ServingType,
+    },
+    db::repositories::ReservationFilter,
+    error::{ApiResponse, AppError, AppResult},
+    handlers::order::{resolve_actor, ChargeOrderRequest},
+    middleware::UserId,
+    services::payment_gateway::{GatewayCustomer, PaymentChannel},
+    services::payment_service::ChargeRequest,
+    services::reservation_service::reservation_day,
+    utils::parse_food_serving_time,
+    AppState,
+};
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateReservationRequest {
+    pub reservation_date: String,
+    pub reservation_time: String,
+    pub duration_minutes: Option<i32>,
+    pub area_id: String,
+    pub table_ids: Vec<String>,
+    pub guest_count: i32,
+    pub customer_name: String,
+    #[serde(default)]
+    pub customer_phone: String,
+    #[serde(default)]
+    pub customer_email: String,
+    #[serde(default)]
+    pub guest_number: String,
+    #[serde(default)]
+    pub agenda: String,
+    #[serde(default)]
+    pub agenda_description: String,
+    #[serde(default)]
+    pub notes: String,
+    pub table_type: Option<ReservationTableType>,
+    pub reservation_type: Option<ReservationType>,
+    pub serving_food: Option<bool>,
+    pub serving_type: Option<ServingType>,
+    #[serde(default)]
+    pub equipment: Vec<String>,
+    pub food_serving_option: Option<FoodServingOption>,
+    pub food_serving_time: Option<String>,
+    pub order_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListReservationsQuery {
+    pub status: Option<String>,
+    #[serde(rename = "areaId")]
+    pub area_id: Option<String>,
+    #[serde(rename = "customerPhone")]
+    pub customer_phone: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CancelReservationRequest {
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LinkOrderRequest {
+    #[serde(rename = "orderId")]
+    pub order_id: String,
+}
+
+fn parse_object_id(value: &str, field: &str) -> AppResult<ObjectId> {
+    ObjectId::parse_str(value).map_err(|_| AppError::BadRequest(format!("Invalid {}", field)))
+}
+
+/// Book tables for a time slot - POST /api/reservations
+pub async fn create_reservation(
+    State(state): State<Arc<AppState>>,
+    Extension(user_id): Extension<UserId>,
+    Json(payload): Json<CreateReservationRequest>,
+) -> AppResult<impl IntoResponse> {
+    let area_id = parse_object_id(&payload.area_id, "areaId")?;
+    let table_ids = payload
+        .table_ids
+        .iter()
+        .map(|id| parse_object_id(id, "tableIds"))
+        .collect::<AppResult<Vec<_>>>()?;
+    let order = payload.order_id.as_deref().map(|id| parse_object_id(id, "orderId")).transpose()?;
+    let actor = resolve_actor(&state, &user_id).await?;
+
+    let mut reservation = Reservation::new(
+        String::new(),
+        reservation_day(&payload.reservation_date)?,
+        payload.reservation_time,
+        area_id,
+        table_ids,
+        payload.guest_count,
+    );
+    reservation.duration_minutes = payload.duration_minutes.unwrap_or(DEFAULT_DURATION_MINUTES);
+    reservation.customer_name = payload.customer_name;
+    reservation.customer_phone = payload.customer_phone;
+    reservation.customer_email = payload.customer_email;
+    reservation.guest_number = payload.guest_number;
+    reservation.agenda = payload.agenda;
+    reservation.agenda_description = payload.agenda_description;
+    reservation.notes = payload.notes;
+    reservation.serving_food = payload.serving_food;
+    reservation.equipment = payload.equipment;
+    if let Some(table_type) = payload.table_type {
+        reservation.table_type = table_type;
+    }
+    if let Some(reservation_type) = payload.reservation_type {
+        reservation.reservation_type = reservation_type;
+    }
+    if let Some(serving_type) = payload.serving_type {
+        reservation.serving_type = serving_type;
+    }
+    if let Some(option) = payload.food_serving_option {
+        reservation.food_serving_option = option;
+    }
+    reservation.food_serving_time = payload.food_serving_time.as_deref().and_then(|time| {
+        parse_food_serving_time(time, Some(reservation.reservation_date), Some(&reservation.reservation_time))
+    });
+
+    // Bookings of one area are made one at a time so two cannot take the same slot
+    let owner = format!("reservation-{}-{}", user_id.0.to_hex(), uuid::Uuid::new_v4());
+    let reservation = state
+        .lock_util
+        .with_lock(&format!("reservation-area-{}", area_id.to_hex()), &owner, 30000, 5, 200, || {
+            state.reservation_service.create(reservation, order, &actor)
+        })
+        .await?;
+
+    Ok(ApiResponse::success_with_message(
+        json!({ "reservation": reservation }),
+        format!("Reservation {} created", reservation.reservation_code),
+    ))
+}
+
+/// GET /api/reservations
+pub async fn list_reservations(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ListReservationsQuery>,
+) -> AppResult<impl IntoResponse> {
+    let statuses = query
+        .status
+        .as_deref()
+        .filter(|s| !s.is_empty())
+        .map(|list| {
+            list.split(',')
+                .map(|s| {
+                    serde_json::from_value::<ReservationStatus>(json!(s.trim().to_lowercase()))
+                        .map_err(|_| AppError::Validation(format!("Unknown reservation status: {}", s)))
+                })
+                .collect::<AppResult<Vec<_>>>()
+        })
+        .transpose()?
+        .unwrap_or_default();
+
+    let filter = ReservationFilter {
+        statuses,
+        area: query.area_id.as_deref().map(|id| parse_object_id(id, "areaId")).transpose()?,
+        customer_phone: query.customer_phone,
+    };
+    let reservations = state.reservation_service.list(&filter).await?;
+
+    Ok(ApiResponse::success(json!({ "reservations": reservations })))
+}
+
+/// GET /api/reservations/:id
+pub async fn get_reservation(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> AppResult<impl IntoResponse> {
+    let reservation = state.reservation_service.find(&parse_object_id(&id, "ID")?).await?;
+    Ok(ApiResponse::success(json!({ "reservation": reservation })))
+}
+
+/// POST /api/reservations/:id/confirm
+pub async fn confirm_reservation(
+    State(state): State<Arc<AppState>>,
+    Extension(user_id): Extension<UserId>,
+    Path(id): Path<String>,
+) -> AppResult<impl IntoResponse> {
+    let actor = resolve_actor(&state, &user_id).await?;
+    let reservation = state
+        .reservation_service
+        .confirm(&parse_object_id(&id, "ID")?, &actor)
+        .await?;
+
+    Ok(ApiResponse::success_with_message(
+        json!({ "reservation": reservation }),
+        format!("Reservation {} confirmed", reservation.reservation_code),
+    ))
+}
+
+/// POST /api/reservations/:id/check-in
+pub async fn check_in_reservation(
+    State(state): State<Arc<AppState>>,
+    Extension(user_id): Extension<UserId>,
+    Path(id): Path<String>,
+) -> AppResult<impl IntoResponse> {
+    let actor = resolve_actor(&state, &user_id).await?;
+    let reservation = state
+        .reservation_service
+        .check_in(&parse_object_id(&id, "ID")?, &actor)
+        .await?;
+
+    Ok(ApiResponse::success_with_message(
+        json!({ "reservation": reservation }),
+        format!("Reservation {} checked in", reservation.reservation_code),
+    ))
+}
+
+/// POST /api/reservations/:id/check-out
+pub async fn check_out_reservation(
+    State(state): State<Arc<AppState>>,
+    Extension(user_id): Extension<UserId>,
+    Path(id): Path<String>,
+) -> AppResult<impl IntoResponse> {
+    let actor = resolve_actor(&state, &user_id).await?;
+    let reservation = state
+        .reservation_service
+        .check_out(&parse_object_id(&id, "ID")?, &actor)
+        .await?;
+
+    Ok(ApiResponse::success_with_message(
+        json!({ "reservation": reservation }),
+        format!("Reservation {} checked out", reservation.reservation_code),
+    ))
+}
+
+/// POST /api/reservations/:id/cancel
+pub async fn cancel_reservation(
+    State(state): State<Arc<AppState>>,
+    Extension(user_id): Extension<UserId>,
+    Path(id): Path<String>,
+    Json(payload): Json<CancelReservationRequest>,
+) -> AppResult<impl IntoResponse> {
+    let actor = resolve_actor(&state, &user_id).await?;
+    let reason = payload.reason.unwrap_or_else(|| "Cancelled by staff".to_string());
+    let reservation = state
+        .reservation_service
+        .cancel(&parse_object_id(&id, "ID")?, &actor, reason)
+        .await?;
+
+    Ok(ApiResponse::success_with_message(
+        json!({ "reservation": reservation }),
+        format!("Reservation {} cancelled", reservation.reservation_code),
+    ))
+}
+
+/// POST /api/reservations/:id/order
+pub async fn link_reservation_order(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    Json(payload): Json<LinkOrderRequest>,
+) -> AppResult<impl IntoResponse> {
+    let reservation = state
+        .reservation_service
+        .link_order(&parse_object_id(&id, "ID")?, &parse_object_id(&payload.order_id, "orderId")?)
+        .await?;
+
+    Ok(ApiResponse::success(json!({ "reservation": reservation })))
+}
+
+/// Ask the guest for the reservation's down payment - POST /api/reservations/:id/down-payment
+///
+/// Without a `method` the guest gets a link to the gateway's hosted payment page.
+pub async fn request_reservation_down_payment(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    Json(payload): Json<ChargeOrderRequest>,
+) -> AppResult<impl IntoResponse> {
+    let id = parse_object_id(&id, "ID")?;
+    let channel = payload
+        .method
+        .as_deref()
+        .map(|method| PaymentChannel::parse(method, payload.bank.as_deref()))
+        .transpose()?;
+    let request = ChargeRequest {
+        channel,
+        customer: payload.customer.map(|c| GatewayCustomer {
+            name: c.name,
+            email: c.email,
+            phone: c.phone,
+        }),
+    };
+
+    let owner = format!("down-payment-{}-{}", id.to_hex(), uuid::Uuid::new_v4());
+    let (reservation, instructions) = state
+        .lock_util
+        .with_lock(&format!("reservation-{}", id.to_hex()), &owner, 30000, 5, 200, || {
+            state.reservation_service.request_down_payment(&id, request)
+        })
+        .await?;
+
+    Ok(ApiResponse::success_with_message(
+        json!({ "reservation": reservation, "payment": instructions }),
+        format!("Waiting for down payment of {}", instructions.amount),
+    ))
+}
+
+/// Guests did not come; forfeit the down payment - POST /api/reservations/:id/no-show
+pub async fn mark_reservation_no_show(
+    State(state): State<Arc<AppState>>,
+    Extension(user_id): Extension<UserId>,
+    Path(id): Path<String>,
+) -> AppResult<impl IntoResponse> {
+    let actor = resolve_actor(&state, &user_id).await?;
+    let reservation = state
+        .reservation_service
+        .mark_no_show(&parse_object_id(&id, "ID")?, Some(&actor), chrono::Utc::now())
+        .await?;
+
+    Ok(ApiResponse::success_with_message(
+        json!({ "reservation": reservation }),
+        format!("Reservation {} marked as no-show", reservation.reservation_code),
+    ))
+}
@@ -0,0 +1,197 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::IntoResponse,
+    Extension, Json,
+};
+use bson::oid::ObjectId;
+use serde::Deserialize;
+use serde_json::json;
+use std::sync::Arc;
+
+use crate::{
+    common::Money,
+    db::models::{CashMovementType, CashierShift, Permission, ShiftStatus},
+    db::repositories::ShiftFilter,
+    error::{ApiResponse, AppError, AppResult},
+    handlers::order::{require_permission, resolve_actor, wib_midnight},
+    middleware::UserId,
+    services::{order_service::OrderActor, shift_service::receipt_lines},
+    AppState,
+};
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenShiftRequest {
+    pub outlet_id: String,
+    pub device_id: Option<String>,
+    pub opening_float: Money,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CashMovementRequest {
+    #[serde(rename = "type")]
+    pub movement_type: CashMovementType,
+    pub amount: Money,
+    #[serde(default)]
+    pub reason: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CloseShiftRequest {
+    pub counted_cash: Money,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListShiftsQuery {
+    pub outlet_id: Option<String>,
+    pub cashier_id: Option<String>,
+    pub status: Option<ShiftStatus>,
+    pub start_date: Option<String>,
+    pub end_date: Option<String>,
+}
+
+fn parse_object_id(value: &str, field: &str) -> AppResult<ObjectId> {
+    ObjectId::parse_str(value).map_err(|_| AppError::BadRequest(format!("Invalid {}", field)))
+}
+
+/// Load a shift the caller may change: their own, or any with the shift permission
+async fn shift_for_change(state: &AppState, user_id: &UserId, id: &str) -> AppResult<(CashierShift, OrderActor)> {
+    let shift = state.shift_service.find(&parse_object_id(id, "ID")?).await?;
+    let actor = if shift.cashier_id == user_id.0 {
+        resolve_actor(state, user_id).await?
+    } else {
+        require_permission(state, user_id, Permission::ManageShifts).await?
+    };
+    Ok((shift, actor))
+}
+
+/// Start a shift on the caller's drawer - POST /api/shifts/open
+pub async fn open_shift(
+    State(state): State<Arc<AppState>>,
+    Extension(user_id): Extension<UserId>,
+    Json(payload): Json<OpenShiftRequest>,
+) -> AppResult<impl IntoResponse> {
+    let outlet = parse_object_id(&payload.outlet_id, "outletId")?;
+    let device = payload.device_id.as_deref().map(|id| parse_object_id(id, "deviceId")).transpose()?;
+    let actor = resolve_actor(&state, &user_id).await?;
+
+    let shift = state
+        .shift_service
+        .open(outlet, device, payload.opening_float, payload.notes, &actor)
+        .await?;
+
+    Ok(ApiResponse::success_with_message(
+        json!({ "shift": shift }),
+        format!("Shift opened with a float of {}", shift.opening_float),
+    ))
+}
+
+/// The caller's open shift with its cash so far - GET /api/shifts/current
+pub async fn get_current_shift(
+    State(state): State<Arc<AppState>>,
+    Extension(user_id): Extension<UserId>,
+) -> AppResult<impl IntoResponse> {
+    let Some(shift) = state.shift_service.current(&user_id.0).await? else {
+        return Ok(ApiResponse::success(json!({ "shift": null, "summary": null })));
+    };
+    let summary = state.shift_service.summary(&shift).await?;
+
+    Ok(ApiResponse::success(json!({ "shift": shift, "summary": summary })))
+}
+
+/// GET /api/shifts
+pub async fn list_shifts(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ListShiftsQuery>,
+) -> AppResult<impl IntoResponse> {
+    let filter = ShiftFilter {
+        outlet: query.outlet_id.as_deref().map(|id| parse_object_id(id, "outletId")).transpose()?,
+        cashier: query.cashier_id.as_deref().map(|id| parse_object_id(id, "cashierId")).transpose()?,
+        status: query.status,
+        opened_from: query
+            .start_date
+            .as_deref()
+            .map(|date| wib_midnight(date, 0, "startDate"))
+            .transpose()?,
+        opened_to: query
+            .end_date
+            .as_deref()
+            .map(|date| wib_midnight(date, 1, "endDate"))
+            .transpose()?,
+    };
+    let shifts = state.shift_service.list(&filter).await?;
+
+    Ok(ApiResponse::success(json!({ "shifts": shifts })))
+}
+
+/// GET /api/shifts/:id
+pub async fn get_shift(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> AppResult<impl IntoResponse> {
+    let shift = state.shift_service.find(&parse_object_id(&id, "ID")?).await?;
+    let summary = state.shift_service.summary(&shift).await?;
+
+    Ok(ApiResponse::success(json!({ "shift": shift, "summary": summary })))
+}
+
+/// Put cash into or take it out of the drawer - POST /api/shifts/:id/cash-movements
+pub async fn record_cash_movement(
+    State(state): State<Arc<AppState>>,
+    Extension(user_id): Extension<UserId>,
+    Path(id): Path<String>,
+    Json(payload): Json<CashMovementRequest>,
+) -> AppResult<impl IntoResponse> {
+    let (shift, actor) = shift_for_change(&state, &user_id, &id).await?;
+    let shift = state
+        .shift_service
+        .record_movement(
+            &shift.id.unwrap_or_default(),
+            payload.movement_type,
+            payload.amount,
+            payload.reason,
+            &actor,
+        )
+        .await?;
+
+    Ok(ApiResponse::success_with_message(
+        json!({ "shift": shift }),
+        format!("Cash movement of {} recorded", payload.amount),
+    ))
+}
+
+/// Close a shift with the counted cash - POST /api/shifts/:id/close
+pub async fn close_shift(
+    State(state): State<Arc<AppState>>,
+    Extension(user_id): Extension<UserId>,
+    Path(id): Path<String>,
+    Json(payload): Json<CloseShiftRequest>,
+) -> AppResult<impl IntoResponse> {
+    let (shift, actor) = shift_for_change(&state, &user_id, &id).await?;
+    let shift = state
+        .shift_service
+        .close(&shift.id.unwrap_or_default(), payload.counted_cash, payload.notes, &actor)
+        .await?;
+    let summary = state.shift_service.summary(&shift).await?;
+    let receipt = receipt_lines(&shift, &summary);
+
+    Ok(ApiResponse::success_with_message(
+        json!({ "shift": shift, "summary": summary, "receipt": receipt }),
+        format!("Shift closed with a variance of {}", summary.variance.unwrap_or_default()),
+    ))
+}
+
+/// The shift summary as receipt lines for the counter printer - GET /api/shifts/:id/receipt
+pub async fn get_shift_receipt(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> AppResult<impl IntoResponse> {
+    let shift = state.shift_service.find(&parse_object_id(&id, "ID")?).await?;
+    let summary = state.shift_service.summary(&shift).await?;
+
+    Ok(ApiResponse::success(json!({ "receipt": receipt_lines(&shift, &summary) })))
+}
@@ -5,7 +5,6 @@ use axum::{
 };
 use std::sync::Arc;
 use bson::{doc, oid::ObjectId};
-use chrono::Utc;
 use futures::stream::TryStreamExt;
 use serde_json::json;
 
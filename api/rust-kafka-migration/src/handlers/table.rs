@@ -0,0 +1,72 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::IntoResponse,
+    Extension, Json,
+};
+use bson::oid::ObjectId;
+use serde::Deserialize;
+use serde_json::json;
+use std::sync::Arc;
+
+use crate::{
+    db::models::TableStatus,
+    error::{ApiResponse, AppError, AppResult},
+    handlers::order::resolve_actor,
+    middleware::UserId,
+    AppState,
+};
+
+#[derive(Deserialize)]
+pub struct FloorQuery {
+    #[serde(rename = "outletId")]
+    pub outlet_id: String,
+    #[serde(rename = "areaId")]
+    pub area_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateTableStatusRequest {
+    pub status: String,
+    pub notes: Option<String>,
+}
+
+/// Live table status per area of an outlet with the orders open at each table - GET /api/tables/floor
+pub async fn get_floor(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<FloorQuery>,
+) -> AppResult<impl IntoResponse> {
+    let outlet_id = ObjectId::parse_str(&query.outlet_id)
+        .map_err(|_| AppError::BadRequest("Invalid outletId".to_string()))?;
+    let area_id = query
+        .area_id
+        .as_deref()
+        .filter(|id| !id.is_empty())
+        .map(|id| ObjectId::parse_str(id).map_err(|_| AppError::BadRequest("Invalid areaId".to_string())))
+        .transpose()?;
+
+    let areas = state.table_service.floor_view(&outlet_id, area_id).await?;
+
+    Ok(ApiResponse::success(json!({ "areas": areas })))
+}
+
+/// Set a table's status by hand - PUT /api/tables/:id/status
+pub async fn update_table_status(
+    State(state): State<Arc<AppState>>,
+    Extension(user_id): Extension<UserId>,
+    Path(id): Path<String>,
+    Json(payload): Json<UpdateTableStatusRequest>,
+) -> AppResult<impl IntoResponse> {
+    let table_id = ObjectId::parse_str(&id).map_err(|_| AppError::BadRequest("Invalid ID".to_string()))?;
+    let status: TableStatus = payload.status.parse().map_err(AppError::Validation)?;
+    let actor = resolve_actor(&state, &user_id).await?;
+
+    let table = state
+        .table_service
+        .set_status(&table_id, status, &actor.name, payload.notes)
+        .await?;
+
+    Ok(ApiResponse::success_with_message(
+        json!({ "table": table }),
+        format!("Table {} is {}", table.table_number, table.status),
+    ))
+}
@@ -1,17 +1,24 @@
 use axum::{
-    extract::{Request, State},
-    http::{HeaderMap, StatusCode},
+    extract::State,
+    http::HeaderMap,
     Json,
 };
 use bson::{doc, oid::ObjectId, Document};
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::sync::Arc;
 use tracing::{error, info, warn};
 
 use crate::{
+    common::Money,
+    db::models::payment::DOWN_PAYMENT,
+    db::models::{NotificationOutcome, OrderStatus, PaymentNotification, PaymentProvider, ReservationStatus},
     error::{AppError, AppResult},
-    utils::lock::LockUtil,
+    services::{
+        midtrans_client::{is_status_progression, verify_notification_signature},
+        xendit_client::{parse_callback, parse_xendit_time, payment_status, verify_callback_token},
+    },
     AppState,
 };
 
@@ -23,6 +30,7 @@ use crate::{
 pub struct MidtransWebhookPayload {
     pub transaction_status: String,
     pub order_id: String,
+    pub status_code: Option<String>,
     pub fraud_status: Option<String>,
     pub payment_type: Option<String>,
     pub gross_amount: Option<String>,
@@ -44,56 +52,246 @@ pub struct WebhookResponse {
     pub order_type: Option<String>,
 }
 
+/// A verified gateway notification in Midtrans terms; Xendit callbacks are mapped onto it
+struct GatewayNotification {
+    /// Our payment code, or the gateway's transaction ID
+    reference: String,
+    transaction_status: String,
+    gross_amount: Money,
+    fraud_status: Option<String>,
+    payment_type: Option<String>,
+    transaction_time: Option<String>,
+    settlement_time: Option<String>,
+    signature_key: Option<String>,
+    merchant_id: Option<String>,
+}
+
+/// Result of applying one verified notification
+struct NotificationResult {
+    outcome: NotificationOutcome,
+    order_id: Option<String>,
+    order_type: Option<String>,
+    message: String,
+}
+
+/// Store a notification before anything else, valid or not
+async fn store_notification(
+    state: &AppState,
+    provider: &str,
+    order_id: Option<String>,
+    transaction_status: Option<String>,
+    signature_valid: bool,
+    raw: &Value,
+) -> AppResult<ObjectId> {
+    state.notification_repo.create(&PaymentNotification {
+        id: None,
+        provider: provider.to_string(),
+        order_id,
+        transaction_status,
+        signature_valid,
+        outcome: NotificationOutcome::Received,
+        error: None,
+        payload: bson::to_document(raw).unwrap_or_default(),
+        received_at: bson::DateTime::now(),
+        processed_at: None,
+    }).await
+}
+
+/// Record why a stored notification was refused and refuse it
+async fn reject_notification(
+    state: &AppState,
+    notification_id: &ObjectId,
+    outcome: NotificationOutcome,
+    error: AppError,
+) -> AppResult<Json<WebhookResponse>> {
+    state.notification_repo.set_outcome(notification_id, outcome, Some(error.to_string())).await?;
+    Err(error)
+}
+
+/// Record how a stored notification ended and answer the gateway
+async fn finish_notification(
+    state: &AppState,
+    request_id: &str,
+    notification_id: &ObjectId,
+    transaction_status: String,
+    result: AppResult<NotificationResult>,
+) -> AppResult<Json<WebhookResponse>> {
+    let result = match result {
+        Ok(result) => result,
+        Err(e) => return reject_notification(state, notification_id, NotificationOutcome::Failed, e).await,
+    };
+    if result.outcome == NotificationOutcome::AmountMismatch {
+        return reject_notification(state, notification_id, result.outcome, AppError::Conflict(result.message)).await;
+    }
+    let error = (result.outcome != NotificationOutcome::Processed).then(|| result.message.clone());
+    state.notification_repo.set_outcome(notification_id, result.outcome, error).await?;
+
+    info!("[WEBHOOK {}] Webhook {} for {:?}", request_id, result.outcome.as_str(), result.order_id);
+
+    Ok(Json(WebhookResponse {
+        status: "ok".to_string(),
+        message: result.message,
+        order_id: result.order_id,
+        transaction_status: Some(transaction_status),
+        order_type: result.order_type,
+    }))
+}
+
 pub async fn midtrans_webhook(
     State(state): State<Arc<AppState>>,
-    Json(payload): Json<MidtransWebhookPayload>,
+    Json(raw): Json<Value>,
 ) -> AppResult<Json<WebhookResponse>> {
     let request_id: String = format!("{:x}", rand::random::<u32>());
-    
+    let payload = serde_json::from_value::<MidtransWebhookPayload>(raw.clone()).ok();
+    let server_key = &state.config.payment.midtrans.server_key;
+
+    let signature_valid = payload.as_ref().is_some_and(|p| {
+        match (&p.status_code, &p.gross_amount, &p.signature_key) {
+            (Some(status_code), Some(gross_amount), Some(signature)) => verify_notification_signature(
+                &p.order_id, status_code, gross_amount, server_key, signature,
+            ),
+            _ => false,
+        }
+    });
+
+    let notification_id = store_notification(
+        &state,
+        PaymentProvider::Midtrans.as_str(),
+        payload.as_ref().map(|p| p.order_id.clone()),
+        payload.as_ref().map(|p| p.transaction_status.clone()),
+        signature_valid,
+        &raw,
+    ).await?;
+
+    // Validate required fields
+    let payload = match payload {
+        Some(p) if !p.order_id.is_empty() && !p.transaction_status.is_empty() => p,
+        _ => {
+            warn!("[WEBHOOK {}] Invalid notification: Missing required fields", request_id);
+            return reject_notification(
+                &state, &notification_id, NotificationOutcome::Failed,
+                AppError::BadRequest("Missing required fields".into()),
+            ).await;
+        }
+    };
+
     info!(
         "[WEBHOOK {}] Received Midtrans notification: order_id={}, status={}, fraud_status={:?}",
         request_id, payload.order_id, payload.transaction_status, payload.fraud_status
     );
 
-    // Validate required fields
-    if payload.order_id.is_empty() || payload.transaction_status.is_empty() {
-        warn!("[WEBHOOK {}] Invalid notification: Missing required fields", request_id);
-        return Err(AppError::BadRequest("Missing required fields".into()));
+    if !signature_valid {
+        warn!("[WEBHOOK {}] Invalid signature for {}", request_id, payload.order_id);
+        return reject_notification(
+            &state, &notification_id, NotificationOutcome::InvalidSignature,
+            AppError::Forbidden("Invalid signature".into()),
+        ).await;
     }
 
+    let notification = GatewayNotification {
+        reference: payload.order_id,
+        transaction_status: payload.transaction_status,
+        gross_amount: payload.gross_amount.as_deref()
+            .and_then(|s| s.parse::<f64>().ok())
+            .map(Money::from_f64)
+            .unwrap_or(Money::ZERO),
+        fraud_status: payload.fraud_status,
+        payment_type: payload.payment_type,
+        transaction_time: payload.transaction_time,
+        settlement_time: payload.settlement_time,
+        signature_key: payload.signature_key,
+        merchant_id: payload.merchant_id,
+    };
+    let result = apply_notification(&state, &request_id, &notification).await;
+
+    finish_notification(&state, &request_id, &notification_id, notification.transaction_status, result).await
+}
+
+/// Total of the completed tenders on a raw order document
+fn completed_tenders(order: &Document) -> Money {
+    order
+        .get_array("payments")
+        .map(|payments| {
+            payments
+                .iter()
+                .filter_map(|p| p.as_document())
+                .filter(|p| p.get_str("status") == Ok("completed"))
+                .map(|p| Money::from_field(p, "amount"))
+                .sum()
+        })
+        .unwrap_or(Money::ZERO)
+}
+
+/// Confirm the pending reservation whose down payment just settled
+async fn confirm_reservation(state: &AppState, request_id: &str, reservation_id: &ObjectId) -> AppResult<()> {
+    let now = bson::to_bson(&Utc::now())?;
+    let result = state
+        .db
+        .database()
+        .collection::<Document>("reservations")
+        .update_one(
+            doc! { "_id": reservation_id, "status": ReservationStatus::Pending.as_str() },
+            doc! { "$set": {
+                "status": ReservationStatus::Confirmed.as_str(),
+                "updatedAt": now.clone(),
+                "updatedAtWIB": now,
+            } },
+            None,
+        )
+        .await?;
+    if result.modified_count > 0 {
+        info!("[WEBHOOK {}] Reservation {} confirmed by its down payment", request_id, reservation_id);
+    }
+    Ok(())
+}
+
+/// Settle the payment and its order for one verified notification, under the
+/// order's lock. Midtrans and Xendit notifications both end up here.
+async fn apply_notification(
+    state: &AppState,
+    request_id: &str,
+    notification: &GatewayNotification,
+) -> AppResult<NotificationResult> {
     let db = state.db.database();
     let payment_coll = db.collection::<Document>("payments");
     let order_coll = db.collection::<Document>("orders");
 
-    // Use lock util from state
+    // Find payment record with multiple criteria
+    let payment_filter = doc! {
+        "$or": [
+            { "order_id": &notification.reference },
+            { "payment_code": &notification.reference },
+            { "transaction_id": &notification.reference }
+        ]
+    };
+    let payment = payment_coll.find_one(payment_filter, None).await
+        .map_err(AppError::Database)?
+        .ok_or_else(|| {
+            error!("[WEBHOOK {}] Payment record not found for: {}", request_id, notification.reference);
+            AppError::NotFound("Payment record not found".into())
+        })?;
+    let payment_id = payment.get_object_id("_id")
+        .map_err(|_| AppError::Internal("Invalid payment record".into()))?;
+
+    // Lock the payment's order, as the cashier handlers do, so a notification
+    // never races a tender or cancellation on the same order
     let lock_util = &state.lock_util;
-    let lock_key = payload.order_id.clone();
-    let owner = format!("webhook-{}", std::process::id());
+    let lock_key = payment.get_str("order_id").unwrap_or(&notification.reference).to_string();
+    let owner = format!("webhook-{}-{}", request_id, uuid::Uuid::new_v4());
 
     // Process webhook with lock
-    let result = lock_util.with_lock(
+    lock_util.with_lock(
         &lock_key,
         &owner,
         60000, // 60 seconds TTL
         5,     // max retries
         200,   // retry delay ms
         || async {
-            // Find payment record with multiple criteria
-            let payment_filter = doc! {
-                "$or": [
-                    { "order_id": &payload.order_id },
-                    { "payment_code": &payload.order_id },
-                    { "transaction_id": &payload.order_id }
-                ]
-            };
-
-            let existing_payment = payment_coll.find_one(payment_filter.clone(), None).await
-                .map_err(|e| AppError::Database(e))?;
-
-            let existing_payment = existing_payment.ok_or_else(|| {
-                error!("[WEBHOOK {}] Payment record not found for: {}", request_id, payload.order_id);
-                AppError::NotFound("Payment record not found".into())
-            })?;
+            // Re-read under the lock; another notification may have moved it on
+            let existing_payment = payment_coll.find_one(doc! { "_id": payment_id }, None).await
+                .map_err(AppError::Database)?
+                .ok_or_else(|| AppError::NotFound("Payment record not found".into()))?;
+            let current_status = existing_payment.get_str("status").unwrap_or("pending").to_string();
 
             info!(
                 "[WEBHOOK {}] Processing webhook for payment: payment_code={:?}, order_id={:?}",
@@ -102,13 +300,48 @@ pub async fn midtrans_webhook(
                 existing_payment.get_str("order_id").ok()
             );
 
-            // Prepare payment update
-            let gross_amount = payload.gross_amount.as_ref()
-                .and_then(|s| s.parse::<f64>().ok())
-                .or_else(|| existing_payment.get_f64("amount").ok())
-                .unwrap_or(0.0);
+            // The notified amount must be what we asked the customer to pay
+            let gross_amount = notification.gross_amount;
+            let expected_amount = Money::from_field(&existing_payment, "amount");
+            if gross_amount != expected_amount {
+                error!(
+                    "[WEBHOOK {}] Amount mismatch for {}: notified {}, expected {}",
+                    request_id, notification.reference, gross_amount, expected_amount
+                );
+                return Ok(NotificationResult {
+                    outcome: NotificationOutcome::AmountMismatch,
+                    order_id: existing_payment.get_str("order_id").ok().map(str::to_string),
+                    order_type: None,
+                    message: format!("Gross amount {} does not match payment amount {}", gross_amount, expected_amount),
+                });
+            }
 
-            let paid_at = if matches!(payload.transaction_status.as_str(), "settlement" | "capture") {
+            if current_status == notification.transaction_status {
+                info!("[WEBHOOK {}] Payment already {}, nothing to do", request_id, current_status);
+                return Ok(NotificationResult {
+                    outcome: NotificationOutcome::Duplicate,
+                    order_id: existing_payment.get_str("order_id").ok().map(str::to_string),
+                    order_type: None,
+                    message: format!("Payment already {}", current_status),
+                });
+            }
+            if !is_status_progression(&current_status, &notification.transaction_status) {
+                warn!(
+                    "[WEBHOOK {}] Ignoring stale notification: {} -> {}",
+                    request_id, current_status, notification.transaction_status
+                );
+                return Ok(NotificationResult {
+                    outcome: NotificationOutcome::Ignored,
+                    order_id: existing_payment.get_str("order_id").ok().map(str::to_string),
+                    order_type: None,
+                    message: format!(
+                        "Payment is {}, not moving back to {}",
+                        current_status, notification.transaction_status
+                    ),
+                });
+            }
+
+            let paid_at = if matches!(notification.transaction_status.as_str(), "settlement" | "capture") {
                 Some(Utc::now())
             } else {
                 existing_payment.get_datetime("paidAt").ok().map(|dt| dt.to_chrono())
@@ -116,44 +349,44 @@ pub async fn midtrans_webhook(
 
             let mut update_doc = doc! {
                 "$set": {
-                    "status": &payload.transaction_status,
+                    "status": &notification.transaction_status,
                     "gross_amount": gross_amount,
                     "updatedAt": Utc::now(),
                 }
             };
 
-            if let Some(fraud_status) = &payload.fraud_status {
+            if let Some(fraud_status) = &notification.fraud_status {
                 update_doc.get_document_mut("$set").unwrap().insert("fraud_status", fraud_status);
             }
-            if let Some(payment_type) = &payload.payment_type {
+            if let Some(payment_type) = &notification.payment_type {
                 update_doc.get_document_mut("$set").unwrap().insert("payment_type", payment_type);
             }
-            if let Some(transaction_time) = &payload.transaction_time {
+            if let Some(transaction_time) = &notification.transaction_time {
                 update_doc.get_document_mut("$set").unwrap().insert("transaction_time", transaction_time);
             }
-            if let Some(settlement_time) = &payload.settlement_time {
+            if let Some(settlement_time) = &notification.settlement_time {
                 update_doc.get_document_mut("$set").unwrap().insert("settlement_time", settlement_time);
             }
-            if let Some(signature_key) = &payload.signature_key {
+            if let Some(signature_key) = &notification.signature_key {
                 update_doc.get_document_mut("$set").unwrap().insert("signature_key", signature_key);
             }
-            if let Some(merchant_id) = &payload.merchant_id {
+            if let Some(merchant_id) = &notification.merchant_id {
                 update_doc.get_document_mut("$set").unwrap().insert("merchant_id", merchant_id);
             }
             if let Some(paid_at) = paid_at {
                 update_doc.get_document_mut("$set").unwrap().insert("paidAt", paid_at);
             }
 
-            // Update payment record
+            // Update payment record, only if nothing moved it since it was read
             let updated_payment = payment_coll.find_one_and_update(
-                payment_filter,
+                doc! { "_id": payment_id, "status": &current_status },
                 update_doc,
                 None,
-            ).await.map_err(|e| AppError::Database(e))?;
+            ).await.map_err(AppError::Database)?;
 
             let updated_payment = updated_payment.ok_or_else(|| {
-                error!("[WEBHOOK {}] Failed to update payment for: {}", request_id, payload.order_id);
-                AppError::Internal("Payment update failed".into())
+                error!("[WEBHOOK {}] Failed to update payment for: {}", request_id, notification.reference);
+                AppError::Conflict("Payment changed while processing".into())
             })?;
 
             info!("[WEBHOOK {}] Payment record updated successfully", request_id);
@@ -165,7 +398,7 @@ pub async fn midtrans_webhook(
             // Find and update order
             let order_filter = doc! { "order_id": target_order_id };
             let order = order_coll.find_one(order_filter.clone(), None).await
-                .map_err(|e| AppError::Database(e))?;
+                .map_err(AppError::Database)?;
 
             let order = order.ok_or_else(|| {
                 warn!("[WEBHOOK {}] Order with ID {} not found", request_id, target_order_id);
@@ -186,18 +419,52 @@ pub async fn midtrans_webhook(
             let mut order_update = doc! {};
             let mut should_update_order = false;
 
-            match payload.transaction_status.as_str() {
+            match notification.transaction_status.as_str() {
                 "capture" | "settlement" => {
-                    if payload.fraud_status.as_deref() == Some("accept") {
-                        // Handle reservation vs regular order
-                        if order_type == "Reservation" && payment_type_from_payment == Some("Down Payment") {
+                    if notification.fraud_status.as_deref() == Some("accept") {
+                        // A down payment can be taken on any order linked to a reservation
+                        if payment_type_from_payment == Some(DOWN_PAYMENT) {
+                            // The down payment becomes a tender of the order, so the
+                            // final payment at check-in only asks for the rest
+                            let amount = Money::from_field(&updated_payment, "amount");
+                            let remaining = Money::from_field(&order, "grandTotal") - completed_tenders(&order) - amount;
+                            let (payment_status, split_status) = if remaining.is_positive() {
+                                ("Partial", "partial")
+                            } else {
+                                ("Paid", "completed")
+                            };
+                            // Orders made for the reservation wait for the guests; a linked
+                            // dine-in order keeps its own status
+                            let status = if order_type == "Reservation" {
+                                OrderStatus::Reserved.as_str()
+                            } else {
+                                order.get_str("status").unwrap_or("Pending")
+                            };
+                            let tender = doc! {
+                                "paymentMethod": updated_payment.get_str("method").unwrap_or(DOWN_PAYMENT),
+                                "amount": amount,
+                                "status": "completed",
+                                "processedAt": bson::DateTime::now(),
+                                "notes": format!(
+                                    "Down payment {}",
+                                    updated_payment.get_str("payment_code").unwrap_or(&notification.reference)
+                                ),
+                            };
                             order_update = doc! {
                                 "$set": {
-                                    "paymentStatus": "Paid",
-                                    "status": "Confirmed"
-                                }
+                                    "paymentStatus": payment_status,
+                                    "splitPaymentStatus": split_status,
+                                    "status": status
+                                },
+                                "$push": { "payments": tender }
                             };
-                            info!("[WEBHOOK {}] Down Payment successful for reservation order {}", request_id, target_order_id);
+                            let reservation_id = order
+                                .get_object_id("reservation")
+                                .or_else(|_| order.get_object_id("originalReservationId"));
+                            if let Ok(reservation_id) = reservation_id {
+                                confirm_reservation(state, request_id, &reservation_id).await?;
+                            }
+                            info!("[WEBHOOK {}] Down Payment successful for order {}", request_id, target_order_id);
                         } else {
                             let current_status = order.get_str("status").unwrap_or("Pending");
                             order_update = doc! {
@@ -209,7 +476,7 @@ pub async fn midtrans_webhook(
                             info!("[WEBHOOK {}] Payment successful for regular order {}", request_id, target_order_id);
                         }
                         should_update_order = true;
-                    } else if payload.fraud_status.as_deref() == Some("challenge") {
+                    } else if notification.fraud_status.as_deref() == Some("challenge") {
                         order_update = doc! {
                             "$set": {
                                 "paymentStatus": "Challenged"
@@ -219,15 +486,25 @@ pub async fn midtrans_webhook(
                         info!("[WEBHOOK {}] Payment challenged for order {}", request_id, target_order_id);
                     }
                 }
+                "deny" | "cancel" | "expire" if payment_type_from_payment == Some(DOWN_PAYMENT) => {
+                    // The reservation stays pending; staff can ask for the down payment again
+                    order_update = doc! {
+                        "$set": {
+                            "paymentStatus": "Failed"
+                        }
+                    };
+                    should_update_order = true;
+                    info!("[WEBHOOK {}] Down Payment failed for order {}: {}", request_id, target_order_id, notification.transaction_status);
+                }
                 "deny" | "cancel" | "expire" => {
                     order_update = doc! {
                         "$set": {
                             "paymentStatus": "Failed",
-                            "status": "Canceled"
+                            "status": OrderStatus::Canceled.as_str()
                         }
                     };
                     should_update_order = true;
-                    info!("[WEBHOOK {}] Payment failed for order {}: {}", request_id, target_order_id, payload.transaction_status);
+                    info!("[WEBHOOK {}] Payment failed for order {}: {}", request_id, target_order_id, notification.transaction_status);
                 }
                 "pending" => {
                     order_update = doc! {
@@ -239,32 +516,104 @@ pub async fn midtrans_webhook(
                     info!("[WEBHOOK {}] Payment pending for order {}", request_id, target_order_id);
                 }
                 _ => {
-                    warn!("[WEBHOOK {}] Unhandled transaction status: {}", request_id, payload.transaction_status);
+                    warn!("[WEBHOOK {}] Unhandled transaction status: {}", request_id, notification.transaction_status);
                 }
             }
 
             // Update order if needed
             if should_update_order {
                 order_coll.update_one(order_filter, order_update, None).await
-                    .map_err(|e| AppError::Database(e))?;
+                    .map_err(AppError::Database)?;
                 info!("[WEBHOOK {}] Order {} updated", request_id, target_order_id);
             }
 
-            Ok::<_, AppError>((target_order_id.to_string(), order_type.to_string()))
+            Ok::<_, AppError>(NotificationResult {
+                outcome: NotificationOutcome::Processed,
+                order_id: Some(target_order_id.to_string()),
+                order_type: Some(order_type.to_string()),
+                message: "Webhook processed successfully".to_string(),
+            })
         },
+    ).await
+}
+
+// ============================================================================
+// XENDIT WEBHOOK
+// ============================================================================
+
+/// Invoice, QR code and e-wallet callbacks - POST /api/webhook/xendit
+pub async fn xendit_webhook(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(raw): Json<Value>,
+) -> AppResult<Json<WebhookResponse>> {
+    let request_id: String = format!("{:x}", rand::random::<u32>());
+    let callback = parse_callback(&raw);
+    let received_token = headers.get("x-callback-token")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    let token_valid = verify_callback_token(&state.config.payment.xendit.callback_token, received_token);
+
+    let notification_id = store_notification(
+        &state,
+        PaymentProvider::Xendit.as_str(),
+        callback.as_ref().map(|c| c.reference.clone()),
+        callback.as_ref().map(|c| c.status.clone()),
+        token_valid,
+        &raw,
     ).await?;
 
-    let (order_id, order_type) = result;
+    let Some(callback) = callback else {
+        warn!("[XENDIT WEBHOOK {}] Invalid callback: Missing required fields", request_id);
+        return reject_notification(
+            &state, &notification_id, NotificationOutcome::Failed,
+            AppError::BadRequest("Missing required fields".into()),
+        ).await;
+    };
 
-    info!("[WEBHOOK {}] Webhook processed successfully for {}", request_id, order_id);
+    info!(
+        "[XENDIT WEBHOOK {}] Received Xendit callback: reference={}, status={}",
+        request_id, callback.reference, callback.status
+    );
 
-    Ok(Json(WebhookResponse {
-        status: "ok".to_string(),
-        message: "Webhook processed successfully".to_string(),
-        order_id: Some(order_id),
-        transaction_status: Some(payload.transaction_status),
-        order_type: Some(order_type),
-    }))
+    if !token_valid {
+        warn!("[XENDIT WEBHOOK {}] Invalid callback token for {}", request_id, callback.reference);
+        return reject_notification(
+            &state, &notification_id, NotificationOutcome::InvalidSignature,
+            AppError::Forbidden("Invalid callback token".into()),
+        ).await;
+    }
+
+    let Some(transaction_status) = payment_status(&callback.status) else {
+        warn!("[XENDIT WEBHOOK {}] Unhandled Xendit status: {}", request_id, callback.status);
+        let message = format!("Unhandled status {}", callback.status);
+        state.notification_repo
+            .set_outcome(&notification_id, NotificationOutcome::Ignored, Some(message.clone()))
+            .await?;
+        return Ok(Json(WebhookResponse {
+            status: "ok".to_string(),
+            message,
+            order_id: None,
+            transaction_status: Some(callback.status),
+            order_type: None,
+        }));
+    };
+
+    // Xendit has no fraud review; a paid callback is final
+    let notification = GatewayNotification {
+        reference: callback.reference,
+        transaction_status: transaction_status.to_string(),
+        gross_amount: callback.amount.unwrap_or(Money::ZERO),
+        fraud_status: Some("accept".to_string()),
+        payment_type: callback.payment_method,
+        transaction_time: None,
+        settlement_time: callback.paid_at.as_deref().and_then(parse_xendit_time),
+        signature_key: None,
+        merchant_id: None,
+    };
+    let result = apply_notification(&state, &request_id, &notification).await;
+
+    finish_notification(&state, &request_id, &notification_id, notification.transaction_status, result).await
 }
 
 // ============================================================================
@@ -344,7 +693,7 @@ pub async fn gosend_webhook(
             // Find GoSend booking
             let booking_filter = doc! { "goSend_order_no": &payload.booking_id };
             let booking = gosend_coll.find_one(booking_filter.clone(), None).await
-                .map_err(|e| AppError::Database(e))?;
+                .map_err(AppError::Database)?;
 
             let booking = booking.ok_or_else(|| {
                 warn!("[GOSEND WEBHOOK {}] GoSend booking not found: {}", request_id, payload.booking_id);
@@ -377,7 +726,7 @@ pub async fn gosend_webhook(
             }
 
             gosend_coll.update_one(booking_filter, update_doc, None).await
-                .map_err(|e| AppError::Database(e))?;
+                .map_err(AppError::Database)?;
 
             // Update order
             let mut order_update = doc! {
@@ -403,12 +752,12 @@ pub async fn gosend_webhook(
 
             // If delivered, mark order as completed
             if payload.status == "delivered" {
-                order_update.get_document_mut("$set").unwrap().insert("status", "completed");
+                order_update.get_document_mut("$set").unwrap().insert("status", OrderStatus::Completed.as_str());
             }
 
             let order_filter = doc! { "order_id": order_id };
             order_coll.update_one(order_filter, order_update, None).await
-                .map_err(|e| AppError::Database(e))?;
+                .map_err(AppError::Database)?;
 
             Ok::<_, AppError>(order_id.to_string())
         },
@@ -0,0 +1,101 @@
+//! Scheduled background jobs.
+//!
+//! Every API instance schedules the jobs, but a run only goes ahead on the
+//! instance holding the Redis leader lock, so each job runs once per tick
+//! across the deployment. The leader keeps the lock by renewing it on every
+//! tick; if it stops, another instance takes over once the lock expires.
+
+use chrono::Utc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio_cron_scheduler::{Job, JobScheduler, JobSchedulerError};
+use tracing::{debug, error, info};
+
+use crate::config::JobsConfig;
+use crate::error::{AppError, AppResult};
+use crate::services::SweeperService;
+use crate::utils::LockUtil;
+
+const LEADER_KEY: &str = "lock:jobs:leader";
+/// Long enough to outlast a missed tick, short enough for a quick takeover
+const LEADER_TTL_MS: u64 = 180_000;
+
+fn scheduler_error(e: JobSchedulerError) -> AppError {
+    AppError::Internal(format!("Job scheduler error: {}", e))
+}
+
+/// Leadership of this instance over the background jobs
+#[derive(Clone)]
+struct Leader {
+    lock_util: LockUtil,
+    owner: String,
+}
+
+impl Leader {
+    /// Whether this instance leads for the current tick
+    async fn acquire(&self) -> bool {
+        match self.lock_util.hold_lock(LEADER_KEY, &self.owner, LEADER_TTL_MS).await {
+            Ok(held) => held,
+            Err(e) => {
+                error!("Job leader lock unavailable: {}", e);
+                false
+            }
+        }
+    }
+}
+
+/// Clears the "sweep running" flag when the sweep ends, even by panicking
+struct RunningGuard(Arc<AtomicBool>);
+
+impl Drop for RunningGuard {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Release);
+    }
+}
+
+/// Schedule the background jobs. The returned scheduler must be kept alive.
+pub async fn start(config: &JobsConfig, lock_util: LockUtil, sweeper: SweeperService) -> AppResult<Option<JobScheduler>> {
+    if !config.enabled {
+        info!("Background jobs disabled on this instance");
+        return Ok(None);
+    }
+
+    let leader = Leader {
+        lock_util,
+        owner: format!("jobs-{}-{:x}", std::process::id(), rand::random::<u32>()),
+    };
+    // A slow sweep is not started again on top of itself
+    let running = Arc::new(AtomicBool::new(false));
+
+    let scheduler = JobScheduler::new().await.map_err(scheduler_error)?;
+    let sweep = Job::new_async(config.sweep_cron.as_str(), move |_, _| {
+        let leader = leader.clone();
+        let sweeper = sweeper.clone();
+        let running = running.clone();
+        Box::pin(async move {
+            if !leader.acquire().await {
+                debug!("Another instance leads the background jobs");
+                return;
+            }
+            if running.swap(true, Ordering::AcqRel) {
+                debug!("Previous sweep still running");
+                return;
+            }
+            let _running = RunningGuard(running);
+
+            let report = sweeper.sweep(Utc::now()).await;
+            if report.expired_payments + report.canceled_orders + report.orphaned_payments + report.no_shows > 0 {
+                info!(
+                    "🧹 Sweep: {} payments expired, {} orders canceled, {} orphaned payments, {} no-shows",
+                    report.expired_payments, report.canceled_orders, report.orphaned_payments, report.no_shows
+                );
+            }
+        })
+    })
+    .map_err(scheduler_error)?;
+    scheduler.add(sweep).await.map_err(scheduler_error)?;
+    scheduler.start().await.map_err(scheduler_error)?;
+
+    info!("Background jobs scheduled ({})", config.sweep_cron);
+    Ok(Some(scheduler))
+}
@@ -81,6 +81,7 @@ pub enum InventoryEvent {
 /// Notification event types
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
+#[allow(clippy::enum_variant_names)]
 pub enum NotificationEvent {
     OrderNotification {
         user_id: String,
@@ -1,6 +1,6 @@
 use axum::{
     extract::{Request, State},
-    http::{HeaderMap, StatusCode},
+    http::HeaderMap,
     middleware::Next,
     response::Response,
 };
@@ -9,9 +9,8 @@ use std::sync::Arc;
 
 use crate::{
     db::models::User,
-    error::{AppError, AppResult},
+    error::AppError,
     AppState,
-    utils::jwt,
 };
 
 // Key for storing company_id in request extensions
@@ -1,5 +1,8 @@
+// Re-exports make up the module's public surface; not all are used by the binary yet
+#![allow(unused_imports)]
+
 pub mod auth;
 pub mod hr_middleware;
 
-pub use auth::{auth_middleware, UserId};
+pub use auth::{auth_middleware, optional_auth_middleware, UserId};
 pub use hr_middleware::{set_company_context, verify_company_access};
@@ -1,5 +1,5 @@
 use axum::{
-    routing::{get, post, put, patch},
+    routing::{get, post, patch},
     Router, middleware,
 };
 use std::sync::Arc;
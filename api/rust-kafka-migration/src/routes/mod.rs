@@ -1,6 +1,6 @@
 use axum::{
     middleware,
-    routing::{delete, get, post, put},
+    routing::{get, post, put},
     Router,
 };
 
@@ -10,22 +10,17 @@ pub mod hr;
 pub mod promo;
 pub mod recipe;
 pub mod report;
+pub mod reservation;
+pub mod shift;
+pub mod table;
 pub mod tax;
 pub mod voucher;
 
-pub use category::category_routes;
-pub use event::event_routes;
-pub use hr::hr_routes;
-pub use promo::promo_routes;
-pub use recipe::recipe_routes;
-pub use report::report_routes;
 use std::sync::Arc;
-pub use tax::tax_routes;
-pub use voucher::voucher_routes;
 
 use crate::error::ApiResponse;
 use crate::handlers;
-use crate::middleware::auth_middleware;
+use crate::middleware::{auth_middleware, optional_auth_middleware};
 use crate::AppState;
 
 /// Health check handler - matches Node.js format
@@ -50,6 +45,7 @@ fn auth_routes(state: Arc<AppState>) -> Router<Arc<AppState>> {
         .route("/me", get(handlers::get_me))
         .route("/update-profile", post(handlers::update_profile))
         .route("/change-password", post(handlers::change_password))
+        .route("/set-pin", post(handlers::set_pin))
         .layer(middleware::from_fn_with_state(
             state.clone(),
             auth_middleware,
@@ -142,18 +138,59 @@ fn inventory_routes(state: Arc<AppState>) -> Router<Arc<AppState>> {
 }
 
 /// Create outlet routes
-fn outlet_routes() -> Router<Arc<AppState>> {
+fn outlet_routes(state: Arc<AppState>) -> Router<Arc<AppState>> {
+    let protected_routes = Router::new()
+        .route("/:id/payment-provider", put(handlers::set_outlet_payment_provider))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth_middleware,
+        ));
+
     Router::new()
         .route("/", get(handlers::get_outlets))
         .route("/:id", get(handlers::get_outlet))
         .route("/warehouses", get(handlers::get_warehouses))
         .route("/suppliers", get(handlers::supplier::get_suppliers))
+        .merge(protected_routes)
 }
 
 /// Create order routes
 fn order_routes(state: Arc<AppState>) -> Router<Arc<AppState>> {
-    Router::new()
+    let protected_routes = Router::new()
+        .route("/", get(handlers::list_orders))
+        .route("/:id/status", put(handlers::update_order_status))
+        .route("/:id/cancel", post(handlers::cancel_order))
+        .route("/:id/refund", post(handlers::refund_order))
+        .route("/:id/refunds", get(handlers::get_order_refunds))
+        .route(
+            "/:id/revisions",
+            get(handlers::get_order_revisions).post(handlers::revise_order),
+        )
+        .route("/:id/items", post(handlers::add_open_bill_items))
+        .route("/:id/close-bill", post(handlers::close_open_bill))
+        .route("/:id/payments", post(handlers::record_order_payment))
+        .route("/:id/discounts", post(handlers::apply_manual_discounts))
+        .route("/:id/gosend", post(handlers::delivery::book_gosend))
+        .route("/:id/gosend/cancel", post(handlers::delivery::cancel_gosend))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth_middleware,
+        ));
+
+    // Cashier orders are tied to the signed-in cashier; other sources need no token
+    let cashier_aware_routes = Router::new()
         .route("/unified-order", post(handlers::create_unified_order))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            optional_auth_middleware,
+        ));
+
+    Router::new()
+        .route("/quote", post(handlers::quote_order))
+        .route("/gosend/estimate", post(handlers::delivery::estimate_gosend))
+        .route("/:id/charge", post(handlers::charge_order))
+        .merge(cashier_aware_routes)
+        .merge(protected_routes)
         .with_state(state)
 }
 
@@ -161,6 +198,7 @@ fn order_routes(state: Arc<AppState>) -> Router<Arc<AppState>> {
 fn webhook_routes() -> Router<Arc<AppState>> {
     Router::new()
         .route("/midtrans", post(handlers::webhook::midtrans_webhook))
+        .route("/xendit", post(handlers::webhook::xendit_webhook))
         .route("/gosend", post(handlers::webhook::gosend_webhook))
 }
 
@@ -179,8 +217,11 @@ pub fn create_routes(state: Arc<AppState>) -> Router<Arc<AppState>> {
         .nest("/api/categories", category::category_routes())
         .nest("/api/recipes", recipe::recipe_routes())
         .nest("/api/inventory", inventory_routes(state.clone()))
-        .nest("/api/outlets", outlet_routes())
+        .nest("/api/outlets", outlet_routes(state.clone()))
         .nest("/api/order", order_routes(state.clone()))
+        .nest("/api/tables", table::table_routes(state.clone()))
+        .nest("/api/reservations", reservation::reservation_routes(state.clone()))
+        .nest("/api/shifts", shift::shift_routes(state.clone()))
         .nest("/api/products", product_routes())
         .nest("/api/suppliers", supplier_routes())
         .nest("/api/marketlist", marketlist_routes(state.clone()))
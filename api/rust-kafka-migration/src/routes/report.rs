@@ -5,7 +5,7 @@ use axum::{
 use std::sync::Arc;
 
 use crate::{
-    handlers::report::{dashboard, payment, sales},
+    handlers::report::{dashboard, discount, payment, reservation, sales},
     AppState,
 };
 
@@ -13,7 +13,9 @@ pub fn report_routes() -> Router<Arc<AppState>> {
     Router::new()
         .route("/dashboard", get(dashboard::get_dashboard_data))
         .route("/dashboard/quick-stats", get(dashboard::get_quick_stats))
+        .route("/discounts", get(discount::get_discount_report))
         .route("/payment", get(payment::generate_sales_report))
+        .route("/reservations/down-payments", get(reservation::get_down_payment_report))
         .route("/sales/summary", get(sales::get_sales_summary))
         .route("/sales/daily-profit", get(sales::get_daily_profit))
         .route("/sales/product", get(sales::get_product_sales_report))
@@ -0,0 +1,25 @@
+use axum::{
+    middleware,
+    routing::{get, post},
+    Router,
+};
+use std::sync::Arc;
+
+use crate::{handlers::reservation, middleware::auth_middleware, AppState};
+
+pub fn reservation_routes(state: Arc<AppState>) -> Router<Arc<AppState>> {
+    Router::new()
+        .route(
+            "/",
+            get(reservation::list_reservations).post(reservation::create_reservation),
+        )
+        .route("/:id", get(reservation::get_reservation))
+        .route("/:id/confirm", post(reservation::confirm_reservation))
+        .route("/:id/check-in", post(reservation::check_in_reservation))
+        .route("/:id/check-out", post(reservation::check_out_reservation))
+        .route("/:id/cancel", post(reservation::cancel_reservation))
+        .route("/:id/order", post(reservation::link_reservation_order))
+        .route("/:id/down-payment", post(reservation::request_reservation_down_payment))
+        .route("/:id/no-show", post(reservation::mark_reservation_no_show))
+        .layer(middleware::from_fn_with_state(state, auth_middleware))
+}
@@ -0,0 +1,20 @@
+use axum::{
+    middleware,
+    routing::{get, post},
+    Router,
+};
+use std::sync::Arc;
+
+use crate::{handlers::shift, middleware::auth_middleware, AppState};
+
+pub fn shift_routes(state: Arc<AppState>) -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/", get(shift::list_shifts))
+        .route("/open", post(shift::open_shift))
+        .route("/current", get(shift::get_current_shift))
+        .route("/:id", get(shift::get_shift))
+        .route("/:id/cash-movements", post(shift::record_cash_movement))
+        .route("/:id/close", post(shift::close_shift))
+        .route("/:id/receipt", get(shift::get_shift_receipt))
+        .layer(middleware::from_fn_with_state(state, auth_middleware))
+}
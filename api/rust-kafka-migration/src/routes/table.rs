@@ -0,0 +1,15 @@
+use axum::{
+    middleware,
+    routing::{get, put},
+    Router,
+};
+use std::sync::Arc;
+
+use crate::{handlers::table, middleware::auth_middleware, AppState};
+
+pub fn table_routes(state: Arc<AppState>) -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/floor", get(table::get_floor))
+        .route("/:id/status", put(table::update_table_status))
+        .layer(middleware::from_fn_with_state(state, auth_middleware))
+}
@@ -0,0 +1,241 @@
+use bson::oid::ObjectId;
+use chrono::{Duration, Utc};
+use tracing::{info, warn};
+
+use crate::db::models::{
+    DeliveryTracking, GoSendBooking, GoSendPricing, GoSendRoutePoint, GoSendRoutes,
+    GoSendTimestamps, Order, Outlet,
+};
+use crate::db::repositories::{GoSendBookingRepository, OrderRepository, OutletRepository};
+use crate::error::{AppError, AppResult};
+use crate::services::gosend_client::{
+    is_valid_phone, GoSendBookingRequest, GoSendClient, GoSendEstimate, GoSendInsuranceDetails,
+    GoSendRoute, ShipmentMethod,
+};
+
+const PROVIDER_GOSEND: &str = "GoSend";
+
+/// Books courier delivery for delivery orders through GoSend
+#[derive(Clone)]
+pub struct DeliveryService {
+    gosend: GoSendClient,
+    booking_repo: GoSendBookingRepository,
+    order_repo: OrderRepository,
+    outlet_repo: OutletRepository,
+}
+
+impl DeliveryService {
+    pub fn new(
+        gosend: GoSendClient,
+        booking_repo: GoSendBookingRepository,
+        order_repo: OrderRepository,
+        outlet_repo: OutletRepository,
+    ) -> Self {
+        Self {
+            gosend,
+            booking_repo,
+            order_repo,
+            outlet_repo,
+        }
+    }
+
+    async fn pickup_outlet(&self, outlet_id: &ObjectId) -> AppResult<(Outlet, String)> {
+        let outlet = self
+            .outlet_repo
+            .find_outlet_by_id(outlet_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Outlet not found".to_string()))?;
+        let coordinates = outlet
+            .coordinates
+            .clone()
+            .filter(|c| !c.trim().is_empty())
+            .ok_or_else(|| {
+                AppError::Validation(format!("Outlet {} has no coordinates for delivery", outlet.name))
+            })?;
+
+        Ok((outlet, coordinates))
+    }
+
+    /// Delivery price from an outlet to "lat,long" coordinates
+    pub async fn estimate(&self, outlet_id: &ObjectId, destination: &str) -> AppResult<GoSendEstimate> {
+        let (_, origin) = self.pickup_outlet(outlet_id).await?;
+        self.gosend.estimate(&origin, destination).await
+    }
+
+    /// Book a GoSend pickup for a delivery order and attach the tracking to the order.
+    ///
+    /// A closed booking (cancelled, rejected, ...) is replaced; an active one is a conflict.
+    pub async fn book_for_order(&self, order: &Order, method: ShipmentMethod) -> AppResult<GoSendBooking> {
+        let order_oid = order
+            .id
+            .ok_or_else(|| AppError::Internal("Order has no ID".to_string()))?;
+        if order.order_type != "Delivery" {
+            return Err(AppError::Validation(format!("Order {} is not a delivery order", order.order_id)));
+        }
+        if order.status.is_final() {
+            return Err(AppError::Conflict(format!("Order {} is already {}", order.order_id, order.status.as_str())));
+        }
+        if let Some(existing) = self.booking_repo.find_by_order_id(&order.order_id).await? {
+            if !existing.is_closed() {
+                return Err(AppError::Conflict(format!(
+                    "Order {} already has GoSend booking {}",
+                    order.order_id, existing.gosend_order_no
+                )));
+            }
+        }
+
+        let recipient = order
+            .recipient_info
+            .as_ref()
+            .ok_or_else(|| AppError::Validation("Order has no recipient".to_string()))?;
+        let destination = recipient
+            .coordinates
+            .clone()
+            .filter(|c| !c.trim().is_empty())
+            .ok_or_else(|| AppError::Validation("Recipient coordinates are required".to_string()))?;
+        let recipient_phone = recipient.phone.clone().unwrap_or_default();
+        if !is_valid_phone(&recipient_phone) {
+            return Err(AppError::Validation("Invalid recipient phone number".to_string()));
+        }
+        let recipient_name = recipient.name.clone().unwrap_or_else(|| order.user.clone());
+
+        let outlet_id = order
+            .outlet
+            .ok_or_else(|| AppError::Internal("Order has no outlet".to_string()))?;
+        let (outlet, origin) = self.pickup_outlet(&outlet_id).await?;
+        if !is_valid_phone(&outlet.contact_number) {
+            return Err(AppError::Validation(format!("Invalid contact number for outlet {}", outlet.name)));
+        }
+
+        let item = format!("ORDER-{}", order.order_id);
+        let route = GoSendRoute {
+            origin_name: outlet.name.clone(),
+            origin_note: format!("Pickup order {}", order.order_id),
+            origin_contact_name: outlet.name.clone(),
+            origin_contact_phone: outlet.contact_number.clone(),
+            origin_lat_long: origin,
+            origin_address: outlet.address.clone(),
+            destination_name: recipient_name.clone(),
+            destination_note: recipient.note.clone().unwrap_or_default(),
+            destination_contact_name: recipient_name,
+            destination_contact_phone: recipient_phone,
+            destination_lat_long: destination,
+            destination_address: recipient.address.clone().unwrap_or_default(),
+            item: item.clone(),
+            store_order_id: order.order_id.clone(),
+            insurance_details: GoSendInsuranceDetails {
+                applied: "false".to_string(),
+                fee: "0".to_string(),
+                product_description: item.clone(),
+                product_price: "0".to_string(),
+            },
+        };
+
+        // The quote only fills in the pricing record; the booking goes ahead without it
+        let pricing = match self.gosend.estimate(&route.origin_lat_long, &route.destination_lat_long).await {
+            Ok(estimate) => estimate
+                .for_method(method)
+                .map(|quote| GoSendPricing {
+                    total_price: quote.price.total_price,
+                    distance: quote.distance,
+                    shipment_method_description: quote.shipment_method_description.clone(),
+                })
+                .unwrap_or_default(),
+            Err(e) => {
+                warn!("GoSend estimate for {} failed, booking without pricing: {}", order.order_id, e);
+                GoSendPricing::default()
+            }
+        };
+
+        let request = GoSendBookingRequest::new(method, route);
+        let response = self.gosend.book(&request).await?;
+        let route = &request.routes[0];
+
+        let now = bson::DateTime::now();
+        let booking = GoSendBooking {
+            id: None,
+            order_id: order.order_id.clone(),
+            gosend_order_no: response.order_no.clone(),
+            store_order_id: order.order_id.clone(),
+            shipment_method: method.to_string(),
+            status: "confirmed".to_string(),
+            routes: GoSendRoutes {
+                origin: GoSendRoutePoint {
+                    name: Some(route.origin_name.clone()),
+                    note: Some(route.origin_note.clone()),
+                    contact_name: Some(route.origin_contact_name.clone()),
+                    contact_phone: Some(route.origin_contact_phone.clone()),
+                    latlong: Some(route.origin_lat_long.clone()),
+                    address: Some(route.origin_address.clone()),
+                },
+                destination: GoSendRoutePoint {
+                    name: Some(route.destination_name.clone()),
+                    note: Some(route.destination_note.clone()),
+                    contact_name: Some(route.destination_contact_name.clone()),
+                    contact_phone: Some(route.destination_contact_phone.clone()),
+                    latlong: Some(route.destination_lat_long.clone()),
+                    address: Some(route.destination_address.clone()),
+                },
+            },
+            item: Some(item),
+            driver_info: Default::default(),
+            pricing,
+            timestamps: GoSendTimestamps {
+                order_created: Some(now),
+                order_closed: None,
+            },
+            live_tracking_url: response.live_tracking_url.clone(),
+            created_at: Some(now),
+            updated_at: Some(now),
+        };
+        self.booking_repo.save(&booking).await?;
+
+        let estimated_arrival = Utc::now() + Duration::hours(method.eta_hours());
+        let tracking = DeliveryTracking {
+            provider: Some(PROVIDER_GOSEND.to_string()),
+            tracking_number: Some(response.order_no.clone()),
+            status: Some("confirmed".to_string()),
+            driver_name: None,
+            driver_phone: None,
+            live_tracking_url: response.live_tracking_url,
+            estimated_arrival: Some(bson::DateTime::from_chrono(estimated_arrival)),
+        };
+        self.order_repo
+            .set_delivery(&order_oid, "pending", PROVIDER_GOSEND, &tracking)
+            .await?;
+
+        info!("🛵 Order {} booked on GoSend as {}", order.order_id, response.order_no);
+        Ok(booking)
+    }
+
+    /// Cancel the order's active GoSend booking
+    pub async fn cancel_for_order(&self, order: &Order) -> AppResult<GoSendBooking> {
+        let order_oid = order
+            .id
+            .ok_or_else(|| AppError::Internal("Order has no ID".to_string()))?;
+        let mut booking = self
+            .booking_repo
+            .find_by_order_id(&order.order_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("Order {} has no GoSend booking", order.order_id)))?;
+        if booking.is_closed() {
+            return Err(AppError::Conflict(format!(
+                "GoSend booking {} is already {}",
+                booking.gosend_order_no, booking.status
+            )));
+        }
+
+        self.gosend.cancel(&booking.gosend_order_no).await?;
+        self.booking_repo.mark_cancelled(&booking.gosend_order_no).await?;
+        booking.status = "cancelled".to_string();
+
+        let mut tracking = order.delivery_tracking.clone().unwrap_or_default();
+        tracking.status = Some("cancelled".to_string());
+        self.order_repo
+            .set_delivery(&order_oid, "cancelled", PROVIDER_GOSEND, &tracking)
+            .await?;
+
+        info!("🛵 GoSend booking {} for order {} cancelled", booking.gosend_order_no, order.order_id);
+        Ok(booking)
+    }
+}
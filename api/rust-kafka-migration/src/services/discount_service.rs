@@ -0,0 +1,407 @@
+use bson::oid::ObjectId;
+use redis::AsyncCommands;
+use serde::Deserialize;
+use tracing::warn;
+
+use crate::common::Money;
+use crate::db::models::{
+    DiscountAudit, ManualDiscount, ManualDiscountScope, ManualDiscountType, Order,
+};
+use crate::db::repositories::{DiscountAuditFilter, DiscountAuditRepository, UserRepository};
+use crate::error::{AppError, AppResult};
+use crate::services::order_service::OrderActor;
+
+/// Slack for comparing percentages computed from whole-rupiah amounts
+const PERCENT_EPSILON: f64 = 1e-6;
+/// Wrong supervisor PINs allowed on one device before it is locked out
+const MAX_PIN_ATTEMPTS: u32 = 5;
+/// How long wrong PINs are counted for, and how long a lockout lasts
+const PIN_LOCKOUT_SECS: i64 = 15 * 60;
+
+/// A manual discount as entered by the cashier
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManualDiscountRequest {
+    pub scope: ManualDiscountScope,
+    pub item_index: Option<usize>,
+    pub discount_type: ManualDiscountType,
+    pub value: f64,
+    pub reason: String,
+}
+
+/// A requested discount turned into an amount, before anyone is recorded on it
+#[derive(Debug, Clone, PartialEq)]
+pub struct PricedDiscount {
+    pub scope: ManualDiscountScope,
+    pub item_index: Option<usize>,
+    pub discount_type: ManualDiscountType,
+    pub value: f64,
+    pub base: Money,
+    pub amount: Money,
+    pub reason: String,
+}
+
+/// Supervisor credentials sent with a discount above the cashier's limit
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SupervisorApproval {
+    pub supervisor_id: String,
+    pub pin: String,
+}
+
+/// Who gives a batch of discounts and, above their limit, who approved it
+#[derive(Debug, Clone)]
+pub struct DiscountAuthority {
+    pub cashier: OrderActor,
+    pub limit_percent: f64,
+    pub approver: Option<OrderActor>,
+}
+
+/// The supervisor named in `approval` and the Redis key counting their wrong
+/// PINs entered for `cashier`. Nothing else from the request goes into the
+/// key, or a client could change it to start counting afresh.
+fn approving_supervisor(cashier: &ObjectId, approval: &SupervisorApproval) -> AppResult<(ObjectId, String)> {
+    let supervisor = ObjectId::parse_str(&approval.supervisor_id)
+        .map_err(|_| AppError::BadRequest("Invalid supervisor ID".to_string()))?;
+    if supervisor == *cashier {
+        return Err(AppError::Forbidden("A discount cannot be approved by the cashier giving it".to_string()));
+    }
+    Ok((supervisor, format!("pin-attempts:{}:{}", supervisor.to_hex(), cashier.to_hex())))
+}
+
+/// Manual discounts already given on one order line
+fn line_discounted(order: &Order, index: usize) -> Money {
+    order
+        .manual_discounts
+        .iter()
+        .filter(|d| d.scope == ManualDiscountScope::Line && d.item_index == Some(index))
+        .map(|d| d.amount)
+        .sum()
+}
+
+/// Turn requested discounts into amounts against the order as it stands.
+///
+/// A line discount is taken from what is left of the line after earlier line
+/// discounts; an order discount from the discounted bill before tax. Neither
+/// may exceed its base, and together they may not exceed the bill.
+pub fn price_discounts(order: &Order, requests: &[ManualDiscountRequest]) -> AppResult<Vec<PricedDiscount>> {
+    if requests.is_empty() {
+        return Err(AppError::Validation("No discounts given".to_string()));
+    }
+
+    let mut remaining_bill = order.total_after_discount;
+    let mut priced: Vec<PricedDiscount> = Vec::with_capacity(requests.len());
+
+    for request in requests {
+        let reason = request.reason.trim();
+        if reason.is_empty() {
+            return Err(AppError::Validation("A reason is required for every discount".to_string()));
+        }
+        if !request.value.is_finite() || request.value <= 0.0 {
+            return Err(AppError::Validation("Discount value must be positive".to_string()));
+        }
+        if request.discount_type == ManualDiscountType::Percentage && request.value > 100.0 {
+            return Err(AppError::Validation("Percentage discount cannot exceed 100%".to_string()));
+        }
+
+        let (item_index, base) = match request.scope {
+            ManualDiscountScope::Line => {
+                let index = request.item_index.ok_or_else(|| {
+                    AppError::Validation("itemIndex is required for a line discount".to_string())
+                })?;
+                let item = order
+                    .items
+                    .get(index)
+                    .filter(|i| i.quantity > 0)
+                    .ok_or_else(|| AppError::Validation(format!("No order line at index {}", index)))?;
+                let earlier: Money = priced
+                    .iter()
+                    .filter(|d| d.scope == ManualDiscountScope::Line && d.item_index == Some(index))
+                    .map(|d| d.amount)
+                    .sum();
+                (Some(index), item.subtotal - line_discounted(order, index) - earlier)
+            }
+            ManualDiscountScope::Order => (None, remaining_bill),
+        };
+        let base = base.max(Money::ZERO);
+
+        let amount = match request.discount_type {
+            ManualDiscountType::Percentage => base.percent(request.value),
+            ManualDiscountType::Fixed => Money::from_f64(request.value),
+        };
+        if !amount.is_positive() {
+            return Err(AppError::Validation("Discount amounts to nothing".to_string()));
+        }
+        if amount > base || amount > remaining_bill {
+            return Err(AppError::Validation(format!(
+                "Discount of {} exceeds the {} it is taken from",
+                amount,
+                base.min(remaining_bill)
+            )));
+        }
+
+        remaining_bill -= amount;
+        priced.push(PricedDiscount {
+            scope: request.scope,
+            item_index,
+            discount_type: request.discount_type,
+            value: request.value,
+            base,
+            amount,
+            reason: reason.to_string(),
+        });
+    }
+
+    Ok(priced)
+}
+
+/// Share of the bill subtotal, in percent, the order's manual discounts reach
+/// once `priced` is added
+pub fn bill_percent(order: &Order, priced: &[PricedDiscount]) -> f64 {
+    let total = order.discounts.as_ref().map_or(Money::ZERO, |d| d.manual_discount)
+        + priced.iter().map(|d| d.amount).sum::<Money>();
+    total.ratio_of(order.total_before_discount) * 100.0
+}
+
+/// The discount limit, in percent, needed to give `priced`: the larger of the
+/// order's total manual discount and any single discount against its base
+pub fn required_percent(order: &Order, priced: &[PricedDiscount]) -> f64 {
+    priced
+        .iter()
+        .map(|d| d.amount.ratio_of(d.base) * 100.0)
+        .fold(bill_percent(order, priced), f64::max)
+}
+
+#[derive(Clone)]
+pub struct DiscountService {
+    user_repo: UserRepository,
+    audit_repo: DiscountAuditRepository,
+    redis: redis::Client,
+}
+
+impl DiscountService {
+    pub fn new(user_repo: UserRepository, audit_repo: DiscountAuditRepository, redis: redis::Client) -> Self {
+        Self { user_repo, audit_repo, redis }
+    }
+
+    /// Check the cashier may give discounts reaching `required_percent`.
+    ///
+    /// Within the limit of the cashier's role no approval is needed. Above it
+    /// another user must approve with their PIN, and their role's limit must
+    /// cover the discount. After [`MAX_PIN_ATTEMPTS`] wrong PINs a supervisor
+    /// cannot approve for that cashier until the lockout runs out.
+    pub async fn authorize(
+        &self,
+        cashier_id: ObjectId,
+        required_percent: f64,
+        approval: Option<&SupervisorApproval>,
+    ) -> AppResult<DiscountAuthority> {
+        let (user, role) = self
+            .user_repo
+            .find_with_role(&cashier_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+        let limit_percent = role.discount_limit_percent();
+        let cashier = OrderActor { id: cashier_id, name: user.username };
+
+        if required_percent <= limit_percent + PERCENT_EPSILON {
+            return Ok(DiscountAuthority { cashier, limit_percent, approver: None });
+        }
+
+        let approval = approval.ok_or_else(|| {
+            AppError::Forbidden(format!(
+                "Discount of {:.2}% exceeds the {:.2}% limit of role '{}'; supervisor approval required",
+                required_percent, limit_percent, role.name
+            ))
+        })?;
+        let (supervisor_id, attempts_key) = approving_supervisor(&cashier_id, approval)?;
+        let mut con = self.redis.get_multiplexed_async_connection().await?;
+        let attempts: Option<u32> = con.get(&attempts_key).await?;
+        if attempts.unwrap_or(0) >= MAX_PIN_ATTEMPTS {
+            let ttl: i64 = con.ttl(&attempts_key).await?;
+            return Err(AppError::Forbidden(format!(
+                "Too many wrong supervisor PINs, try again in {} minute(s)",
+                (ttl.max(1) + 59) / 60
+            )));
+        }
+
+        let (supervisor, supervisor_role) = self
+            .user_repo
+            .find_with_role(&supervisor_id)
+            .await?
+            .filter(|(u, _)| u.is_active)
+            .ok_or_else(|| AppError::Forbidden("Supervisor not found".to_string()))?;
+        let hashed_pin = supervisor
+            .pin
+            .as_deref()
+            .ok_or_else(|| AppError::Forbidden("Supervisor has no PIN set".to_string()))?;
+        let pin_ok = bcrypt::verify(&approval.pin, hashed_pin)
+            .map_err(|e| AppError::Internal(format!("PIN verification failed: {}", e)))?;
+        if !pin_ok {
+            let attempts: u32 = con.incr(&attempts_key, 1).await?;
+            // Counting starts at the first wrong PIN; the last one starts a full lockout
+            if attempts == 1 || attempts >= MAX_PIN_ATTEMPTS {
+                con.expire::<_, ()>(&attempts_key, PIN_LOCKOUT_SECS).await?;
+            }
+            warn!(
+                "Rejected supervisor PIN for {} on a discount by {} ({} of {})",
+                supervisor.username, cashier.name, attempts, MAX_PIN_ATTEMPTS
+            );
+            return Err(AppError::Forbidden("Invalid supervisor PIN".to_string()));
+        }
+        con.del::<_, ()>(&attempts_key).await?;
+
+        let supervisor_limit = supervisor_role.discount_limit_percent();
+        if required_percent > supervisor_limit + PERCENT_EPSILON {
+            return Err(AppError::Forbidden(format!(
+                "Discount of {:.2}% exceeds the {:.2}% limit of supervisor role '{}'",
+                required_percent, supervisor_limit, supervisor_role.name
+            )));
+        }
+
+        Ok(DiscountAuthority {
+            cashier,
+            limit_percent,
+            approver: Some(OrderActor { id: supervisor_id, name: supervisor.username }),
+        })
+    }
+
+    /// Discount audit entries for reports
+    pub async fn audit_trail(&self, filter: &DiscountAuditFilter) -> AppResult<Vec<DiscountAudit>> {
+        self.audit_repo.find(filter).await
+    }
+}
+
+/// One audit entry per discount granted on `order`
+pub fn audits(
+    order: &Order,
+    granted: &[ManualDiscount],
+    authority: &DiscountAuthority,
+    bill_percent: f64,
+) -> AppResult<Vec<DiscountAudit>> {
+    let order_oid = order
+        .id
+        .ok_or_else(|| AppError::Internal("Order has no ID".to_string()))?;
+
+    Ok(granted
+        .iter()
+        .map(|discount| DiscountAudit {
+            id: None,
+            order: order_oid,
+            order_id: order.order_id.clone(),
+            outlet: order.outlet,
+            item_name: discount
+                .item_index
+                .and_then(|i| order.items.get(i))
+                .map(|item| item.menu_item_data.name.clone()),
+            discount: discount.clone(),
+            bill_percent,
+            role_limit_percent: authority.limit_percent,
+        })
+        .collect())
+}
+
+/// Record who gave, and who approved, each priced discount
+pub fn grant(priced: Vec<PricedDiscount>, authority: &DiscountAuthority, now: bson::DateTime) -> Vec<ManualDiscount> {
+    priced
+        .into_iter()
+        .map(|d| ManualDiscount {
+            scope: d.scope,
+            item_index: d.item_index,
+            discount_type: d.discount_type,
+            value: d.value,
+            base: d.base,
+            amount: d.amount,
+            reason: d.reason,
+            granted_by: authority.cashier.id,
+            granted_by_name: authority.cashier.name.clone(),
+            approved_by: authority.approver.as_ref().map(|a| a.id),
+            approved_by_name: authority.approver.as_ref().map(|a| a.name.clone()),
+            created_at: now,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::models::OrderItem;
+    use crate::db::models::order::Discounts;
+
+    fn order() -> Order {
+        let line = |subtotal| OrderItem {
+            quantity: 1,
+            subtotal: Money::from_rupiah(subtotal),
+            ..OrderItem::default()
+        };
+        Order {
+            items: vec![line(60_000), line(40_000)],
+            total_before_discount: Money::from_rupiah(100_000),
+            total_after_discount: Money::from_rupiah(90_000),
+            discounts: Some(Discounts {
+                auto_promo_discount: Money::from_rupiah(10_000),
+                ..Discounts::default()
+            }),
+            ..Order::default()
+        }
+    }
+
+    fn request(scope: ManualDiscountScope, item_index: Option<usize>, discount_type: ManualDiscountType, value: f64) -> ManualDiscountRequest {
+        ManualDiscountRequest { scope, item_index, discount_type, value, reason: "Late order".to_string() }
+    }
+
+    #[test]
+    fn test_price_discounts() {
+        let order = order();
+        let priced = price_discounts(&order, &[
+            request(ManualDiscountScope::Line, Some(0), ManualDiscountType::Percentage, 50.0),
+            request(ManualDiscountScope::Order, None, ManualDiscountType::Fixed, 5_000.0),
+        ])
+        .unwrap();
+
+        assert_eq!(priced[0].base, Money::from_rupiah(60_000));
+        assert_eq!(priced[0].amount, Money::from_rupiah(30_000));
+        // The order discount is taken from what the line discount left
+        assert_eq!(priced[1].base, Money::from_rupiah(60_000));
+        assert_eq!(priced[1].amount, Money::from_rupiah(5_000));
+
+        assert!((bill_percent(&order, &priced) - 35.0).abs() < 1e-9);
+        assert!((required_percent(&order, &priced) - 50.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_price_discounts_rejects_bad_requests() {
+        let order = order();
+        let mut no_reason = request(ManualDiscountScope::Order, None, ManualDiscountType::Fixed, 1_000.0);
+        no_reason.reason = "  ".to_string();
+
+        assert!(price_discounts(&order, &[no_reason]).is_err());
+        assert!(price_discounts(&order, &[request(ManualDiscountScope::Line, None, ManualDiscountType::Fixed, 1_000.0)]).is_err());
+        assert!(price_discounts(&order, &[request(ManualDiscountScope::Line, Some(5), ManualDiscountType::Fixed, 1_000.0)]).is_err());
+        assert!(price_discounts(&order, &[request(ManualDiscountScope::Line, Some(1), ManualDiscountType::Fixed, 45_000.0)]).is_err());
+        assert!(price_discounts(&order, &[request(ManualDiscountScope::Order, None, ManualDiscountType::Percentage, 120.0)]).is_err());
+        assert!(price_discounts(&order, &[]).is_err());
+    }
+
+    #[test]
+    fn test_pin_attempts_ignore_the_device() {
+        let (cashier, supervisor) = (ObjectId::new(), ObjectId::new());
+        let approval = |device: &str| -> SupervisorApproval {
+            serde_json::from_value(serde_json::json!({
+                "supervisorId": supervisor.to_hex(),
+                "pin": "1234",
+                "deviceId": device,
+            }))
+            .unwrap()
+        };
+
+        // A fresh deviceId on every try keeps counting against the same key
+        let (approver, key) = approving_supervisor(&cashier, &approval("tablet-1")).unwrap();
+        assert_eq!(approver, supervisor);
+        assert_eq!(key, format!("pin-attempts:{}:{}", supervisor.to_hex(), cashier.to_hex()));
+        assert_eq!(approving_supervisor(&cashier, &approval("tablet-2")).unwrap().1, key);
+        assert_ne!(approving_supervisor(&ObjectId::new(), &approval("tablet-1")).unwrap().1, key);
+
+        assert!(approving_supervisor(&supervisor, &approval("tablet-1")).is_err());
+    }
+}
@@ -1,6 +1,6 @@
 use bson::oid::ObjectId;
-use std::sync::Arc;
 
+use crate::common::Money;
 use crate::db::models::{Event, EventStatus, FreeRegistration, MenuItem};
 use crate::db::repositories::{EventRepository, MenuRepository};
 use crate::error::AppResult;
@@ -28,7 +28,7 @@ impl EventService {
         let menu_item = MenuItem {
             id: None,
             name: event.name.clone(),
-            price: event.price,
+            price: Money::from_f64(event.price),
             description: Some(event.description.clone()),
             main_category: Some(crate::db::models::menu_item::MainCategory::Event),
             workstation: None,
@@ -45,7 +45,7 @@ impl EventService {
             category: None,
             sub_category: None,
             image_url: Some(event.image_url.clone()),
-            cost_price: 0.0,
+            cost_price: Money::ZERO,
             available_stock: event.capacity as f64,
             warehouse_stocks: vec![],
             available_at: vec![],
@@ -98,7 +98,7 @@ impl EventService {
         if let Some(menu_item_id) = existing_event.menu_item {
             if let Some(mut menu_item) = self.menu_repo.find_menu_item_by_id(&menu_item_id).await? {
                 menu_item.name = event.name.clone();
-                menu_item.price = event.price;
+                menu_item.price = Money::from_f64(event.price);
                 menu_item.description = Some(event.description.clone());
                 menu_item.is_active = event.status != EventStatus::Cancelled;
                 menu_item.available_stock = event.capacity as f64 - event.sold_tickets as f64;
@@ -148,6 +148,7 @@ impl EventService {
     }
 
     // Register for free event
+    #[allow(clippy::too_many_arguments)]
     pub async fn register_free_event(
         &self,
         event_id: &ObjectId,
@@ -158,7 +159,7 @@ impl EventService {
         current_city: Option<String>,
         notes: Option<String>,
     ) -> AppResult<(String, Event)> {
-        let mut event = self
+        let event = self
             .event_repo
             .find_by_id(event_id)
             .await?
@@ -0,0 +1,395 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use tracing::{info, warn};
+
+use crate::common::Money;
+use crate::config::GoSendConfig;
+use crate::error::{AppError, AppResult};
+
+const STAGING_BASE_URL: &str = "https://integration-kilat-api.gojekapi.com";
+
+/// Corporate billing; GoSend does not support COD for API bookings
+const PAYMENT_TYPE_CORPORATE: u8 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum ShipmentMethod {
+    #[default]
+    Instant,
+    SameDay,
+}
+
+impl ShipmentMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ShipmentMethod::Instant => "Instant",
+            ShipmentMethod::SameDay => "SameDay",
+        }
+    }
+
+    /// Hours until the parcel is expected to arrive
+    pub fn eta_hours(&self) -> i64 {
+        match self {
+            ShipmentMethod::Instant => 2,
+            ShipmentMethod::SameDay => 6,
+        }
+    }
+}
+
+impl fmt::Display for ShipmentMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ShipmentMethod {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Instant" => Ok(ShipmentMethod::Instant),
+            "SameDay" => Ok(ShipmentMethod::SameDay),
+            other => Err(AppError::Validation(format!("Unsupported shipment method: {}", other))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GoSendPrice {
+    pub total_price: Money,
+}
+
+/// Quote for one shipment method
+#[derive(Debug, Clone, Deserialize)]
+pub struct GoSendMethodEstimate {
+    #[serde(default)]
+    pub serviceable: bool,
+    #[serde(default)]
+    pub active: bool,
+    #[serde(default)]
+    pub distance: f64,
+    #[serde(default)]
+    pub shipment_method_description: String,
+    pub price: GoSendPrice,
+}
+
+/// Response of the price calculator, keyed by shipment method
+#[derive(Debug, Clone, Deserialize)]
+pub struct GoSendEstimate {
+    #[serde(rename = "Instant")]
+    pub instant: Option<GoSendMethodEstimate>,
+    #[serde(rename = "SameDay")]
+    pub same_day: Option<GoSendMethodEstimate>,
+}
+
+impl GoSendEstimate {
+    pub fn for_method(&self, method: ShipmentMethod) -> Option<&GoSendMethodEstimate> {
+        match method {
+            ShipmentMethod::Instant => self.instant.as_ref(),
+            ShipmentMethod::SameDay => self.same_day.as_ref(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GoSendInsuranceDetails {
+    pub applied: String,
+    pub fee: String,
+    pub product_description: String,
+    pub product_price: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GoSendRoute {
+    pub origin_name: String,
+    pub origin_note: String,
+    pub origin_contact_name: String,
+    pub origin_contact_phone: String,
+    pub origin_lat_long: String,
+    pub origin_address: String,
+    pub destination_name: String,
+    pub destination_note: String,
+    pub destination_contact_name: String,
+    pub destination_contact_phone: String,
+    pub destination_lat_long: String,
+    pub destination_address: String,
+    /// Package name; the internal order ID is used so the contents stay masked
+    pub item: String,
+    pub store_order_id: String,
+    pub insurance_details: GoSendInsuranceDetails,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GoSendBookingRequest {
+    pub payment_type: u8,
+    #[serde(rename = "shipment_method")]
+    pub shipment_method: ShipmentMethod,
+    pub routes: Vec<GoSendRoute>,
+}
+
+impl GoSendBookingRequest {
+    pub fn new(shipment_method: ShipmentMethod, route: GoSendRoute) -> Self {
+        Self {
+            payment_type: PAYMENT_TYPE_CORPORATE,
+            shipment_method,
+            routes: vec![route],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GoSendBookingResponse {
+    #[serde(default)]
+    pub id: Option<i64>,
+    pub order_no: String,
+    #[serde(default)]
+    pub live_tracking_url: Option<String>,
+}
+
+/// GoSend only accepts Indonesian numbers: 08xx (10-13 digits),
+/// 62xx (11-14 digits) or 021 landlines (10-11 digits).
+pub fn is_valid_phone(phone: &str) -> bool {
+    let digits: String = phone.chars().filter(|c| c.is_ascii_digit()).collect();
+    let len = digits.len();
+
+    if digits.starts_with("08") {
+        (10..=13).contains(&len)
+    } else if digits.starts_with("62") {
+        (11..=14).contains(&len)
+    } else if digits.starts_with("021") {
+        (10..=11).contains(&len)
+    } else {
+        false
+    }
+}
+
+/// Thin client for the GoSend (Gojek Kilat) API
+#[derive(Clone)]
+pub struct GoSendClient {
+    http: reqwest::Client,
+    base_url: String,
+    client_id: String,
+    pass_key: String,
+}
+
+impl GoSendClient {
+    pub fn new(config: &GoSendConfig) -> Self {
+        let base_url = if config.base_url.is_empty() {
+            STAGING_BASE_URL.to_string()
+        } else {
+            config.base_url.clone()
+        };
+
+        Self::with_base_url(config.client_id.clone(), config.api_key.clone(), base_url)
+    }
+
+    pub fn with_base_url(client_id: String, pass_key: String, base_url: String) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            client_id,
+            pass_key,
+        }
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        self.http
+            .request(method, format!("{}{}", self.base_url, path))
+            .header("Client-ID", &self.client_id)
+            .header("Pass-Key", &self.pass_key)
+    }
+
+    /// Map non-2xx responses to `ExternalService`, keeping GoSend's error body in the message
+    async fn check(response: reqwest::Response, action: &str) -> AppResult<reqwest::Response> {
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+
+        let body = response.text().await.unwrap_or_default();
+        warn!("GoSend {} failed: {} {}", action, status, body);
+        Err(AppError::ExternalService(format!("GoSend {} failed: {} {}", action, status, body)))
+    }
+
+    /// Price and distance per shipment method - GET /gokilat/v10/calculate/price
+    ///
+    /// Coordinates are "lat,long" strings.
+    pub async fn estimate(&self, origin: &str, destination: &str) -> AppResult<GoSendEstimate> {
+        let payment_type = PAYMENT_TYPE_CORPORATE.to_string();
+        let response = self
+            .request(reqwest::Method::GET, "/gokilat/v10/calculate/price")
+            .query(&[
+                ("origin", origin),
+                ("destination", destination),
+                ("paymentType", payment_type.as_str()),
+            ])
+            .send()
+            .await?;
+
+        Ok(Self::check(response, "price estimate").await?.json().await?)
+    }
+
+    /// Book a pickup - POST /gokilat/v10/booking
+    pub async fn book(&self, request: &GoSendBookingRequest) -> AppResult<GoSendBookingResponse> {
+        let response = self
+            .request(reqwest::Method::POST, "/gokilat/v10/booking")
+            .json(request)
+            .send()
+            .await?;
+
+        let booking: GoSendBookingResponse = Self::check(response, "booking").await?.json().await?;
+        info!("🛵 GoSend booking {} created ({})", booking.order_no, request.shipment_method);
+        Ok(booking)
+    }
+
+    /// Cancel a booking - PUT /gokilat/v10/booking/cancel
+    pub async fn cancel(&self, order_no: &str) -> AppResult<()> {
+        let response = self
+            .request(reqwest::Method::PUT, "/gokilat/v10/booking/cancel")
+            .json(&serde_json::json!({ "orderNo": order_no }))
+            .send()
+            .await?;
+
+        Self::check(response, "cancellation").await?;
+        info!("🛵 GoSend booking {} cancelled", order_no);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_partial_json, header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn client(server: &MockServer) -> GoSendClient {
+        GoSendClient::with_base_url("client-1".to_string(), "pass-1".to_string(), server.uri())
+    }
+
+    fn route() -> GoSendRoute {
+        GoSendRoute {
+            origin_name: "Outlet".to_string(),
+            origin_note: "Pickup order ORD-1".to_string(),
+            origin_contact_name: "Outlet".to_string(),
+            origin_contact_phone: "081234567890".to_string(),
+            origin_lat_long: "-6.2,106.8".to_string(),
+            origin_address: "Jl. Sudirman 1".to_string(),
+            destination_name: "Budi".to_string(),
+            destination_note: String::new(),
+            destination_contact_name: "Budi".to_string(),
+            destination_contact_phone: "081298765432".to_string(),
+            destination_lat_long: "-6.25,106.85".to_string(),
+            destination_address: "Jl. Thamrin 2".to_string(),
+            item: "ORDER-ORD-1".to_string(),
+            store_order_id: "ORD-1".to_string(),
+            insurance_details: GoSendInsuranceDetails {
+                applied: "false".to_string(),
+                fee: "0".to_string(),
+                product_description: "ORDER-ORD-1".to_string(),
+                product_price: "0".to_string(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_is_valid_phone() {
+        assert!(is_valid_phone("0812-3456-7890"));
+        assert!(is_valid_phone("+62 812 3456 7890"));
+        assert!(is_valid_phone("0215551234"));
+        assert!(!is_valid_phone("08123"));
+        assert!(!is_valid_phone("0215551234567"));
+        assert!(!is_valid_phone("+1 555 123 4567"));
+    }
+
+    #[tokio::test]
+    async fn test_estimate() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/gokilat/v10/calculate/price"))
+            .and(header("Client-ID", "client-1"))
+            .and(header("Pass-Key", "pass-1"))
+            .and(query_param("origin", "-6.2,106.8"))
+            .and(query_param("paymentType", "3"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "Instant": {
+                    "shipment_method": "Instant",
+                    "serviceable": true,
+                    "active": true,
+                    "distance": 6.4,
+                    "shipment_method_description": "Instant Delivery",
+                    "price": { "total_price": 27000 }
+                },
+                "SameDay": {
+                    "shipment_method": "SameDay",
+                    "serviceable": true,
+                    "active": true,
+                    "distance": 6.4,
+                    "shipment_method_description": "Same Day Delivery",
+                    "price": { "total_price": 18000 }
+                }
+            })))
+            .mount(&server)
+            .await;
+
+        let estimate = client(&server).estimate("-6.2,106.8", "-6.25,106.85").await.unwrap();
+
+        assert_eq!(estimate.for_method(ShipmentMethod::Instant).unwrap().price.total_price, Money::from_rupiah(27_000));
+        assert_eq!(estimate.for_method(ShipmentMethod::SameDay).unwrap().price.total_price, Money::from_rupiah(18_000));
+    }
+
+    #[tokio::test]
+    async fn test_book_and_cancel() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/gokilat/v10/booking"))
+            .and(body_partial_json(serde_json::json!({
+                "paymentType": 3,
+                "shipment_method": "SameDay",
+                "routes": [{ "storeOrderId": "ORD-1", "originLatLong": "-6.2,106.8" }]
+            })))
+            .respond_with(ResponseTemplate::new(201).set_body_json(serde_json::json!({
+                "id": 1234,
+                "orderNo": "GK-11-1234",
+                "liveTrackingUrl": "https://gojek.com/track/GK-11-1234"
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("PUT"))
+            .and(path("/gokilat/v10/booking/cancel"))
+            .and(body_partial_json(serde_json::json!({ "orderNo": "GK-11-1234" })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "statusCode": 200,
+                "message": "Booking cancelled"
+            })))
+            .mount(&server)
+            .await;
+
+        let client = client(&server);
+        let request = GoSendBookingRequest::new(ShipmentMethod::SameDay, route());
+        let booking = client.book(&request).await.unwrap();
+
+        assert_eq!(booking.order_no, "GK-11-1234");
+        assert!(booking.live_tracking_url.is_some());
+        client.cancel(&booking.order_no).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_booking_rejected() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/gokilat/v10/booking"))
+            .respond_with(ResponseTemplate::new(400).set_body_json(serde_json::json!({
+                "errors": [{ "message": "Destination is not serviceable" }]
+            })))
+            .mount(&server)
+            .await;
+
+        let request = GoSendBookingRequest::new(ShipmentMethod::Instant, route());
+        let result = client(&server).book(&request).await;
+
+        assert!(matches!(result, Err(AppError::ExternalService(_))));
+    }
+}
@@ -1,8 +1,7 @@
-use std::sync::Arc;
 use bson::oid::ObjectId;
-use chrono::{DateTime, Utc};
+use chrono::Utc;
 
-use crate::db::models::{Attendance, AttendanceStatus, CheckInfo, CheckType, Company, ApprovalInfo};
+use crate::db::models::{Attendance, AttendanceStatus, CheckInfo, ApprovalInfo};
 use crate::db::repositories::{AttendanceRepository, CompanyRepository};
 use crate::error::{AppError, AppResult};
 
@@ -117,7 +116,7 @@ impl AttendanceService {
         let overtime_hours = (hours - 8.0).max(0.0);
 
         let check_out_doc = bson::to_document(&check_out_data)
-            .map_err(|e| AppError::BsonSerialization(e))?;
+            .map_err(AppError::BsonSerialization)?;
 
         self.attendance_repo.update_check_out(
             &attendance.id.unwrap(),
@@ -1,6 +1,4 @@
-use std::sync::Arc;
 use bson::oid::ObjectId;
-use bson::doc;
 
 use crate::db::models::Employee;
 use crate::db::repositories::{EmployeeRepository, CompanyRepository, UserRepository};
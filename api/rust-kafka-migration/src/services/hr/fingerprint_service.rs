@@ -1,6 +1,4 @@
-use std::sync::Arc;
 use bson::oid::ObjectId;
-use bson::doc;
 
 use crate::db::models::{Fingerprint, RawFingerprint};
 use crate::db::repositories::{FingerprintRepository, EmployeeRepository};
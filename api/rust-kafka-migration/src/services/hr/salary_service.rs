@@ -1,8 +1,6 @@
-use std::sync::Arc;
 use bson::oid::ObjectId;
-use futures::TryStreamExt;
 
-use crate::db::models::{Salary, SalaryStatus, Employee, Attendance, SalaryPeriod, AttendanceSummary, Earnings, SalaryDeductions, CalculationRates, hr_salary::PaymentMethod};
+use crate::db::models::{Salary, SalaryStatus, Attendance, SalaryPeriod, AttendanceSummary, Earnings, SalaryDeductions, CalculationRates, hr_salary::PaymentMethod};
 use crate::db::repositories::{SalaryRepository, EmployeeRepository, AttendanceRepository, CompanyRepository};
 use crate::services::hr::BpjsService;
 use crate::error::{AppError, AppResult};
@@ -189,17 +187,12 @@ impl SalaryService {
         let mut overtime2_hours = 0.0;
 
         for att in attendances {
-            match att.status {
-                // TODO: Update AttendanceStatus enum to include specific types or check strings
-                _ => {
-                    if att.check_in.time.is_some() {
-                        total_tapping_days += 1;
-                        if att.fingerprint_tapping {
-                            fingerprint_tapping_days += 1;
-                        }
-                    }
+            if att.check_in.time.is_some() {
+                total_tapping_days += 1;
+                if att.fingerprint_tapping {
+                    fingerprint_tapping_days += 1;
                 }
-            }
+            };
             overtime1_hours += att.overtime1_hours;
             overtime2_hours += att.overtime2_hours;
         }
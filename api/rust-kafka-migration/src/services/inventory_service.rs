@@ -1,11 +1,109 @@
+use std::collections::BTreeMap;
 use std::sync::Arc;
 use bson::oid::ObjectId;
+use tracing::warn;
 
 use crate::db::repositories::{InventoryRepository, MenuRepository};
-use crate::db::models::{ProductStock, ProductMovement, MenuStock, StockUpdateType, StockReason};
+use mongodb::ClientSession;
+
+use crate::db::models::{
+    OrderItem, ProductStock, ProductMovement, ProductMovementType, MenuStock, Recipe, StockUpdateType, StockReason,
+};
 use crate::kafka::{KafkaProducer, events::InventoryEvent};
 use crate::error::AppResult;
 
+/// Portions of a menu item sold from a warehouse, used to refresh its menu stock.
+/// Portions a cancel puts back are negative.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SoldMenuItem {
+    pub menu_item_id: ObjectId,
+    pub warehouse_id: ObjectId,
+    pub quantity: f64,
+}
+
+/// What `deduct_order_stock` took out of stock for an order
+#[derive(Debug, Clone, Default)]
+pub struct StockDeduction {
+    /// Indexes of the order lines whose ingredients were deducted
+    pub lines: Vec<usize>,
+    pub sold: Vec<SoldMenuItem>,
+}
+
+/// Ingredients consumed by one order line: the recipe's base ingredients plus
+/// those of the selected toppings and addon options, times the line quantity
+pub fn line_ingredients(recipe: &Recipe, item: &OrderItem) -> BTreeMap<ObjectId, f64> {
+    let mut needed: BTreeMap<ObjectId, f64> = BTreeMap::new();
+    let mut add = |ingredients: &[crate::db::models::recipe::Ingredient]| {
+        for ingredient in ingredients {
+            *needed.entry(ingredient.product_id).or_default() += ingredient.quantity;
+        }
+    };
+
+    add(&recipe.base_ingredients);
+
+    for topping in &item.toppings {
+        if let Some(option) = recipe.topping_options.iter().find(|t| t.topping_name == topping.name) {
+            add(&option.ingredients);
+        }
+    }
+
+    for addon in &item.addons {
+        for selected in &addon.options {
+            if let Some(option) = recipe
+                .addon_options
+                .iter()
+                .find(|a| a.addon_name == addon.name && a.option_label == selected.label)
+            {
+                add(&option.ingredients);
+            }
+        }
+    }
+
+    let quantity = item.quantity as f64;
+    needed.retain(|_, qty| *qty > 0.0);
+    needed.values_mut().for_each(|qty| *qty *= quantity);
+    needed
+}
+
+/// Lines of an order whose ingredients were taken out of stock
+pub fn deducted_lines(items: &[OrderItem]) -> impl Iterator<Item = &OrderItem> {
+    items
+        .iter()
+        .filter(|i| i.stock_deducted && i.menu_item.is_some() && i.quantity > 0)
+}
+
+/// Ingredients a revised line needs beyond what was already taken out for
+/// it; negative quantities go back into stock
+pub fn ingredient_delta(
+    deducted: &BTreeMap<ObjectId, f64>,
+    needed: &BTreeMap<ObjectId, f64>,
+) -> BTreeMap<ObjectId, f64> {
+    let mut delta = needed.clone();
+    for (product_id, quantity) in deducted {
+        *delta.entry(*product_id).or_insert(0.0) -= quantity;
+    }
+    delta.retain(|_, quantity| quantity.abs() > f64::EPSILON);
+    delta
+}
+
+/// A manually set menu stock after `sold` portions: it counts down with
+/// sales and back up with returns, never below zero
+fn counted_manual_stock(manual_stock: Option<f64>, sold: f64) -> Option<f64> {
+    manual_stock.map(|m| (m - sold).max(0.0))
+}
+
+/// Portions the base ingredients in stock are enough for
+pub fn portions_available(recipe: &Recipe, stock_of: impl Fn(&ObjectId) -> f64) -> f64 {
+    recipe
+        .base_ingredients
+        .iter()
+        .filter(|i| i.quantity > 0.0)
+        .map(|i| (stock_of(&i.product_id) / i.quantity).floor())
+        .fold(None, |min: Option<f64>, portions| Some(min.map_or(portions, |m| m.min(portions))))
+        .unwrap_or(0.0)
+        .max(0.0)
+}
+
 #[derive(Clone)]
 pub struct InventoryService {
     inventory_repo: InventoryRepository,
@@ -115,4 +213,392 @@ impl InventoryService {
 
         Ok(())
     }
+
+    /// Put back every stock movement taken out for an order.
+    ///
+    /// Only the net quantity still out for the order is returned, so running
+    /// this twice for the same order does not restock twice.
+    /// Returns the number of product stocks that were restored.
+    pub async fn restore_order_stock(
+        &self,
+        order_oid: &ObjectId,
+        order_id: &str,
+        handled_by: Option<String>,
+        session: &mut ClientSession,
+    ) -> AppResult<usize> {
+        let stocks = self
+            .inventory_repo
+            .find_product_stocks_by_reference_with_session(order_oid, session)
+            .await?;
+
+        let mut restored = 0;
+        for stock in stocks {
+            let Some(stock_id) = stock.id else { continue };
+
+            let net_out: f64 = stock
+                .movements
+                .iter()
+                .filter(|m| m.reference_id.as_ref() == Some(order_oid))
+                .map(|m| match m.movement_type {
+                    ProductMovementType::Out => m.quantity,
+                    ProductMovementType::In => -m.quantity,
+                    _ => 0.0,
+                })
+                .sum();
+
+            if net_out <= 0.0 {
+                continue;
+            }
+
+            let movement = ProductMovement {
+                quantity: net_out,
+                movement_type: ProductMovementType::In,
+                reference_id: Some(*order_oid),
+                notes: Some(format!("Stock returned from canceled order {}", order_id)),
+                source_warehouse: None,
+                destination_warehouse: Some(stock.warehouse),
+                handled_by: handled_by.clone(),
+                date: mongodb::bson::DateTime::now(),
+            };
+
+            self.inventory_repo
+                .apply_product_movement_with_session(&stock_id, net_out, movement, session)
+                .await?;
+            restored += 1;
+        }
+
+        Ok(restored)
+    }
+
+    /// The portions of an order's deducted lines, as negative sales, so
+    /// `refresh_menu_stocks` counts menu stock back up once a cancel is committed
+    pub async fn returned_menu_items(&self, items: &[OrderItem]) -> AppResult<Vec<SoldMenuItem>> {
+        let mut returned = Vec::new();
+        for item in deducted_lines(items) {
+            let Some(menu_item_id) = item.menu_item else { continue };
+            let Some(menu_item) = self.menu_repo.find_menu_item_by_id(&menu_item_id).await? else { continue };
+            let Some(warehouse_id) = menu_item.get_primary_warehouse_id() else { continue };
+            returned.push(SoldMenuItem {
+                menu_item_id,
+                warehouse_id,
+                quantity: -(item.quantity as f64),
+            });
+        }
+        Ok(returned)
+    }
+
+    /// Take the recipe ingredients of every sold order line out of its
+    /// workstation's warehouse.
+    ///
+    /// Lines already flagged `stock_deducted` are skipped, and deducted lines
+    /// are flagged on `items`, so an order is never deducted twice. Movements
+    /// reference the order, which is what `restore_order_stock` puts back on
+    /// cancel. Lines without a warehouse mapping or recipe are left alone.
+    pub async fn deduct_order_stock(
+        &self,
+        order_oid: &ObjectId,
+        order_id: &str,
+        items: &mut [OrderItem],
+        handled_by: Option<String>,
+        session: &mut ClientSession,
+    ) -> AppResult<StockDeduction> {
+        let mut deduction = StockDeduction::default();
+
+        for (index, item) in items.iter_mut().enumerate() {
+            let Some(menu_item_id) = item.menu_item else { continue };
+            if item.stock_deducted || item.quantity <= 0 {
+                continue;
+            }
+
+            let Some(menu_item) = self.menu_repo.find_menu_item_by_id(&menu_item_id).await? else {
+                warn!("Menu item {} of order {} not found, stock not deducted", menu_item_id, order_id);
+                continue;
+            };
+            let Some(warehouse_id) = menu_item.get_primary_warehouse_id() else {
+                warn!("Menu item {} has no warehouse mapping, stock not deducted", menu_item.name);
+                continue;
+            };
+            let Some(recipe) = self.inventory_repo.find_recipe_by_menu_item(&menu_item_id).await? else {
+                warn!("Menu item {} has no recipe, stock not deducted", menu_item.name);
+                continue;
+            };
+
+            for (product_id, quantity) in line_ingredients(&recipe, item) {
+                let movement = ProductMovement {
+                    quantity,
+                    movement_type: ProductMovementType::Out,
+                    reference_id: Some(*order_oid),
+                    notes: Some(format!("Used for order {}: {}", order_id, item.menu_item_data.name)),
+                    source_warehouse: Some(warehouse_id),
+                    destination_warehouse: None,
+                    handled_by: handled_by.clone(),
+                    date: mongodb::bson::DateTime::now(),
+                };
+                self.inventory_repo
+                    .apply_warehouse_movement_with_session(&product_id, &warehouse_id, -quantity, movement, session)
+                    .await?;
+            }
+
+            item.stock_deducted = true;
+            deduction.lines.push(index);
+            deduction.sold.push(SoldMenuItem {
+                menu_item_id,
+                warehouse_id,
+                quantity: item.quantity as f64,
+            });
+        }
+
+        Ok(deduction)
+    }
+
+    /// Bring the stock of a confirmed order in line with a revision, inside
+    /// the revision's transaction.
+    ///
+    /// For each line, what was deducted for the old version is compared with
+    /// what the revised line needs: the extra leaves stock and what is no
+    /// longer needed, e.g. for removed lines, comes back. Revised lines are
+    /// flagged `stock_deducted` while they still sell anything. Returns the
+    /// portions sold, negative when returned, for `refresh_menu_stocks`.
+    pub async fn revise_order_stock(
+        &self,
+        order_oid: &ObjectId,
+        order_id: &str,
+        before: &[OrderItem],
+        after: &mut [OrderItem],
+        handled_by: Option<String>,
+        session: &mut ClientSession,
+    ) -> AppResult<Vec<SoldMenuItem>> {
+        let mut sold = Vec::new();
+
+        for (index, item) in after.iter_mut().enumerate() {
+            let deducted = before.get(index).filter(|old| old.stock_deducted);
+            let Some(menu_item_id) = item.menu_item.or(deducted.and_then(|old| old.menu_item)) else { continue };
+            if deducted.is_none() && item.quantity <= 0 {
+                continue;
+            }
+
+            let Some(menu_item) = self.menu_repo.find_menu_item_by_id(&menu_item_id).await? else {
+                warn!("Menu item {} of order {} not found, stock not revised", menu_item_id, order_id);
+                continue;
+            };
+            let Some(warehouse_id) = menu_item.get_primary_warehouse_id() else {
+                warn!("Menu item {} has no warehouse mapping, stock not revised", menu_item.name);
+                continue;
+            };
+            let Some(recipe) = self.inventory_repo.find_recipe_by_menu_item(&menu_item_id).await? else {
+                warn!("Menu item {} has no recipe, stock not revised", menu_item.name);
+                continue;
+            };
+
+            let taken = deducted.map(|old| line_ingredients(&recipe, old)).unwrap_or_default();
+            let needed = if item.quantity > 0 { line_ingredients(&recipe, item) } else { BTreeMap::new() };
+
+            for (product_id, change) in ingredient_delta(&taken, &needed) {
+                let (movement_type, notes, source_warehouse, destination_warehouse) = if change > 0.0 {
+                    let notes = format!("Used for revised order {}: {}", order_id, item.menu_item_data.name);
+                    (ProductMovementType::Out, notes, Some(warehouse_id), None)
+                } else {
+                    let notes = format!("Returned from revised order {}: {}", order_id, item.menu_item_data.name);
+                    (ProductMovementType::In, notes, None, Some(warehouse_id))
+                };
+                let movement = ProductMovement {
+                    quantity: change.abs(),
+                    movement_type,
+                    reference_id: Some(*order_oid),
+                    notes: Some(notes),
+                    source_warehouse,
+                    destination_warehouse,
+                    handled_by: handled_by.clone(),
+                    date: mongodb::bson::DateTime::now(),
+                };
+                self.inventory_repo
+                    .apply_warehouse_movement_with_session(&product_id, &warehouse_id, -change, movement, session)
+                    .await?;
+            }
+
+            item.stock_deducted = item.quantity > 0;
+            let portions = item.quantity.max(0) - deducted.map_or(0, |old| old.quantity);
+            if portions != 0 {
+                sold.push(SoldMenuItem {
+                    menu_item_id,
+                    warehouse_id,
+                    quantity: portions as f64,
+                });
+            }
+        }
+
+        Ok(sold)
+    }
+
+    /// Recalculate the menu stock of sold or returned items from their
+    /// ingredient stock.
+    ///
+    /// Runs after the sale is committed; a failure is logged and does not
+    /// undo the sale.
+    pub async fn refresh_menu_stocks(&self, sold: &[SoldMenuItem]) {
+        for item in sold {
+            if let Err(e) = self.recalculate_menu_stock(item).await {
+                warn!("Failed to recalculate menu stock for {}: {}", item.menu_item_id, e);
+            }
+        }
+    }
+
+    async fn recalculate_menu_stock(&self, sold: &SoldMenuItem) -> AppResult<()> {
+        let Some(recipe) = self.inventory_repo.find_recipe_by_menu_item(&sold.menu_item_id).await? else {
+            return Ok(());
+        };
+
+        let mut stocks = BTreeMap::new();
+        for ingredient in &recipe.base_ingredients {
+            let stock = self
+                .inventory_repo
+                .find_product_stock(&ingredient.product_id, &sold.warehouse_id)
+                .await?
+                .map(|s| s.current_stock)
+                .unwrap_or(0.0);
+            stocks.insert(ingredient.product_id, stock);
+        }
+        let calculated = portions_available(&recipe, |id| stocks.get(id).copied().unwrap_or(0.0));
+
+        let current = self.inventory_repo.find_menu_stock(&sold.menu_item_id, &sold.warehouse_id).await?;
+        let previous = current.as_ref().map(|s| s.get_effective_stock()).unwrap_or(0.0);
+        // A manually set stock counts down with sales until it is adjusted again
+        let manual_stock = counted_manual_stock(current.as_ref().and_then(|s| s.manual_stock), sold.quantity);
+        let now = mongodb::bson::DateTime::now();
+
+        let stock = MenuStock {
+            id: current.as_ref().and_then(|s| s.id),
+            menu_item_id: sold.menu_item_id,
+            warehouse_id: sold.warehouse_id,
+            update_type: StockUpdateType::Sale,
+            quantity: sold.quantity,
+            reason: StockReason::OrderFulfillment,
+            previous_stock: previous,
+            current_stock: manual_stock.unwrap_or(calculated),
+            calculated_stock: calculated,
+            manual_stock,
+            adjustment_note: current.as_ref().and_then(|s| s.adjustment_note.clone()),
+            adjusted_by: current.as_ref().and_then(|s| s.adjusted_by.clone()),
+            handled_by: "system".to_string(),
+            notes: None,
+            related_warehouse: None,
+            transfer_id: None,
+            last_calculated_at: now,
+            last_adjusted_at: current.as_ref().map(|s| s.last_adjusted_at).unwrap_or(now),
+            created_at: current.as_ref().and_then(|s| s.created_at),
+            updated_at: Some(now),
+        };
+        let effective = stock.get_effective_stock();
+
+        self.inventory_repo.upsert_menu_stock(stock).await?;
+        self.menu_repo
+            .update_stock_for_warehouse(&sold.menu_item_id, &sold.warehouse_id, effective)
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::models::order::{AddonOption as SelectedOption, OrderItemAddon, OrderItemTopping};
+    use crate::db::models::recipe::{AddonOption, Ingredient, ToppingOption};
+
+    fn ingredient(product_id: ObjectId, quantity: f64) -> Ingredient {
+        Ingredient {
+            product_id,
+            product_name: None,
+            product_sku: None,
+            quantity,
+            unit: "gram".to_string(),
+            is_default: true,
+        }
+    }
+
+    #[test]
+    fn test_line_ingredients() {
+        let (coffee, milk, syrup, ice) = (ObjectId::new(), ObjectId::new(), ObjectId::new(), ObjectId::new());
+        let recipe = Recipe {
+            id: None,
+            menu_item_id: ObjectId::new(),
+            base_ingredients: vec![ingredient(coffee, 18.0), ingredient(milk, 100.0)],
+            topping_options: vec![ToppingOption {
+                topping_name: "Extra Shot".to_string(),
+                ingredients: vec![ingredient(coffee, 9.0)],
+            }],
+            addon_options: vec![
+                AddonOption {
+                    addon_name: "Sweetness".to_string(),
+                    option_label: "Less".to_string(),
+                    ingredients: vec![ingredient(syrup, 5.0)],
+                },
+                AddonOption {
+                    addon_name: "Sweetness".to_string(),
+                    option_label: "Normal".to_string(),
+                    ingredients: vec![ingredient(syrup, 10.0)],
+                },
+                AddonOption {
+                    addon_name: "Ice".to_string(),
+                    option_label: "Normal".to_string(),
+                    ingredients: vec![ingredient(ice, 50.0)],
+                },
+            ],
+            created_at: mongodb::bson::DateTime::now(),
+        };
+        let item = OrderItem {
+            quantity: 2,
+            toppings: vec![OrderItemTopping { name: "Extra Shot".to_string(), ..Default::default() }],
+            addons: vec![OrderItemAddon {
+                name: "Sweetness".to_string(),
+                options: vec![SelectedOption { label: "Normal".to_string(), ..Default::default() }],
+                ..Default::default()
+            }],
+            ..OrderItem::default()
+        };
+
+        let needed = line_ingredients(&recipe, &item);
+        assert_eq!(needed.len(), 3);
+        assert_eq!(needed[&coffee], 54.0);
+        assert_eq!(needed[&milk], 200.0);
+        assert_eq!(needed[&syrup], 20.0);
+
+        assert_eq!(portions_available(&recipe, |id| if *id == coffee { 40.0 } else { 1000.0 }), 2.0);
+        assert_eq!(portions_available(&recipe, |_| -5.0), 0.0);
+    }
+
+    #[test]
+    fn test_cancel_returns_deducted_portions() {
+        let line = |stock_deducted, quantity| OrderItem {
+            menu_item: Some(ObjectId::new()),
+            quantity,
+            stock_deducted,
+            ..OrderItem::default()
+        };
+        let items = vec![line(true, 3), line(false, 2), line(true, 0), OrderItem { stock_deducted: true, ..OrderItem::default() }];
+        let returned: Vec<i32> = deducted_lines(&items).map(|i| i.quantity).collect();
+        assert_eq!(returned, vec![3]);
+
+        // A manual stock of 10 sells 3, and the cancel puts the 3 back
+        let after_sale = counted_manual_stock(Some(10.0), 3.0);
+        assert_eq!(after_sale, Some(7.0));
+        assert_eq!(counted_manual_stock(after_sale, -3.0), Some(10.0));
+        assert_eq!(counted_manual_stock(Some(2.0), 3.0), Some(0.0));
+        assert_eq!(counted_manual_stock(None, -3.0), None);
+    }
+
+    #[test]
+    fn test_ingredient_delta() {
+        let (coffee, milk, syrup) = (ObjectId::new(), ObjectId::new(), ObjectId::new());
+        let taken = BTreeMap::from([(coffee, 36.0), (milk, 200.0)]);
+
+        // Two cups become three, without milk but with syrup
+        let needed = BTreeMap::from([(coffee, 54.0), (syrup, 30.0)]);
+        let delta = ingredient_delta(&taken, &needed);
+        assert_eq!(delta, BTreeMap::from([(coffee, 18.0), (milk, -200.0), (syrup, 30.0)]));
+
+        // A removed line gives everything back; an unchanged one moves nothing
+        assert_eq!(ingredient_delta(&taken, &BTreeMap::new()), BTreeMap::from([(coffee, -36.0), (milk, -200.0)]));
+        assert!(ingredient_delta(&taken, &taken).is_empty());
+    }
 }
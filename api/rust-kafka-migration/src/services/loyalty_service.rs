@@ -1,11 +1,53 @@
-use mongodb::{Client, Collection, Database};
-use mongodb::bson::{doc, oid::ObjectId};
-use mongodb::options::{ClientOptions, FindOneOptions, FindOptions};
-use futures::stream::{StreamExt, TryStreamExt};
-use chrono::Utc;
+use mongodb::{ClientSession, Collection, Database};
+use mongodb::bson::{doc, oid::ObjectId, Document};
+use mongodb::options::{FindOneOptions, FindOptions};
+use futures::stream::TryStreamExt;
+use crate::common::Money;
 use crate::db::models::{LoyaltyProgram, LoyaltyLevel, CustomerLoyalty};
 use crate::error::Result;
 
+/// Base points and first-transaction bonus an order of `order_amount` earns
+fn points_for_order(program: &LoyaltyProgram, order_amount: Money, is_first_transaction: bool) -> (f64, f64) {
+    let base_points = (order_amount.as_f64() / program.points_per_rp).floor();
+    let bonus_points = if is_first_transaction { program.first_transaction_points } else { 0.0 };
+    (base_points, bonus_points)
+}
+
+/// Points an order would earn for a customer, without accruing them. A
+/// customer without a loyalty record yet earns the first-transaction bonus.
+fn quoted_points(program: &LoyaltyProgram, customer_loyalty: Option<&CustomerLoyalty>, order_amount: Money) -> f64 {
+    let is_first_transaction = customer_loyalty.is_none_or(|cl| cl.is_first_transaction);
+    let (base_points, bonus_points) = points_for_order(program, order_amount, is_first_transaction);
+    base_points + bonus_points
+}
+
+/// Discount `points_to_redeem` is worth, or `None` when the customer has too few points
+fn redemption_discount(program: &LoyaltyProgram, customer_loyalty: &CustomerLoyalty, points_to_redeem: f64) -> Option<Money> {
+    if points_to_redeem <= 0.0 || customer_loyalty.current_points < points_to_redeem {
+        return None;
+    }
+    Some(Money::from_f64(points_to_redeem * program.discount_value_per_point))
+}
+
+fn redeem_points(customer_loyalty: &mut CustomerLoyalty, points: f64) {
+    customer_loyalty.current_points -= points;
+    customer_loyalty.total_points_redeemed += points;
+}
+
+fn accrue_points(customer_loyalty: &mut CustomerLoyalty, points: f64) {
+    customer_loyalty.current_points += points;
+    customer_loyalty.total_points_earned += points;
+    customer_loyalty.transaction_count += 1;
+}
+
+/// Undo an order's points. Balances never go below zero when earned points
+/// were already spent.
+fn reverse_points(customer_loyalty: &mut CustomerLoyalty, points_earned: f64, points_redeemed: f64) {
+    customer_loyalty.current_points = (customer_loyalty.current_points - points_earned + points_redeemed).max(0.0);
+    customer_loyalty.total_points_earned = (customer_loyalty.total_points_earned - points_earned).max(0.0);
+    customer_loyalty.total_points_redeemed = (customer_loyalty.total_points_redeemed - points_redeemed).max(0.0);
+}
+
 #[derive(Clone)]
 pub struct LoyaltyService {
     db: Database,
@@ -24,11 +66,49 @@ impl LoyaltyService {
         }
     }
 
+    async fn find_program(
+        &self,
+        filter: Document,
+        session: Option<&mut ClientSession>,
+    ) -> Result<Option<LoyaltyProgram>> {
+        Ok(match session {
+            Some(s) => self.loyalty_program_collection.find_one_with_session(filter, None, s).await?,
+            None => self.loyalty_program_collection.find_one(filter, None).await?,
+        })
+    }
+
+    async fn find_customer_loyalty(
+        &self,
+        filter: Document,
+        session: Option<&mut ClientSession>,
+    ) -> Result<Option<CustomerLoyalty>> {
+        Ok(match session {
+            Some(s) => self.customer_loyalty_collection.find_one_with_session(filter, None, s).await?,
+            None => self.customer_loyalty_collection.find_one(filter, None).await?,
+        })
+    }
+
+    async fn update_customer_loyalty(
+        &self,
+        filter: Document,
+        update: Document,
+        session: Option<&mut ClientSession>,
+    ) -> Result<()> {
+        match session {
+            Some(s) => self.customer_loyalty_collection.update_one_with_session(filter, update, None, s).await?,
+            None => self.customer_loyalty_collection.update_one(filter, update, None).await?,
+        };
+        Ok(())
+    }
+
+    /// Accrue points for an order. Pass a session to make the writes part of
+    /// the order's transaction.
     pub async fn calculate_loyalty_points(
         &self,
-        order_amount: f64,
+        order_amount: Money,
         customer_id: ObjectId,
         outlet_id: ObjectId,
+        mut session: Option<&mut ClientSession>,
     ) -> Result<(f64, Option<serde_json::Value>)> {
         // Find active loyalty program
         let filter = doc! {
@@ -39,7 +119,7 @@ impl LoyaltyService {
             ]
         };
 
-        let loyalty_program = match self.loyalty_program_collection.find_one(filter, None).await? {
+        let loyalty_program = match self.find_program(filter, session.as_deref_mut()).await? {
             Some(program) => program,
             None => return Ok((0.0, None)),
         };
@@ -50,7 +130,7 @@ impl LoyaltyService {
             "loyaltyProgram": loyalty_program.id.unwrap()
         };
 
-        let mut customer_loyalty = match self.customer_loyalty_collection.find_one(customer_filter.clone(), None).await? {
+        let mut customer_loyalty = match self.find_customer_loyalty(customer_filter, session.as_deref_mut()).await? {
             Some(cl) => cl,
             None => {
                 // Create new customer loyalty record
@@ -68,7 +148,10 @@ impl LoyaltyService {
                     created_at: Some(mongodb::bson::DateTime::now()),
                     updated_at: Some(mongodb::bson::DateTime::now()),
                 };
-                let insert_result = self.customer_loyalty_collection.insert_one(new_cl.clone(), None).await?;
+                let insert_result = match session.as_deref_mut() {
+                    Some(s) => self.customer_loyalty_collection.insert_one_with_session(new_cl.clone(), None, s).await?,
+                    None => self.customer_loyalty_collection.insert_one(new_cl.clone(), None).await?,
+                };
                 let mut created_cl = new_cl;
                 created_cl.id = Some(insert_result.inserted_id.as_object_id().unwrap());
                 created_cl
@@ -76,23 +159,14 @@ impl LoyaltyService {
         };
 
         // Calculate base points
-        let base_points = (order_amount / loyalty_program.points_per_rp).floor();
-        
-        let mut bonus_points = 0.0;
-        let mut is_first_transaction = false;
-
-        if customer_loyalty.is_first_transaction {
-            bonus_points = loyalty_program.first_transaction_points;
-            is_first_transaction = true;
-            customer_loyalty.is_first_transaction = false;
-        }
+        let is_first_transaction = customer_loyalty.is_first_transaction;
+        let (base_points, bonus_points) = points_for_order(&loyalty_program, order_amount, is_first_transaction);
+        customer_loyalty.is_first_transaction = false;
 
         let total_points_earned = base_points + bonus_points;
 
         // Update customer loyalty struct (in memory)
-        customer_loyalty.current_points += total_points_earned;
-        customer_loyalty.total_points_earned += total_points_earned;
-        customer_loyalty.transaction_count += 1;
+        accrue_points(&mut customer_loyalty, total_points_earned);
         customer_loyalty.last_transaction_date = Some(mongodb::bson::DateTime::now());
         customer_loyalty.updated_at = Some(mongodb::bson::DateTime::now());
 
@@ -113,7 +187,7 @@ impl LoyaltyService {
             }
         };
         
-        self.customer_loyalty_collection.update_one(update_filter, update_doc, None).await?;
+        self.update_customer_loyalty(update_filter, update_doc, session).await?;
 
         let loyalty_details = serde_json::json!({
             "basePoints": base_points,
@@ -165,14 +239,17 @@ impl LoyaltyService {
         Ok(new_level)
     }
 
-    pub async fn redeem_loyalty_points(
+    /// Customer's loyalty record and the discount `points_to_redeem` is
+    /// worth, or `None` when the points cannot be redeemed
+    async fn find_redemption(
         &self,
         customer_id: ObjectId,
         points_to_redeem: f64,
         outlet_id: ObjectId,
-    ) -> Result<(f64, f64)> {
+        mut session: Option<&mut ClientSession>,
+    ) -> Result<Option<(CustomerLoyalty, Money)>> {
         if points_to_redeem <= 0.0 {
-            return Ok((0.0, 0.0));
+            return Ok(None);
         }
 
        // Find active loyalty program
@@ -184,9 +261,9 @@ impl LoyaltyService {
             ]
         };
 
-        let loyalty_program = match self.loyalty_program_collection.find_one(filter, None).await? {
+        let loyalty_program = match self.find_program(filter, session.as_deref_mut()).await? {
             Some(program) => program,
-            None => return Ok((0.0, 0.0)),
+            None => return Ok(None),
         };
 
         let customer_filter = doc! {
@@ -194,19 +271,75 @@ impl LoyaltyService {
             "loyaltyProgram": loyalty_program.id.unwrap()
         };
 
-        let mut customer_loyalty = match self.customer_loyalty_collection.find_one(customer_filter, None).await? {
+        let customer_loyalty = match self.find_customer_loyalty(customer_filter, session).await? {
             Some(cl) => cl,
-            None => return Ok((0.0, 0.0)),
+            None => return Ok(None),
         };
 
-        if customer_loyalty.current_points < points_to_redeem {
-             return Ok((0.0, 0.0));
-        }
+        Ok(redemption_discount(&loyalty_program, &customer_loyalty, points_to_redeem)
+            .map(|discount_amount| (customer_loyalty, discount_amount)))
+    }
+
+    /// Discount and points a redemption would give, without redeeming
+    pub async fn quote_redemption(
+        &self,
+        customer_id: ObjectId,
+        points_to_redeem: f64,
+        outlet_id: ObjectId,
+    ) -> Result<(Money, f64)> {
+        Ok(match self.find_redemption(customer_id, points_to_redeem, outlet_id, None).await? {
+            Some((_, discount)) => (discount, points_to_redeem),
+            None => (Money::ZERO, 0.0),
+        })
+    }
+
+    /// Points an order of `order_amount` would earn, without accruing them
+    pub async fn quote_points_earned(
+        &self,
+        order_amount: Money,
+        customer_id: ObjectId,
+        outlet_id: ObjectId,
+    ) -> Result<f64> {
+        let filter = doc! {
+            "isActive": true,
+            "$or": [
+                { "outlet": outlet_id },
+                { "outlet": { "$exists": false } }
+            ]
+        };
+
+        let loyalty_program = match self.find_program(filter, None).await? {
+            Some(program) => program,
+            None => return Ok(0.0),
+        };
+
+        let customer_filter = doc! {
+            "customer": customer_id,
+            "loyaltyProgram": loyalty_program.id.unwrap()
+        };
+        let customer_loyalty = self.find_customer_loyalty(customer_filter, None).await?;
 
-        let discount_amount = points_to_redeem * loyalty_program.discount_value_per_point;
+        Ok(quoted_points(&loyalty_program, customer_loyalty.as_ref(), order_amount))
+    }
+
+    /// Redeem points for a discount. Pass a session to make the writes part
+    /// of the order's transaction.
+    pub async fn redeem_loyalty_points(
+        &self,
+        customer_id: ObjectId,
+        points_to_redeem: f64,
+        outlet_id: ObjectId,
+        mut session: Option<&mut ClientSession>,
+    ) -> Result<(Money, f64)> {
+        let (mut customer_loyalty, discount_amount) = match self
+            .find_redemption(customer_id, points_to_redeem, outlet_id, session.as_deref_mut())
+            .await?
+        {
+            Some(redemption) => redemption,
+            None => return Ok((Money::ZERO, 0.0)),
+        };
 
-        customer_loyalty.current_points -= points_to_redeem;
-        customer_loyalty.total_points_redeemed += points_to_redeem;
+        redeem_points(&mut customer_loyalty, points_to_redeem);
         customer_loyalty.updated_at = Some(mongodb::bson::DateTime::now());
 
         let update_filter = doc! { "_id": customer_loyalty.id.unwrap() };
@@ -218,8 +351,154 @@ impl LoyaltyService {
             }
         };
 
-        self.customer_loyalty_collection.update_one(update_filter, update_doc, None).await?;
+        self.update_customer_loyalty(update_filter, update_doc, session).await?;
 
         Ok((discount_amount, points_to_redeem))
     }
+
+    /// Undo the points an order earned and give back the points it redeemed.
+    ///
+    /// Current points never go below zero when earned points were already spent.
+    pub async fn reverse_order_points(
+        &self,
+        customer_id: ObjectId,
+        outlet_id: ObjectId,
+        points_earned: f64,
+        points_redeemed: f64,
+        session: &mut ClientSession,
+    ) -> Result<()> {
+        if points_earned <= 0.0 && points_redeemed <= 0.0 {
+            return Ok(());
+        }
+
+        let filter = doc! {
+            "isActive": true,
+            "$or": [
+                { "outlet": outlet_id },
+                { "outlet": { "$exists": false } }
+            ]
+        };
+
+        let loyalty_program = match self
+            .loyalty_program_collection
+            .find_one_with_session(filter, None, session)
+            .await?
+        {
+            Some(program) => program,
+            None => return Ok(()),
+        };
+
+        let customer_filter = doc! {
+            "customer": customer_id,
+            "loyaltyProgram": loyalty_program.id.unwrap()
+        };
+
+        let mut customer_loyalty = match self
+            .customer_loyalty_collection
+            .find_one_with_session(customer_filter, None, session)
+            .await?
+        {
+            Some(cl) => cl,
+            None => return Ok(()),
+        };
+
+        reverse_points(&mut customer_loyalty, points_earned, points_redeemed);
+
+        let update_doc = doc! {
+            "$set": {
+                "currentPoints": customer_loyalty.current_points,
+                "totalPointsEarned": customer_loyalty.total_points_earned,
+                "totalPointsRedeemed": customer_loyalty.total_points_redeemed,
+                "updatedAt": mongodb::bson::DateTime::now()
+            }
+        };
+
+        self.customer_loyalty_collection
+            .update_one_with_session(doc! { "_id": customer_loyalty.id.unwrap() }, update_doc, None, session)
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn program() -> LoyaltyProgram {
+        serde_json::from_value(serde_json::json!({ "name": "Member" })).unwrap()
+    }
+
+    fn member(current_points: f64) -> CustomerLoyalty {
+        CustomerLoyalty {
+            id: Some(ObjectId::new()),
+            customer: ObjectId::new(),
+            loyalty_program: ObjectId::new(),
+            current_points,
+            total_points_earned: current_points,
+            total_points_redeemed: 0.0,
+            current_level: None,
+            is_first_transaction: false,
+            last_transaction_date: None,
+            transaction_count: 3,
+            created_at: None,
+            updated_at: None,
+        }
+    }
+
+    #[test]
+    fn test_order_points_roll_back() {
+        let program = program();
+        let mut loyalty = member(500.0);
+
+        // The order redeems 200 points and earns points on what is left to pay
+        let discount = redemption_discount(&program, &loyalty, 200.0).unwrap();
+        assert_eq!(discount, Money::from_rupiah(10_000));
+        redeem_points(&mut loyalty, 200.0);
+        let (base, bonus) = points_for_order(&program, Money::from_rupiah(45_000), loyalty.is_first_transaction);
+        assert_eq!((base, bonus), (450.0, 0.0));
+        accrue_points(&mut loyalty, base + bonus);
+        assert_eq!(loyalty.current_points, 750.0);
+
+        // A cancel gives the redeemed points back and takes the earned ones away
+        reverse_points(&mut loyalty, base + bonus, 200.0);
+        assert_eq!(loyalty.current_points, 500.0);
+        assert_eq!(loyalty.total_points_earned, 500.0);
+        assert_eq!(loyalty.total_points_redeemed, 0.0);
+
+        // Earned points already spent elsewhere cannot push the balance below zero
+        let mut spent = member(100.0);
+        reverse_points(&mut spent, 450.0, 0.0);
+        assert_eq!(spent.current_points, 0.0);
+        assert_eq!(spent.total_points_earned, 0.0);
+
+        assert!(redemption_discount(&program, &member(100.0), 200.0).is_none());
+        assert!(redemption_discount(&program, &member(100.0), 0.0).is_none());
+    }
+
+    #[test]
+    fn test_quote_matches_order_without_touching_points() {
+        let program = program();
+        let amount = Money::from_rupiah(45_000);
+        let loyalty = member(500.0);
+        let before = loyalty.clone();
+
+        // A quote reads the member record only
+        let quoted_discount = redemption_discount(&program, &loyalty, 200.0).unwrap();
+        let quoted_earned = quoted_points(&program, Some(&loyalty), amount);
+        assert_eq!(loyalty.current_points, before.current_points);
+        assert_eq!(loyalty.transaction_count, before.transaction_count);
+
+        // ...and gives what placing the order would redeem and accrue
+        let mut placed = loyalty.clone();
+        redeem_points(&mut placed, 200.0);
+        let (base, bonus) = points_for_order(&program, amount, placed.is_first_transaction);
+        accrue_points(&mut placed, base + bonus);
+        assert_eq!(quoted_discount, Money::from_f64(200.0 * program.discount_value_per_point));
+        assert_eq!(quoted_earned, base + bonus);
+        assert_eq!(placed.current_points, before.current_points - 200.0 + quoted_earned);
+
+        // A customer's first order includes the first-transaction bonus
+        assert_eq!(quoted_points(&program, None, amount), 450.0 + program.first_transaction_points);
+    }
 }
@@ -1,11 +1,9 @@
-use std::sync::Arc;
 use bson::{doc, oid::ObjectId};
-use chrono::Utc;
 
 use crate::db::repositories::MarketListRepository;
 use crate::db::models::{
-    Request, RequestItem, RequestStatus, FulfillmentStatus, RequestType, RequestItemStatus,
-    MarketList, MarketListItem, MarketListPurpose, ProductMovement, ProductMovementType
+    Request, RequestStatus, FulfillmentStatus, RequestItemStatus,
+    MarketList, ProductMovement, ProductMovementType
 };
 use crate::error::{AppResult, AppError};
 use crate::services::InventoryService;
@@ -1,6 +1,7 @@
 use std::sync::Arc;
 use bson::oid::ObjectId;
 
+use crate::common::Money;
 use crate::db::repositories::{MenuRepository, InventoryRepository};
 use crate::db::models::{MenuItem, Category};
 use crate::kafka::{KafkaProducer, events::OrderEvent};
@@ -48,7 +49,7 @@ impl MenuService {
                 order_id: id.to_hex(),
                 user_id: "system".to_string(),
                 order_type: "menu_item_created".to_string(),
-                total: item.price,
+                total: item.price.as_f64(),
                 timestamp: chrono::Utc::now(),
             }
         ).await;
@@ -96,7 +97,7 @@ impl MenuService {
                         total_cost += price * ing.quantity;
                     }
                 }
-                item.cost_price = total_cost;
+                item.cost_price = Money::from_f64(total_cost);
             }
         }
         Ok(())
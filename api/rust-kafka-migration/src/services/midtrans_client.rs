@@ -0,0 +1,681 @@
+use chrono::{DateTime, Duration, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Asia::Jakarta;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha512};
+use tracing::{info, warn};
+
+use crate::common::Money;
+use crate::config::MidtransConfig;
+use crate::db::models::order::{PaymentAction, VaNumber};
+use crate::error::{AppError, AppResult};
+use crate::services::payment_gateway::{
+    secrets_match, EWallet, GatewayCharge, GatewayChargeRequest, GatewayRefund, GatewayRefundRequest,
+    PaymentChannel, PaymentGateway, VaBank,
+};
+
+const SANDBOX_BASE_URL: &str = "https://api.sandbox.midtrans.com";
+const PRODUCTION_BASE_URL: &str = "https://api.midtrans.com";
+const SANDBOX_SNAP_URL: &str = "https://app.sandbox.midtrans.com";
+const PRODUCTION_SNAP_URL: &str = "https://app.midtrans.com";
+
+/// `method` recorded for payments made on a Snap page
+pub const SNAP_METHOD: &str = "snap";
+
+/// Midtrans timestamps are "YYYY-MM-DD HH:MM:SS" in WIB
+const MIDTRANS_TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+pub fn format_midtrans_time(at: DateTime<Utc>) -> String {
+    at.with_timezone(&Jakarta).format(MIDTRANS_TIME_FORMAT).to_string()
+}
+
+pub fn parse_midtrans_time(value: &str) -> Option<DateTime<Utc>> {
+    let naive = NaiveDateTime::parse_from_str(value.trim(), MIDTRANS_TIME_FORMAT).ok()?;
+    Jakarta.from_local_datetime(&naive).single().map(|at| at.with_timezone(&Utc))
+}
+
+/// `signature_key` Midtrans sends with notifications:
+/// hex SHA-512 of order_id + status_code + gross_amount + server_key
+pub fn notification_signature(order_id: &str, status_code: &str, gross_amount: &str, server_key: &str) -> String {
+    let mut hasher = Sha512::new();
+    hasher.update(order_id.as_bytes());
+    hasher.update(status_code.as_bytes());
+    hasher.update(gross_amount.as_bytes());
+    hasher.update(server_key.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+/// Check a notification's `signature_key`. Nothing verifies without a server key.
+pub fn verify_notification_signature(
+    order_id: &str,
+    status_code: &str,
+    gross_amount: &str,
+    server_key: &str,
+    signature: &str,
+) -> bool {
+    if server_key.is_empty() {
+        return false;
+    }
+    let expected = notification_signature(order_id, status_code, gross_amount, server_key);
+    secrets_match(&expected, &signature.trim().to_ascii_lowercase())
+}
+
+/// Whether a transaction stored as `current` may move to `next`.
+///
+/// Notifications can arrive late or out of order; anything that would move a
+/// payment backwards (settlement -> pending, expire -> settlement) is refused.
+/// Local statuses such as `paid` count as settled.
+pub fn is_status_progression(current: &str, next: &str) -> bool {
+    let allowed: &[&str] = match current {
+        "pending" => &[
+            "authorize", "capture", "settlement", "deny", "cancel", "expire", "failure",
+        ],
+        "authorize" => &["capture", "settlement", "deny", "cancel", "expire", "failure"],
+        "capture" => &["settlement", "cancel", "refund", "partial_refund", "chargeback"],
+        "settlement" | "paid" => &["refund", "partial_refund", "chargeback", "partial_chargeback"],
+        "partial_refund" => &["refund", "partial_refund", "chargeback"],
+        "partial_chargeback" => &["chargeback"],
+        "deny" | "cancel" | "expire" | "failure" | "refund" | "chargeback" => &[],
+        // Unknown local status: only a successful payment may replace it
+        _ => &["capture", "settlement"],
+    };
+
+    allowed.contains(&next)
+}
+
+/// `payment_type` of a Core API charge for `channel`
+pub fn payment_type(channel: PaymentChannel) -> AppResult<&'static str> {
+    match channel {
+        PaymentChannel::Qris => Ok("qris"),
+        PaymentChannel::EWallet(EWallet::Gopay) => Ok("gopay"),
+        PaymentChannel::EWallet(EWallet::ShopeePay) => Ok("shopeepay"),
+        PaymentChannel::EWallet(wallet) => Err(AppError::Validation(format!(
+            "{} is not available through Midtrans",
+            wallet.as_str()
+        ))),
+        PaymentChannel::BankTransfer(VaBank::Mandiri) => Ok("echannel"),
+        PaymentChannel::BankTransfer(_) => Ok("bank_transfer"),
+    }
+}
+
+/// Name of `channel` in Snap's `enabled_payments`
+pub fn snap_channel(channel: PaymentChannel) -> AppResult<&'static str> {
+    match channel {
+        PaymentChannel::Qris => Ok("other_qris"),
+        PaymentChannel::BankTransfer(VaBank::Mandiri) => Ok("echannel"),
+        PaymentChannel::BankTransfer(VaBank::Bca) => Ok("bca_va"),
+        PaymentChannel::BankTransfer(VaBank::Bni) => Ok("bni_va"),
+        PaymentChannel::BankTransfer(VaBank::Bri) => Ok("bri_va"),
+        PaymentChannel::BankTransfer(VaBank::Cimb) => Ok("cimb_va"),
+        PaymentChannel::BankTransfer(VaBank::Permata) => Ok("permata_va"),
+        other => payment_type(other),
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MidtransTransactionDetails {
+    pub order_id: String,
+    /// Whole rupiah; Midtrans rejects decimals for IDR
+    pub gross_amount: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct MidtransCustomer {
+    pub first_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub phone: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MidtransCustomExpiry {
+    pub expiry_duration: i64,
+    pub unit: String,
+}
+
+impl MidtransCustomExpiry {
+    pub fn minutes(minutes: i64) -> Self {
+        Self {
+            expiry_duration: minutes,
+            unit: "minute".to_string(),
+        }
+    }
+}
+
+/// Body of a Core API charge; only the section matching `payment_type` is set
+#[derive(Debug, Clone, Serialize)]
+pub struct MidtransChargeRequest {
+    pub payment_type: String,
+    pub transaction_details: MidtransTransactionDetails,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub customer_details: Option<MidtransCustomer>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bank_transfer: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub echannel: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub qris: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gopay: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shopeepay: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub custom_expiry: Option<MidtransCustomExpiry>,
+}
+
+impl MidtransChargeRequest {
+    pub fn new(channel: PaymentChannel, order_id: String, gross_amount: Money) -> AppResult<Self> {
+        let mut request = Self {
+            payment_type: payment_type(channel)?.to_string(),
+            transaction_details: MidtransTransactionDetails {
+                order_id,
+                gross_amount: gross_amount.rupiah(),
+            },
+            customer_details: None,
+            bank_transfer: None,
+            echannel: None,
+            qris: None,
+            gopay: None,
+            shopeepay: None,
+            custom_expiry: None,
+        };
+
+        match channel {
+            PaymentChannel::Qris => request.qris = Some(json!({ "acquirer": "gopay" })),
+            PaymentChannel::EWallet(EWallet::ShopeePay) => request.shopeepay = Some(json!({})),
+            PaymentChannel::EWallet(_) => request.gopay = Some(json!({})),
+            PaymentChannel::BankTransfer(VaBank::Mandiri) => {
+                request.echannel = Some(json!({
+                    "bill_info1": "Payment:",
+                    "bill_info2": "Order",
+                }))
+            }
+            PaymentChannel::BankTransfer(bank) => {
+                request.bank_transfer = Some(json!({ "bank": bank.as_str() }))
+            }
+        }
+
+        Ok(request)
+    }
+}
+
+/// Core API charge result. Which instruction fields are present depends on the channel.
+#[derive(Debug, Clone, Deserialize)]
+pub struct MidtransChargeResponse {
+    pub status_code: String,
+    #[serde(default)]
+    pub status_message: String,
+    #[serde(default)]
+    pub transaction_id: Option<String>,
+    #[serde(default)]
+    pub order_id: Option<String>,
+    #[serde(default)]
+    pub payment_type: Option<String>,
+    #[serde(default)]
+    pub transaction_status: Option<String>,
+    #[serde(default)]
+    pub fraud_status: Option<String>,
+    #[serde(default)]
+    pub transaction_time: Option<String>,
+    #[serde(default)]
+    pub expiry_time: Option<String>,
+    #[serde(default)]
+    pub currency: Option<String>,
+    #[serde(default)]
+    pub merchant_id: Option<String>,
+    #[serde(default)]
+    pub va_numbers: Vec<VaNumber>,
+    #[serde(default)]
+    pub permata_va_number: Option<String>,
+    #[serde(default)]
+    pub bill_key: Option<String>,
+    #[serde(default)]
+    pub biller_code: Option<String>,
+    #[serde(default)]
+    pub actions: Vec<PaymentAction>,
+    #[serde(default)]
+    pub qr_string: Option<String>,
+    /// The body exactly as Midtrans returned it
+    #[serde(skip)]
+    pub raw: Value,
+}
+
+/// Body of a Snap transaction
+#[derive(Debug, Clone, Serialize)]
+pub struct SnapTransactionRequest {
+    pub transaction_details: MidtransTransactionDetails,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub customer_details: Option<MidtransCustomer>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub enabled_payments: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expiry: Option<Value>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SnapTransaction {
+    pub token: String,
+    pub redirect_url: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MidtransRefundRequest {
+    pub refund_key: String,
+    pub amount: i64,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MidtransRefundResponse {
+    pub status_code: String,
+    #[serde(default)]
+    pub status_message: String,
+    #[serde(default)]
+    pub transaction_id: Option<String>,
+    #[serde(default)]
+    pub refund_key: Option<String>,
+    #[serde(default)]
+    pub refund_amount: Option<String>,
+}
+
+/// Thin client for the Midtrans Core and Snap APIs
+#[derive(Clone)]
+pub struct MidtransClient {
+    http: reqwest::Client,
+    base_url: String,
+    snap_base_url: String,
+    server_key: String,
+}
+
+impl MidtransClient {
+    pub fn new(config: &MidtransConfig) -> Self {
+        if !config.base_url.is_empty() {
+            return Self::with_base_url(config.server_key.clone(), config.base_url.clone());
+        }
+
+        let (base_url, snap_base_url) = if config.is_production {
+            (PRODUCTION_BASE_URL, PRODUCTION_SNAP_URL)
+        } else {
+            (SANDBOX_BASE_URL, SANDBOX_SNAP_URL)
+        };
+
+        Self {
+            http: reqwest::Client::new(),
+            base_url: base_url.to_string(),
+            snap_base_url: snap_base_url.to_string(),
+            server_key: config.server_key.clone(),
+        }
+    }
+
+    /// Client sending both Core and Snap requests to `base_url`
+    pub fn with_base_url(server_key: String, base_url: String) -> Self {
+        let base_url = base_url.trim_end_matches('/').to_string();
+        Self {
+            http: reqwest::Client::new(),
+            snap_base_url: base_url.clone(),
+            base_url,
+            server_key,
+        }
+    }
+
+    /// Charge through the Core API - POST /v2/charge
+    pub async fn core_charge(&self, request: &MidtransChargeRequest) -> AppResult<MidtransChargeResponse> {
+        let url = format!("{}/v2/charge", self.base_url);
+
+        let raw: Value = self
+            .http
+            .post(&url)
+            .basic_auth(&self.server_key, Some(""))
+            .json(request)
+            .send()
+            .await?
+            .json()
+            .await?;
+        let mut response: MidtransChargeResponse = serde_json::from_value(raw.clone())?;
+        response.raw = raw;
+
+        // Failures come back in the body; 201 is a pending charge
+        if !response.status_code.starts_with('2') {
+            warn!(
+                "Midtrans charge for {} rejected: {} {}",
+                request.transaction_details.order_id, response.status_code, response.status_message
+            );
+            return Err(AppError::ExternalService(format!(
+                "Midtrans charge failed: {} {}",
+                response.status_code, response.status_message
+            )));
+        }
+
+        info!(
+            "💳 Midtrans {} charge created for {}",
+            request.payment_type, request.transaction_details.order_id
+        );
+        Ok(response)
+    }
+
+    /// Create a Snap payment page - POST /snap/v1/transactions
+    pub async fn create_snap_transaction(&self, request: &SnapTransactionRequest) -> AppResult<SnapTransaction> {
+        let url = format!("{}/snap/v1/transactions", self.snap_base_url);
+
+        let response = self
+            .http
+            .post(&url)
+            .basic_auth(&self.server_key, Some(""))
+            .json(request)
+            .send()
+            .await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            warn!(
+                "Midtrans Snap transaction for {} rejected: {} {}",
+                request.transaction_details.order_id, status, body
+            );
+            return Err(AppError::ExternalService(format!("Midtrans Snap failed: {} {}", status, body)));
+        }
+
+        let transaction: SnapTransaction = response.json().await?;
+        info!("💳 Midtrans Snap transaction created for {}", request.transaction_details.order_id);
+        Ok(transaction)
+    }
+
+    /// Refund a settled transaction - POST /v2/{id}/refund
+    ///
+    /// `transaction` is the Midtrans order ID or transaction ID.
+    pub async fn refund_transaction(
+        &self,
+        transaction: &str,
+        request: &MidtransRefundRequest,
+    ) -> AppResult<MidtransRefundResponse> {
+        let url = format!("{}/v2/{}/refund", self.base_url, transaction);
+
+        let response: MidtransRefundResponse = self
+            .http
+            .post(&url)
+            .basic_auth(&self.server_key, Some(""))
+            .json(request)
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        // Midtrans reports failures in the body with an HTTP 200
+        if !response.status_code.starts_with('2') {
+            warn!(
+                "Midtrans refund for {} rejected: {} {}",
+                transaction, response.status_code, response.status_message
+            );
+            return Err(AppError::ExternalService(format!(
+                "Midtrans refund failed: {} {}",
+                response.status_code, response.status_message
+            )));
+        }
+
+        info!("💸 Midtrans refund {} accepted for {}", request.refund_key, transaction);
+        Ok(response)
+    }
+}
+
+impl PaymentGateway for MidtransClient {
+    /// Snap for hosted payments, a Core API charge otherwise
+    async fn charge(&self, request: &GatewayChargeRequest) -> AppResult<GatewayCharge> {
+        let now = Utc::now();
+        let customer = MidtransCustomer {
+            first_name: request.customer.name.clone(),
+            email: request.customer.email.clone(),
+            phone: request.customer.phone.clone(),
+        };
+
+        if request.hosted {
+            let enabled_payments = match request.channel {
+                Some(channel) => vec![snap_channel(channel)?.to_string()],
+                None => Vec::new(),
+            };
+            let transaction = self
+                .create_snap_transaction(&SnapTransactionRequest {
+                    transaction_details: MidtransTransactionDetails {
+                        order_id: request.reference.clone(),
+                        gross_amount: request.amount.rupiah(),
+                    },
+                    customer_details: Some(customer),
+                    enabled_payments,
+                    expiry: Some(json!({
+                        "start_time": format!("{} +0700", format_midtrans_time(now)),
+                        "duration": request.expiry_minutes,
+                        "unit": "minutes",
+                    })),
+                })
+                .await?;
+
+            return Ok(GatewayCharge {
+                method: SNAP_METHOD.to_string(),
+                status: "pending".to_string(),
+                redirect_url: Some(transaction.redirect_url.clone()),
+                transaction_time: Some(format_midtrans_time(now)),
+                expiry_time: Some(format_midtrans_time(now + Duration::minutes(request.expiry_minutes))),
+                raw: json!({
+                    "token": transaction.token,
+                    "redirect_url": transaction.redirect_url,
+                }),
+                ..GatewayCharge::default()
+            });
+        }
+
+        let channel = request
+            .channel
+            .ok_or_else(|| AppError::Validation("method is required".to_string()))?;
+        let mut charge = MidtransChargeRequest::new(channel, request.reference.clone(), request.amount)?;
+        charge.customer_details = Some(customer);
+        charge.custom_expiry = Some(MidtransCustomExpiry::minutes(request.expiry_minutes));
+        let response = self.core_charge(&charge).await?;
+
+        Ok(GatewayCharge {
+            method: channel.method_name().to_string(),
+            transaction_id: response.transaction_id,
+            status: response.transaction_status.unwrap_or_else(|| "pending".to_string()),
+            fraud_status: response.fraud_status,
+            redirect_url: None,
+            transaction_time: response.transaction_time,
+            expiry_time: response.expiry_time,
+            va_numbers: response.va_numbers,
+            permata_va_number: response.permata_va_number,
+            bill_key: response.bill_key,
+            biller_code: response.biller_code,
+            actions: response.actions,
+            merchant_id: response.merchant_id,
+            raw: response.raw,
+        })
+    }
+
+    async fn refund(&self, request: &GatewayRefundRequest) -> AppResult<GatewayRefund> {
+        let response = self
+            .refund_transaction(
+                &request.transaction_id,
+                &MidtransRefundRequest {
+                    refund_key: request.reference.clone(),
+                    amount: request.amount.rupiah(),
+                    reason: request.reason.clone(),
+                },
+            )
+            .await?;
+        Ok(GatewayRefund {
+            refund_key: response.refund_key.unwrap_or_else(|| request.reference.clone()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_partial_json, header_exists, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn refund_request() -> MidtransRefundRequest {
+        MidtransRefundRequest {
+            refund_key: "REF-1".to_string(),
+            amount: 25_000,
+            reason: "Wrong item".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_refund_accepted() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v2/ORD-1/refund"))
+            .and(header_exists("authorization"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "status_code": "200",
+                "status_message": "Success, refund request is approved",
+                "transaction_id": "tx-1",
+                "refund_key": "REF-1",
+                "refund_amount": "25000.00"
+            })))
+            .mount(&server)
+            .await;
+
+        let client = MidtransClient::with_base_url("server-key".to_string(), server.uri());
+        let response = client.refund_transaction("ORD-1", &refund_request()).await.unwrap();
+
+        assert_eq!(response.refund_key.as_deref(), Some("REF-1"));
+        assert_eq!(response.transaction_id.as_deref(), Some("tx-1"));
+    }
+
+    #[tokio::test]
+    async fn test_refund_rejected_in_body() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v2/ORD-1/refund"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "status_code": "412",
+                "status_message": "Merchant cannot modify the status of the transaction"
+            })))
+            .mount(&server)
+            .await;
+
+        let client = MidtransClient::with_base_url("server-key".to_string(), server.uri());
+        let result = client.refund_transaction("ORD-1", &refund_request()).await;
+
+        assert!(matches!(result, Err(AppError::ExternalService(_))));
+    }
+
+    #[test]
+    fn test_midtrans_time_is_wib() {
+        let at = parse_midtrans_time("2024-03-10 00:30:00").unwrap();
+
+        assert_eq!(at, Utc.with_ymd_and_hms(2024, 3, 9, 17, 30, 0).unwrap());
+        assert_eq!(format_midtrans_time(at), "2024-03-10 00:30:00");
+        assert!(parse_midtrans_time("not a time").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_va_charge() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v2/charge"))
+            .and(body_partial_json(json!({
+                "payment_type": "bank_transfer",
+                "transaction_details": { "order_id": "PAY-1", "gross_amount": 50000 },
+                "bank_transfer": { "bank": "bca" }
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "status_code": "201",
+                "status_message": "Success, Bank Transfer transaction is created",
+                "transaction_id": "tx-9",
+                "order_id": "PAY-1",
+                "gross_amount": "50000.00",
+                "payment_type": "bank_transfer",
+                "transaction_status": "pending",
+                "fraud_status": "accept",
+                "transaction_time": "2024-03-10 10:00:00",
+                "expiry_time": "2024-03-10 10:30:00",
+                "va_numbers": [{ "bank": "bca", "va_number": "12345678901" }]
+            })))
+            .mount(&server)
+            .await;
+
+        let client = MidtransClient::with_base_url("server-key".to_string(), server.uri());
+        let channel = PaymentChannel::parse("bank_transfer", Some("BCA")).unwrap();
+        let request = MidtransChargeRequest::new(channel, "PAY-1".to_string(), Money::from_rupiah(50_000)).unwrap();
+        let response = client.core_charge(&request).await.unwrap();
+
+        assert_eq!(response.va_numbers[0].va_number, "12345678901");
+        assert_eq!(response.expiry_time.as_deref(), Some("2024-03-10 10:30:00"));
+        assert_eq!(response.raw["gross_amount"], "50000.00");
+    }
+
+    #[tokio::test]
+    async fn test_charge_rejected_in_body() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v2/charge"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "status_code": "406",
+                "status_message": "The request could not be completed due to a conflict"
+            })))
+            .mount(&server)
+            .await;
+
+        let client = MidtransClient::with_base_url("server-key".to_string(), server.uri());
+        let request =
+            MidtransChargeRequest::new(PaymentChannel::Qris, "PAY-1".to_string(), Money::from_rupiah(50_000)).unwrap();
+
+        assert!(matches!(client.core_charge(&request).await, Err(AppError::ExternalService(_))));
+    }
+
+    #[tokio::test]
+    async fn test_snap_transaction() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/snap/v1/transactions"))
+            .and(body_partial_json(json!({ "enabled_payments": ["gopay"] })))
+            .respond_with(ResponseTemplate::new(201).set_body_json(json!({
+                "token": "snap-token",
+                "redirect_url": "https://app.sandbox.midtrans.com/snap/v2/vtweb/snap-token"
+            })))
+            .mount(&server)
+            .await;
+
+        let client = MidtransClient::with_base_url("server-key".to_string(), server.uri());
+        let request = SnapTransactionRequest {
+            transaction_details: MidtransTransactionDetails {
+                order_id: "PAY-2".to_string(),
+                gross_amount: 75_000,
+            },
+            customer_details: None,
+            enabled_payments: vec![snap_channel(PaymentChannel::EWallet(EWallet::Gopay)).unwrap().to_string()],
+            expiry: None,
+        };
+        let transaction = client.create_snap_transaction(&request).await.unwrap();
+
+        assert_eq!(transaction.token, "snap-token");
+    }
+
+    #[test]
+    fn test_notification_signature() {
+        let signature = notification_signature("PAY-1", "200", "50000.00", "server-key");
+
+        assert_eq!(signature.len(), 128);
+        assert!(verify_notification_signature("PAY-1", "200", "50000.00", "server-key", &signature));
+        assert!(verify_notification_signature(
+            "PAY-1", "200", "50000.00", "server-key", &signature.to_uppercase()
+        ));
+        assert!(!verify_notification_signature("PAY-1", "200", "1.00", "server-key", &signature));
+        assert!(!verify_notification_signature("PAY-1", "200", "50000.00", "other-key", &signature));
+        assert!(!verify_notification_signature("PAY-1", "200", "50000.00", "", &signature));
+    }
+
+    #[test]
+    fn test_status_progression() {
+        assert!(is_status_progression("pending", "settlement"));
+        assert!(is_status_progression("capture", "settlement"));
+        assert!(is_status_progression("settlement", "refund"));
+        assert!(!is_status_progression("settlement", "pending"));
+        assert!(!is_status_progression("settlement", "expire"));
+        assert!(!is_status_progression("expire", "settlement"));
+        assert!(!is_status_progression("paid", "pending"));
+        assert!(!is_status_progression("pending", "pending"));
+    }
+}
@@ -1,8 +1,15 @@
+pub mod delivery_service;
+pub mod discount_service;
 pub mod event_service;
+pub mod gosend_client;
 pub mod inventory_service;
 pub mod marketlist_service;
 pub mod menu_service;
+pub mod midtrans_client;
+pub mod order_service;
 pub mod outlet_service;
+pub mod payment_gateway;
+pub mod payment_service;
 
 pub mod loyalty_service;
 pub mod tax_service;
@@ -10,14 +17,34 @@ pub mod tax_service;
 pub mod hr;
 pub mod print_service;
 pub mod promo_service;
+pub mod refund_service;
+pub mod reservation_service;
+pub mod revision_service;
+pub mod shift_service;
+pub mod sweeper_service;
+pub mod table_service;
+pub mod xendit_client;
 
+pub use delivery_service::DeliveryService;
+pub use discount_service::DiscountService;
 pub use event_service::EventService;
+pub use gosend_client::GoSendClient;
 pub use hr::{AttendanceService, BpjsService, EmployeeService, FingerprintService, SalaryService};
 pub use inventory_service::InventoryService;
 pub use loyalty_service::LoyaltyService;
 pub use marketlist_service::MarketListService;
 pub use menu_service::MenuService;
+pub use midtrans_client::MidtransClient;
+pub use order_service::OrderService;
 pub use outlet_service::OutletService;
+pub use payment_service::PaymentService;
 pub use print_service::PrintService;
 pub use promo_service::PromoService;
+pub use refund_service::RefundService;
+pub use reservation_service::ReservationService;
+pub use revision_service::RevisionService;
+pub use shift_service::ShiftService;
+pub use sweeper_service::SweeperService;
+pub use table_service::TableService;
 pub use tax_service::TaxService;
+pub use xendit_client::XenditClient;
@@ -0,0 +1,905 @@
+use std::sync::Arc;
+
+use bson::oid::ObjectId;
+use chrono::Utc;
+use mongodb::ClientSession;
+use tracing::{info, warn};
+
+use crate::common::Money;
+use crate::db::models::order::SplitPayment;
+use crate::db::models::payment::Payment;
+use crate::db::models::{Order, OrderStatus, OrderStatusHistoryEntry};
+use crate::db::repositories::{OrderRepository, PaymentRepository};
+use crate::db::{with_transaction, DbConnection};
+use crate::error::{AppError, AppResult};
+use crate::kafka::{events::OrderEvent, KafkaProducer};
+use crate::services::inventory_service::SoldMenuItem;
+use crate::services::table_service::TableChange;
+use crate::services::{InventoryService, LoyaltyService, PrintService, PromoService, TableService};
+use crate::websocket::events::{CashierData, StatusUpdate};
+
+/// Who performed an action on an order
+#[derive(Debug, Clone)]
+pub struct OrderActor {
+    pub id: ObjectId,
+    pub name: String,
+}
+
+/// Side effects of a status change, handled once it is committed
+#[derive(Debug, Default)]
+struct TransitionEffects {
+    sold: Vec<SoldMenuItem>,
+    table: Option<TableChange>,
+}
+
+/// An order whose status changed in a transaction, to announce once it is committed
+#[derive(Debug)]
+pub struct TransitionedOrder {
+    order: Order,
+    reason: Option<String>,
+    effects: TransitionEffects,
+}
+
+/// Side effects of a cancel, handled once it is committed
+#[derive(Debug, Default)]
+struct CancelEffects {
+    returned: Vec<SoldMenuItem>,
+    table: Option<TableChange>,
+}
+
+/// An order canceled in a transaction, to announce once it is committed
+#[derive(Debug)]
+pub struct CanceledOrder {
+    order: Order,
+    reason: String,
+    effects: CancelEffects,
+}
+
+#[derive(Clone)]
+pub struct OrderService {
+    db: Arc<DbConnection>,
+    order_repo: OrderRepository,
+    payment_repo: PaymentRepository,
+    table_service: TableService,
+    inventory_service: InventoryService,
+    loyalty_service: LoyaltyService,
+    promo_service: PromoService,
+    kafka: Arc<KafkaProducer>,
+    print_service: PrintService,
+}
+
+impl OrderService {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        db: Arc<DbConnection>,
+        order_repo: OrderRepository,
+        payment_repo: PaymentRepository,
+        table_service: TableService,
+        inventory_service: InventoryService,
+        loyalty_service: LoyaltyService,
+        promo_service: PromoService,
+        kafka: Arc<KafkaProducer>,
+        print_service: PrintService,
+    ) -> Self {
+        Self {
+            db,
+            order_repo,
+            payment_repo,
+            table_service,
+            inventory_service,
+            loyalty_service,
+            promo_service,
+            kafka,
+            print_service,
+        }
+    }
+
+    /// Move an order to a new status following the allowed-transition table.
+    ///
+    /// Records the actor in the order's status history, publishes the matching
+    /// Kafka event and notifies the `order_{id}` WebSocket room.
+    pub async fn transition_status(
+        &self,
+        order: &Order,
+        next: OrderStatus,
+        actor: Option<&OrderActor>,
+        reason: Option<String>,
+    ) -> AppResult<Order> {
+        let (order_oid, entry) = transition_entry(order, next, actor, reason.clone())?;
+
+        let deducts_stock = next.is_confirmed()
+            && order.items.iter().any(|i| !i.stock_deducted && i.menu_item.is_some());
+        let releases_table =
+            next == OrderStatus::Completed && order.table_number.is_some() && !order.table_released;
+
+        let (updated, items, effects) = if deducts_stock || releases_table {
+            // Ingredients leave stock and the table is freed together with the status change
+            let draft = order.items.clone();
+            let handled_by = actor.map(|a| a.name.clone());
+            let entry_ref = &entry;
+            with_transaction(&self.db, |mut session| {
+                let mut items = draft.clone();
+                let handled_by = handled_by.clone();
+                async move {
+                    let result = self
+                        .transition_in_transaction(order, &order_oid, next, entry_ref, &mut items, handled_by, &mut session)
+                        .await;
+                    (session, result.map(|(updated, effects)| (updated, items, effects)))
+                }
+            })
+            .await?
+        } else {
+            let updated = self
+                .order_repo
+                .update_status(&order_oid, order.status, next, &entry, None)
+                .await?;
+            (updated, order.items.clone(), TransitionEffects::default())
+        };
+
+        if !updated {
+            return Err(AppError::Conflict(format!(
+                "Order {} was modified concurrently, please retry",
+                order.order_id
+            )));
+        }
+
+        Ok(self
+            .finish_transition(transitioned_order(order, entry, items, reason, effects), actor)
+            .await)
+    }
+
+    /// Change an order's status as part of the caller's transaction. Nothing
+    /// is announced until [`Self::finish_transition`] is called after the commit.
+    pub async fn transition_with_session(
+        &self,
+        order: &Order,
+        next: OrderStatus,
+        actor: Option<&OrderActor>,
+        reason: Option<String>,
+        session: &mut ClientSession,
+    ) -> AppResult<TransitionedOrder> {
+        let (order_oid, entry) = transition_entry(order, next, actor, reason.clone())?;
+        let mut items = order.items.clone();
+        let (updated, effects) = self
+            .transition_in_transaction(order, &order_oid, next, &entry, &mut items, actor.map(|a| a.name.clone()), session)
+            .await?;
+        if !updated {
+            return Err(AppError::Conflict(format!(
+                "Order {} was modified concurrently, please retry",
+                order.order_id
+            )));
+        }
+        Ok(transitioned_order(order, entry, items, reason, effects))
+    }
+
+    /// Announce a committed status change: the refreshed menu stock, the
+    /// freed table, the status event and the order's watchers
+    pub async fn finish_transition(&self, transitioned: TransitionedOrder, actor: Option<&OrderActor>) -> Order {
+        let TransitionedOrder { order, reason, effects } = transitioned;
+        if let Some(entry) = order.status_history.last() {
+            info!(
+                "🔁 Order {} status changed: {} → {}",
+                order.order_id, entry.from_status, entry.to_status
+            );
+        }
+
+        self.inventory_service.refresh_menu_stocks(&effects.sold).await;
+        self.table_service.broadcast(effects.table.as_ref()).await;
+
+        self.publish_status_event(&order, reason).await;
+        self.broadcast_status(&order, actor);
+
+        order
+    }
+
+    /// Change the status, deduct the ingredients of undeducted lines and free
+    /// the table of a completed order. Nothing else happens when the status
+    /// was changed concurrently.
+    #[allow(clippy::too_many_arguments)]
+    async fn transition_in_transaction(
+        &self,
+        order: &Order,
+        order_oid: &ObjectId,
+        next: OrderStatus,
+        entry: &OrderStatusHistoryEntry,
+        items: &mut [crate::db::models::OrderItem],
+        handled_by: Option<String>,
+        session: &mut ClientSession,
+    ) -> AppResult<(bool, TransitionEffects)> {
+        let updated = self
+            .order_repo
+            .update_status(order_oid, order.status, next, entry, Some(&mut *session))
+            .await?;
+        if !updated {
+            return Ok((false, TransitionEffects::default()));
+        }
+
+        let mut effects = TransitionEffects::default();
+
+        if next.is_confirmed() {
+            let deduction = self
+                .inventory_service
+                .deduct_order_stock(order_oid, &order.order_id, items, handled_by.clone(), session)
+                .await?;
+            self.order_repo
+                .mark_items_stock_deducted_with_session(order_oid, &deduction.lines, session)
+                .await?;
+            effects.sold = deduction.sold;
+        }
+
+        if next == OrderStatus::Completed {
+            effects.table = self
+                .table_service
+                .release_for_order(
+                    order,
+                    handled_by.as_deref().unwrap_or("system"),
+                    Some(format!("Order {} completed", order.order_id)),
+                    session,
+                )
+                .await?;
+        }
+
+        Ok((true, effects))
+    }
+
+    /// Cancel an order and roll back everything it consumed.
+    ///
+    /// Stock, loyalty points, voucher usage and the table are restored in a
+    /// single MongoDB transaction together with the status change. Canceling
+    /// an already canceled order returns it unchanged, so nothing is restored
+    /// twice.
+    pub async fn cancel_order(
+        &self,
+        order: &Order,
+        actor: Option<&OrderActor>,
+        reason: String,
+        canceled_by_system: bool,
+    ) -> AppResult<Order> {
+        if order.status == OrderStatus::Canceled {
+            info!("Order {} is already canceled", order.order_id);
+            return Ok(order.clone());
+        }
+
+        let (order_oid, entry) = cancel_entry(order, actor, &reason)?;
+
+        let (order_oid_ref, entry_ref, reason_ref) = (&order_oid, &entry, &reason);
+        let (canceled, effects) = with_transaction(&self.db, |mut session| async move {
+            let result = self
+                .cancel_in_transaction(order, order_oid_ref, entry_ref, reason_ref, canceled_by_system, actor, &mut session)
+                .await;
+            (session, result)
+        })
+        .await?;
+
+        if !canceled {
+            // Someone else changed the order first; a concurrent cancel is not an error
+            return match self.order_repo.find_by_id(&order_oid).await? {
+                Some(current) if current.status == OrderStatus::Canceled => Ok(current),
+                _ => Err(AppError::Conflict(format!(
+                    "Order {} was modified concurrently, please retry",
+                    order.order_id
+                ))),
+            };
+        }
+
+        Ok(self
+            .finish_cancel(canceled_order(order, entry, reason, canceled_by_system, effects), actor)
+            .await)
+    }
+
+    /// Cancel an order as part of the caller's transaction. Nothing is
+    /// announced until [`Self::finish_cancel`] is called after the commit.
+    pub async fn cancel_with_session(
+        &self,
+        order: &Order,
+        actor: Option<&OrderActor>,
+        reason: String,
+        canceled_by_system: bool,
+        session: &mut ClientSession,
+    ) -> AppResult<CanceledOrder> {
+        let (order_oid, entry) = cancel_entry(order, actor, &reason)?;
+        let (canceled, effects) = self
+            .cancel_in_transaction(order, &order_oid, &entry, &reason, canceled_by_system, actor, session)
+            .await?;
+        if !canceled {
+            return Err(AppError::Conflict(format!(
+                "Order {} was modified concurrently, please retry",
+                order.order_id
+            )));
+        }
+        Ok(canceled_order(order, entry, reason, canceled_by_system, effects))
+    }
+
+    /// Announce a committed cancel: the returned menu stock, the freed table,
+    /// the status event and the order's watchers
+    pub async fn finish_cancel(&self, canceled: CanceledOrder, actor: Option<&OrderActor>) -> Order {
+        let CanceledOrder { order, reason, effects } = canceled;
+        info!("🚫 Order {} canceled: {}", order.order_id, reason);
+
+        self.inventory_service.refresh_menu_stocks(&effects.returned).await;
+        self.table_service.broadcast(effects.table.as_ref()).await;
+        self.publish_status_event(&order, Some(reason)).await;
+        self.broadcast_status(&order, actor);
+
+        order
+    }
+
+    /// Flip the order to canceled first so a concurrent cancel conflicts
+    /// before anything is restored, then undo its side effects.
+    #[allow(clippy::too_many_arguments)]
+    async fn cancel_in_transaction(
+        &self,
+        order: &Order,
+        order_oid: &ObjectId,
+        entry: &OrderStatusHistoryEntry,
+        reason: &str,
+        canceled_by_system: bool,
+        actor: Option<&OrderActor>,
+        session: &mut ClientSession,
+    ) -> AppResult<(bool, CancelEffects)> {
+        let marked = self
+            .order_repo
+            .mark_canceled_with_session(order_oid, order.status, entry, reason, canceled_by_system, session)
+            .await?;
+        if !marked {
+            return Ok((false, CancelEffects::default()));
+        }
+
+        let handled_by = actor.map(|a| a.name.clone());
+        let mut effects = CancelEffects::default();
+
+        if !order.stock_rolled_back {
+            effects.returned = self.inventory_service.returned_menu_items(&order.items).await?;
+            let restored = self
+                .inventory_service
+                .restore_order_stock(order_oid, &order.order_id, handled_by.clone(), session)
+                .await?;
+            info!("Restored {} stock entries for order {}", restored, order.order_id);
+        }
+
+        if let (Some(customer_id), Some(outlet_id), false) =
+            (order.user_id, order.outlet, order.loyalty_rolled_back)
+        {
+            self.loyalty_service
+                .reverse_order_points(
+                    customer_id,
+                    outlet_id,
+                    order.loyalty_points_earned,
+                    order.loyalty_points_redeemed,
+                    session,
+                )
+                .await?;
+        }
+
+        if let Some(voucher_id) = order.applied_voucher {
+            self.promo_service
+                .release_voucher(voucher_id, *order_oid, Some(session))
+                .await?;
+        }
+
+        effects.table = self
+            .table_service
+            .release_for_order(
+                order,
+                handled_by.as_deref().unwrap_or("system"),
+                Some(format!("Order {} canceled", order.order_id)),
+                session,
+            )
+            .await?;
+
+        Ok((true, effects))
+    }
+
+    /// Record one tender against an order and persist the new balance.
+    ///
+    /// Stores the tender on the order and as a `payments` record, in one
+    /// transaction. When the balance reaches zero the order is marked paid and
+    /// a pending order is completed in the same transaction.
+    ///
+    /// A paid order that a revision raised owes only the revision's adjustment
+    /// charge, so the tender goes towards that charge instead.
+    pub async fn record_tender(
+        &self,
+        order: &Order,
+        tender: SplitPayment,
+        actor: Option<&OrderActor>,
+    ) -> AppResult<Order> {
+        let mut order = order.clone();
+        let now = mongodb::bson::DateTime::now();
+        let adjustment = self.payment_repo.find_pending_adjustment(&order.order_id).await?;
+        let tender = match &adjustment {
+            Some(charge) => apply_adjustment_tender(&mut order, tender, charge.amount, actor.map(|a| a.id), now)?,
+            None => apply_tender(&mut order, tender, actor.map(|a| a.id), now)?,
+        };
+        order.updated_at_wib = now;
+
+        // A pending order the tender pays off is completed in the same transaction
+        let completes = order.split_payment_status == "completed" && order.status == OrderStatus::Pending;
+        let (payment_repo, order_repo) = (&self.payment_repo, &self.order_repo);
+        let (order_ref, tender_ref, adjustment) = (&order, &tender, adjustment.as_ref());
+        let completed = with_transaction(&self.db, |mut session| async move {
+            let result = async {
+                match adjustment {
+                    Some(charge) => {
+                        let charge_oid = charge
+                            .id
+                            .ok_or_else(|| AppError::Internal("Payment has no ID".to_string()))?;
+                        if tender_ref.amount < charge.amount {
+                            payment_repo
+                                .create_with_session(adjustment_part_payment(charge, tender_ref, now), &mut session)
+                                .await?;
+                            payment_repo
+                                .adjust_amount_with_session(&charge_oid, -tender_ref.amount, &mut session)
+                                .await?;
+                        } else {
+                            payment_repo
+                                .settle_adjustment_with_session(&charge_oid, &tender_ref.payment_method, now, &mut session)
+                                .await?;
+                        }
+                    }
+                    None => {
+                        payment_repo
+                            .create_with_session(tender_payment(order_ref, tender_ref, now), &mut session)
+                            .await?;
+                    }
+                }
+                order_repo.update_with_session(order_ref, &mut session).await?;
+                if !completes {
+                    return Ok(None);
+                }
+                self.transition_with_session(order_ref, OrderStatus::Completed, actor, None, &mut session)
+                    .await
+                    .map(Some)
+            }
+            .await;
+            (session, result)
+        })
+        .await?;
+
+        info!(
+            "💳 Recorded {} {} for order {} (remaining {}, {})",
+            tender.payment_method,
+            tender.amount,
+            order.order_id,
+            remaining_balance(&order),
+            order.split_payment_status
+        );
+
+        match completed {
+            Some(completed) => Ok(self.finish_transition(completed, actor).await),
+            None => Ok(order),
+        }
+    }
+
+    async fn publish_status_event(&self, order: &Order, reason: Option<String>) {
+        let timestamp = Utc::now();
+        let event = match order.status {
+            OrderStatus::Completed => OrderEvent::Completed {
+                order_id: order.order_id.clone(),
+                timestamp,
+            },
+            OrderStatus::Canceled => OrderEvent::Cancelled {
+                order_id: order.order_id.clone(),
+                reason: reason.unwrap_or_default(),
+                timestamp,
+            },
+            status => OrderEvent::Updated {
+                order_id: order.order_id.clone(),
+                status: status.to_string(),
+                timestamp,
+            },
+        };
+
+        if let Err(e) = self.kafka.publish_order_event(&order.order_id, &event).await {
+            warn!("Failed to publish order event for {}: {}", order.order_id, e);
+        }
+    }
+
+    fn broadcast_status(&self, order: &Order, actor: Option<&OrderActor>) {
+        let update = StatusUpdate {
+            order_id: order.order_id.clone(),
+            order_status: order.status.to_string(),
+            payment_status: order
+                .payment_status
+                .clone()
+                .unwrap_or_else(|| "Pending".to_string()),
+            message: format!("Order status changed to {}", order.status),
+            timestamp: Utc::now(),
+            cashier: actor.map(|a| CashierData {
+                id: a.id.to_hex(),
+                name: a.name.clone(),
+            }),
+        };
+
+        self.print_service.broadcast_order_status(&order.order_id, update);
+    }
+}
+
+/// The history entry of moving `order` to `next`, if the move is allowed
+fn transition_entry(
+    order: &Order,
+    next: OrderStatus,
+    actor: Option<&OrderActor>,
+    reason: Option<String>,
+) -> AppResult<(ObjectId, OrderStatusHistoryEntry)> {
+    let order_oid = order
+        .id
+        .ok_or_else(|| AppError::Internal("Order has no ID".to_string()))?;
+
+    if !order.status.can_transition_to(next) {
+        return Err(AppError::Conflict(format!(
+            "Cannot change order {} from {} to {}",
+            order.order_id, order.status, next
+        )));
+    }
+
+    let entry = OrderStatusHistoryEntry {
+        from_status: order.status,
+        to_status: next,
+        changed_by: actor.map(|a| a.id),
+        changed_by_name: actor.map(|a| a.name.clone()),
+        reason,
+        changed_at: mongodb::bson::DateTime::now(),
+    };
+    Ok((order_oid, entry))
+}
+
+/// `order` as it reads once the status change is stored
+fn transitioned_order(
+    order: &Order,
+    entry: OrderStatusHistoryEntry,
+    items: Vec<crate::db::models::OrderItem>,
+    reason: Option<String>,
+    effects: TransitionEffects,
+) -> TransitionedOrder {
+    let mut order = order.clone();
+    order.table_released |= entry.to_status == OrderStatus::Completed && order.table_number.is_some();
+    order.status = entry.to_status;
+    order.status_history.push(entry);
+    order.items = items;
+
+    TransitionedOrder { order, reason, effects }
+}
+
+/// The history entry of canceling `order`, if it can still be canceled
+fn cancel_entry(
+    order: &Order,
+    actor: Option<&OrderActor>,
+    reason: &str,
+) -> AppResult<(ObjectId, OrderStatusHistoryEntry)> {
+    let order_oid = order
+        .id
+        .ok_or_else(|| AppError::Internal("Order has no ID".to_string()))?;
+
+    if !order.status.can_transition_to(OrderStatus::Canceled) {
+        return Err(AppError::Conflict(format!(
+            "Cannot cancel order {} in status {}",
+            order.order_id, order.status
+        )));
+    }
+
+    let entry = OrderStatusHistoryEntry {
+        from_status: order.status,
+        to_status: OrderStatus::Canceled,
+        changed_by: actor.map(|a| a.id),
+        changed_by_name: actor.map(|a| a.name.clone()),
+        reason: Some(reason.to_string()),
+        changed_at: mongodb::bson::DateTime::now(),
+    };
+    Ok((order_oid, entry))
+}
+
+/// `order` as it reads once the cancel is stored
+fn canceled_order(
+    order: &Order,
+    entry: OrderStatusHistoryEntry,
+    reason: String,
+    canceled_by_system: bool,
+    effects: CancelEffects,
+) -> CanceledOrder {
+    let mut order = order.clone();
+    order.status = OrderStatus::Canceled;
+    order.status_history.push(entry);
+    order.cancellation_reason = Some(reason.clone());
+    order.canceled_by_system = canceled_by_system;
+    order.stock_rolled_back = true;
+    order.loyalty_rolled_back = true;
+    order.table_released = true;
+
+    CanceledOrder { order, reason, effects }
+}
+
+/// Payment record for a tender just applied with [`apply_tender`]
+pub fn tender_payment(order: &Order, tender: &SplitPayment, now: mongodb::bson::DateTime) -> Payment {
+    Payment {
+        order_id: order.order_id.clone(),
+        method: tender.payment_method.clone(),
+        status: "paid".to_string(),
+        payment_type: if order.is_split_payment {
+            "Partial".to_string()
+        } else {
+            "Full".to_string()
+        },
+        amount: tender.amount,
+        total_amount: Some(order.grand_total),
+        remaining_amount: remaining_balance(order),
+        paid_at: Some(now),
+        created_at: now,
+        updated_at: now,
+        ..Payment::default()
+    }
+}
+
+/// Paid record for a tender covering part of an adjustment charge; the charge
+/// keeps the rest
+pub fn adjustment_part_payment(charge: &Payment, tender: &SplitPayment, now: mongodb::bson::DateTime) -> Payment {
+    Payment {
+        order_id: charge.order_id.clone(),
+        method: tender.payment_method.clone(),
+        status: "paid".to_string(),
+        payment_type: charge.payment_type.clone(),
+        amount: tender.amount,
+        total_amount: Some(charge.amount),
+        remaining_amount: charge.amount - tender.amount,
+        related_payment_id: charge.id,
+        is_adjustment: true,
+        direction: charge.direction.clone(),
+        revision_id: charge.revision_id,
+        adjustment_id: charge.adjustment_id,
+        paid_at: Some(now),
+        created_at: now,
+        updated_at: now,
+        ..Payment::default()
+    }
+}
+
+/// Total of the completed tenders on an order
+pub fn amount_paid(order: &Order) -> Money {
+    order
+        .payments
+        .iter()
+        .filter(|p| p.status == "completed")
+        .map(|p| p.amount)
+        .sum()
+}
+
+/// Amount still owed on an order, never negative
+pub fn remaining_balance(order: &Order) -> Money {
+    (order.grand_total - amount_paid(order)).max(Money::ZERO)
+}
+
+/// Recompute `split_payment_status` and `payment_status` from the tenders
+pub fn refresh_payment_state(order: &mut Order) {
+    let paid = amount_paid(order);
+
+    order.split_payment_status = if !paid.is_positive() {
+        "not_started"
+    } else if paid < order.grand_total {
+        "partial"
+    } else if paid > order.grand_total {
+        "overpaid"
+    } else {
+        "completed"
+    }
+    .to_string();
+
+    order.payment_status = Some(
+        match order.split_payment_status.as_str() {
+            "not_started" => "Pending",
+            "partial" => "Partial",
+            _ => "Paid",
+        }
+        .to_string(),
+    );
+}
+
+/// Whether a tender was paid in cash
+pub fn is_cash(method: &str) -> bool {
+    method.eq_ignore_ascii_case("cash")
+}
+
+/// Apply a single tender to the order's balance.
+///
+/// Non-cash tenders may not exceed the remaining balance. Cash may be
+/// tendered above it; only the balance is applied and the rest is recorded
+/// as change. Returns the tender as stored on the order.
+pub fn apply_tender(
+    order: &mut Order,
+    tender: SplitPayment,
+    processed_by: Option<ObjectId>,
+    processed_at: mongodb::bson::DateTime,
+) -> AppResult<SplitPayment> {
+    let remaining = remaining_balance(order);
+    let tender = take_tender(order, tender, remaining, processed_by, processed_at)?;
+
+    order.is_split_payment =
+        order.payments.len() > 1 || tender.amount < order.grand_total;
+    refresh_payment_state(order);
+
+    Ok(tender)
+}
+
+/// Apply a tender towards the `outstanding` adjustment charge a revision added
+/// to a paid order. The earlier tenders stay as they are; the order is paid
+/// again once the charge is.
+pub fn apply_adjustment_tender(
+    order: &mut Order,
+    tender: SplitPayment,
+    outstanding: Money,
+    processed_by: Option<ObjectId>,
+    processed_at: mongodb::bson::DateTime,
+) -> AppResult<SplitPayment> {
+    let tender = take_tender(order, tender, outstanding, processed_by, processed_at)?;
+    order.payment_status = Some(if tender.amount < outstanding { "Partial" } else { "Paid" }.to_string());
+    Ok(tender)
+}
+
+/// Validate a tender against `remaining`, work out cash change and add it to the order
+fn take_tender(
+    order: &mut Order,
+    mut tender: SplitPayment,
+    remaining: Money,
+    processed_by: Option<ObjectId>,
+    processed_at: mongodb::bson::DateTime,
+) -> AppResult<SplitPayment> {
+    if tender.payment_method.trim().is_empty() {
+        return Err(AppError::Validation("Payment method is required".to_string()));
+    }
+    if !tender.amount.is_positive() {
+        return Err(AppError::Validation(
+            "Payment amount must be greater than zero".to_string(),
+        ));
+    }
+
+    if !remaining.is_positive() {
+        return Err(AppError::Conflict(format!(
+            "Order {} is already fully paid",
+            order.order_id
+        )));
+    }
+
+    if is_cash(&tender.payment_method) {
+        let mut details = tender.payment_details.take().unwrap_or_default();
+        let tendered = details.cash_tendered.unwrap_or(tender.amount);
+        if tendered < tender.amount {
+            return Err(AppError::Validation(format!(
+                "Cash tendered {} is less than the payment amount {}",
+                tendered, tender.amount
+            )));
+        }
+
+        tender.amount = tender.amount.min(remaining);
+        let change = tendered - tender.amount;
+        details.cash_tendered = Some(tendered);
+        details.change = Some(change);
+        tender.payment_details = Some(details);
+        order.change = change;
+    } else if tender.amount > remaining {
+        return Err(AppError::Validation(format!(
+            "Payment of {} exceeds the remaining balance of {}",
+            tender.amount, remaining
+        )));
+    }
+
+    tender.status = "completed".to_string();
+    tender.processed_by = processed_by;
+    tender.processed_at = Some(processed_at);
+    order.payments.push(tender.clone());
+
+    Ok(tender)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::models::order::PaymentDetails;
+
+    fn order_with_total(grand_total: i64) -> Order {
+        Order {
+            order_id: "ORD-TEST".to_string(),
+            grand_total: Money::from_rupiah(grand_total),
+            ..Order::default()
+        }
+    }
+
+    fn tender(method: &str, amount: i64, cash_tendered: Option<i64>) -> SplitPayment {
+        SplitPayment {
+            payment_method: method.to_string(),
+            amount: Money::from_rupiah(amount),
+            payment_details: cash_tendered.map(|c| PaymentDetails {
+                cash_tendered: Some(Money::from_rupiah(c)),
+                ..PaymentDetails::default()
+            }),
+            ..SplitPayment::default()
+        }
+    }
+
+    #[test]
+    fn test_split_payment_settles_balance() {
+        let now = mongodb::bson::DateTime::now();
+        let mut order = order_with_total(100_000);
+
+        apply_tender(&mut order, tender("QRIS", 40_000, None), None, now).unwrap();
+        assert_eq!(order.split_payment_status, "partial");
+        assert_eq!(remaining_balance(&order), Money::from_rupiah(60_000));
+        assert!(order.is_split_payment);
+
+        let cash = apply_tender(&mut order, tender("Cash", 60_000, Some(100_000)), None, now)
+            .unwrap();
+        assert_eq!(cash.amount, Money::from_rupiah(60_000));
+        assert_eq!(cash.payment_details.unwrap().change, Some(Money::from_rupiah(40_000)));
+        assert_eq!(order.split_payment_status, "completed");
+        assert_eq!(order.payment_status.as_deref(), Some("Paid"));
+        assert_eq!(remaining_balance(&order), Money::ZERO);
+
+        let err = apply_tender(&mut order, tender("Card", 1, None), None, now);
+        assert!(matches!(err, Err(AppError::Conflict(_))));
+    }
+
+    #[test]
+    fn test_non_cash_overpayment_rejected() {
+        let now = mongodb::bson::DateTime::now();
+        let mut order = order_with_total(50_000);
+
+        let err = apply_tender(&mut order, tender("Transfer", 60_000, None), None, now);
+        assert!(matches!(err, Err(AppError::Validation(_))));
+        assert!(order.payments.is_empty());
+        assert_eq!(order.split_payment_status, "not_started");
+
+        apply_tender(&mut order, tender("Card", 50_000, None), None, now).unwrap();
+        assert!(!order.is_split_payment);
+        assert_eq!(order.split_payment_status, "completed");
+    }
+
+    #[test]
+    fn test_adjustment_tender_only_owes_the_charge() {
+        // Paid at creation, then revised up by 20.000
+        let now = mongodb::bson::DateTime::now();
+        let mut order = order_with_total(120_000);
+        order.payment_status = Some("Partial".to_string());
+        order.payments.push(tender("Cash", 100_000, Some(100_000)));
+        let outstanding = Money::from_rupiah(20_000);
+
+        let err = apply_adjustment_tender(&mut order, tender("QRIS", 30_000, None), outstanding, None, now);
+        assert!(matches!(err, Err(AppError::Validation(_))));
+
+        apply_adjustment_tender(&mut order, tender("QRIS", 5_000, None), outstanding, None, now).unwrap();
+        assert_eq!(order.payment_status.as_deref(), Some("Partial"));
+
+        let cash = apply_adjustment_tender(
+            &mut order,
+            tender("Cash", 15_000, Some(50_000)),
+            outstanding - Money::from_rupiah(5_000),
+            None,
+            now,
+        )
+        .unwrap();
+        assert_eq!(cash.payment_details.unwrap().change, Some(Money::from_rupiah(35_000)));
+        assert_eq!(order.payment_status.as_deref(), Some("Paid"));
+    }
+
+    #[test]
+    fn test_cancel_rolls_back_once() {
+        let mut order = order_with_total(50_000);
+        order.id = Some(ObjectId::new());
+        order.status = OrderStatus::OnProcess;
+        order.table_number = Some("A1".to_string());
+        let actor = OrderActor { id: ObjectId::new(), name: "Sari".to_string() };
+
+        let (order_oid, entry) = cancel_entry(&order, Some(&actor), "Customer left").unwrap();
+        assert_eq!(Some(order_oid), order.id);
+        assert_eq!((entry.from_status, entry.to_status), (OrderStatus::OnProcess, OrderStatus::Canceled));
+        assert_eq!(entry.changed_by, Some(actor.id));
+
+        let canceled = canceled_order(&order, entry, "Customer left".to_string(), false, CancelEffects::default()).order;
+        assert_eq!(canceled.status, OrderStatus::Canceled);
+        assert_eq!(canceled.cancellation_reason.as_deref(), Some("Customer left"));
+        assert!(canceled.stock_rolled_back && canceled.loyalty_rolled_back && canceled.table_released);
+        assert_eq!(canceled.status_history.len(), 1);
+
+        // Stock and points come back once: a canceled order cannot be canceled again
+        assert!(matches!(cancel_entry(&canceled, None, "Again"), Err(AppError::Conflict(_))));
+        let unsaved = Order { id: None, ..order };
+        assert!(matches!(cancel_entry(&unsaved, None, "Customer left"), Err(AppError::Internal(_))));
+    }
+}
@@ -1,8 +1,8 @@
 use bson::oid::ObjectId;
 
 use crate::db::repositories::OutletRepository;
-use crate::db::models::{Outlet, Warehouse, Supplier};
-use crate::error::AppResult;
+use crate::db::models::{Outlet, PaymentProvider, Warehouse, Supplier};
+use crate::error::{AppError, AppResult};
 
 #[derive(Clone)]
 pub struct OutletService {
@@ -22,6 +22,17 @@ impl OutletService {
         self.outlet_repo.find_outlet_by_id(id).await
     }
 
+    /// Choose the gateway for an outlet's online payments
+    pub async fn set_payment_provider(&self, id: &ObjectId, provider: PaymentProvider) -> AppResult<Outlet> {
+        if !self.outlet_repo.set_payment_provider(id, provider).await? {
+            return Err(AppError::NotFound("Outlet not found".to_string()));
+        }
+        self.outlet_repo
+            .find_outlet_by_id(id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Outlet not found".to_string()))
+    }
+
     pub async fn get_all_warehouses(&self) -> AppResult<Vec<Warehouse>> {
         self.outlet_repo.find_all_warehouses().await
     }
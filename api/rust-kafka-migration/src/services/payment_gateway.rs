@@ -0,0 +1,229 @@
+use serde_json::Value;
+use std::fmt;
+use std::future::Future;
+use std::str::FromStr;
+
+use crate::common::Money;
+use crate::db::models::order::{PaymentAction, VaNumber};
+use crate::error::{AppError, AppResult};
+
+/// Banks issuing virtual accounts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VaBank {
+    Bca,
+    Bni,
+    Bri,
+    Cimb,
+    Permata,
+    /// Mandiri bills are paid through `echannel` on Midtrans
+    Mandiri,
+}
+
+impl VaBank {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VaBank::Bca => "bca",
+            VaBank::Bni => "bni",
+            VaBank::Bri => "bri",
+            VaBank::Cimb => "cimb",
+            VaBank::Permata => "permata",
+            VaBank::Mandiri => "mandiri",
+        }
+    }
+}
+
+impl FromStr for VaBank {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "bca" => Ok(VaBank::Bca),
+            "bni" => Ok(VaBank::Bni),
+            "bri" => Ok(VaBank::Bri),
+            "cimb" => Ok(VaBank::Cimb),
+            "permata" => Ok(VaBank::Permata),
+            "mandiri" => Ok(VaBank::Mandiri),
+            other => Err(AppError::Validation(format!("Unsupported bank: {}", other))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EWallet {
+    Gopay,
+    ShopeePay,
+    Ovo,
+    Dana,
+    LinkAja,
+}
+
+impl EWallet {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EWallet::Gopay => "gopay",
+            EWallet::ShopeePay => "shopeepay",
+            EWallet::Ovo => "ovo",
+            EWallet::Dana => "dana",
+            EWallet::LinkAja => "linkaja",
+        }
+    }
+}
+
+/// What the customer pays with, independent of the gateway
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaymentChannel {
+    Qris,
+    EWallet(EWallet),
+    BankTransfer(VaBank),
+}
+
+impl PaymentChannel {
+    /// Parse the `method` and optional `bank` sent by the client
+    pub fn parse(method: &str, bank: Option<&str>) -> AppResult<Self> {
+        match method.to_lowercase().as_str() {
+            "qris" => Ok(PaymentChannel::Qris),
+            "gopay" => Ok(PaymentChannel::EWallet(EWallet::Gopay)),
+            "shopeepay" => Ok(PaymentChannel::EWallet(EWallet::ShopeePay)),
+            "ovo" => Ok(PaymentChannel::EWallet(EWallet::Ovo)),
+            "dana" => Ok(PaymentChannel::EWallet(EWallet::Dana)),
+            "linkaja" => Ok(PaymentChannel::EWallet(EWallet::LinkAja)),
+            "bank_transfer" | "va" => {
+                let bank = bank
+                    .ok_or_else(|| AppError::Validation("bank is required for bank_transfer".to_string()))?;
+                Ok(PaymentChannel::BankTransfer(bank.parse()?))
+            }
+            other => Err(AppError::Validation(format!("Unsupported payment method: {}", other))),
+        }
+    }
+
+    /// Name recorded as the payment's `method`
+    pub fn method_name(&self) -> &'static str {
+        match self {
+            PaymentChannel::Qris => "qris",
+            PaymentChannel::EWallet(wallet) => wallet.as_str(),
+            PaymentChannel::BankTransfer(_) => "bank_transfer",
+        }
+    }
+
+    pub fn bank(&self) -> Option<VaBank> {
+        match self {
+            PaymentChannel::BankTransfer(bank) => Some(*bank),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for PaymentChannel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PaymentChannel::BankTransfer(bank) => write!(f, "bank_transfer ({})", bank.as_str()),
+            other => f.write_str(other.method_name()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct GatewayCustomer {
+    pub name: String,
+    pub email: Option<String>,
+    pub phone: Option<String>,
+}
+
+/// One payment to start on a gateway
+#[derive(Debug, Clone)]
+pub struct GatewayChargeRequest {
+    /// Our payment code; the gateway echoes it back in notifications
+    pub reference: String,
+    pub amount: Money,
+    /// Send the customer to the gateway's own checkout page (Snap, Xendit invoice)
+    /// instead of charging `channel` directly
+    pub hosted: bool,
+    /// Required for direct charges; narrows the choices on a hosted page
+    pub channel: Option<PaymentChannel>,
+    pub customer: GatewayCustomer,
+    pub expiry_minutes: i64,
+}
+
+/// A started gateway payment, in the shape the `payments` collection stores it.
+/// Timestamps use the WIB "YYYY-MM-DD HH:MM:SS" format of Midtrans.
+#[derive(Debug, Clone, Default)]
+pub struct GatewayCharge {
+    /// Recorded as the payment's `method`
+    pub method: String,
+    pub transaction_id: Option<String>,
+    /// Already mapped onto the Midtrans status names `Payment.status` uses
+    pub status: String,
+    pub fraud_status: Option<String>,
+    pub redirect_url: Option<String>,
+    pub transaction_time: Option<String>,
+    pub expiry_time: Option<String>,
+    pub va_numbers: Vec<VaNumber>,
+    pub permata_va_number: Option<String>,
+    pub bill_key: Option<String>,
+    pub biller_code: Option<String>,
+    pub actions: Vec<PaymentAction>,
+    pub merchant_id: Option<String>,
+    /// What the gateway returned, for the payment's `raw_response`
+    pub raw: Value,
+}
+
+/// Money to give back on a settled gateway payment
+#[derive(Debug, Clone)]
+pub struct GatewayRefundRequest {
+    /// Our refund ID; a retried refund with the same ID is not paid twice
+    pub reference: String,
+    /// The gateway's ID of the payment being refunded
+    pub transaction_id: String,
+    /// `method` recorded on the payment being refunded
+    pub method: String,
+    pub amount: Money,
+    pub reason: String,
+}
+
+/// A refund the gateway accepted
+#[derive(Debug, Clone)]
+pub struct GatewayRefund {
+    /// The gateway's key for the refund
+    pub refund_key: String,
+}
+
+/// A payment provider able to start charges and refund them; its webhook
+/// settles the charges
+pub trait PaymentGateway {
+    fn charge(&self, request: &GatewayChargeRequest) -> impl Future<Output = AppResult<GatewayCharge>> + Send;
+
+    fn refund(&self, request: &GatewayRefundRequest) -> impl Future<Output = AppResult<GatewayRefund>> + Send;
+}
+
+/// Compare a secret the caller sent with ours without short-circuiting,
+/// so timing does not reveal how much of it matched
+pub fn secrets_match(expected: &str, given: &str) -> bool {
+    expected.len() == given.len()
+        && expected
+            .bytes()
+            .zip(given.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::models::PaymentProvider;
+
+    #[test]
+    fn test_parse_channel() {
+        assert_eq!(PaymentChannel::parse("QRIS", None).unwrap(), PaymentChannel::Qris);
+        assert_eq!(
+            PaymentChannel::parse("ovo", None).unwrap(),
+            PaymentChannel::EWallet(EWallet::Ovo)
+        );
+        assert_eq!(
+            PaymentChannel::parse("va", Some("BNI")).unwrap(),
+            PaymentChannel::BankTransfer(VaBank::Bni)
+        );
+        assert!(PaymentChannel::parse("bank_transfer", None).is_err());
+        assert!(PaymentChannel::parse("cash", None).is_err());
+        assert_eq!("Xendit".parse::<PaymentProvider>().unwrap(), PaymentProvider::Xendit);
+    }
+}
@@ -0,0 +1,348 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tracing::info;
+
+use crate::common::Money;
+use crate::db::models::order::{PaymentAction, VaNumber};
+use crate::db::models::payment::DOWN_PAYMENT;
+use crate::db::models::{Order, OrderPayment, OrderStatus, PaymentProvider};
+use crate::db::repositories::{OutletRepository, PaymentRepository};
+use crate::error::{AppError, AppResult};
+use crate::services::midtrans_client::{parse_midtrans_time, MidtransClient, SNAP_METHOD};
+use crate::services::order_service::remaining_balance;
+use crate::services::payment_gateway::{
+    GatewayChargeRequest, GatewayCustomer, PaymentChannel, PaymentGateway,
+};
+use crate::services::xendit_client::{XenditClient, INVOICE_METHOD};
+
+/// `paymentType` of a charge settling the whole balance
+const FULL_PAYMENT: &str = "Full";
+
+/// How long a customer has to complete a gateway payment
+pub const CHARGE_EXPIRY_MINUTES: i64 = 30;
+
+/// What the customer chose to pay with
+#[derive(Debug, Clone)]
+pub struct ChargeRequest {
+    /// Required for App orders; narrows the hosted page for Web orders
+    pub channel: Option<PaymentChannel>,
+    pub customer: Option<GatewayCustomer>,
+}
+
+/// How to complete a pending gateway payment, as shown to the customer
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PaymentInstructions {
+    pub payment_id: Option<String>,
+    pub payment_code: Option<String>,
+    pub order_id: String,
+    pub method: String,
+    pub bank: Option<String>,
+    pub amount: Money,
+    pub status: String,
+    pub expiry_time: Option<String>,
+    pub va_numbers: Vec<VaNumber>,
+    pub permata_va_number: Option<String>,
+    pub bill_key: Option<String>,
+    pub biller_code: Option<String>,
+    pub qr_string: Option<String>,
+    pub actions: Vec<PaymentAction>,
+    pub redirect_url: Option<String>,
+    pub snap_token: Option<String>,
+}
+
+impl PaymentInstructions {
+    pub fn from_payment(payment: &OrderPayment) -> Self {
+        let raw_str = |key: &str| {
+            payment
+                .raw_response
+                .as_ref()
+                .and_then(|raw| raw.get(key))
+                .and_then(|v| v.as_str())
+                .map(str::to_string)
+        };
+
+        Self {
+            payment_id: payment.id.map(|id| id.to_hex()),
+            payment_code: payment.payment_code.clone(),
+            order_id: payment.order_id.clone(),
+            method: payment.method.clone(),
+            bank: payment.method_type.clone(),
+            amount: payment.amount,
+            status: payment.status.clone(),
+            expiry_time: payment.expiry_time.clone(),
+            va_numbers: payment.va_numbers.clone(),
+            permata_va_number: payment.permata_va_number.clone(),
+            bill_key: payment.bill_key.clone(),
+            biller_code: payment.biller_code.clone(),
+            qr_string: raw_str("qr_string"),
+            actions: payment.actions.clone(),
+            redirect_url: payment
+                .midtrans_redirect_url
+                .clone()
+                .or_else(|| payment.redirect_url.clone()),
+            snap_token: raw_str("token"),
+        }
+    }
+}
+
+/// Whether a pending payment was already sent to the gateway
+pub fn is_gateway_charge(payment: &OrderPayment) -> bool {
+    payment.transaction_id.is_some()
+        || payment.midtrans_redirect_url.is_some()
+        || payment.redirect_url.is_some()
+}
+
+/// Whether a gateway charge is past its `expiry_time`; charges without one never expire here
+pub fn charge_expired(payment: &OrderPayment, now: DateTime<Utc>) -> bool {
+    payment
+        .expiry_time
+        .as_deref()
+        .and_then(parse_midtrans_time)
+        .is_some_and(|expiry| expiry <= now)
+}
+
+/// Starts gateway payments for Web and App orders through the outlet's
+/// provider. The provider's webhook settles them.
+#[derive(Clone)]
+pub struct PaymentService {
+    payment_repo: PaymentRepository,
+    outlet_repo: OutletRepository,
+    midtrans: MidtransClient,
+    xendit: XenditClient,
+}
+
+impl PaymentService {
+    pub fn new(
+        payment_repo: PaymentRepository,
+        outlet_repo: OutletRepository,
+        midtrans: MidtransClient,
+        xendit: XenditClient,
+    ) -> Self {
+        Self {
+            payment_repo,
+            outlet_repo,
+            midtrans,
+            xendit,
+        }
+    }
+
+    /// Gateway the order's outlet takes payments through
+    async fn provider_for(&self, order: &Order) -> AppResult<PaymentProvider> {
+        let Some(outlet_id) = order.outlet else {
+            return Ok(PaymentProvider::default());
+        };
+        Ok(self
+            .outlet_repo
+            .find_outlet_by_id(&outlet_id)
+            .await?
+            .and_then(|outlet| outlet.payment_provider)
+            .unwrap_or_default())
+    }
+
+    /// Charge the order's remaining balance through the outlet's gateway: a hosted
+    /// page (Snap, Xendit invoice) for Web orders, a direct charge for App orders.
+    ///
+    /// A live charge for the same method is returned again instead of charging twice;
+    /// a live charge for another method is a conflict until it expires.
+    pub async fn charge_order(&self, order: &Order, request: ChargeRequest) -> AppResult<PaymentInstructions> {
+        let hosted = match order.source.as_str() {
+            "Web" => true,
+            "App" => false,
+            other => {
+                return Err(AppError::BadRequest(format!(
+                    "Online payment is only available for Web and App orders, not {}",
+                    other
+                )))
+            }
+        };
+        if order.payment_status.as_deref() == Some("Paid") {
+            return Err(AppError::Conflict(format!("Order {} is already paid", order.order_id)));
+        }
+
+        let amount = remaining_balance(order);
+        if !amount.is_positive() {
+            return Err(AppError::Conflict(format!("Order {} has nothing left to pay", order.order_id)));
+        }
+        self.charge(order, amount, FULL_PAYMENT, hosted, request).await
+    }
+
+    /// Charge the down payment securing a reservation order. Without a channel the
+    /// guest gets a link to the gateway's hosted page, whatever the order's source.
+    pub async fn charge_down_payment(
+        &self,
+        order: &Order,
+        amount: Money,
+        request: ChargeRequest,
+    ) -> AppResult<PaymentInstructions> {
+        if !amount.is_positive() || amount > order.grand_total {
+            return Err(AppError::Validation(format!(
+                "Down payment {} must be positive and at most the order total {}",
+                amount, order.grand_total
+            )));
+        }
+        let hosted = request.channel.is_none();
+        self.charge(order, amount, DOWN_PAYMENT, hosted, request).await
+    }
+
+    async fn charge(
+        &self,
+        order: &Order,
+        amount: Money,
+        payment_type: &str,
+        hosted: bool,
+        request: ChargeRequest,
+    ) -> AppResult<PaymentInstructions> {
+        if order.status == OrderStatus::Canceled {
+            return Err(AppError::Conflict(format!("Order {} is canceled", order.order_id)));
+        }
+
+        let provider = self.provider_for(order).await?;
+        let method_name = match (hosted, request.channel) {
+            (true, _) if provider == PaymentProvider::Xendit => INVOICE_METHOD,
+            (true, _) => SNAP_METHOD,
+            (false, Some(channel)) => channel.method_name(),
+            (false, None) => return Err(AppError::Validation("method is required".to_string())),
+        };
+        let bank = if hosted { None } else { request.channel.and_then(|c| c.bank()) };
+
+        let now = Utc::now();
+        let pending = self.payment_repo.find_pending_by_order_id(&order.order_id).await?;
+        if let Some(live) = pending
+            .iter()
+            .find(|p| is_gateway_charge(p) && !charge_expired(p, now))
+        {
+            let same_channel = live.provider.as_deref().unwrap_or(PaymentProvider::Midtrans.as_str())
+                == provider.as_str()
+                && (live.payment_type == DOWN_PAYMENT) == (payment_type == DOWN_PAYMENT)
+                && live.method == method_name
+                && live.method_type.as_deref() == bank.map(|b| b.as_str())
+                && live.amount == amount;
+            if same_channel {
+                return Ok(PaymentInstructions::from_payment(live));
+            }
+            return Err(AppError::Conflict(format!(
+                "Order {} already has a pending {} payment until {}",
+                order.order_id,
+                live.method,
+                live.expiry_time.as_deref().unwrap_or("it expires")
+            )));
+        }
+
+        // The pending payment recorded with the order, or generated at check-in,
+        // is charged in place; a down payment is always a payment of its own
+        let placeholder = (payment_type != DOWN_PAYMENT)
+            .then(|| pending.into_iter().find(|p| !is_gateway_charge(p)))
+            .flatten();
+        let mut payment = match placeholder {
+            Some(payment) => payment,
+            None => OrderPayment {
+                order_id: order.order_id.clone(),
+                payment_type: payment_type.to_string(),
+                ..OrderPayment::default()
+            },
+        };
+        let code = match payment.payment_code.clone() {
+            Some(code) => code,
+            None => self.payment_repo.next_code().await,
+        };
+
+        let customer = request.customer.unwrap_or_else(|| GatewayCustomer {
+            name: if order.user.is_empty() { "Customer".to_string() } else { order.user.clone() },
+            email: None,
+            phone: order.recipient_info.as_ref().and_then(|r| r.phone.clone()),
+        });
+        let charge_request = GatewayChargeRequest {
+            reference: code.clone(),
+            amount,
+            hosted,
+            channel: request.channel,
+            customer,
+            expiry_minutes: CHARGE_EXPIRY_MINUTES,
+        };
+        let charge = match provider {
+            PaymentProvider::Midtrans => self.midtrans.charge(&charge_request).await?,
+            PaymentProvider::Xendit => self.xendit.charge(&charge_request).await?,
+        };
+
+        payment.payment_code = Some(code.clone());
+        payment.provider = Some(provider.as_str().to_string());
+        payment.method = charge.method;
+        payment.method_type = bank.map(|b| b.as_str().to_string());
+        payment.status = charge.status;
+        payment.amount = amount;
+        payment.total_amount = Some(order.grand_total);
+        payment.remaining_amount = (remaining_balance(order) - amount).max(Money::ZERO);
+        payment.transaction_id = charge.transaction_id;
+        payment.fraud_status = charge.fraud_status;
+        payment.transaction_time = charge.transaction_time;
+        payment.expiry_time = charge.expiry_time;
+        payment.va_numbers = charge.va_numbers;
+        payment.permata_va_number = charge.permata_va_number;
+        payment.bill_key = charge.bill_key;
+        payment.biller_code = charge.biller_code;
+        payment.actions = charge.actions;
+        payment.merchant_id = charge.merchant_id;
+        payment.raw_response = Some(charge.raw);
+        match provider {
+            PaymentProvider::Midtrans => payment.midtrans_redirect_url = charge.redirect_url,
+            _ => payment.redirect_url = charge.redirect_url,
+        }
+        payment.updated_at = bson::DateTime::now();
+
+        if payment.id.is_some() {
+            self.payment_repo.update(&payment).await?;
+        } else {
+            payment.created_at = payment.updated_at;
+            payment.id = Some(self.payment_repo.create(payment.clone()).await?);
+        }
+
+        info!(
+            "💳 {} {} {} payment {} started for order {} ({})",
+            provider, payment.payment_type, payment.method, code, order.order_id, amount
+        );
+        Ok(PaymentInstructions::from_payment(&payment))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+    use serde_json::json;
+
+    #[test]
+    fn test_charge_expiry() {
+        let now = Utc.with_ymd_and_hms(2024, 3, 10, 3, 0, 0).unwrap(); // 10:00 WIB
+        let mut payment = OrderPayment {
+            transaction_id: Some("tx-1".to_string()),
+            expiry_time: Some("2024-03-10 10:30:00".to_string()),
+            ..OrderPayment::default()
+        };
+
+        assert!(is_gateway_charge(&payment));
+        assert!(!charge_expired(&payment, now));
+        assert!(charge_expired(&payment, now + Duration::minutes(30)));
+
+        payment.expiry_time = None;
+        assert!(!charge_expired(&payment, now + Duration::days(1)));
+    }
+
+    #[test]
+    fn test_instructions_from_payment() {
+        let payment = OrderPayment {
+            order_id: "ORD-1".to_string(),
+            payment_code: Some("PAY-20240310-0001".to_string()),
+            method: "qris".to_string(),
+            amount: Money::from_rupiah(42_000),
+            raw_response: Some(json!({ "qr_string": "00020101021126" })),
+            ..OrderPayment::default()
+        };
+
+        let instructions = PaymentInstructions::from_payment(&payment);
+
+        assert_eq!(instructions.qr_string.as_deref(), Some("00020101021126"));
+        assert_eq!(instructions.snap_token, None);
+        assert_eq!(instructions.status, "pending");
+    }
+}
@@ -70,8 +70,7 @@ impl PrintService {
         }
 
         // Emit beverage print
-        if !beverage_items.is_empty() && area_code.is_some() {
-            let area = area_code.as_ref().unwrap();
+        if let (false, Some(area)) = (beverage_items.is_empty(), area_code.as_ref()) {
             let bar_room = if area.as_str() <= "I" {
                 "bar_depan"
             } else {
@@ -108,4 +107,8 @@ impl PrintService {
     pub fn broadcast_order_status(&self, order_id: &str, status_update: StatusUpdate) {
         self.broadcaster.emit_order_status_update(order_id, status_update);
     }
+
+    pub fn broadcast_table_status(&self, table_status: TableStatusData) {
+        self.broadcaster.emit_table_status_update(table_status);
+    }
 }
@@ -1,7 +1,8 @@
-use mongodb::{Collection, Database};
+use mongodb::{ClientSession, Collection, Database};
 use mongodb::bson::{doc, oid::ObjectId};
 use futures::stream::TryStreamExt;
 use chrono::Utc;
+use crate::common::Money;
 use crate::db::models::{Promo, AutoPromo, Voucher, OrderItem, MenuItem};
 use crate::error::{Result, AppError};
 use crate::handlers::order::PromoRequest;
@@ -31,7 +32,7 @@ impl PromoService {
         outlet_id: ObjectId,
         order_type: &str,
     ) -> Result<PromoResult> {
-        let mut total_discount = 0.0;
+        let mut total_discount = Money::ZERO;
         let mut applied_details = Vec::new();
 
         // 1. Process requested promos
@@ -42,14 +43,14 @@ impl PromoService {
             // Try finding in manual promos first
             if let Some(manual_promo) = self.promo_collection.find_one(doc! { "_id": promo_oid, "isActive": true }, None).await? {
                 // Apply manual promo (simplistic for now)
-                let subtotal: f64 = order_items.iter().map(|i| i.subtotal).sum();
+                let subtotal: Money = order_items.iter().map(|i| i.subtotal).sum();
                 let discount = if manual_promo.discount_type == "percentage" {
-                    subtotal * (manual_promo.discount_amount / 100.0)
+                    subtotal.percent(manual_promo.discount_amount)
                 } else {
-                    manual_promo.discount_amount.min(subtotal)
+                    Money::from_f64(manual_promo.discount_amount).min(subtotal)
                 };
 
-                if discount > 0.0 {
+                if discount.is_positive() {
                     total_discount += discount;
                     applied_details.push(AppliedPromoDetail {
                         id: manual_promo.id.unwrap(),
@@ -122,7 +123,7 @@ impl PromoService {
         use mongodb::Cursor;
         let mut cursor: Cursor<AutoPromo> = self.auto_promo_collection.find(filter, None).await?;
         let mut applied_promos = Vec::new();
-        let mut total_discount = 0.0;
+        let mut total_discount = Money::ZERO;
 
         while let Some(promo) = cursor.try_next().await? {
             let promo: AutoPromo = promo;
@@ -131,7 +132,7 @@ impl PromoService {
             }
 
             let result = self.evaluate_auto_promo(&promo, order_items, order_type).await?;
-            if result.applied && result.discount > 0.0 {
+            if result.applied && result.discount.is_positive() {
                 total_discount += result.discount;
                 applied_promos.push(AppliedPromoDetail {
                     id: promo.id.unwrap(),
@@ -156,12 +157,12 @@ impl PromoService {
     ) -> Result<EvaluationResult> {
         // Consumer Type Check
         if promo.consumer_type != "all" && promo.consumer_type != order_type {
-            return Ok(EvaluationResult { applied: false, discount: 0.0 });
+            return Ok(EvaluationResult { applied: false, discount: Money::ZERO });
         }
 
         match promo.promo_type.as_str() {
             "discount_on_total" => {
-                let subtotal: f64 = order_items.iter().map(|i| i.subtotal).sum();
+                let subtotal: Money = order_items.iter().map(|i| i.subtotal).sum();
                 if let Some(min_total) = promo.conditions.min_total {
                     if subtotal >= min_total {
                         let discount = promo.calculate_discount(subtotal);
@@ -170,7 +171,7 @@ impl PromoService {
                 }
             }
             "discount_on_quantity" => {
-                let mut total_discount = 0.0;
+                let mut total_discount = Money::ZERO;
                 let min_qty = promo.conditions.min_quantity.unwrap_or(1);
                 
                 for item in order_items {
@@ -178,12 +179,12 @@ impl PromoService {
                          total_discount += promo.calculate_discount(item.subtotal);
                     }
                 }
-                if total_discount > 0.0 {
+                if total_discount.is_positive() {
                     return Ok(EvaluationResult { applied: true, discount: total_discount });
                 }
             }
             "product_specific" => {
-                let mut total_discount = 0.0;
+                let mut total_discount = Money::ZERO;
                 for item in order_items {
                     if let Some(menu_item_oid) = item.menu_item {
                         if promo.conditions.products.contains(&menu_item_oid) {
@@ -191,17 +192,17 @@ impl PromoService {
                         }
                     }
                 }
-                if total_discount > 0.0 {
+                if total_discount.is_positive() {
                     return Ok(EvaluationResult { applied: true, discount: total_discount });
                 }
             }
             "bundling" => {
                 let mut min_sets = i32::MAX;
                 if promo.conditions.bundle_products.is_empty() {
-                    return Ok(EvaluationResult { applied: false, discount: 0.0 });
+                    return Ok(EvaluationResult { applied: false, discount: Money::ZERO });
                 }
 
-                let mut original_bundle_price = 0.0;
+                let mut original_bundle_price = Money::ZERO;
                 for bp in &promo.conditions.bundle_products {
                     if let Some(p_oid) = bp.product {
                         let item_qty = order_items.iter()
@@ -216,10 +217,10 @@ impl PromoService {
 
                         // Get item price for original total calculation
                         if let Some(item) = order_items.iter().find(|i| i.menu_item == Some(p_oid)) {
-                            original_bundle_price += item.menu_item_data.price * bp.quantity.unwrap_or(1) as f64;
+                            original_bundle_price += item.menu_item_data.price * bp.quantity.unwrap_or(1);
                         } else {
                             // Fallback if item not in order_items (shouldn't happen if sets > 0)
-                            return Ok(EvaluationResult { applied: false, discount: 0.0 });
+                            return Ok(EvaluationResult { applied: false, discount: Money::ZERO });
                         }
                     }
                 }
@@ -227,8 +228,8 @@ impl PromoService {
                 if min_sets > 0 && min_sets != i32::MAX {
                     if let Some(b_price_per_set) = promo.bundle_price {
                         let discount_per_set = original_bundle_price - b_price_per_set;
-                        let total_discount = discount_per_set * min_sets as f64;
-                        if total_discount > 0.0 {
+                        let total_discount = discount_per_set * min_sets;
+                        if total_discount.is_positive() {
                             return Ok(EvaluationResult { applied: true, discount: total_discount });
                         }
                     }
@@ -249,7 +250,7 @@ impl PromoService {
                          // But we need to know the price of `get_product`. 
                          // For now, let's assume we can fetch it or it's in order_items.
                          if let Some(get_item) = order_items.iter().find(|i| i.menu_item == Some(get_p)) {
-                             let discount = get_item.menu_item_data.price * free_sets.min(get_item.quantity) as f64;
+                             let discount = get_item.menu_item_data.price * free_sets.min(get_item.quantity);
                              return Ok(EvaluationResult { applied: true, discount });
                          }
                      }
@@ -258,74 +259,252 @@ impl PromoService {
             _ => {}
         }
 
-        Ok(EvaluationResult { applied: false, discount: 0.0 })
+        Ok(EvaluationResult { applied: false, discount: Money::ZERO })
     }
 
+    /// Give back the voucher usage recorded for an order.
+    ///
+    /// Only a usage tagged with the order is removed, so releasing twice is a no-op.
+    pub async fn release_voucher(
+        &self,
+        voucher_id: ObjectId,
+        order_id: ObjectId,
+        session: Option<&mut ClientSession>,
+    ) -> Result<bool> {
+        let filter = doc! { "_id": voucher_id, "usedBy.orderId": order_id };
+        let update = doc! {
+            "$inc": { "quota": 1 },
+            "$pull": { "usedBy": { "orderId": order_id } },
+            "$set": { "updatedAt": mongodb::bson::DateTime::now() }
+        };
+        let result = match session {
+            Some(s) => self.voucher_collection.update_one_with_session(filter, update, None, s).await?,
+            None => self.voucher_collection.update_one(filter, update, None).await?,
+        };
+
+        Ok(result.modified_count == 1)
+    }
+
+    /// Look up an active voucher by code and check it against the order,
+    /// without recording any usage.
+    ///
+    /// Returns the voucher with the discount it gives on `subtotal`.
     pub async fn check_voucher(
         &self,
         voucher_code: &str,
-        subtotal: f64,
+        subtotal: Money,
         outlet_id: ObjectId,
+        customer: Option<ObjectId>,
+        session: Option<&mut ClientSession>,
     ) -> Result<VoucherResult> {
-        let now = Utc::now();
-        let filter = doc! {
-            "code": voucher_code,
-            "isActive": true,
-            "validFrom": { "$lte": now },
-            "validTo": { "$gte": now },
-            "quota": { "$gt": 0 }
+        let filter = doc! { "code": voucher_code.trim(), "isActive": true };
+        let found = match session {
+            Some(s) => self.voucher_collection.find_one_with_session(filter, None, s).await?,
+            None => self.voucher_collection.find_one(filter, None).await?,
         };
+        let voucher = found.ok_or_else(|| AppError::NotFound(format!("Voucher {} not found", voucher_code)))?;
 
-        let voucher = match self.voucher_collection.find_one(filter, None).await? {
-            Some(v) => v,
-            None => return Ok(VoucherResult { discount: 0.0, voucher: None }),
-        };
+        validate_voucher(&voucher, outlet_id, customer, mongodb::bson::DateTime::now())?;
 
-        if !voucher.applicable_outlets.is_empty() && !voucher.applicable_outlets.contains(&outlet_id) {
-             return Ok(VoucherResult { discount: 0.0, voucher: None });
-        }
+        Ok(VoucherResult {
+            discount: voucher_discount(&voucher, subtotal),
+            voucher,
+        })
+    }
 
-        let discount = if voucher.discount_type == "percentage" {
-            subtotal * (voucher.discount_amount / 100.0)
-        } else {
-             voucher.discount_amount.min(subtotal)
+    /// Validate a voucher and record its use by an order.
+    ///
+    /// The quota decrement and the `usedBy` entry are written by one conditional
+    /// update that re-checks the quota and, for one-time vouchers, the customer,
+    /// so two orders racing for the last use cannot both succeed.
+    pub async fn redeem_voucher(
+        &self,
+        voucher_code: &str,
+        subtotal: Money,
+        outlet_id: ObjectId,
+        customer: Option<ObjectId>,
+        order_id: ObjectId,
+        mut session: Option<&mut ClientSession>,
+    ) -> Result<VoucherResult> {
+        let checked = self
+            .check_voucher(voucher_code, subtotal, outlet_id, customer, session.as_deref_mut())
+            .await?;
+        let voucher_id = checked.voucher.id
+            .ok_or_else(|| AppError::Internal("Voucher without _id".to_string()))?;
+
+        let mut filter = doc! { "_id": voucher_id, "isActive": true, "quota": { "$gt": 0 } };
+        if checked.voucher.one_time_use {
+            filter.insert("usedBy.userId", doc! { "$ne": customer });
+        }
+        let now = mongodb::bson::DateTime::now();
+        let update = doc! {
+            "$inc": { "quota": -1 },
+            "$push": { "usedBy": { "userId": customer, "orderId": order_id, "usedAt": now } },
+            "$set": { "updatedAt": now }
         };
+        let result = match session {
+            Some(s) => self.voucher_collection.update_one_with_session(filter, update, None, s).await?,
+            None => self.voucher_collection.update_one(filter, update, None).await?,
+        };
+        if result.modified_count == 0 {
+            return Err(AppError::Conflict(format!(
+                "Voucher {} is no longer available",
+                checked.voucher.code
+            )));
+        }
 
-        Ok(VoucherResult {
-            discount,
-            voucher: Some(voucher),
-        })
+        Ok(checked)
     }
 }
 
+/// The customer type an order counts as for voucher eligibility
+fn customer_type(customer: Option<ObjectId>) -> &'static str {
+    if customer.is_some() { "member" } else { "guest" }
+}
+
+/// Check a voucher's date window, outlet, customer type, quota and one-time use
+fn validate_voucher(
+    voucher: &Voucher,
+    outlet_id: ObjectId,
+    customer: Option<ObjectId>,
+    now: mongodb::bson::DateTime,
+) -> Result<()> {
+    if !voucher.is_active {
+        return Err(AppError::Validation(format!("Voucher {} is not active", voucher.code)));
+    }
+    if now < voucher.valid_from || now > voucher.valid_to {
+        return Err(AppError::Validation(format!("Voucher {} is not valid at this time", voucher.code)));
+    }
+    if !voucher.applicable_outlets.is_empty() && !voucher.applicable_outlets.contains(&outlet_id) {
+        return Err(AppError::Validation(format!("Voucher {} is not valid at this outlet", voucher.code)));
+    }
+    let wanted = voucher.customer_type.trim();
+    if !wanted.is_empty() && !wanted.eq_ignore_ascii_case("all")
+        && !wanted.eq_ignore_ascii_case(customer_type(customer))
+    {
+        return Err(AppError::Validation(format!(
+            "Voucher {} is only for {} customers",
+            voucher.code, wanted
+        )));
+    }
+    if voucher.quota <= 0 {
+        return Err(AppError::Validation(format!("Voucher {} has been fully used", voucher.code)));
+    }
+    if voucher.one_time_use {
+        let customer = customer.ok_or_else(|| {
+            AppError::Validation(format!("Voucher {} requires a registered customer", voucher.code))
+        })?;
+        if voucher.used_by.iter().any(|u| u.user_id == Some(customer)) {
+            return Err(AppError::Validation(format!("Voucher {} has already been used", voucher.code)));
+        }
+    }
+    Ok(())
+}
+
+/// The discount a voucher gives on `subtotal`, never more than the subtotal
+fn voucher_discount(voucher: &Voucher, subtotal: Money) -> Money {
+    let discount = if voucher.discount_type == "percentage" {
+        subtotal.percent(voucher.discount_amount)
+    } else {
+        Money::from_f64(voucher.discount_amount)
+    };
+    discount.min(subtotal).max(Money::ZERO)
+}
+
 pub struct PromoResult {
-    pub total_discount: f64,
+    pub total_discount: Money,
     pub applied_promos: Vec<AppliedPromoDetail>,
 }
 
 pub struct AutoPromoResult {
-    pub total_discount: f64,
+    pub total_discount: Money,
     pub applied_promos: Vec<AppliedPromoDetail>,
 }
 
 pub struct AppliedPromoDetail {
     pub id: ObjectId,
     pub name: String,
-    pub amount: f64,
+    pub amount: Money,
     pub promo_type: String,
 }
 
 pub struct ManualPromoResult {
-    pub discount: f64,
+    pub discount: Money,
     pub applied_promo: Option<Promo>,
 }
 
 pub struct VoucherResult {
-    pub discount: f64,
-    pub voucher: Option<Voucher>,
+    pub discount: Money,
+    pub voucher: Voucher,
 }
 
 struct EvaluationResult {
     applied: bool,
-    discount: f64,
+    discount: Money,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::models::VoucherUsage;
+    use mongodb::bson::DateTime;
+
+    fn voucher(outlet: ObjectId) -> Voucher {
+        Voucher {
+            id: Some(ObjectId::new()),
+            code: "HEMAT10".to_string(),
+            name: "Hemat 10%".to_string(),
+            description: None,
+            discount_amount: 10.0,
+            discount_type: "percentage".to_string(),
+            valid_from: DateTime::from_millis(1_000),
+            valid_to: DateTime::from_millis(10_000),
+            quota: 1,
+            one_time_use: false,
+            used_by: Vec::new(),
+            applicable_outlets: vec![outlet],
+            customer_type: "all".to_string(),
+            print_on_receipt: false,
+            is_active: true,
+            created_at: None,
+            updated_at: None,
+        }
+    }
+
+    #[test]
+    fn test_validate_voucher() {
+        let outlet = ObjectId::new();
+        let customer = ObjectId::new();
+        let now = DateTime::from_millis(5_000);
+        let v = voucher(outlet);
+
+        assert!(validate_voucher(&v, outlet, None, now).is_ok());
+        assert!(validate_voucher(&v, ObjectId::new(), None, now).is_err());
+        assert!(validate_voucher(&v, outlet, None, DateTime::from_millis(20_000)).is_err());
+
+        let members_only = Voucher { customer_type: "member".to_string(), ..v.clone() };
+        assert!(validate_voucher(&members_only, outlet, None, now).is_err());
+        assert!(validate_voucher(&members_only, outlet, Some(customer), now).is_ok());
+
+        let exhausted = Voucher { quota: 0, ..v.clone() };
+        assert!(validate_voucher(&exhausted, outlet, Some(customer), now).is_err());
+
+        let once = Voucher {
+            one_time_use: true,
+            used_by: vec![VoucherUsage { user_id: Some(customer), order_id: None, used_at: now }],
+            ..v
+        };
+        assert!(validate_voucher(&once, outlet, None, now).is_err());
+        assert!(validate_voucher(&once, outlet, Some(customer), now).is_err());
+        assert!(validate_voucher(&once, outlet, Some(ObjectId::new()), now).is_ok());
+    }
+
+    #[test]
+    fn test_voucher_discount_capped_at_subtotal() {
+        let v = voucher(ObjectId::new());
+        assert_eq!(voucher_discount(&v, Money::from_rupiah(50_000)), Money::from_rupiah(5_000));
+
+        let fixed = Voucher { discount_type: "fixed".to_string(), discount_amount: 25_000.0, ..v };
+        assert_eq!(voucher_discount(&fixed, Money::from_rupiah(20_000)), Money::from_rupiah(20_000));
+    }
 }
@@ -0,0 +1,644 @@
+use std::sync::Arc;
+
+use bson::oid::ObjectId;
+use chrono::Utc;
+use mongodb::ClientSession;
+use serde::Deserialize;
+use tracing::{error, info, warn};
+
+use crate::common::Money;
+use crate::db::models::order::RefundDetails;
+use crate::db::models::payment::Payment;
+use crate::db::models::{Order, OrderStatus, PaymentProvider, Refund, RefundItem, RefundTender};
+use crate::db::repositories::{OrderRepository, PaymentRepository, RefundRepository};
+use crate::db::{with_transaction, DbConnection};
+use crate::error::{AppError, AppResult};
+use crate::kafka::{events::PaymentEvent, KafkaProducer};
+use crate::services::midtrans_client::MidtransClient;
+use crate::services::order_service::OrderActor;
+use crate::services::payment_gateway::{GatewayRefund, GatewayRefundRequest, PaymentGateway};
+use crate::services::xendit_client::XenditClient;
+
+/// Quantity to refund from one order line
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RefundLine {
+    pub item_index: usize,
+    pub quantity: i32,
+}
+
+/// What a refund takes off an order, before anything is persisted
+#[derive(Debug, Clone)]
+pub struct RefundPlan {
+    pub refund_type: String,
+    pub items: Vec<RefundItem>,
+    /// Fraction of the order's current totals being refunded
+    pub share: f64,
+    pub amount: Money,
+}
+
+/// Work out the refund for the given lines, or for everything left when `lines` is `None`.
+///
+/// The refunded amount is the lines' share of the pre-discount total applied
+/// to the grand total, so discounts, tax and service are refunded pro rata.
+pub fn plan_refund(order: &Order, lines: Option<&[RefundLine]>) -> AppResult<RefundPlan> {
+    if !order.total_before_discount.is_positive() || !order.grand_total.is_positive() {
+        return Err(AppError::Validation(format!(
+            "Order {} has nothing left to refund",
+            order.order_id
+        )));
+    }
+
+    let refund_item = |index: usize, item: &crate::db::models::OrderItem, quantity: i32| {
+        let unit_price = item.subtotal.per(item.quantity);
+        RefundItem {
+            item_index: index,
+            menu_item: item.menu_item,
+            menu_item_name: item.menu_item_data.name.clone(),
+            quantity: item.quantity,
+            refund_quantity: quantity,
+            unit_price,
+            subtotal: item.subtotal,
+            refund_amount: unit_price * quantity,
+        }
+    };
+
+    let Some(lines) = lines else {
+        let items = order
+            .items
+            .iter()
+            .enumerate()
+            .filter(|(_, item)| item.quantity > 0)
+            .map(|(index, item)| refund_item(index, item, item.quantity))
+            .collect();
+
+        return Ok(RefundPlan {
+            refund_type: "full".to_string(),
+            items,
+            share: 1.0,
+            amount: order.grand_total,
+        });
+    };
+
+    if lines.is_empty() {
+        return Err(AppError::Validation("Select at least one item to refund".to_string()));
+    }
+
+    let mut items: Vec<RefundItem> = Vec::with_capacity(lines.len());
+    for line in lines {
+        let item = order.items.get(line.item_index).ok_or_else(|| {
+            AppError::Validation(format!("Order has no item at index {}", line.item_index))
+        })?;
+
+        let already = items
+            .iter()
+            .filter(|i| i.item_index == line.item_index)
+            .map(|i| i.refund_quantity)
+            .sum::<i32>();
+
+        if line.quantity <= 0 || line.quantity + already > item.quantity {
+            return Err(AppError::Validation(format!(
+                "Cannot refund {} of '{}', {} left",
+                line.quantity, item.menu_item_data.name, item.quantity - already
+            )));
+        }
+
+        items.push(refund_item(line.item_index, item, line.quantity));
+    }
+
+    let gross: Vec<Money> = items.iter().map(|i| i.refund_amount).collect();
+    let share = gross
+        .iter()
+        .sum::<Money>()
+        .ratio_of(order.total_before_discount)
+        .min(1.0);
+    let amount = order.grand_total * share;
+
+    for (item, part) in items.iter_mut().zip(amount.allocate(&gross)) {
+        item.refund_amount = part;
+    }
+
+    Ok(RefundPlan {
+        refund_type: "partial".to_string(),
+        items,
+        share,
+        amount,
+    })
+}
+
+/// Take a planned refund off the order's lines and totals
+pub fn apply_refund_plan(order: &mut Order, plan: &RefundPlan) {
+    for refund in &plan.items {
+        if let Some(item) = order.items.get_mut(refund.item_index) {
+            item.quantity -= refund.refund_quantity;
+            item.subtotal = if item.quantity > 0 {
+                (item.subtotal - refund.unit_price * refund.refund_quantity).max(Money::ZERO)
+            } else {
+                Money::ZERO
+            };
+            item.refunded_quantity += refund.refund_quantity;
+        }
+    }
+
+    let keep = 1.0 - plan.share;
+    if plan.refund_type == "full" {
+        order.total_custom_amount = Money::ZERO;
+    }
+    order.total_before_discount = order.total_before_discount * keep;
+    order.total_after_discount = order.total_after_discount * keep;
+    order.total_tax = order.total_tax * keep;
+    order.total_service_fee = order.total_service_fee * keep;
+    for detail in &mut order.tax_and_service_details {
+        detail.amount = detail.amount * keep;
+    }
+
+    order.grand_total = (order.grand_total - plan.amount).max(Money::ZERO);
+    order.total_refunded += plan.amount;
+    order.payment_status = Some(
+        if order.grand_total.is_zero() {
+            "Refunded"
+        } else {
+            "Partially Refunded"
+        }
+        .to_string(),
+    );
+}
+
+/// Record a refund on the tender it is paid back from, marking the tender
+/// refunded once nothing is left of the bill
+pub fn note_refund_on_tender(
+    order: &mut Order,
+    amount: Money,
+    reason: &str,
+    approver: &OrderActor,
+    now: mongodb::bson::DateTime,
+) {
+    let settled = order.grand_total.is_zero();
+    if let Some(tender) = order
+        .payments
+        .iter_mut()
+        .rev()
+        .find(|p| p.status == "completed" || p.status == "refunded")
+    {
+        let details = tender.refund_details.get_or_insert_with(RefundDetails::default);
+        details.refund_amount += amount;
+        details.refund_reason = Some(reason.to_string());
+        details.refunded_at = Some(now);
+        details.refunded_by = Some(approver.id);
+        if settled {
+            tender.status = "refunded".to_string();
+        }
+    }
+}
+
+/// A settled payment that went through a gateway and is refunded there
+fn is_gateway_payment(payment: &Payment) -> bool {
+    matches!(payment.status.as_str(), "settlement" | "capture") && payment.transaction_id.is_some()
+}
+
+/// The part of a refund paid back on one tender. `tender` is `None` for what
+/// no recorded payment covers, such as orders paid in full at creation.
+#[derive(Debug, Clone)]
+pub struct RefundPart<'a> {
+    pub tender: Option<&'a Payment>,
+    pub amount: Money,
+}
+
+/// Spread a refund over the order's settled payments, newest first. Each takes
+/// at most what is left of it after earlier refunds, so a gateway is never
+/// asked for more than it took.
+pub fn split_refund(payments: &[Payment], amount: Money) -> Vec<RefundPart<'_>> {
+    let refunded = |tender: &Payment| -> Money {
+        payments
+            .iter()
+            .filter(|p| p.direction.as_deref() == Some("refund") && p.related_payment_id.is_some())
+            .filter(|p| p.related_payment_id == tender.id)
+            .map(|p| p.amount)
+            .sum()
+    };
+
+    let mut parts = Vec::new();
+    let mut remaining = amount;
+    for tender in payments.iter().rev().filter(|p| {
+        p.direction.as_deref() != Some("refund") && matches!(p.status.as_str(), "settlement" | "capture" | "paid")
+    }) {
+        if !remaining.is_positive() {
+            break;
+        }
+        let take = (tender.amount - refunded(tender)).min(remaining);
+        if take.is_positive() {
+            parts.push(RefundPart { tender: Some(tender), amount: take });
+            remaining -= take;
+        }
+    }
+    if remaining.is_positive() {
+        parts.push(RefundPart { tender: None, amount: remaining });
+    }
+    parts
+}
+
+/// A refund the gateways have accepted, where there are any, not yet stored
+#[derive(Debug, Clone)]
+pub struct IssuedRefund {
+    pub refund: Refund,
+    /// One refund payment per tender paid back, with their IDs already set
+    pub payments: Vec<Payment>,
+}
+
+#[derive(Clone)]
+pub struct RefundService {
+    db: Arc<DbConnection>,
+    order_repo: OrderRepository,
+    payment_repo: PaymentRepository,
+    refund_repo: RefundRepository,
+    midtrans: MidtransClient,
+    xendit: XenditClient,
+    kafka: Arc<KafkaProducer>,
+}
+
+impl RefundService {
+    pub fn new(
+        db: Arc<DbConnection>,
+        order_repo: OrderRepository,
+        payment_repo: PaymentRepository,
+        refund_repo: RefundRepository,
+        midtrans: MidtransClient,
+        xendit: XenditClient,
+        kafka: Arc<KafkaProducer>,
+    ) -> Self {
+        Self {
+            db,
+            order_repo,
+            payment_repo,
+            refund_repo,
+            midtrans,
+            xendit,
+            kafka,
+        }
+    }
+
+    pub async fn find_by_order(&self, order: &Order) -> AppResult<Vec<Refund>> {
+        let order_oid = order
+            .id
+            .ok_or_else(|| AppError::Internal("Order has no ID".to_string()))?;
+        self.refund_repo.find_by_order(&order_oid).await
+    }
+
+    /// Refund a paid order in full (`lines` is `None`) or per item.
+    ///
+    /// Orders paid through a gateway are refunded there first; the
+    /// refund record, the refund `Payment` and the adjusted order are then
+    /// written in one transaction.
+    pub async fn refund_order(
+        &self,
+        order: &Order,
+        lines: Option<&[RefundLine]>,
+        reason: String,
+        reason_description: Option<String>,
+        approver: &OrderActor,
+    ) -> AppResult<(Order, Refund)> {
+        let is_paid = matches!(
+            order.payment_status.as_deref(),
+            Some("Paid") | Some("Partially Refunded")
+        ) || (order.payment_status.is_none() && order.status == OrderStatus::Completed);
+        if !is_paid {
+            return Err(AppError::Conflict(format!(
+                "Order {} is not paid and cannot be refunded",
+                order.order_id
+            )));
+        }
+
+        let plan = plan_refund(order, lines)?;
+        let now = mongodb::bson::DateTime::now();
+        let issued = self
+            .issue(order, &plan.refund_type, plan.items.clone(), plan.amount, reason.clone(), reason_description, approver)
+            .await?;
+
+        let mut updated = order.clone();
+        apply_refund_plan(&mut updated, &plan);
+        note_refund_on_tender(&mut updated, plan.amount, &reason, approver, now);
+        updated.updated_at_wib = now;
+
+        let refund_oid = {
+            let (issued, updated, order_repo) = (&issued, &updated, &self.order_repo);
+            let persisted = with_transaction(&self.db, |mut session| async move {
+                let result = async {
+                    let refund_oid = self.write_with_session(issued, &mut session).await?;
+                    order_repo.update_with_session(updated, &mut session).await?;
+                    Ok(refund_oid)
+                }
+                .await;
+                (session, result)
+            })
+            .await;
+            self.check_persisted(issued, persisted)?
+        };
+
+        info!(
+            "💸 Refunded {} ({}) on order {} approved by {}",
+            plan.amount, plan.refund_type, order.order_id, approver.name
+        );
+        self.publish_refunded(&issued).await;
+
+        let mut refund = issued.refund;
+        refund.id = Some(refund_oid);
+        Ok((updated, refund))
+    }
+
+    /// Refund the drop in a paid order's total after a revision.
+    ///
+    /// Only goes to the gateway; the caller stores the result with
+    /// [`write_with_session`](Self::write_with_session) together with the
+    /// revised order, then announces it with [`publish_refunded`](Self::publish_refunded).
+    pub async fn issue_revision_refund(
+        &self,
+        order: &Order,
+        amount: Money,
+        reason: Option<String>,
+        approver: &OrderActor,
+    ) -> AppResult<IssuedRefund> {
+        let reason = reason.unwrap_or_else(|| "Order revised".to_string());
+        let mut issued = self
+            .issue(order, "partial", Vec::new(), amount, reason, None, approver)
+            .await?;
+        for payment in &mut issued.payments {
+            payment.payment_type = "Adjustment".to_string();
+            payment.is_adjustment = true;
+        }
+        Ok(issued)
+    }
+
+    /// Refund `amount` over the order's tenders, at the gateway for those that
+    /// went through one, then build the refund record and refund payments
+    #[allow(clippy::too_many_arguments)]
+    async fn issue(
+        &self,
+        order: &Order,
+        refund_type: &str,
+        items: Vec<RefundItem>,
+        amount: Money,
+        reason: String,
+        reason_description: Option<String>,
+        approver: &OrderActor,
+    ) -> AppResult<IssuedRefund> {
+        let order_oid = order
+            .id
+            .ok_or_else(|| AppError::Internal("Order has no ID".to_string()))?;
+        let now = mongodb::bson::DateTime::now();
+        let refund_id = format!(
+            "REF-{}-{}",
+            Utc::now().timestamp_millis(),
+            &uuid::Uuid::new_v4().simple().to_string()[..9]
+        );
+
+        let payments = self.payment_repo.find_by_order_id(&order.order_id).await?;
+        let parts = split_refund(&payments, amount);
+        let fallback_method = order.payment_method.clone().unwrap_or_else(|| "Cash".to_string());
+
+        let mut tenders = Vec::with_capacity(parts.len());
+        let mut refund_payments = Vec::with_capacity(parts.len());
+        for (index, part) in parts.iter().enumerate() {
+            let method = part.tender.map(|p| p.method.clone()).unwrap_or_else(|| fallback_method.clone());
+
+            let mut gateway_refund_key = None;
+            if let Some(payment) = part.tender.filter(|p| is_gateway_payment(p)) {
+                let request = GatewayRefundRequest {
+                    reference: if parts.len() > 1 { format!("{}-{}", refund_id, index + 1) } else { refund_id.clone() },
+                    transaction_id: payment.transaction_id.clone().unwrap_or_default(),
+                    method: payment.method.clone(),
+                    amount: part.amount,
+                    reason: reason.clone(),
+                };
+                match self.refund_at_gateway(payment, &request).await {
+                    Ok(accepted) => gateway_refund_key = Some(accepted.refund_key),
+                    Err(e) => {
+                        let accepted: Vec<&str> =
+                            tenders.iter().filter_map(|t: &RefundTender| t.gateway_refund_key.as_deref()).collect();
+                        if !accepted.is_empty() {
+                            error!(
+                                "Refund {} failed after the gateway accepted {:?}: {}",
+                                refund_id, accepted, e
+                            );
+                        }
+                        return Err(e);
+                    }
+                }
+            }
+
+            let payment_oid = ObjectId::new();
+            refund_payments.push(Payment {
+                id: Some(payment_oid),
+                order_id: order.order_id.clone(),
+                method: method.clone(),
+                status: "refunded".to_string(),
+                payment_type: if refund_type == "full" { "Full" } else { "Partial" }.to_string(),
+                amount: part.amount,
+                total_amount: Some(part.amount),
+                related_payment_id: part.tender.and_then(|p| p.id),
+                direction: Some("refund".to_string()),
+                notes: Some(format!("{}: {}", refund_id, reason)),
+                created_at: now,
+                updated_at: now,
+                ..Payment::default()
+            });
+            tenders.push(RefundTender {
+                method,
+                amount: part.amount,
+                payment_id: Some(payment_oid),
+                gateway_refund_key,
+            });
+        }
+
+        let refund_method = match tenders.as_slice() {
+            [first, rest @ ..] if rest.iter().any(|t| t.method != first.method) => "Split".to_string(),
+            [first, ..] => first.method.clone(),
+            [] => fallback_method,
+        };
+        let refund = Refund {
+            id: None,
+            refund_id,
+            order_id: order.order_id.clone(),
+            order: order_oid,
+            user_id: order.user_id,
+            requested_by: approver.name.clone(),
+            refund_type: refund_type.to_string(),
+            refund_items: items,
+            total_refund_amount: amount,
+            refund_reason: reason,
+            refund_reason_description: reason_description,
+            status: "processed".to_string(),
+            processed_by: Some(approver.id),
+            processed_at: Some(now),
+            refund_method,
+            original_payment_method: parts.first().and_then(|p| p.tender).map(|p| p.method.clone()),
+            gateway_refund_key: tenders.iter().find_map(|t| t.gateway_refund_key.clone()),
+            tenders,
+            created_at: Some(now),
+            updated_at: Some(now),
+        };
+
+        Ok(IssuedRefund { refund, payments: refund_payments })
+    }
+
+    /// Send a refund to the gateway that took `payment`
+    async fn refund_at_gateway(&self, payment: &Payment, request: &GatewayRefundRequest) -> AppResult<GatewayRefund> {
+        let provider = match payment.provider.as_deref() {
+            Some(provider) => provider.parse()?,
+            None => PaymentProvider::Midtrans,
+        };
+        match provider {
+            PaymentProvider::Midtrans => self.midtrans.refund(request).await,
+            PaymentProvider::Xendit => self.xendit.refund(request).await,
+        }
+    }
+
+    /// Store an issued refund and its refund payments inside a transaction
+    pub async fn write_with_session(&self, issued: &IssuedRefund, session: &mut ClientSession) -> AppResult<ObjectId> {
+        let refund_oid = self.refund_repo.create_with_session(&issued.refund, session).await?;
+        for payment in &issued.payments {
+            self.payment_repo.create_with_session(payment.clone(), session).await?;
+        }
+        Ok(refund_oid)
+    }
+
+    /// Pass the result of storing an issued refund through, flagging a refund the
+    /// gateway accepted but that could not be saved
+    pub fn check_persisted<T>(&self, issued: &IssuedRefund, persisted: AppResult<T>) -> AppResult<T> {
+        if let Err(e) = &persisted {
+            if issued.refund.gateway_refund_key.is_some() {
+                error!(
+                    "Refund {} was accepted by the gateway but could not be saved: {}",
+                    issued.refund.refund_id, e
+                );
+            }
+        }
+        persisted
+    }
+
+    /// Announce each refund payment of a stored refund
+    pub async fn publish_refunded(&self, issued: &IssuedRefund) {
+        for payment in &issued.payments {
+            let payment_id = payment.id.map(|id| id.to_hex()).unwrap_or_default();
+            let event = PaymentEvent::Refunded {
+                payment_id: payment_id.clone(),
+                order_id: payment.order_id.clone(),
+                amount: payment.amount.as_f64(),
+                timestamp: Utc::now(),
+            };
+            if let Err(e) = self.kafka.publish_payment_event(&payment_id, &event).await {
+                warn!("Failed to publish refund event for {}: {}", payment.order_id, e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::models::{MenuItemData, OrderItem};
+
+    fn paid_order() -> Order {
+        let item = |name: &str, quantity: i32, subtotal: i64| OrderItem {
+            menu_item_data: MenuItemData {
+                name: name.to_string(),
+                ..MenuItemData::default()
+            },
+            quantity,
+            subtotal: Money::from_rupiah(subtotal),
+            ..OrderItem::default()
+        };
+
+        Order {
+            order_id: "ORD-REFUND".to_string(),
+            items: vec![item("Latte", 2, 60_000), item("Croissant", 1, 40_000)],
+            total_before_discount: Money::from_rupiah(100_000),
+            total_after_discount: Money::from_rupiah(90_000),
+            total_tax: Money::from_rupiah(9_900),
+            grand_total: Money::from_rupiah(99_900),
+            payment_status: Some("Paid".to_string()),
+            ..Order::default()
+        }
+    }
+
+    #[test]
+    fn test_partial_refund_is_pro_rata() {
+        let mut order = paid_order();
+        let lines = [RefundLine { item_index: 0, quantity: 1 }];
+
+        let plan = plan_refund(&order, Some(&lines)).unwrap();
+        assert_eq!(plan.refund_type, "partial");
+        assert!((plan.share - 0.3).abs() < 1e-9);
+        assert_eq!(plan.amount, Money::from_rupiah(29_970));
+
+        apply_refund_plan(&mut order, &plan);
+        assert_eq!(order.items[0].quantity, 1);
+        assert_eq!(order.items[0].refunded_quantity, 1);
+        assert_eq!(order.items[0].subtotal, Money::from_rupiah(30_000));
+        assert_eq!(order.grand_total, Money::from_rupiah(69_930));
+        assert_eq!(order.total_before_discount, Money::from_rupiah(70_000));
+        assert_eq!(order.payment_status.as_deref(), Some("Partially Refunded"));
+
+        let too_many = [RefundLine { item_index: 0, quantity: 2 }];
+        assert!(plan_refund(&order, Some(&too_many)).is_err());
+    }
+
+    #[test]
+    fn test_full_refund_takes_remaining_balance() {
+        let mut order = paid_order();
+        let partial = plan_refund(&order, Some(&[RefundLine { item_index: 1, quantity: 1 }])).unwrap();
+        apply_refund_plan(&mut order, &partial);
+
+        let full = plan_refund(&order, None).unwrap();
+        assert_eq!(full.refund_type, "full");
+        assert_eq!(full.items.len(), 1);
+        apply_refund_plan(&mut order, &full);
+
+        assert!(order.grand_total.is_zero());
+        assert_eq!(order.total_refunded, Money::from_rupiah(99_900));
+        assert_eq!(order.payment_status.as_deref(), Some("Refunded"));
+        assert!(plan_refund(&order, None).is_err());
+    }
+
+    #[test]
+    fn test_split_refund_caps_each_tender() {
+        let payment = |method: &str, amount: i64, status: &str, transaction: Option<&str>| Payment {
+            id: Some(ObjectId::new()),
+            order_id: "ORD-REFUND".to_string(),
+            method: method.to_string(),
+            status: status.to_string(),
+            amount: Money::from_rupiah(amount),
+            transaction_id: transaction.map(str::to_string),
+            ..Payment::default()
+        };
+        let cash = payment("Cash", 60_000, "paid", None);
+        let qris = payment("qris", 40_000, "settlement", Some("tx-1"));
+        let mut payments = vec![cash.clone(), qris.clone(), payment("qris", 10_000, "expire", Some("tx-2"))];
+
+        // The gateway gives back at most what it took, the rest comes from the cash
+        let split = |payments: &[Payment], amount: i64| -> Vec<(Option<ObjectId>, Money)> {
+            split_refund(payments, Money::from_rupiah(amount))
+                .iter()
+                .map(|part| (part.tender.and_then(|p| p.id), part.amount))
+                .collect()
+        };
+        assert_eq!(
+            split(&payments, 55_000),
+            vec![(qris.id, Money::from_rupiah(40_000)), (cash.id, Money::from_rupiah(15_000))]
+        );
+        assert!(is_gateway_payment(&qris) && !is_gateway_payment(&cash));
+
+        // Earlier refunds on a tender are not given back again
+        payments.push(Payment {
+            direction: Some("refund".to_string()),
+            related_payment_id: qris.id,
+            ..payment("qris", 30_000, "refunded", None)
+        });
+        assert_eq!(
+            split(&payments, 20_000),
+            vec![(qris.id, Money::from_rupiah(10_000)), (cash.id, Money::from_rupiah(10_000))]
+        );
+
+        // What no recorded payment covers has no tender
+        assert_eq!(split(&[], 5_000), vec![(None, Money::from_rupiah(5_000))]);
+    }
+}
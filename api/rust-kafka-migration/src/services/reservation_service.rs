@@ -0,0 +1,839 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use bson::{doc, oid::ObjectId, Document};
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, TimeZone, Utc};
+use chrono_tz::Asia::Jakarta;
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::common::Money;
+use crate::config::ReservationConfig;
+use crate::db::models::payment::FINAL_PAYMENT;
+use crate::db::models::{
+    EmployeeInfo, OrderPayment, OrderStatus, Reservation, ReservationStatus, ReservationType,
+};
+use crate::db::repositories::{
+    CounterRepository, OrderRepository, PaymentRepository, ReservationFilter, ReservationRepository,
+    TableRepository,
+};
+use crate::db::{with_transaction, DbConnection};
+use crate::error::{AppError, AppResult};
+use crate::services::order_service::{remaining_balance, OrderActor, OrderService};
+use crate::services::payment_gateway::GatewayCustomer;
+use crate::services::payment_service::{is_gateway_charge, ChargeRequest, PaymentInstructions};
+use crate::services::{PaymentService, TableService};
+use crate::utils::generate_reservation_code;
+
+/// Reservations handled per no-show sweep; the rest wait for the next run
+const NO_SHOW_BATCH_SIZE: i64 = 200;
+const NO_SHOW_REASON: &str = "No-show";
+
+/// WIB midnight of a `YYYY-MM-DD` reservation date
+pub fn reservation_day(date: &str) -> AppResult<DateTime<Utc>> {
+    let day = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map_err(|_| AppError::Validation("reservationDate must be YYYY-MM-DD".to_string()))?;
+    Jakarta
+        .from_local_datetime(&day.and_time(NaiveTime::MIN))
+        .single()
+        .map(|d| d.with_timezone(&Utc))
+        .ok_or_else(|| AppError::Validation("Invalid reservationDate".to_string()))
+}
+
+/// Start of a reservation's slot: its WIB date at its `HH:MM` time
+pub fn slot_start(reservation: &Reservation) -> AppResult<DateTime<Utc>> {
+    let time = NaiveTime::parse_from_str(&reservation.reservation_time, "%H:%M")
+        .map_err(|_| AppError::Validation("reservationTime must be HH:MM".to_string()))?;
+    let day = reservation.reservation_date.with_timezone(&Jakarta).date_naive();
+    Jakarta
+        .from_local_datetime(&day.and_time(time))
+        .single()
+        .map(|d| d.with_timezone(&Utc))
+        .ok_or_else(|| AppError::Validation("Invalid reservationTime".to_string()))
+}
+
+/// Whether two slots share any time; a slot ending as another starts does not
+pub fn slots_overlap(a_start: DateTime<Utc>, a_minutes: i32, b_start: DateTime<Utc>, b_minutes: i32) -> bool {
+    let a_end = a_start + Duration::minutes(a_minutes as i64);
+    let b_end = b_start + Duration::minutes(b_minutes as i64);
+    a_start < b_end && b_start < a_end
+}
+
+/// Reservations among `others` that share a table and overlap the slot of `reservation`
+pub fn conflicting<'a>(reservation: &Reservation, others: &'a [Reservation]) -> AppResult<Vec<&'a Reservation>> {
+    let start = slot_start(reservation)?;
+    let mut conflicts = Vec::new();
+    for other in others {
+        if other.id.is_some() && other.id == reservation.id {
+            continue;
+        }
+        if !other.table_id.iter().any(|t| reservation.table_id.contains(t)) {
+            continue;
+        }
+        // Records with an unreadable slot cannot be checked and do not block
+        let Ok(other_start) = slot_start(other) else { continue };
+        if slots_overlap(start, reservation.duration_minutes, other_start, other.duration_minutes) {
+            conflicts.push(other);
+        }
+    }
+    Ok(conflicts)
+}
+
+/// Down payment of `percent` percent of `total`, never more than the total
+pub fn down_payment_amount(total: Money, percent: f64) -> Money {
+    total.percent(percent.clamp(0.0, 100.0)).min(total).max(Money::ZERO)
+}
+
+/// Whether confirmed guests are still missing `grace` after their slot started
+pub fn is_no_show(reservation: &Reservation, now: DateTime<Utc>, grace: Duration) -> bool {
+    reservation.status == ReservationStatus::Confirmed
+        && reservation.check_in_time.is_none()
+        && slot_start(reservation).is_ok_and(|start| start + grace <= now)
+}
+
+/// A settled down payment and what is left of its order, for the balance report
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownPaymentBalance {
+    pub payment_id: Option<ObjectId>,
+    pub payment_code: Option<String>,
+    pub order_id: String,
+    pub reservation_id: Option<ObjectId>,
+    pub reservation_code: Option<String>,
+    pub reservation_date: Option<DateTime<Utc>>,
+    pub reservation_time: Option<String>,
+    pub customer_name: Option<String>,
+    pub reservation_status: Option<ReservationStatus>,
+    pub down_payment: Money,
+    pub order_total: Money,
+    /// Still owed on the order; zero once collected, canceled or forfeited
+    pub outstanding: Money,
+    pub paid_at: Option<bson::DateTime>,
+    pub forfeited_at: Option<bson::DateTime>,
+}
+
+fn employee(actor: &OrderActor) -> EmployeeInfo {
+    EmployeeInfo {
+        employee_id: Some(actor.id),
+        employee_name: Some(actor.name.clone()),
+        ..EmployeeInfo::default()
+    }
+}
+
+#[derive(Clone)]
+pub struct ReservationService {
+    db: Arc<DbConnection>,
+    reservation_repo: ReservationRepository,
+    table_repo: TableRepository,
+    order_repo: OrderRepository,
+    payment_repo: PaymentRepository,
+    counter_repo: CounterRepository,
+    table_service: TableService,
+    order_service: OrderService,
+    payment_service: PaymentService,
+    config: ReservationConfig,
+}
+
+impl ReservationService {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        db: Arc<DbConnection>,
+        reservation_repo: ReservationRepository,
+        table_repo: TableRepository,
+        order_repo: OrderRepository,
+        payment_repo: PaymentRepository,
+        counter_repo: CounterRepository,
+        table_service: TableService,
+        order_service: OrderService,
+        payment_service: PaymentService,
+        config: ReservationConfig,
+    ) -> Self {
+        Self {
+            db,
+            reservation_repo,
+            table_repo,
+            order_repo,
+            payment_repo,
+            counter_repo,
+            table_service,
+            order_service,
+            payment_service,
+            config,
+        }
+    }
+
+    /// Configured down payment percentage for the reservation's type
+    fn down_payment_percent(&self, reservation: &Reservation) -> f64 {
+        match reservation.reservation_type {
+            ReservationType::Blocking => self.config.blocking_down_payment_percent,
+            ReservationType::NonBlocking => self.config.non_blocking_down_payment_percent,
+        }
+    }
+
+    pub async fn find(&self, id: &ObjectId) -> AppResult<Reservation> {
+        self.reservation_repo
+            .find_by_id(id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Reservation not found".to_string()))
+    }
+
+    pub async fn list(&self, filter: &ReservationFilter) -> AppResult<Vec<Reservation>> {
+        self.reservation_repo.find(filter).await
+    }
+
+    /// Book tables for a slot.
+    ///
+    /// The tables must be active, in the reservation's area and not held by
+    /// another pending or confirmed reservation at an overlapping time. The
+    /// caller serializes bookings per area so two cannot take the same slot.
+    pub async fn create(
+        &self,
+        mut reservation: Reservation,
+        order: Option<ObjectId>,
+        actor: &OrderActor,
+    ) -> AppResult<Reservation> {
+        if reservation.table_id.is_empty() {
+            return Err(AppError::Validation("At least one table is required".to_string()));
+        }
+        if reservation.guest_count <= 0 {
+            return Err(AppError::Validation("guestCount must be positive".to_string()));
+        }
+        if reservation.duration_minutes <= 0 {
+            return Err(AppError::Validation("durationMinutes must be positive".to_string()));
+        }
+        reservation.table_id.sort();
+        reservation.table_id.dedup();
+        slot_start(&reservation)?;
+
+        for table_id in &reservation.table_id {
+            let table = self
+                .table_repo
+                .find_by_id(table_id)
+                .await?
+                .filter(|t| t.is_active)
+                .ok_or_else(|| AppError::Validation(format!("Table {} not found", table_id)))?;
+            if table.area_id != reservation.area_id {
+                return Err(AppError::Validation(format!(
+                    "Table {} is not in the reservation's area",
+                    table.table_number
+                )));
+            }
+        }
+        self.ensure_available(&reservation).await?;
+
+        if let Some(order) = order {
+            let open = self.order_repo.find_by_id(&order).await?.is_some_and(|o| !o.status.is_final());
+            if !open {
+                return Err(AppError::Validation("orderId must be an open order".to_string()));
+            }
+        }
+
+        let now = Utc::now();
+        reservation.reservation_code = generate_reservation_code(&self.counter_repo, now).await;
+        reservation.status = ReservationStatus::Pending;
+        reservation.created_by = Some(EmployeeInfo {
+            created_at: Some(now),
+            ..employee(actor)
+        });
+        reservation.order_id = order;
+
+        // The reservation holds its slot only if the order takes it too
+        let id = match order {
+            Some(order) => {
+                let (reservation_repo, order_repo) = (&self.reservation_repo, &self.order_repo);
+                let reservation = &reservation;
+                with_transaction(&self.db, |mut session| async move {
+                    let result = async {
+                        let id = reservation_repo.create_with_session(reservation, &mut session).await?;
+                        if !order_repo.link_reservation_with_session(&order, &id, &mut session).await? {
+                            return Err(AppError::Conflict(
+                                "Order is closed or belongs to another reservation".to_string(),
+                            ));
+                        }
+                        Ok(id)
+                    }
+                    .await;
+                    (session, result)
+                })
+                .await?
+            }
+            None => self.reservation_repo.create(&reservation).await?,
+        };
+        reservation.id = Some(id);
+        info!("📅 Reservation {} created for {}", reservation.reservation_code, reservation.reservation_time);
+
+        Ok(reservation)
+    }
+
+    pub async fn confirm(&self, id: &ObjectId, actor: &OrderActor) -> AppResult<Reservation> {
+        let reservation = self.find(id).await?;
+        if reservation.status != ReservationStatus::Pending {
+            return Err(AppError::Conflict(format!(
+                "Reservation {} is {}, only pending reservations can be confirmed",
+                reservation.reservation_code, reservation.status
+            )));
+        }
+
+        let now = Utc::now();
+        let confirm_by = EmployeeInfo {
+            confirmed_at: Some(now),
+            ..employee(actor)
+        };
+        self.step(
+            &reservation,
+            doc! { "status": ReservationStatus::Pending.as_str() },
+            doc! {
+                "status": ReservationStatus::Confirmed.as_str(),
+                "confirm_by": bson::to_bson(&confirm_by)?,
+            },
+        )
+        .await
+    }
+
+    /// Guests have arrived: record the time and occupy the tables
+    pub async fn check_in(&self, id: &ObjectId, actor: &OrderActor) -> AppResult<Reservation> {
+        let reservation = self.find(id).await?;
+        if reservation.status != ReservationStatus::Confirmed || reservation.check_in_time.is_some() {
+            return Err(AppError::Conflict(format!(
+                "Reservation {} must be confirmed and not yet checked in",
+                reservation.reservation_code
+            )));
+        }
+
+        let final_payment = self.prepare_final_payment(&reservation).await?;
+
+        let now = Utc::now();
+        let checked_in_by = EmployeeInfo {
+            checked_in_at: Some(now),
+            ..employee(actor)
+        };
+        let mut set = doc! {
+            "check_in_time": bson::to_bson(&now)?,
+            "checked_in_by": bson::to_bson(&checked_in_by)?,
+        };
+        if let Some(final_payment) = final_payment {
+            set.insert("final_payment_id", final_payment);
+        }
+        let reservation = self
+            .step(
+                &reservation,
+                doc! { "status": ReservationStatus::Confirmed.as_str(), "check_in_time": null },
+                set,
+            )
+            .await?;
+
+        self.table_service
+            .occupy_for_reservation(&reservation.table_id, &actor.name, &reservation.reservation_code)
+            .await?;
+
+        Ok(reservation)
+    }
+
+    /// Guests have left: complete the reservation and free its tables
+    pub async fn check_out(&self, id: &ObjectId, actor: &OrderActor) -> AppResult<Reservation> {
+        let reservation = self.find(id).await?;
+        if reservation.status != ReservationStatus::Confirmed || reservation.check_in_time.is_none() {
+            return Err(AppError::Conflict(format!(
+                "Reservation {} has not been checked in",
+                reservation.reservation_code
+            )));
+        }
+
+        let now = Utc::now();
+        let checked_out_by = EmployeeInfo {
+            checked_out_at: Some(now),
+            ..employee(actor)
+        };
+        let reservation = self
+            .step(
+                &reservation,
+                doc! { "status": ReservationStatus::Confirmed.as_str(), "check_out_time": null },
+                doc! {
+                    "status": ReservationStatus::Completed.as_str(),
+                    "check_out_time": bson::to_bson(&now)?,
+                    "checked_out_by": bson::to_bson(&checked_out_by)?,
+                },
+            )
+            .await?;
+
+        self.table_service
+            .release_for_reservation(&reservation.table_id, &actor.name, &reservation.reservation_code)
+            .await?;
+
+        Ok(reservation)
+    }
+
+    /// Cancel a reservation whose guests have not checked in yet
+    pub async fn cancel(&self, id: &ObjectId, actor: &OrderActor, reason: String) -> AppResult<Reservation> {
+        let reservation = self.find(id).await?;
+        if !reservation.status.holds_tables() || reservation.check_in_time.is_some() {
+            return Err(AppError::Conflict(format!(
+                "Reservation {} is {} and can no longer be cancelled",
+                reservation.reservation_code, reservation.status
+            )));
+        }
+
+        let now = Utc::now();
+        let cancelled_by = EmployeeInfo {
+            cancelled_at: Some(now),
+            ..employee(actor)
+        };
+        self.step(
+            &reservation,
+            doc! { "status": reservation.status.as_str(), "check_in_time": null },
+            doc! {
+                "status": ReservationStatus::Cancelled.as_str(),
+                "cancelled_by": bson::to_bson(&cancelled_by)?,
+                "cancellation_reason": reason,
+            },
+        )
+        .await
+    }
+
+    /// Link a reservation and the order made for it, both ways, in one transaction
+    pub async fn link_order(&self, id: &ObjectId, order: &ObjectId) -> AppResult<Reservation> {
+        let reservation = self.find(id).await?;
+        if matches!(reservation.status, ReservationStatus::Cancelled) {
+            return Err(AppError::Conflict(format!(
+                "Reservation {} is cancelled",
+                reservation.reservation_code
+            )));
+        }
+
+        let (reservation_repo, order_repo) = (&self.reservation_repo, &self.order_repo);
+        with_transaction(&self.db, |mut session| async move {
+            let result = async {
+                if !reservation_repo.link_order_with_session(id, order, &mut session).await? {
+                    return Err(AppError::Conflict("Reservation is already linked to another order".to_string()));
+                }
+                if !order_repo.link_reservation_with_session(order, id, &mut session).await? {
+                    return Err(AppError::Conflict(
+                        "Order is closed or belongs to another reservation".to_string(),
+                    ));
+                }
+                Ok(())
+            }
+            .await;
+            (session, result)
+        })
+        .await?;
+
+        self.find(id).await
+    }
+
+    /// Ask for the down payment of a pending reservation through the outlet's gateway.
+    ///
+    /// The amount is the configured percentage of the linked order's total for the
+    /// reservation's type. The webhook confirms the reservation once it settles.
+    pub async fn request_down_payment(
+        &self,
+        id: &ObjectId,
+        mut request: ChargeRequest,
+    ) -> AppResult<(Reservation, PaymentInstructions)> {
+        let reservation = self.find(id).await?;
+        if reservation.status != ReservationStatus::Pending {
+            return Err(AppError::Conflict(format!(
+                "Reservation {} is {}, a down payment is only taken while pending",
+                reservation.reservation_code, reservation.status
+            )));
+        }
+        let order_oid = reservation.order_id.ok_or_else(|| {
+            AppError::Validation("Link an order to the reservation before requesting a down payment".to_string())
+        })?;
+        let order = self
+            .order_repo
+            .find_by_id(&order_oid)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Reservation order not found".to_string()))?;
+        if order.reservation_id() != reservation.id {
+            return Err(AppError::Validation(format!(
+                "Order {} is not linked to reservation {}",
+                order.order_id, reservation.reservation_code
+            )));
+        }
+        if order.status.is_final() {
+            return Err(AppError::Conflict(format!("Order {} is {}", order.order_id, order.status)));
+        }
+        if let Some(paid) = self.payment_repo.find_settled_down_payment(&order.order_id).await? {
+            return Err(AppError::Conflict(format!(
+                "Down payment {} of {} is already paid",
+                paid.payment_code.as_deref().unwrap_or("-"),
+                paid.amount
+            )));
+        }
+
+        let percent = self.down_payment_percent(&reservation);
+        let amount = down_payment_amount(order.grand_total, percent);
+        if !amount.is_positive() {
+            return Err(AppError::Conflict(format!(
+                "Reservation {} does not require a down payment",
+                reservation.reservation_code
+            )));
+        }
+
+        if request.customer.is_none() && !reservation.customer_name.is_empty() {
+            request.customer = Some(GatewayCustomer {
+                name: reservation.customer_name.clone(),
+                email: Some(reservation.customer_email.clone()).filter(|e| !e.is_empty()),
+                phone: Some(reservation.customer_phone.clone()).filter(|p| !p.is_empty()),
+            });
+        }
+        let instructions = self.payment_service.charge_down_payment(&order, amount, request).await?;
+
+        let payment_id = instructions
+            .payment_id
+            .as_deref()
+            .and_then(|id| ObjectId::parse_str(id).ok());
+        let reservation = match payment_id {
+            Some(payment_id) if reservation.down_payment_id != Some(payment_id) => {
+                self.step(
+                    &reservation,
+                    doc! { "status": ReservationStatus::Pending.as_str() },
+                    doc! { "down_payment_id": payment_id },
+                )
+                .await?
+            }
+            _ => reservation,
+        };
+
+        info!(
+            "📅 Down payment of {} ({}%) requested for reservation {}",
+            amount, percent, reservation.reservation_code
+        );
+        Ok((reservation, instructions))
+    }
+
+    /// Generate the pending final payment of a reservation whose down payment was
+    /// paid: the rest of the order, linked to the down payment. The payment the
+    /// order was created with is reused, so checking in again changes nothing.
+    async fn prepare_final_payment(&self, reservation: &Reservation) -> AppResult<Option<ObjectId>> {
+        let Some(order_oid) = reservation.order_id else {
+            return Ok(None);
+        };
+        let Some(order) = self.order_repo.find_by_id(&order_oid).await? else {
+            return Ok(None);
+        };
+        if order.status == OrderStatus::Canceled {
+            return Ok(None);
+        }
+        let Some(down_payment) = self.payment_repo.find_settled_down_payment(&order.order_id).await? else {
+            return Ok(None);
+        };
+        let amount = remaining_balance(&order);
+        if !amount.is_positive() {
+            return Ok(None);
+        }
+
+        let pending = self.payment_repo.find_pending_by_order_id(&order.order_id).await?;
+        let now = bson::DateTime::now();
+        let mut payment = match pending.into_iter().find(|p| !is_gateway_charge(p)) {
+            Some(payment) => payment,
+            None => OrderPayment {
+                order_id: order.order_id.clone(),
+                method: "Cash".to_string(),
+                created_at: now,
+                ..OrderPayment::default()
+            },
+        };
+        payment.payment_type = FINAL_PAYMENT.to_string();
+        payment.amount = amount;
+        payment.total_amount = Some(order.grand_total);
+        payment.remaining_amount = amount;
+        payment.related_payment_id = down_payment.id;
+        payment.updated_at = now;
+
+        let id = match payment.id {
+            Some(id) => {
+                self.payment_repo.update(&payment).await?;
+                id
+            }
+            None => self.payment_repo.create(payment).await?,
+        };
+
+        info!(
+            "📅 Final payment of {} generated for reservation {} after down payment {}",
+            amount,
+            reservation.reservation_code,
+            down_payment.payment_code.as_deref().unwrap_or("-")
+        );
+        Ok(Some(id))
+    }
+
+    /// Record that the guests of a confirmed reservation did not come. Its settled
+    /// down payment is kept as forfeited and its order canceled, returning stock.
+    ///
+    /// Staff may do this once the slot has started; without an actor (the sweeper)
+    /// only after the configured grace period.
+    pub async fn mark_no_show(
+        &self,
+        id: &ObjectId,
+        actor: Option<&OrderActor>,
+        now: DateTime<Utc>,
+    ) -> AppResult<Reservation> {
+        let reservation = self.find(id).await?;
+        let grace = match actor {
+            Some(_) => Duration::zero(),
+            None => Duration::minutes(self.config.no_show_grace_minutes),
+        };
+        if !is_no_show(&reservation, now, grace) {
+            return Err(AppError::Conflict(format!(
+                "Reservation {} must be confirmed, not checked in and past its slot start",
+                reservation.reservation_code
+            )));
+        }
+
+        let cancelled_by = EmployeeInfo {
+            cancelled_at: Some(now),
+            ..actor.map(employee).unwrap_or_default()
+        };
+        let updated_at = bson::to_bson(&Utc::now())?;
+        let expected = doc! { "status": ReservationStatus::Confirmed.as_str(), "check_in_time": null };
+        let set = doc! {
+            "status": ReservationStatus::NoShow.as_str(),
+            "cancelled_by": bson::to_bson(&cancelled_by)?,
+            "cancellation_reason": NO_SHOW_REASON,
+            "updatedAt": updated_at.clone(),
+            "updatedAtWIB": updated_at,
+        };
+        let order = match reservation.order_id {
+            Some(order_oid) => self.order_repo.find_by_id(&order_oid).await?,
+            None => None,
+        };
+
+        // The status, the forfeit and the cancel land together or not at all
+        let (reservation_repo, payment_repo) = (&self.reservation_repo, &self.payment_repo);
+        let order_service = &self.order_service;
+        let (expected, set, order_ref, code) = (&expected, &set, order.as_ref(), &reservation.reservation_code);
+        let (reservation, forfeited, canceled) = with_transaction(&self.db, |mut session| async move {
+            let result = async {
+                let reservation = reservation_repo
+                    .update_if_with_session(id, expected.clone(), set.clone(), &mut session)
+                    .await?
+                    .ok_or_else(|| {
+                        AppError::Conflict(format!("Reservation {} was modified concurrently, please retry", code))
+                    })?;
+                let (mut forfeited, mut canceled) = (0, None);
+                if let Some(order) = order_ref {
+                    forfeited = payment_repo
+                        .forfeit_down_payments_with_session(&order.order_id, &mut session)
+                        .await?;
+                    if order.status != OrderStatus::Canceled {
+                        canceled = Some(
+                            order_service
+                                .cancel_with_session(
+                                    order,
+                                    actor,
+                                    NO_SHOW_REASON.to_string(),
+                                    actor.is_none(),
+                                    &mut session,
+                                )
+                                .await?,
+                        );
+                    }
+                }
+                Ok((reservation, forfeited, canceled))
+            }
+            .await;
+            (session, result)
+        })
+        .await?;
+
+        if let Some(canceled) = canceled {
+            self.order_service.finish_cancel(canceled, actor).await;
+        }
+        if order.is_some() {
+            info!(
+                "📅 Reservation {} is a no-show, {} down payment(s) forfeited",
+                reservation.reservation_code, forfeited
+            );
+        }
+
+        Ok(reservation)
+    }
+
+    /// Mark confirmed reservations whose guests are past the grace period as
+    /// no-shows. Returns how many were.
+    pub async fn forfeit_no_shows(&self, now: DateTime<Utc>) -> AppResult<u64> {
+        let grace = Duration::minutes(self.config.no_show_grace_minutes);
+        let candidates = self
+            .reservation_repo
+            .find_unattended_until(now, NO_SHOW_BATCH_SIZE)
+            .await?;
+
+        let mut marked = 0;
+        for reservation in candidates.iter().filter(|r| is_no_show(r, now, grace)) {
+            let Some(id) = reservation.id else { continue };
+            match self.mark_no_show(&id, None, now).await {
+                Ok(_) => marked += 1,
+                Err(AppError::Conflict(_)) => {}
+                Err(e) => warn!("Could not mark reservation {} as no-show: {}", reservation.reservation_code, e),
+            }
+        }
+        Ok(marked)
+    }
+
+    /// Settled down payments paid in `[from, to)` with what their orders still owe
+    pub async fn down_payment_balances(
+        &self,
+        from: Option<bson::DateTime>,
+        to: Option<bson::DateTime>,
+    ) -> AppResult<Vec<DownPaymentBalance>> {
+        let payments = self.payment_repo.find_settled_down_payments(from, to).await?;
+
+        let order_ids: Vec<String> = payments.iter().map(|p| p.order_id.clone()).collect();
+        let orders: HashMap<String, _> = self
+            .order_repo
+            .find_by_order_ids(&order_ids)
+            .await?
+            .into_iter()
+            .map(|o| (o.order_id.clone(), o))
+            .collect();
+        let reservation_ids: Vec<ObjectId> = orders.values().filter_map(|o| o.reservation_id()).collect();
+        let reservations: HashMap<ObjectId, Reservation> = self
+            .reservation_repo
+            .find_by_ids(&reservation_ids)
+            .await?
+            .into_iter()
+            .filter_map(|r| r.id.map(|id| (id, r)))
+            .collect();
+
+        Ok(payments
+            .into_iter()
+            .map(|payment| {
+                let order = orders.get(&payment.order_id);
+                let reservation = order
+                    .and_then(|o| o.reservation_id())
+                    .and_then(|id| reservations.get(&id));
+                let outstanding = match order {
+                    Some(order) if payment.forfeited_at.is_none() && order.status != OrderStatus::Canceled => {
+                        remaining_balance(order)
+                    }
+                    _ => Money::ZERO,
+                };
+
+                DownPaymentBalance {
+                    payment_id: payment.id,
+                    payment_code: payment.payment_code,
+                    order_id: payment.order_id,
+                    reservation_id: reservation.and_then(|r| r.id),
+                    reservation_code: reservation.map(|r| r.reservation_code.clone()),
+                    reservation_date: reservation.map(|r| r.reservation_date),
+                    reservation_time: reservation.map(|r| r.reservation_time.clone()),
+                    customer_name: reservation.map(|r| r.customer_name.clone()),
+                    reservation_status: reservation.map(|r| r.status),
+                    down_payment: payment.amount,
+                    order_total: order.map(|o| o.grand_total).unwrap_or(Money::ZERO),
+                    outstanding,
+                    paid_at: payment.paid_at,
+                    forfeited_at: payment.forfeited_at,
+                }
+            })
+            .collect())
+    }
+
+    async fn ensure_available(&self, reservation: &Reservation) -> AppResult<()> {
+        let held = self
+            .reservation_repo
+            .find_holding_tables(&reservation.table_id, reservation.id.as_ref())
+            .await?;
+        let conflicts = conflicting(reservation, &held)?;
+
+        match conflicts.first() {
+            None => Ok(()),
+            Some(other) => Err(AppError::Conflict(format!(
+                "Tables are already reserved by {} at {}",
+                other.reservation_code, other.reservation_time
+            ))),
+        }
+    }
+
+    /// Apply one step if the reservation is still as it was read
+    async fn step(&self, reservation: &Reservation, expected: Document, mut set: Document) -> AppResult<Reservation> {
+        let id = reservation
+            .id
+            .ok_or_else(|| AppError::Internal("Reservation has no ID".to_string()))?;
+        let now = bson::to_bson(&Utc::now())?;
+        set.insert("updatedAt", now.clone());
+        set.insert("updatedAtWIB", now);
+
+        self.reservation_repo
+            .update_if(&id, expected, set)
+            .await?
+            .ok_or_else(|| {
+                AppError::Conflict(format!(
+                    "Reservation {} was modified concurrently, please retry",
+                    reservation.reservation_code
+                ))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reservation(time: &str, minutes: i32, tables: Vec<ObjectId>) -> Reservation {
+        let mut r = Reservation::new(
+            "RSV".to_string(),
+            reservation_day("2024-05-01").unwrap(),
+            time.to_string(),
+            ObjectId::new(),
+            tables,
+            4,
+        );
+        r.duration_minutes = minutes;
+        r
+    }
+
+    #[test]
+    fn test_slot_start_is_wib() {
+        let r = reservation("19:30", 120, vec![]);
+        assert_eq!(slot_start(&r).unwrap().to_rfc3339(), "2024-05-01T12:30:00+00:00");
+    }
+
+    #[test]
+    fn test_down_payment_amount() {
+        let total = Money::from_rupiah(350_500);
+        assert_eq!(down_payment_amount(total, 30.0), Money::from_rupiah(105_150));
+        assert_eq!(down_payment_amount(total, 0.0), Money::ZERO);
+        assert_eq!(down_payment_amount(total, 150.0), total);
+    }
+
+    #[test]
+    fn test_is_no_show() {
+        let mut r = reservation("19:00", 120, vec![]);
+        let start = slot_start(&r).unwrap();
+        let grace = Duration::minutes(30);
+
+        // Pending reservations were never confirmed, so nobody is expected
+        assert!(!is_no_show(&r, start + Duration::hours(1), grace));
+
+        r.status = ReservationStatus::Confirmed;
+        assert!(!is_no_show(&r, start + Duration::minutes(29), grace));
+        assert!(is_no_show(&r, start + Duration::minutes(30), grace));
+
+        r.check_in_time = Some(start);
+        assert!(!is_no_show(&r, start + Duration::hours(1), grace));
+    }
+
+    #[test]
+    fn test_conflicting() {
+        let (t1, t2, t3) = (ObjectId::new(), ObjectId::new(), ObjectId::new());
+        let mut existing = vec![
+            reservation("18:00", 120, vec![t1]),
+            reservation("20:00", 60, vec![t2]),
+            reservation("19:00", 60, vec![t3]),
+        ];
+        for r in existing.iter_mut() {
+            r.id = Some(ObjectId::new());
+        }
+
+        // 19:00-21:00 on t1 and t2 overlaps both, t3 is not requested
+        let wanted = reservation("19:00", 120, vec![t1, t2]);
+        assert_eq!(conflicting(&wanted, &existing).unwrap().len(), 2);
+
+        // Starting exactly when the earlier booking ends is free
+        let wanted = reservation("20:00", 60, vec![t1]);
+        assert!(conflicting(&wanted, &existing).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,482 @@
+use std::sync::Arc;
+
+use bson::oid::ObjectId;
+use chrono::Utc;
+use tracing::{info, warn};
+
+use crate::common::Money;
+use crate::db::models::order_revision::PendingPaymentAdjusted;
+use crate::db::models::payment::Payment;
+use crate::db::models::{
+    DiscountAudit, Order, OrderItem, OrderRevision, OrderSnapshot, OrderStatus, PaymentAdjustment,
+    RevisionChanges, RevisionDelta, RevisionEffects, RevisionItemChange,
+};
+use crate::db::repositories::{
+    DiscountAuditRepository, OrderRepository, PaymentRepository, RevisionRepository,
+};
+use crate::db::{with_transaction, DbConnection};
+use crate::error::{AppError, AppResult};
+use crate::kafka::{events::OrderEvent, KafkaProducer};
+use crate::services::order_service::{amount_paid, refresh_payment_state, OrderActor};
+use crate::services::InventoryService;
+use crate::services::refund_service::{note_refund_on_tender, RefundService};
+
+fn item_change(index: usize, before: Option<&OrderItem>, after: Option<&OrderItem>) -> RevisionItemChange {
+    let item = after.or(before).expect("at least one side of a change");
+    let notes_before = before.map(|i| i.notes.clone());
+    let notes_after = after.map(|i| i.notes.clone());
+    let notes_changed = notes_before != notes_after;
+
+    RevisionItemChange {
+        item_index: index,
+        menu_item: item.menu_item,
+        name: item.menu_item_data.name.clone(),
+        quantity_before: before.map_or(0, |i| i.quantity),
+        quantity_after: after.map_or(0, |i| i.quantity),
+        subtotal_before: before.map_or(Money::ZERO, |i| i.subtotal),
+        subtotal_after: after.map_or(Money::ZERO, |i| i.subtotal),
+        notes_before: notes_before.filter(|_| notes_changed),
+        notes_after: notes_after.filter(|_| notes_changed),
+    }
+}
+
+/// Line-by-line diff of two versions of an order.
+///
+/// Lines are matched by index: removed lines keep their slot with a zero
+/// quantity, so indexes stay stable for refunds and later revisions.
+pub fn diff_items(before: &[OrderItem], after: &[OrderItem]) -> RevisionChanges {
+    let mut changes = RevisionChanges::default();
+
+    for (index, new) in after.iter().enumerate() {
+        match before.get(index) {
+            None => {
+                if new.quantity > 0 {
+                    changes.added.push(item_change(index, None, Some(new)));
+                }
+            }
+            Some(old) if old.quantity > 0 && new.quantity == 0 => {
+                changes.removed.push(item_change(index, Some(old), Some(new)));
+            }
+            Some(old) => {
+                if old.quantity != new.quantity
+                    || old.subtotal != new.subtotal
+                    || old.notes != new.notes
+                {
+                    changes.updated.push(item_change(index, Some(old), Some(new)));
+                }
+            }
+        }
+    }
+
+    changes
+}
+
+/// Change in the order's totals between two versions
+pub fn revision_delta(before: &Order, after: &Order) -> RevisionDelta {
+    let discount = |o: &Order| o.total_before_discount - o.total_after_discount;
+
+    RevisionDelta {
+        subtotal_delta: after.total_before_discount - before.total_before_discount,
+        discount_delta: discount(after) - discount(before),
+        tax_delta: after.total_tax - before.total_tax,
+        service_delta: after.total_service_fee - before.total_service_fee,
+        grand_delta: after.grand_total - before.grand_total,
+    }
+}
+
+fn snapshot(order: &Order) -> OrderSnapshot {
+    OrderSnapshot {
+        items: order.items.clone(),
+        applied_promos: order.applied_promos.clone(),
+        total_before_discount: order.total_before_discount,
+        total_after_discount: order.total_after_discount,
+        total_tax: order.total_tax,
+        total_service_fee: order.total_service_fee,
+        grand_total: order.grand_total,
+    }
+}
+
+/// Whether the order's bill has already been settled in full
+pub fn is_settled(order: &Order) -> bool {
+    matches!(
+        order.payment_status.as_deref(),
+        Some("Paid") | Some("Partially Refunded")
+    ) || (order.payment_status.is_none() && order.status == OrderStatus::Completed)
+}
+
+#[derive(Clone)]
+pub struct RevisionService {
+    db: Arc<DbConnection>,
+    order_repo: OrderRepository,
+    payment_repo: PaymentRepository,
+    revision_repo: RevisionRepository,
+    discount_audit_repo: DiscountAuditRepository,
+    refund_service: RefundService,
+    inventory_service: InventoryService,
+    kafka: Arc<KafkaProducer>,
+}
+
+impl RevisionService {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        db: Arc<DbConnection>,
+        order_repo: OrderRepository,
+        payment_repo: PaymentRepository,
+        revision_repo: RevisionRepository,
+        discount_audit_repo: DiscountAuditRepository,
+        refund_service: RefundService,
+        inventory_service: InventoryService,
+        kafka: Arc<KafkaProducer>,
+    ) -> Self {
+        Self {
+            db,
+            order_repo,
+            payment_repo,
+            revision_repo,
+            discount_audit_repo,
+            refund_service,
+            inventory_service,
+            kafka,
+        }
+    }
+
+    /// Revisions and payment adjustments of an order, oldest first
+    pub async fn timeline(&self, order: &Order) -> AppResult<(Vec<OrderRevision>, Vec<PaymentAdjustment>)> {
+        let order_oid = order
+            .id
+            .ok_or_else(|| AppError::Internal("Order has no ID".to_string()))?;
+
+        let revisions = self.revision_repo.find_by_order(&order_oid).await?;
+        let adjustments = self.revision_repo.find_adjustments_by_order(&order_oid).await?;
+        Ok((revisions, adjustments))
+    }
+
+    /// Persist an edited order together with its revision record.
+    ///
+    /// `revised` must already be re-priced. Any change to the grand total is
+    /// settled against the order's payments: an unpaid order has its pending
+    /// payment resized, a paid order gets a new pending adjustment charge or is
+    /// refunded the difference through [`RefundService`]. On a confirmed order
+    /// the ingredients of added lines leave stock and those of removed lines
+    /// come back. Everything, including the `discount_audits` behind the
+    /// change, is written in one transaction.
+    pub async fn record_revision(
+        &self,
+        before: &Order,
+        mut revised: Order,
+        editor: &OrderActor,
+        reason: Option<String>,
+        discount_audits: &[DiscountAudit],
+    ) -> AppResult<(Order, OrderRevision)> {
+        let order_oid = before
+            .id
+            .ok_or_else(|| AppError::Internal("Order has no ID".to_string()))?;
+
+        let changes = diff_items(&before.items, &revised.items);
+        let delta = revision_delta(before, &revised);
+        if changes.is_empty() && delta.grand_delta.is_zero() {
+            return Err(AppError::Validation("Revision does not change the order".to_string()));
+        }
+
+        let now = mongodb::bson::DateTime::now();
+        let revision_oid = ObjectId::new();
+        let settled = is_settled(before);
+
+        let mut effects = RevisionEffects::default();
+        let mut adjustments = Vec::new();
+        let mut new_payment = None;
+        let mut pending_adjustment = None;
+        let mut refund = None;
+
+        if revised.is_split_payment && !settled {
+            // Tenders already taken stay on the order; only the balance moves
+            if amount_paid(&revised) > revised.grand_total {
+                return Err(AppError::Validation(format!(
+                    "Revised total {} is below the {} already paid",
+                    revised.grand_total,
+                    amount_paid(&revised)
+                )));
+            }
+            refresh_payment_state(&mut revised);
+        } else if !delta.grand_delta.is_zero() {
+            let amount = delta.grand_delta.abs();
+            let payments = self.payment_repo.find_by_order_id(&before.order_id).await?;
+
+            if !settled {
+                let pending = payments
+                    .iter()
+                    .rev()
+                    .find(|p| p.status == "pending" && p.direction.as_deref() != Some("refund"));
+
+                if let Some(payment) = pending {
+                    let payment_oid = payment
+                        .id
+                        .ok_or_else(|| AppError::Internal("Payment has no ID".to_string()))?;
+                    if (payment.amount + delta.grand_delta).is_negative() {
+                        return Err(AppError::Validation(
+                            "Revision would make the pending payment negative".to_string(),
+                        ));
+                    }
+
+                    let increase = delta.grand_delta.is_positive();
+                    adjustments.push(PaymentAdjustment {
+                        id: Some(ObjectId::new()),
+                        order_id: order_oid,
+                        payment_id: Some(payment_oid),
+                        revision_id: revision_oid,
+                        kind: if increase { "increase_pending" } else { "decrease_pending" }.to_string(),
+                        direction: if increase { "charge" } else { "refund" }.to_string(),
+                        amount,
+                        note: reason.clone(),
+                        created_at: Some(now),
+                        updated_at: Some(now),
+                    });
+                    effects.pending_payment_adjusted.push(PendingPaymentAdjusted {
+                        payment_id: payment_oid,
+                        amount_delta: delta.grand_delta,
+                    });
+                    pending_adjustment = Some(payment_oid);
+                }
+            } else if delta.grand_delta.is_positive() {
+                // The extra is charged on its own; the order's payment endpoint
+                // collects only this charge
+                let original = payments.iter().rev().find(|p| {
+                    p.direction.as_deref() != Some("refund")
+                        && matches!(p.status.as_str(), "settlement" | "capture" | "paid")
+                });
+                let method = original
+                    .map(|p| p.method.clone())
+                    .or_else(|| before.payment_method.clone())
+                    .unwrap_or_else(|| "Cash".to_string());
+
+                let payment_oid = ObjectId::new();
+                let adjustment_oid = ObjectId::new();
+                new_payment = Some(Payment {
+                    id: Some(payment_oid),
+                    order_id: before.order_id.clone(),
+                    method,
+                    status: "pending".to_string(),
+                    payment_type: "Adjustment".to_string(),
+                    amount,
+                    total_amount: Some(amount),
+                    related_payment_id: original.and_then(|p| p.id),
+                    is_adjustment: true,
+                    direction: Some("charge".to_string()),
+                    revision_id: Some(revision_oid),
+                    adjustment_id: Some(adjustment_oid),
+                    notes: reason.clone(),
+                    created_at: now,
+                    updated_at: now,
+                    ..Payment::default()
+                });
+                adjustments.push(PaymentAdjustment {
+                    id: Some(adjustment_oid),
+                    order_id: order_oid,
+                    payment_id: Some(payment_oid),
+                    revision_id: revision_oid,
+                    kind: "new_pending".to_string(),
+                    direction: "charge".to_string(),
+                    amount,
+                    note: reason.clone(),
+                    created_at: Some(now),
+                    updated_at: Some(now),
+                });
+                effects.new_pending_payment_id = Some(payment_oid);
+                revised.payment_status = Some("Partial".to_string());
+            } else {
+                // The difference is paid back like any refund, at the gateway first
+                let mut issued = self
+                    .refund_service
+                    .issue_revision_refund(before, amount, reason.clone(), editor)
+                    .await?;
+                for payment in &mut issued.payments {
+                    let adjustment_oid = ObjectId::new();
+                    payment.revision_id = Some(revision_oid);
+                    payment.adjustment_id = Some(adjustment_oid);
+
+                    adjustments.push(PaymentAdjustment {
+                        id: Some(adjustment_oid),
+                        order_id: order_oid,
+                        payment_id: payment.id,
+                        revision_id: revision_oid,
+                        kind: "settled_refund".to_string(),
+                        direction: "refund".to_string(),
+                        amount: payment.amount,
+                        note: reason.clone(),
+                        created_at: Some(now),
+                        updated_at: Some(now),
+                    });
+                }
+                effects.refund_payment_id = issued.payments.first().and_then(|p| p.id);
+
+                revised.total_refunded += amount;
+                revised.payment_status = Some(
+                    if revised.grand_total.is_zero() { "Refunded" } else { "Partially Refunded" }.to_string(),
+                );
+                let refund_reason = issued.refund.refund_reason.clone();
+                note_refund_on_tender(&mut revised, amount, &refund_reason, editor, now);
+                refund = Some(issued);
+            }
+        }
+
+        revised.revision = before.revision + 1;
+        revised.updated_at_wib = now;
+
+        let revision = OrderRevision {
+            id: Some(revision_oid),
+            order_id: order_oid,
+            order_code: before.order_id.clone(),
+            revision_number: revised.revision,
+            created_by: editor.id,
+            created_by_name: editor.name.clone(),
+            reason,
+            changes,
+            delta,
+            effects,
+            snapshot: snapshot(&revised),
+            created_at: Some(now),
+            updated_at: Some(now),
+        };
+
+        // Stock moves only for orders whose ingredients have already left stock
+        let revises_stock = before.status.is_confirmed() && !before.stock_rolled_back;
+        let persisted = {
+            let (revision_ref, adjustments, revised_ref) = (&revision, &adjustments, &revised);
+            let (new_payment, refund_ref) = (new_payment.as_ref(), refund.as_ref());
+            with_transaction(&self.db, |mut session| {
+                let mut revised = revised_ref.clone();
+                async move {
+                    let result = async {
+                        self.revision_repo.create_with_session(revision_ref, &mut session).await?;
+                        for adjustment in adjustments {
+                            self.revision_repo
+                                .create_adjustment_with_session(adjustment, &mut session)
+                                .await?;
+                        }
+                        if let Some(payment_oid) = pending_adjustment {
+                            self.payment_repo
+                                .adjust_amount_with_session(&payment_oid, revision_ref.delta.grand_delta, &mut session)
+                                .await?;
+                        }
+                        if let Some(payment) = new_payment {
+                            self.payment_repo.create_with_session(payment.clone(), &mut session).await?;
+                        }
+                        if let Some(issued) = refund_ref {
+                            self.refund_service.write_with_session(issued, &mut session).await?;
+                        }
+                        self.discount_audit_repo
+                            .create_many_with_session(discount_audits, &mut session)
+                            .await?;
+                        let sold = if revises_stock {
+                            self.inventory_service
+                                .revise_order_stock(
+                                    &order_oid,
+                                    &before.order_id,
+                                    &before.items,
+                                    &mut revised.items,
+                                    Some(editor.name.clone()),
+                                    &mut session,
+                                )
+                                .await?
+                        } else {
+                            Vec::new()
+                        };
+                        self.order_repo.update_with_session(&revised, &mut session).await?;
+                        Ok((revised, sold))
+                    }
+                    .await;
+                    (session, result)
+                }
+            })
+            .await
+        };
+        let (revised, sold) = match &refund {
+            Some(issued) => self.refund_service.check_persisted(issued, persisted)?,
+            None => persisted?,
+        };
+        self.inventory_service.refresh_menu_stocks(&sold).await;
+
+        info!(
+            "✏️ Order {} revised to #{} by {} (grand total {:+})",
+            revised.order_id, revision.revision_number, editor.name, revision.delta.grand_delta.rupiah()
+        );
+
+        let event = OrderEvent::Updated {
+            order_id: revised.order_id.clone(),
+            status: revised.status.to_string(),
+            timestamp: Utc::now(),
+        };
+        if let Err(e) = self.kafka.publish_order_event(&revised.order_id, &event).await {
+            warn!("Failed to publish order event for {}: {}", revised.order_id, e);
+        }
+        if let Some(issued) = &refund {
+            self.refund_service.publish_refunded(issued).await;
+        }
+
+        Ok((revised, revision))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::models::MenuItemData;
+
+    fn item(name: &str, quantity: i32, subtotal: i64) -> OrderItem {
+        OrderItem {
+            menu_item_data: MenuItemData {
+                name: name.to_string(),
+                ..MenuItemData::default()
+            },
+            quantity,
+            subtotal: Money::from_rupiah(subtotal),
+            ..OrderItem::default()
+        }
+    }
+
+    #[test]
+    fn test_diff_items_classifies_changes() {
+        let before = vec![item("Latte", 2, 60_000), item("Croissant", 1, 40_000), item("Tea", 1, 20_000)];
+        let after = vec![
+            item("Latte", 3, 90_000),
+            item("Croissant", 0, 0),
+            item("Tea", 1, 20_000),
+            item("Cake", 1, 35_000),
+        ];
+
+        let changes = diff_items(&before, &after);
+        assert_eq!(changes.updated.len(), 1);
+        assert_eq!(changes.updated[0].item_index, 0);
+        assert_eq!(changes.updated[0].quantity_before, 2);
+        assert_eq!(changes.updated[0].quantity_after, 3);
+        assert_eq!(changes.removed.len(), 1);
+        assert_eq!(changes.removed[0].name, "Croissant");
+        assert_eq!(changes.added.len(), 1);
+        assert_eq!(changes.added[0].item_index, 3);
+        assert!(diff_items(&before, &before).is_empty());
+    }
+
+    #[test]
+    fn test_revision_delta() {
+        let before = Order {
+            total_before_discount: Money::from_rupiah(100_000),
+            total_after_discount: Money::from_rupiah(90_000),
+            total_tax: Money::from_rupiah(9_000),
+            grand_total: Money::from_rupiah(99_000),
+            ..Order::default()
+        };
+        let after = Order {
+            total_before_discount: Money::from_rupiah(130_000),
+            total_after_discount: Money::from_rupiah(117_000),
+            total_tax: Money::from_rupiah(11_700),
+            grand_total: Money::from_rupiah(128_700),
+            ..Order::default()
+        };
+
+        let delta = revision_delta(&before, &after);
+        assert_eq!(delta.subtotal_delta, Money::from_rupiah(30_000));
+        assert_eq!(delta.discount_delta, Money::from_rupiah(3_000));
+        assert_eq!(delta.tax_delta, Money::from_rupiah(2_700));
+        assert_eq!(delta.grand_delta, Money::from_rupiah(29_700));
+    }
+}
@@ -0,0 +1,471 @@
+use bson::{doc, oid::ObjectId};
+use chrono_tz::Asia::Jakarta;
+use tracing::info;
+
+use crate::common::Money;
+use crate::db::models::{
+    CashMovement, CashMovementType, CashierShift, Order, OrderStatus, Refund, ShiftCashSummary, ShiftStatus,
+};
+use crate::db::repositories::{CashierShiftRepository, OrderRepository, RefundRepository, ShiftFilter};
+use crate::error::{AppError, AppResult};
+use crate::services::order_service::{is_cash, OrderActor};
+
+/// Characters per line on the 58mm receipt printers at the counter
+const RECEIPT_WIDTH: usize = 32;
+/// Most shifts returned by one listing
+const LIST_LIMIT: i64 = 200;
+
+/// Cash `cashier` took on `order` in `[from, to)`: each cash tender counts what
+/// was handed over minus the change given back.
+///
+/// Tenders recorded one by one carry who took them and when. Orders paid in
+/// full at creation do not, so their tenders belong to the order's cashier at
+/// the time the order was created. Refunded tenders still count: the cash paid
+/// back is taken off separately, see [`cash_refunded`].
+pub fn cash_collected(order: &Order, cashier: &ObjectId, from: bson::DateTime, to: bson::DateTime) -> Money {
+    if order.status == OrderStatus::Canceled {
+        return Money::ZERO;
+    }
+
+    order
+        .payments
+        .iter()
+        .filter(|tender| is_cash(&tender.payment_method))
+        .filter(|tender| {
+            let settled = match tender.status.as_str() {
+                "completed" | "refunded" => true,
+                "failed" => false,
+                _ => tender.processed_by.is_none() && order.payment_status.as_deref() == Some("Paid"),
+            };
+            let taken_by = tender.processed_by.or(order.cashier_id);
+            let taken_at = tender.processed_at.unwrap_or(order.created_at_wib);
+            settled && taken_by.as_ref() == Some(cashier) && from <= taken_at && taken_at < to
+        })
+        .map(|tender| {
+            match tender.payment_details.as_ref().and_then(|d| d.cash_tendered.map(|t| (t, d.change))) {
+                Some((tendered, change)) => tendered - change.unwrap_or(Money::ZERO),
+                None => tender.amount,
+            }
+        })
+        .sum()
+}
+
+/// Cash paid back to customers out of the drawer by the given refunds: the
+/// cash part of split refunds, or the whole of older unsplit cash refunds
+pub fn cash_refunded(refunds: &[Refund]) -> Money {
+    refunds
+        .iter()
+        .filter(|refund| refund.status == "processed")
+        .map(|refund| match refund.tenders.as_slice() {
+            [] if is_cash(&refund.refund_method) => refund.total_refund_amount,
+            [] => Money::ZERO,
+            tenders => tenders.iter().filter(|t| is_cash(&t.method)).map(|t| t.amount).sum(),
+        })
+        .sum()
+}
+
+/// Cash totals of `shift` given the cash sales taken and refunds paid during it
+pub fn cash_summary(
+    shift: &CashierShift,
+    cash_sales: Money,
+    cash_order_count: u32,
+    cash_refunds: Money,
+) -> ShiftCashSummary {
+    let cash_in = shift.movement_total(CashMovementType::In);
+    let cash_out = shift.movement_total(CashMovementType::Out);
+
+    ShiftCashSummary {
+        opening_float: shift.opening_float,
+        cash_sales,
+        cash_order_count,
+        cash_in,
+        cash_out,
+        cash_refunds,
+        expected_cash: shift.opening_float + cash_sales + cash_in - cash_out - cash_refunds,
+        counted_cash: None,
+        variance: None,
+    }
+}
+
+/// Rupiah with thousands separators, e.g. "Rp 1.250.000" or "-Rp 10.000"
+fn rupiah(amount: Money) -> String {
+    let digits = amount.rupiah().unsigned_abs().to_string();
+    let mut grouped = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push('.');
+        }
+        grouped.push(c);
+    }
+    let sign = if amount.is_negative() { "-" } else { "" };
+    format!("{}Rp {}", sign, grouped)
+}
+
+fn wib(time: bson::DateTime) -> String {
+    time.to_chrono().with_timezone(&Jakarta).format("%Y-%m-%d %H:%M").to_string()
+}
+
+fn receipt_row(label: &str, value: &str) -> String {
+    let pad = RECEIPT_WIDTH.saturating_sub(label.chars().count() + value.chars().count()).max(1);
+    format!("{}{}{}", label, " ".repeat(pad), value)
+}
+
+/// The shift summary as lines of a receipt, for the counter printer
+pub fn receipt_lines(shift: &CashierShift, summary: &ShiftCashSummary) -> Vec<String> {
+    let rule = "-".repeat(RECEIPT_WIDTH);
+    let title = match shift.status {
+        ShiftStatus::Open => "SHIFT SUMMARY (OPEN)",
+        ShiftStatus::Closed => "SHIFT CLOSING",
+    };
+
+    let mut lines = vec![
+        format!("{:^width$}", title, width = RECEIPT_WIDTH),
+        rule.clone(),
+        receipt_row("Cashier", &shift.cashier_name),
+        receipt_row("Opened", &wib(shift.opened_at)),
+    ];
+    if let Some(closed_at) = shift.closed_at {
+        lines.push(receipt_row("Closed", &wib(closed_at)));
+    }
+    lines.push(rule.clone());
+    lines.push(receipt_row("Opening float", &rupiah(summary.opening_float)));
+    lines.push(receipt_row("Cash sales", &rupiah(summary.cash_sales)));
+    lines.push(receipt_row("Cash orders", &summary.cash_order_count.to_string()));
+    lines.push(receipt_row("Cash in", &rupiah(summary.cash_in)));
+    lines.push(receipt_row("Cash out", &rupiah(summary.cash_out)));
+    lines.push(receipt_row("Cash refunds", &rupiah(summary.cash_refunds)));
+    lines.push(rule.clone());
+    lines.push(receipt_row("Expected cash", &rupiah(summary.expected_cash)));
+    if let Some(counted) = summary.counted_cash {
+        lines.push(receipt_row("Counted cash", &rupiah(counted)));
+    }
+    if let Some(variance) = summary.variance {
+        lines.push(receipt_row("Variance", &rupiah(variance)));
+    }
+    lines.push(rule);
+
+    lines
+}
+
+/// Cashier shifts: the float a drawer starts with, cash moved in and out of it,
+/// and the count that closes it against the cash sales taken and refunds paid
+/// meanwhile
+#[derive(Clone)]
+pub struct ShiftService {
+    shift_repo: CashierShiftRepository,
+    order_repo: OrderRepository,
+    refund_repo: RefundRepository,
+}
+
+impl ShiftService {
+    pub fn new(shift_repo: CashierShiftRepository, order_repo: OrderRepository, refund_repo: RefundRepository) -> Self {
+        Self { shift_repo, order_repo, refund_repo }
+    }
+
+    pub async fn find(&self, id: &ObjectId) -> AppResult<CashierShift> {
+        self.shift_repo
+            .find_by_id(id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Shift not found".to_string()))
+    }
+
+    pub async fn list(&self, filter: &ShiftFilter) -> AppResult<Vec<CashierShift>> {
+        self.shift_repo.find(filter, LIST_LIMIT).await
+    }
+
+    pub async fn current(&self, cashier: &ObjectId) -> AppResult<Option<CashierShift>> {
+        self.shift_repo.find_open_for_cashier(cashier).await
+    }
+
+    /// The cashier's open shift, at the outlet when it is known; orders and
+    /// cash cannot be taken without one
+    pub async fn require_open(&self, cashier: &ObjectId, outlet: Option<&ObjectId>) -> AppResult<CashierShift> {
+        let shift = self.current(cashier).await?.ok_or_else(|| {
+            AppError::Conflict("Cashier has no open shift, open one before taking orders".to_string())
+        })?;
+        if outlet.is_some_and(|outlet| shift.outlet_id != *outlet) {
+            return Err(AppError::Conflict(
+                "Cashier's open shift is at another outlet".to_string(),
+            ));
+        }
+        Ok(shift)
+    }
+
+    /// Start a shift for `actor` with `opening_float` in the drawer
+    pub async fn open(
+        &self,
+        outlet: ObjectId,
+        device: Option<ObjectId>,
+        opening_float: Money,
+        notes: Option<String>,
+        actor: &OrderActor,
+    ) -> AppResult<CashierShift> {
+        if opening_float.is_negative() {
+            return Err(AppError::Validation("openingFloat cannot be negative".to_string()));
+        }
+        if let Some(open) = self.current(&actor.id).await? {
+            return Err(AppError::Conflict(format!(
+                "{} already has a shift open since {}",
+                actor.name,
+                wib(open.opened_at)
+            )));
+        }
+
+        let mut shift = CashierShift::open(outlet, actor.id, actor.name.clone(), opening_float, bson::DateTime::now());
+        shift.device_id = device;
+        shift.notes = notes.filter(|n| !n.trim().is_empty());
+        shift.id = Some(self.shift_repo.create(&shift).await?);
+
+        info!("🧾 Shift opened by {} with a float of {}", actor.name, opening_float);
+        Ok(shift)
+    }
+
+    /// Record cash put into or taken out of the drawer outside of sales
+    pub async fn record_movement(
+        &self,
+        id: &ObjectId,
+        movement_type: CashMovementType,
+        amount: Money,
+        reason: String,
+        actor: &OrderActor,
+    ) -> AppResult<CashierShift> {
+        if !amount.is_positive() {
+            return Err(AppError::Validation("amount must be greater than zero".to_string()));
+        }
+        if reason.trim().is_empty() {
+            return Err(AppError::Validation("reason is required".to_string()));
+        }
+
+        let movement = CashMovement {
+            movement_type,
+            amount,
+            reason,
+            recorded_by: actor.id,
+            recorded_by_name: actor.name.clone(),
+            recorded_at: bson::DateTime::now(),
+        };
+        self.shift_repo
+            .push_movement(id, &movement)
+            .await?
+            .ok_or_else(|| AppError::Conflict("Shift is not open".to_string()))
+    }
+
+    /// Cash totals of a shift: frozen at closing, or so far for an open shift
+    pub async fn summary(&self, shift: &CashierShift) -> AppResult<ShiftCashSummary> {
+        match (&shift.summary, shift.closed_at) {
+            (Some(summary), Some(_)) => Ok(summary.clone()),
+            (_, closed_at) => self.compute_summary(shift, closed_at.unwrap_or_else(bson::DateTime::now)).await,
+        }
+    }
+
+    async fn compute_summary(&self, shift: &CashierShift, until: bson::DateTime) -> AppResult<ShiftCashSummary> {
+        let orders = self
+            .order_repo
+            .find_tendered_by_cashier(&shift.cashier_id, shift.opened_at, until)
+            .await?;
+
+        let mut cash_sales = Money::ZERO;
+        let mut cash_order_count = 0;
+        for order in &orders {
+            let cash = cash_collected(order, &shift.cashier_id, shift.opened_at, until);
+            if cash.is_positive() {
+                cash_sales += cash;
+                cash_order_count += 1;
+            }
+        }
+
+        let refunds = self
+            .refund_repo
+            .find_processed_by(&shift.cashier_id, shift.opened_at, until)
+            .await?;
+
+        Ok(cash_summary(shift, cash_sales, cash_order_count, cash_refunded(&refunds)))
+    }
+
+    /// Close a shift with the cash counted in the drawer, recording how far it is
+    /// from what the drawer should hold
+    pub async fn close(
+        &self,
+        id: &ObjectId,
+        counted_cash: Money,
+        notes: Option<String>,
+        actor: &OrderActor,
+    ) -> AppResult<CashierShift> {
+        if counted_cash.is_negative() {
+            return Err(AppError::Validation("countedCash cannot be negative".to_string()));
+        }
+        let shift = self.find(id).await?;
+        if shift.status != ShiftStatus::Open {
+            return Err(AppError::Conflict("Shift is already closed".to_string()));
+        }
+
+        let closed_at = bson::DateTime::now();
+        let mut summary = self.compute_summary(&shift, closed_at).await?;
+        let variance = counted_cash - summary.expected_cash;
+        summary.counted_cash = Some(counted_cash);
+        summary.variance = Some(variance);
+
+        let mut set = doc! {
+            "status": ShiftStatus::Closed.as_str(),
+            "closedAt": closed_at,
+            "closedBy": actor.id,
+            "closedByName": &actor.name,
+            "summary": bson::to_bson(&summary)?,
+        };
+        if let Some(notes) = notes.filter(|n| !n.trim().is_empty()) {
+            set.insert("closingNotes", notes);
+        }
+        let shift = self
+            .shift_repo
+            .update_open(id, set)
+            .await?
+            .ok_or_else(|| AppError::Conflict("Shift was closed concurrently".to_string()))?;
+
+        info!(
+            "🧾 Shift of {} closed by {}: expected {}, counted {}, variance {}",
+            shift.cashier_name, actor.name, summary.expected_cash, counted_cash, variance
+        );
+        Ok(shift)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::models::order::{PaymentDetails, SplitPayment};
+    use crate::db::models::RefundTender;
+
+    fn cash(amount: i64, tendered: i64, status: &str, by: Option<ObjectId>, at: bson::DateTime) -> SplitPayment {
+        SplitPayment {
+            payment_method: "Cash".to_string(),
+            amount: Money::from_rupiah(amount),
+            payment_details: Some(PaymentDetails {
+                cash_tendered: Some(Money::from_rupiah(tendered)),
+                change: Some(Money::from_rupiah(tendered - amount)),
+                ..PaymentDetails::default()
+            }),
+            status: status.to_string(),
+            processed_by: by,
+            processed_at: by.map(|_| at),
+            ..SplitPayment::default()
+        }
+    }
+
+    #[test]
+    fn test_cash_collected() {
+        let cashier = ObjectId::new();
+        let other = ObjectId::new();
+        let opened = bson::DateTime::from_millis(1_700_000_000_000);
+        let during = bson::DateTime::from_millis(opened.timestamp_millis() + 60_000);
+        let closed = bson::DateTime::from_millis(opened.timestamp_millis() + 3_600_000);
+
+        // Split tenders count for whoever took them, net of change
+        let mut order = Order {
+            status: OrderStatus::Completed,
+            cashier_id: Some(other),
+            created_at_wib: during,
+            payments: vec![
+                cash(30_000, 50_000, "completed", Some(cashier), during),
+                cash(20_000, 20_000, "completed", Some(other), during),
+                SplitPayment {
+                    payment_method: "QRIS".to_string(),
+                    amount: Money::from_rupiah(10_000),
+                    status: "completed".to_string(),
+                    processed_by: Some(cashier),
+                    processed_at: Some(during),
+                    ..SplitPayment::default()
+                },
+            ],
+            ..Order::default()
+        };
+        assert_eq!(cash_collected(&order, &cashier, opened, closed), Money::from_rupiah(30_000));
+        assert_eq!(cash_collected(&order, &cashier, closed, closed), Money::ZERO);
+
+        // Orders paid at creation belong to their cashier
+        order.cashier_id = Some(cashier);
+        order.payment_status = Some("Paid".to_string());
+        order.payments = vec![cash(45_000, 100_000, "", None, during)];
+        assert_eq!(cash_collected(&order, &cashier, opened, closed), Money::from_rupiah(45_000));
+
+        // A refunded tender was still taken; the payout is counted on its own
+        order.payments[0].status = "refunded".to_string();
+        assert_eq!(cash_collected(&order, &cashier, opened, closed), Money::from_rupiah(45_000));
+
+        order.status = OrderStatus::Canceled;
+        assert_eq!(cash_collected(&order, &cashier, opened, closed), Money::ZERO);
+    }
+
+    #[test]
+    fn test_cash_refunded() {
+        let refund = |method: &str, amount: i64, status: &str| Refund {
+            id: None,
+            refund_id: "REF-1".to_string(),
+            order_id: "ORD-1".to_string(),
+            order: ObjectId::new(),
+            user_id: None,
+            requested_by: "Budi".to_string(),
+            refund_type: "partial".to_string(),
+            refund_items: vec![],
+            total_refund_amount: Money::from_rupiah(amount),
+            refund_reason: "Wrong item".to_string(),
+            refund_reason_description: None,
+            status: status.to_string(),
+            processed_by: None,
+            processed_at: None,
+            refund_method: method.to_string(),
+            original_payment_method: None,
+            gateway_refund_key: None,
+            tenders: vec![],
+            created_at: None,
+            updated_at: None,
+        };
+        let tender = |method: &str, amount: i64| RefundTender {
+            method: method.to_string(),
+            amount: Money::from_rupiah(amount),
+            payment_id: None,
+            gateway_refund_key: None,
+        };
+        let mut split = refund("Split", 30_000, "processed");
+        split.tenders = vec![tender("QRIS", 20_000), tender("Cash", 10_000)];
+        let refunds = vec![
+            refund("Cash", 15_000, "processed"),
+            refund("QRIS", 40_000, "processed"),
+            refund("Cash", 5_000, "pending"),
+            split,
+        ];
+        assert_eq!(cash_refunded(&refunds), Money::from_rupiah(25_000));
+
+        let now = bson::DateTime::from_millis(1_700_000_000_000);
+        let shift = CashierShift::open(ObjectId::new(), ObjectId::new(), "Budi".to_string(), Money::from_rupiah(500_000), now);
+        let summary = cash_summary(&shift, Money::from_rupiah(100_000), 2, cash_refunded(&refunds));
+        assert_eq!(summary.expected_cash, Money::from_rupiah(575_000));
+    }
+
+    #[test]
+    fn test_summary_and_receipt() {
+        let cashier = OrderActor { id: ObjectId::new(), name: "Budi".to_string() };
+        let now = bson::DateTime::from_millis(1_700_000_000_000);
+        let mut shift = CashierShift::open(ObjectId::new(), cashier.id, cashier.name.clone(), Money::from_rupiah(500_000), now);
+        for (movement_type, amount) in [(CashMovementType::In, 100_000), (CashMovementType::Out, 50_000)] {
+            shift.movements.push(CashMovement {
+                movement_type,
+                amount: Money::from_rupiah(amount),
+                reason: "Change".to_string(),
+                recorded_by: cashier.id,
+                recorded_by_name: cashier.name.clone(),
+                recorded_at: now,
+            });
+        }
+
+        let mut summary = cash_summary(&shift, Money::from_rupiah(1_250_000), 12, Money::ZERO);
+        assert_eq!(summary.expected_cash, Money::from_rupiah(1_800_000));
+
+        summary.counted_cash = Some(Money::from_rupiah(1_790_000));
+        summary.variance = Some(Money::from_rupiah(-10_000));
+        let lines = receipt_lines(&shift, &summary);
+
+        assert!(lines.iter().all(|l| l.chars().count() <= RECEIPT_WIDTH));
+        assert!(lines.contains(&receipt_row("Expected cash", "Rp 1.800.000")));
+        assert!(lines.contains(&receipt_row("Variance", "-Rp 10.000")));
+    }
+}
@@ -0,0 +1,212 @@
+use chrono::{DateTime, Duration, Utc};
+use tracing::{error, info, warn};
+
+use crate::db::models::{Order, OrderPayment, OrderStatus};
+use crate::db::repositories::{OrderRepository, PaymentRepository};
+use crate::error::{AppError, AppResult};
+use crate::services::midtrans_client::format_midtrans_time;
+use crate::services::order_service::OrderService;
+use crate::services::payment_service::{charge_expired, is_gateway_charge};
+use crate::services::ReservationService;
+use crate::utils::LockUtil;
+
+/// Gateways may still report a payment shortly after its expiry; give them this long first
+const EXPIRY_GRACE_MINUTES: i64 = 5;
+/// A payment is written just before its order, so only older payments can be orphans
+const ORPHAN_GRACE_MINUTES: i64 = 15;
+/// Orphan checks look this far back; older payments were checked on earlier runs
+const ORPHAN_LOOKBACK_HOURS: i64 = 24;
+/// Records handled per step and run; the rest wait for the next run
+const BATCH_SIZE: i64 = 200;
+
+/// What one sweep changed
+#[derive(Debug, Default)]
+pub struct SweepReport {
+    pub expired_payments: u64,
+    pub canceled_orders: u64,
+    pub orphaned_payments: u64,
+    pub no_shows: u64,
+}
+
+/// Whether a pending online order should be canceled for going unpaid.
+///
+/// Orders with a gateway charge the customer can still complete are left alone.
+pub fn is_abandoned(order: &Order, pending: &[OrderPayment], now: DateTime<Utc>, window: Duration) -> bool {
+    matches!(order.source.as_str(), "Web" | "App")
+        && order.status == OrderStatus::Pending
+        && order.payment_status.as_deref() != Some("Paid")
+        && order.order_type != "Reservation"
+        && !order.is_open_bill
+        && order.created_at_wib.to_chrono() + window <= now
+        && !pending
+            .iter()
+            .any(|payment| is_gateway_charge(payment) && !charge_expired(payment, now))
+}
+
+/// Periodic cleanup of gateway payments and online orders nobody finished,
+/// and of reservations nobody came to
+#[derive(Clone)]
+pub struct SweeperService {
+    payment_repo: PaymentRepository,
+    order_repo: OrderRepository,
+    order_service: OrderService,
+    reservation_service: ReservationService,
+    lock_util: LockUtil,
+    unpaid_order_window: Duration,
+}
+
+impl SweeperService {
+    pub fn new(
+        payment_repo: PaymentRepository,
+        order_repo: OrderRepository,
+        order_service: OrderService,
+        reservation_service: ReservationService,
+        lock_util: LockUtil,
+        unpaid_order_minutes: i64,
+    ) -> Self {
+        Self {
+            payment_repo,
+            order_repo,
+            order_service,
+            reservation_service,
+            lock_util,
+            unpaid_order_window: Duration::minutes(unpaid_order_minutes),
+        }
+    }
+
+    /// Run every step; a failing step is logged and does not stop the others
+    pub async fn sweep(&self, now: DateTime<Utc>) -> SweepReport {
+        let mut report = SweepReport::default();
+
+        match self.expire_payments(now).await {
+            Ok(count) => report.expired_payments = count,
+            Err(e) => error!("Expiring payments failed: {}", e),
+        }
+        match self.cancel_abandoned_orders(now).await {
+            Ok(count) => report.canceled_orders = count,
+            Err(e) => error!("Canceling abandoned orders failed: {}", e),
+        }
+        match self.flag_orphaned_payments(now).await {
+            Ok(count) => report.orphaned_payments = count,
+            Err(e) => error!("Flagging orphaned payments failed: {}", e),
+        }
+        match self.reservation_service.forfeit_no_shows(now).await {
+            Ok(count) => report.no_shows = count,
+            Err(e) => error!("Marking reservation no-shows failed: {}", e),
+        }
+
+        report
+    }
+
+    /// Mark pending gateway payments past their `expiry_time` as expired
+    pub async fn expire_payments(&self, now: DateTime<Utc>) -> AppResult<u64> {
+        let cutoff = format_midtrans_time(now - Duration::minutes(EXPIRY_GRACE_MINUTES));
+        let payments = self.payment_repo.find_expired_pending(&cutoff, BATCH_SIZE).await?;
+
+        let mut expired = 0;
+        for payment in payments {
+            let Some(id) = payment.id else { continue };
+            if self.payment_repo.mark_expired(&id).await? {
+                expired += 1;
+                info!(
+                    "⌛ Payment {} for order {} expired at {}",
+                    payment.payment_code.as_deref().unwrap_or("-"),
+                    payment.order_id,
+                    payment.expiry_time.as_deref().unwrap_or("-")
+                );
+            }
+        }
+        Ok(expired)
+    }
+
+    /// Cancel Web and App orders left unpaid for longer than the configured window,
+    /// giving back their stock and table
+    pub async fn cancel_abandoned_orders(&self, now: DateTime<Utc>) -> AppResult<u64> {
+        let cutoff = bson::DateTime::from_chrono(now - self.unpaid_order_window);
+        let orders = self.order_repo.find_unpaid_online_before(cutoff, BATCH_SIZE).await?;
+        let owner = format!("sweeper-{}", std::process::id());
+        let reason = format!("Unpaid after {} minutes", self.unpaid_order_window.num_minutes());
+
+        let mut canceled = 0;
+        for order in orders {
+            // One attempt: an order someone is working on is retried next run
+            let result = self
+                .lock_util
+                .with_lock(&order.order_id, &owner, 30000, 1, 0, || async {
+                    let Some(order) = self.order_repo.find_by_order_id(&order.order_id).await? else {
+                        return Ok(false);
+                    };
+                    let pending = self.payment_repo.find_pending_by_order_id(&order.order_id).await?;
+                    if !is_abandoned(&order, &pending, now, self.unpaid_order_window) {
+                        return Ok(false);
+                    }
+                    self.order_service
+                        .cancel_order(&order, None, reason.clone(), true)
+                        .await?;
+                    Ok::<_, AppError>(true)
+                })
+                .await;
+
+            match result {
+                Ok(true) => canceled += 1,
+                Ok(false) | Err(AppError::Lock(_)) => {}
+                Err(e) => warn!("Could not cancel abandoned order {}: {}", order.order_id, e),
+            }
+        }
+        Ok(canceled)
+    }
+
+    /// Flag recent payments whose order does not exist
+    pub async fn flag_orphaned_payments(&self, now: DateTime<Utc>) -> AppResult<u64> {
+        let until = now - Duration::minutes(ORPHAN_GRACE_MINUTES);
+        let since = until - Duration::hours(ORPHAN_LOOKBACK_HOURS);
+        let flagged = self
+            .payment_repo
+            .flag_orphaned(bson::DateTime::from_chrono(since), bson::DateTime::from_chrono(until), BATCH_SIZE)
+            .await?;
+
+        if flagged > 0 {
+            warn!("Flagged {} payments without an order", flagged);
+        }
+        Ok(flagged)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_is_abandoned() {
+        let now = Utc.with_ymd_and_hms(2024, 3, 10, 5, 0, 0).unwrap(); // 12:00 WIB
+        let window = Duration::minutes(60);
+        let mut order = Order {
+            order_id: "ORD-1".to_string(),
+            source: "Web".to_string(),
+            order_type: "Pickup".to_string(),
+            created_at_wib: bson::DateTime::from_chrono(now - Duration::minutes(61)),
+            ..Order::default()
+        };
+
+        assert!(is_abandoned(&order, &[], now, window));
+        assert!(!is_abandoned(&order, &[], now - Duration::minutes(2), window));
+
+        // A charge the customer can still pay keeps the order open
+        let live = OrderPayment {
+            order_id: "ORD-1".to_string(),
+            transaction_id: Some("tx-1".to_string()),
+            expiry_time: Some("2024-03-10 12:10:00".to_string()),
+            ..OrderPayment::default()
+        };
+        assert!(!is_abandoned(&order, std::slice::from_ref(&live), now, window));
+        assert!(is_abandoned(&order, &[live], now + Duration::minutes(10), window));
+
+        order.payment_status = Some("Paid".to_string());
+        assert!(!is_abandoned(&order, &[], now, window));
+
+        order.payment_status = None;
+        order.source = "Cashier".to_string();
+        assert!(!is_abandoned(&order, &[], now, window));
+    }
+}
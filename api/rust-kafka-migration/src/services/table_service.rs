@@ -0,0 +1,427 @@
+use std::collections::HashMap;
+
+use bson::oid::ObjectId;
+use chrono::Utc;
+use mongodb::ClientSession;
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::common::Money;
+use crate::db::models::{Area, Order, OrderStatus, Table, TableStatus};
+use crate::db::repositories::{OrderRepository, TableRepository};
+use crate::error::{AppError, AppResult};
+use crate::services::PrintService;
+use crate::websocket::events::TableStatusData;
+
+/// A table status change, broadcast once it is committed
+#[derive(Debug, Clone)]
+pub struct TableChange {
+    pub table: Table,
+    pub updated_by: String,
+    pub notes: Option<String>,
+}
+
+/// An open order shown on its table in the floor view
+#[derive(Debug, Serialize)]
+pub struct FloorOrder {
+    #[serde(rename = "orderId")]
+    pub order_id: String,
+    pub status: OrderStatus,
+    #[serde(rename = "grandTotal")]
+    pub grand_total: Money,
+    #[serde(rename = "guestNumber", skip_serializing_if = "Option::is_none")]
+    pub guest_number: Option<i32>,
+    #[serde(rename = "createdAt")]
+    pub created_at: bson::DateTime,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FloorTable {
+    pub id: Option<ObjectId>,
+    #[serde(rename = "tableNumber")]
+    pub table_number: String,
+    pub seats: i32,
+    pub status: TableStatus,
+    #[serde(rename = "isAvailable")]
+    pub is_available: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(rename = "openOrders")]
+    pub open_orders: Vec<FloorOrder>,
+}
+
+#[derive(Debug, Default, Serialize, PartialEq)]
+pub struct FloorSummary {
+    pub total: usize,
+    pub available: usize,
+    pub occupied: usize,
+    pub reserved: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FloorArea {
+    pub id: Option<ObjectId>,
+    #[serde(rename = "areaName")]
+    pub area_name: String,
+    #[serde(rename = "areaCode")]
+    pub area_code: String,
+    pub capacity: i32,
+    pub summary: FloorSummary,
+    pub tables: Vec<FloorTable>,
+}
+
+/// Group tables under their areas with the orders still open at each table
+pub fn floor_areas(areas: Vec<Area>, tables: Vec<Table>, orders: Vec<Order>) -> Vec<FloorArea> {
+    let mut orders_by_table: HashMap<String, Vec<FloorOrder>> = HashMap::new();
+    for order in orders {
+        let Some(table_number) = order.table_number.clone() else { continue };
+        orders_by_table.entry(table_number).or_default().push(FloorOrder {
+            order_id: order.order_id,
+            status: order.status,
+            grand_total: order.grand_total,
+            guest_number: order.guest_number,
+            created_at: order.created_at_wib,
+        });
+    }
+
+    let mut tables_by_area: HashMap<ObjectId, Vec<FloorTable>> = HashMap::new();
+    for table in tables {
+        tables_by_area.entry(table.area_id).or_default().push(FloorTable {
+            id: table.id,
+            open_orders: orders_by_table.remove(&table.table_number).unwrap_or_default(),
+            table_number: table.table_number,
+            seats: table.seats,
+            status: table.status,
+            is_available: table.is_available,
+            description: table.description,
+        });
+    }
+
+    areas
+        .into_iter()
+        .map(|area| {
+            let tables = area
+                .id
+                .and_then(|id| tables_by_area.remove(&id))
+                .unwrap_or_default();
+            let mut summary = FloorSummary { total: tables.len(), ..FloorSummary::default() };
+            for table in &tables {
+                match table.status {
+                    TableStatus::Available => summary.available += 1,
+                    TableStatus::Occupied => summary.occupied += 1,
+                    TableStatus::Reserved => summary.reserved += 1,
+                }
+            }
+            FloorArea {
+                id: area.id,
+                area_name: area.area_name,
+                area_code: area.area_code,
+                capacity: area.capacity,
+                summary,
+                tables,
+            }
+        })
+        .collect()
+}
+
+#[derive(Clone)]
+pub struct TableService {
+    table_repo: TableRepository,
+    order_repo: OrderRepository,
+    print_service: PrintService,
+}
+
+impl TableService {
+    pub fn new(table_repo: TableRepository, order_repo: OrderRepository, print_service: PrintService) -> Self {
+        Self {
+            table_repo,
+            order_repo,
+            print_service,
+        }
+    }
+
+    /// Occupy the table of a new dine-in order inside the order's transaction.
+    ///
+    /// A table that is already occupied, e.g. by an earlier order of the same
+    /// guests, is left as it is. Table numbers unknown at the order's outlet
+    /// are only logged.
+    pub async fn occupy_for_order(
+        &self,
+        order: &Order,
+        updated_by: &str,
+        session: &mut ClientSession,
+    ) -> AppResult<Option<TableChange>> {
+        let Some(table_number) = order.table_number.as_deref().filter(|t| !t.is_empty()) else {
+            return Ok(None);
+        };
+        if order.order_type != "Dine-In" || order.status.is_final() {
+            return Ok(None);
+        }
+        let Some(outlet) = order.outlet else {
+            warn!("Order {} has no outlet to find table {} at", order.order_id, table_number);
+            return Ok(None);
+        };
+
+        let Some(table) = self.table_repo.find_active_by_number(&outlet, table_number, Some(&mut *session)).await? else {
+            warn!("Table {} of order {} not found", table_number, order.order_id);
+            return Ok(None);
+        };
+        if table.status == TableStatus::Occupied {
+            return Ok(None);
+        }
+
+        let notes = Some(format!("Order {}", order.order_id));
+        self.change_status(table, TableStatus::Occupied, updated_by, notes, Some(session)).await
+    }
+
+    /// Free the table of a completed or canceled order inside its transaction.
+    ///
+    /// The order is flagged `table_released` either way, but the table stays
+    /// occupied while other orders are still open at it.
+    pub async fn release_for_order(
+        &self,
+        order: &Order,
+        updated_by: &str,
+        notes: Option<String>,
+        session: &mut ClientSession,
+    ) -> AppResult<Option<TableChange>> {
+        let (Some(order_oid), Some(table_number), false) =
+            (order.id, order.table_number.as_deref(), order.table_released)
+        else {
+            return Ok(None);
+        };
+
+        self.order_repo.mark_table_released_with_session(&order_oid, session).await?;
+
+        let Some(outlet) = order.outlet else { return Ok(None) };
+        let others = self
+            .order_repo
+            .count_open_for_table_with_session(&outlet, table_number, &order_oid, session)
+            .await?;
+        if others > 0 {
+            return Ok(None);
+        }
+
+        let Some(table) = self.table_repo.find_active_by_number(&outlet, table_number, Some(&mut *session)).await? else {
+            return Ok(None);
+        };
+        if table.status != TableStatus::Occupied {
+            return Ok(None);
+        }
+
+        self.change_status(table, TableStatus::Available, updated_by, notes, Some(session)).await
+    }
+
+    /// Occupy the tables of a reservation whose guests have arrived and
+    /// broadcast each change. Tables already occupied are left as they are.
+    pub async fn occupy_for_reservation(
+        &self,
+        table_ids: &[ObjectId],
+        updated_by: &str,
+        reservation_code: &str,
+    ) -> AppResult<Vec<TableChange>> {
+        let mut changes = Vec::new();
+        for table_id in table_ids {
+            let Some(table) = self.table_repo.find_by_id(table_id).await? else { continue };
+            if table.status == TableStatus::Occupied {
+                continue;
+            }
+            let notes = Some(format!("Reservation {} checked in", reservation_code));
+            if let Some(change) = self.change_status(table, TableStatus::Occupied, updated_by, notes, None).await? {
+                self.broadcast(Some(&change)).await;
+                changes.push(change);
+            }
+        }
+        Ok(changes)
+    }
+
+    /// Free the tables of a reservation whose guests have left, except those
+    /// with orders still open, and broadcast each change
+    pub async fn release_for_reservation(
+        &self,
+        table_ids: &[ObjectId],
+        updated_by: &str,
+        reservation_code: &str,
+    ) -> AppResult<Vec<TableChange>> {
+        let mut changes = Vec::new();
+        for table_id in table_ids {
+            let Some(table) = self.table_repo.find_by_id(table_id).await? else { continue };
+            if table.status != TableStatus::Occupied {
+                continue;
+            }
+            if !self.open_orders_at(&table).await?.is_empty() {
+                continue;
+            }
+            let notes = Some(format!("Reservation {} checked out", reservation_code));
+            if let Some(change) = self.change_status(table, TableStatus::Available, updated_by, notes, None).await? {
+                self.broadcast(Some(&change)).await;
+                changes.push(change);
+            }
+        }
+        Ok(changes)
+    }
+
+    /// Set a table's status by hand and broadcast it.
+    /// A table cannot be made available while orders are still open at it.
+    pub async fn set_status(
+        &self,
+        table_id: &ObjectId,
+        status: TableStatus,
+        updated_by: &str,
+        notes: Option<String>,
+    ) -> AppResult<Table> {
+        let table = self
+            .table_repo
+            .find_by_id(table_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Table not found".to_string()))?;
+
+        if table.status == status {
+            return Ok(table);
+        }
+
+        if status == TableStatus::Available {
+            let open = self.open_orders_at(&table).await?;
+            if !open.is_empty() {
+                return Err(AppError::Conflict(format!(
+                    "Table {} still has {} open order(s)",
+                    table.table_number,
+                    open.len()
+                )));
+            }
+        }
+
+        let table_number = table.table_number.clone();
+        let change = self
+            .change_status(table, status, updated_by, notes, None)
+            .await?
+            .ok_or_else(|| {
+                AppError::Conflict(format!("Table {} was modified concurrently, please retry", table_number))
+            })?;
+
+        self.broadcast(Some(&change)).await;
+        Ok(change.table)
+    }
+
+    /// Orders still open at a table, looked up at the outlet of the table's area
+    async fn open_orders_at(&self, table: &Table) -> AppResult<Vec<Order>> {
+        let area = self.table_repo.find_area_by_id(&table.area_id).await?.ok_or_else(|| {
+            AppError::NotFound(format!("Area of table {} not found", table.table_number))
+        })?;
+        self.order_repo
+            .find_open_for_tables(&area.outlet_id, std::slice::from_ref(&table.table_number))
+            .await
+    }
+
+    /// An outlet's areas with their tables and open orders
+    pub async fn floor_view(&self, outlet_id: &ObjectId, area_id: Option<ObjectId>) -> AppResult<Vec<FloorArea>> {
+        let areas = self.table_repo.find_active_areas(outlet_id, area_id).await?;
+        let tables = self.table_repo.find_active(outlet_id, area_id).await?;
+        let table_numbers: Vec<String> = tables.iter().map(|t| t.table_number.clone()).collect();
+        let orders = self.order_repo.find_open_for_tables(outlet_id, &table_numbers).await?;
+
+        Ok(floor_areas(areas, tables, orders))
+    }
+
+    /// Tell the cashiers and the table's area about a committed change
+    pub async fn broadcast(&self, change: Option<&TableChange>) {
+        let Some(change) = change else { return };
+
+        let area_code = match self.table_repo.find_area_by_id(&change.table.area_id).await {
+            Ok(area) => area.map(|a| a.area_code),
+            Err(e) => {
+                warn!("Failed to load area of table {}: {}", change.table.table_number, e);
+                None
+            }
+        };
+
+        self.print_service.broadcast_table_status(TableStatusData {
+            table_id: change.table.id.map(|id| id.to_hex()).unwrap_or_default(),
+            table_number: change.table.table_number.clone(),
+            area_id: change.table.area_id.to_hex(),
+            area_code,
+            status: change.table.status.to_string(),
+            is_available: change.table.is_available,
+            updated_by: change.updated_by.clone(),
+            notes: change.notes.clone(),
+            timestamp: Utc::now(),
+        });
+    }
+
+    async fn change_status(
+        &self,
+        mut table: Table,
+        to: TableStatus,
+        updated_by: &str,
+        notes: Option<String>,
+        session: Option<&mut ClientSession>,
+    ) -> AppResult<Option<TableChange>> {
+        let Some(table_id) = table.id else { return Ok(None) };
+        let from = table.status;
+
+        let updated = self
+            .table_repo
+            .update_status(&table_id, from, to, updated_by, notes.clone(), session)
+            .await?;
+        if !updated {
+            return Ok(None);
+        }
+
+        info!("🪑 Table {} status changed: {} → {}", table.table_number, from, to);
+        table.add_status_history(from.to_string(), to.to_string(), updated_by.to_string(), notes.clone());
+        table.status = to;
+        table.is_available = to == TableStatus::Available;
+
+        Ok(Some(TableChange {
+            table,
+            updated_by: updated_by.to_string(),
+            notes,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_floor_areas() {
+        let outlet = ObjectId::new();
+        let mut indoor = Area::new("Indoor".to_string(), "A".to_string(), 20, outlet);
+        indoor.id = Some(ObjectId::new());
+        let mut terrace = Area::new("Terrace".to_string(), "B".to_string(), 10, outlet);
+        terrace.id = Some(ObjectId::new());
+
+        let mut a1 = Table::new("A1".to_string(), 4, indoor.id.unwrap());
+        a1.status = TableStatus::Occupied;
+        a1.is_available = false;
+        let a2 = Table::new("A2".to_string(), 2, indoor.id.unwrap());
+        let mut b1 = Table::new("B1".to_string(), 4, terrace.id.unwrap());
+        b1.status = TableStatus::Reserved;
+
+        let order = |id: &str, table: &str| Order {
+            order_id: id.to_string(),
+            table_number: Some(table.to_string()),
+            grand_total: Money::from_rupiah(50_000),
+            ..Order::default()
+        };
+
+        let floor = floor_areas(
+            vec![indoor, terrace],
+            vec![a1, a2, b1],
+            vec![order("ORD-1", "A1"), order("ORD-2", "A1")],
+        );
+
+        assert_eq!(floor.len(), 2);
+        assert_eq!(
+            floor[0].summary,
+            FloorSummary { total: 2, available: 1, occupied: 1, reserved: 0 }
+        );
+        assert_eq!(floor[0].tables[0].open_orders.len(), 2);
+        assert!(floor[0].tables[1].open_orders.is_empty());
+        assert_eq!(
+            floor[1].summary,
+            FloorSummary { total: 1, available: 0, occupied: 0, reserved: 1 }
+        );
+    }
+}
@@ -1,6 +1,7 @@
-use mongodb::{Client, Collection, Database};
+use mongodb::{Collection, Database};
 use mongodb::bson::{doc, oid::ObjectId};
 use futures::stream::TryStreamExt;
+use crate::common::Money;
 use crate::db::models::{TaxAndService, OrderItem, CustomAmountItem};
 use crate::error::Result;
 
@@ -19,7 +20,7 @@ impl TaxService {
     pub async fn calculate_taxes_and_services(
         &self,
         outlet_id: ObjectId,
-        taxable_amount: f64,
+        taxable_amount: Money,
         order_items: &[OrderItem],
         custom_amount_items: &[CustomAmountItem],
     ) -> Result<TaxCalculationResult> {
@@ -31,8 +32,8 @@ impl TaxService {
         let mut cursor = self.tax_collection.find(filter, None).await?;
         
         let mut tax_details = Vec::new();
-        let mut total_tax = 0.0;
-        let mut total_service_fee = 0.0;
+        let mut total_tax = Money::ZERO;
+        let mut total_service_fee = Money::ZERO;
         
         // Calculate bazar items amount (to exclude)
         let _bazar_items: Vec<&OrderItem> = order_items.iter().filter(|_item| {
@@ -67,7 +68,7 @@ impl TaxService {
             
             // If specific menu items
             if !charge.applies_to_menu_items.is_empty() {
-                applicable_amount = 0.0;
+                applicable_amount = Money::ZERO;
                 
                 for item in order_items {
                      if let Some(menu_item_id) = item.menu_item {
@@ -94,7 +95,7 @@ impl TaxService {
             
             if charge.kind == "tax" {
                 let percentage = charge.percentage.unwrap_or(0.0);
-                let tax_amount = (percentage / 100.0) * applicable_amount;
+                let tax_amount = applicable_amount.percent(percentage);
                 total_tax += tax_amount;
                 
                 tax_details.push(TaxDetail {
@@ -112,7 +113,7 @@ impl TaxService {
                     fixed
                 } else {
                     let percentage = charge.percentage.unwrap_or(0.0);
-                    (percentage / 100.0) * applicable_amount
+                    applicable_amount.percent(percentage)
                 };
                 
                 total_service_fee += fee_amount;
@@ -140,17 +141,17 @@ impl TaxService {
 
 pub struct TaxCalculationResult {
     pub tax_details: Vec<TaxDetail>,
-    pub total_tax: f64,
-    pub total_service_fee: f64,
+    pub total_tax: Money,
+    pub total_service_fee: Money,
 }
 
 pub struct TaxDetail {
     pub id: ObjectId,
     pub name: String,
     pub kind: String,
-    pub amount: f64,
+    pub amount: Money,
     pub percentage: Option<f64>,
-    pub fixed_fee: Option<f64>,
+    pub fixed_fee: Option<Money>,
     pub applies_to: String,
-    pub applicable_amount: f64,
+    pub applicable_amount: Money,
 }
@@ -0,0 +1,713 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::{info, warn};
+
+use crate::common::Money;
+use crate::config::XenditConfig;
+use crate::db::models::order::PaymentAction;
+use crate::error::{AppError, AppResult};
+use crate::services::midtrans_client::format_midtrans_time;
+use crate::services::payment_gateway::{
+    secrets_match, EWallet, GatewayCharge, GatewayChargeRequest, GatewayRefund, GatewayRefundRequest,
+    PaymentChannel, PaymentGateway, VaBank,
+};
+
+/// Xendit serves test and live mode from one host; the key decides which
+const BASE_URL: &str = "https://api.xendit.co";
+const QR_API_VERSION: &str = "2022-07-31";
+
+/// `method` recorded for payments made on a Xendit invoice page
+pub const INVOICE_METHOD: &str = "invoice";
+
+/// The `Payment.status` a Xendit status corresponds to, using the Midtrans names
+/// so both gateways settle payments and orders the same way
+pub fn payment_status(xendit_status: &str) -> Option<&'static str> {
+    match xendit_status.to_uppercase().as_str() {
+        "PENDING" | "ACTIVE" => Some("pending"),
+        "PAID" | "SETTLED" | "SUCCEEDED" | "COMPLETED" => Some("settlement"),
+        "EXPIRED" => Some("expire"),
+        "FAILED" => Some("deny"),
+        "VOIDED" | "CANCELED" | "CANCELLED" => Some("cancel"),
+        "REFUNDED" => Some("refund"),
+        _ => None,
+    }
+}
+
+/// Check a callback's `x-callback-token`. Nothing verifies without a configured token.
+pub fn verify_callback_token(expected: &str, given: &str) -> bool {
+    !expected.is_empty() && secrets_match(expected, given.trim())
+}
+
+/// The parts of a callback the webhook needs, whichever product sent it
+#[derive(Debug, Clone, PartialEq)]
+pub struct XenditCallback {
+    /// Our payment code, sent as `external_id` or `reference_id`
+    pub reference: String,
+    pub status: String,
+    pub amount: Option<Money>,
+    pub payment_method: Option<String>,
+    pub paid_at: Option<String>,
+}
+
+/// Invoice callbacks are flat; QR code and e-wallet callbacks wrap the charge in `data`
+pub fn parse_callback(body: &Value) -> Option<XenditCallback> {
+    let str_field = |value: &Value, key: &str| value.get(key).and_then(|v| v.as_str()).map(str::to_string);
+    let amount_field = |value: &Value, keys: &[&str]| {
+        keys.iter()
+            .find_map(|key| value.get(*key).and_then(|v| v.as_f64()))
+            .map(Money::from_f64)
+    };
+
+    let callback = match (body.get("event"), body.get("data")) {
+        (Some(_), Some(data)) => XenditCallback {
+            reference: str_field(data, "reference_id")?,
+            status: str_field(data, "status")?,
+            amount: amount_field(data, &["amount", "capture_amount", "charge_amount"]),
+            payment_method: str_field(data, "channel_code").or_else(|| {
+                str_field(body, "event")
+                    .filter(|event| event.starts_with("qr."))
+                    .map(|_| "QRIS".to_string())
+            }),
+            paid_at: str_field(data, "created"),
+        },
+        _ => XenditCallback {
+            reference: str_field(body, "external_id")?,
+            status: str_field(body, "status")?,
+            amount: amount_field(body, &["paid_amount", "amount"]),
+            payment_method: str_field(body, "payment_channel").or_else(|| str_field(body, "payment_method")),
+            paid_at: str_field(body, "paid_at"),
+        },
+    };
+
+    (!callback.reference.is_empty() && !callback.status.is_empty()).then_some(callback)
+}
+
+/// Xendit wants "+62..." numbers
+fn international_phone(phone: &str) -> String {
+    let digits: String = phone.chars().filter(|c| c.is_ascii_digit()).collect();
+    match digits.strip_prefix('0') {
+        Some(rest) => format!("+62{}", rest),
+        None => format!("+{}", digits),
+    }
+}
+
+/// Xendit RFC 3339 timestamp in the WIB format payments store
+pub fn parse_xendit_time(value: &str) -> Option<String> {
+    DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|at| format_midtrans_time(at.with_timezone(&Utc)))
+}
+
+fn ewallet_channel_code(wallet: EWallet) -> AppResult<&'static str> {
+    match wallet {
+        EWallet::Ovo => Ok("ID_OVO"),
+        EWallet::Dana => Ok("ID_DANA"),
+        EWallet::ShopeePay => Ok("ID_SHOPEEPAY"),
+        EWallet::LinkAja => Ok("ID_LINKAJA"),
+        EWallet::Gopay => Err(AppError::Validation("gopay is not available through Xendit".to_string())),
+    }
+}
+
+/// Name of `channel` in an invoice's `payment_methods`
+fn invoice_payment_method(channel: PaymentChannel) -> AppResult<&'static str> {
+    match channel {
+        PaymentChannel::Qris => Ok("QRIS"),
+        PaymentChannel::EWallet(wallet) => ewallet_channel_code(wallet).map(|code| code.trim_start_matches("ID_")),
+        PaymentChannel::BankTransfer(VaBank::Bca) => Ok("BCA"),
+        PaymentChannel::BankTransfer(VaBank::Bni) => Ok("BNI"),
+        PaymentChannel::BankTransfer(VaBank::Bri) => Ok("BRI"),
+        PaymentChannel::BankTransfer(VaBank::Cimb) => Ok("CIMB"),
+        PaymentChannel::BankTransfer(VaBank::Permata) => Ok("PERMATA"),
+        PaymentChannel::BankTransfer(VaBank::Mandiri) => Ok("MANDIRI"),
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct XenditInvoiceCustomer {
+    pub given_names: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mobile_number: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct XenditInvoiceRequest {
+    pub external_id: String,
+    pub amount: i64,
+    pub description: String,
+    /// Seconds
+    pub invoice_duration: i64,
+    pub currency: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub customer: Option<XenditInvoiceCustomer>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub payment_methods: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct XenditInvoice {
+    pub id: String,
+    pub external_id: String,
+    pub status: String,
+    pub invoice_url: String,
+    #[serde(default)]
+    pub expiry_date: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct XenditQrCodeRequest {
+    pub reference_id: String,
+    #[serde(rename = "type")]
+    pub qr_type: String,
+    pub currency: String,
+    pub amount: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct XenditQrCode {
+    pub id: String,
+    pub reference_id: String,
+    pub status: String,
+    pub qr_string: String,
+    #[serde(default)]
+    pub expires_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct XenditEWalletChargeRequest {
+    pub reference_id: String,
+    pub currency: String,
+    pub amount: i64,
+    pub checkout_method: String,
+    pub channel_code: String,
+    pub channel_properties: Value,
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct XenditEWalletActions {
+    #[serde(default)]
+    pub desktop_web_checkout_url: Option<String>,
+    #[serde(default)]
+    pub mobile_web_checkout_url: Option<String>,
+    #[serde(default)]
+    pub mobile_deeplink_checkout_url: Option<String>,
+    #[serde(default)]
+    pub qr_checkout_string: Option<String>,
+}
+
+impl XenditEWalletActions {
+    /// The checkout links as the `actions` stored on a payment
+    pub fn payment_actions(&self) -> Vec<PaymentAction> {
+        [
+            ("desktop-web-checkout", &self.desktop_web_checkout_url),
+            ("mobile-web-checkout", &self.mobile_web_checkout_url),
+            ("deeplink-redirect", &self.mobile_deeplink_checkout_url),
+        ]
+        .into_iter()
+        .filter_map(|(name, url)| {
+            url.as_ref().map(|url| PaymentAction {
+                name: name.to_string(),
+                method: "GET".to_string(),
+                url: url.clone(),
+            })
+        })
+        .collect()
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct XenditEWalletCharge {
+    pub id: String,
+    pub reference_id: String,
+    pub status: String,
+    #[serde(default)]
+    pub actions: Option<XenditEWalletActions>,
+}
+
+/// One of `invoice_id` and `payment_request_id` names the payment; e-wallet
+/// charge IDs are accepted as a `payment_request_id`
+#[derive(Debug, Clone, Serialize)]
+pub struct XenditRefundRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub invoice_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payment_request_id: Option<String>,
+    pub reference_id: String,
+    pub amount: i64,
+    pub currency: String,
+    /// One of Xendit's reason codes
+    pub reason: String,
+    pub metadata: Value,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct XenditRefund {
+    pub id: String,
+    pub status: String,
+}
+
+/// Thin client for the Xendit invoice, QR code, e-wallet and refund APIs
+#[derive(Clone)]
+pub struct XenditClient {
+    http: reqwest::Client,
+    base_url: String,
+    secret_key: String,
+    success_redirect_url: String,
+}
+
+impl XenditClient {
+    pub fn new(config: &XenditConfig) -> Self {
+        let base_url = if config.base_url.is_empty() { BASE_URL } else { config.base_url.as_str() };
+        let mut client = Self::with_base_url(config.secret_key.clone(), base_url.to_string());
+        client.success_redirect_url = config.success_redirect_url.clone();
+        client
+    }
+
+    pub fn with_base_url(secret_key: String, base_url: String) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            secret_key,
+            success_redirect_url: String::new(),
+        }
+    }
+
+    /// POST `body` and decode the reply, keeping the raw body alongside it
+    async fn post<B: Serialize, R: DeserializeOwned>(
+        &self,
+        path: &str,
+        api_version: Option<&str>,
+        body: &B,
+    ) -> AppResult<(R, Value)> {
+        let mut request = self
+            .http
+            .post(format!("{}{}", self.base_url, path))
+            .basic_auth(&self.secret_key, Some(""))
+            .json(body);
+        if let Some(version) = api_version {
+            request = request.header("api-version", version);
+        }
+
+        let response = request.send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            warn!("Xendit {} rejected: {} {}", path, status, body);
+            return Err(AppError::ExternalService(format!("Xendit {} failed: {} {}", path, status, body)));
+        }
+
+        let raw: Value = response.json().await?;
+        Ok((serde_json::from_value(raw.clone())?, raw))
+    }
+
+    /// Create a hosted invoice page - POST /v2/invoices
+    pub async fn create_invoice(&self, request: &XenditInvoiceRequest) -> AppResult<(XenditInvoice, Value)> {
+        let result = self.post("/v2/invoices", None, request).await?;
+        info!("💳 Xendit invoice created for {}", request.external_id);
+        Ok(result)
+    }
+
+    /// Create a dynamic QRIS code - POST /qr_codes
+    pub async fn create_qr_code(&self, request: &XenditQrCodeRequest) -> AppResult<(XenditQrCode, Value)> {
+        let result = self.post("/qr_codes", Some(QR_API_VERSION), request).await?;
+        info!("💳 Xendit QR code created for {}", request.reference_id);
+        Ok(result)
+    }
+
+    /// Charge an e-wallet - POST /ewallets/charges
+    pub async fn create_ewallet_charge(
+        &self,
+        request: &XenditEWalletChargeRequest,
+    ) -> AppResult<(XenditEWalletCharge, Value)> {
+        let result = self.post("/ewallets/charges", None, request).await?;
+        info!("💳 Xendit {} charge created for {}", request.channel_code, request.reference_id);
+        Ok(result)
+    }
+
+    /// Refund a paid invoice or e-wallet charge - POST /refunds
+    pub async fn create_refund(&self, request: &XenditRefundRequest) -> AppResult<(XenditRefund, Value)> {
+        let result = self.post("/refunds", None, request).await?;
+        info!("💸 Xendit refund {} requested", request.reference_id);
+        Ok(result)
+    }
+}
+
+impl PaymentGateway for XenditClient {
+    /// An invoice for hosted payments, a QR code or e-wallet charge otherwise.
+    /// Virtual accounts are only offered on the invoice page.
+    async fn charge(&self, request: &GatewayChargeRequest) -> AppResult<GatewayCharge> {
+        let now = Utc::now();
+        let expiry = now + Duration::minutes(request.expiry_minutes);
+
+        if request.hosted {
+            let payment_methods = match request.channel {
+                Some(channel) => vec![invoice_payment_method(channel)?.to_string()],
+                None => Vec::new(),
+            };
+            let (invoice, raw) = self
+                .create_invoice(&XenditInvoiceRequest {
+                    external_id: request.reference.clone(),
+                    amount: request.amount.rupiah(),
+                    description: format!("Payment {}", request.reference),
+                    invoice_duration: request.expiry_minutes * 60,
+                    currency: "IDR".to_string(),
+                    customer: Some(XenditInvoiceCustomer {
+                        given_names: request.customer.name.clone(),
+                        email: request.customer.email.clone(),
+                        mobile_number: request.customer.phone.as_deref().map(international_phone),
+                    }),
+                    payment_methods,
+                })
+                .await?;
+
+            return Ok(GatewayCharge {
+                method: INVOICE_METHOD.to_string(),
+                transaction_id: Some(invoice.id),
+                status: payment_status(&invoice.status).unwrap_or("pending").to_string(),
+                redirect_url: Some(invoice.invoice_url),
+                transaction_time: Some(format_midtrans_time(now)),
+                expiry_time: invoice
+                    .expiry_date
+                    .as_deref()
+                    .and_then(parse_xendit_time)
+                    .or_else(|| Some(format_midtrans_time(expiry))),
+                raw,
+                ..GatewayCharge::default()
+            });
+        }
+
+        let channel = request
+            .channel
+            .ok_or_else(|| AppError::Validation("method is required".to_string()))?;
+        match channel {
+            PaymentChannel::Qris => {
+                let (qr, raw) = self
+                    .create_qr_code(&XenditQrCodeRequest {
+                        reference_id: request.reference.clone(),
+                        qr_type: "DYNAMIC".to_string(),
+                        currency: "IDR".to_string(),
+                        amount: request.amount.rupiah(),
+                        expires_at: Some(expiry.to_rfc3339()),
+                    })
+                    .await?;
+
+                Ok(GatewayCharge {
+                    method: channel.method_name().to_string(),
+                    transaction_id: Some(qr.id),
+                    status: payment_status(&qr.status).unwrap_or("pending").to_string(),
+                    transaction_time: Some(format_midtrans_time(now)),
+                    expiry_time: qr
+                        .expires_at
+                        .as_deref()
+                        .and_then(parse_xendit_time)
+                        .or_else(|| Some(format_midtrans_time(expiry))),
+                    raw,
+                    ..GatewayCharge::default()
+                })
+            }
+            PaymentChannel::EWallet(wallet) => {
+                let channel_properties = if wallet == EWallet::Ovo {
+                    let phone = request
+                        .customer
+                        .phone
+                        .as_deref()
+                        .ok_or_else(|| AppError::Validation("phone is required for ovo".to_string()))?;
+                    json!({ "mobile_number": international_phone(phone) })
+                } else {
+                    json!({ "success_redirect_url": self.success_redirect_url })
+                };
+                let (charge, raw) = self
+                    .create_ewallet_charge(&XenditEWalletChargeRequest {
+                        reference_id: request.reference.clone(),
+                        currency: "IDR".to_string(),
+                        amount: request.amount.rupiah(),
+                        checkout_method: "ONE_TIME_PAYMENT".to_string(),
+                        channel_code: ewallet_channel_code(wallet)?.to_string(),
+                        channel_properties,
+                    })
+                    .await?;
+
+                Ok(GatewayCharge {
+                    method: channel.method_name().to_string(),
+                    transaction_id: Some(charge.id),
+                    status: payment_status(&charge.status).unwrap_or("pending").to_string(),
+                    transaction_time: Some(format_midtrans_time(now)),
+                    expiry_time: Some(format_midtrans_time(expiry)),
+                    actions: charge.actions.unwrap_or_default().payment_actions(),
+                    raw,
+                    ..GatewayCharge::default()
+                })
+            }
+            PaymentChannel::BankTransfer(_) => Err(AppError::Validation(
+                "Xendit virtual accounts are only available on the invoice page".to_string(),
+            )),
+        }
+    }
+
+    /// Invoices and e-wallet charges can be refunded; QRIS payments cannot
+    async fn refund(&self, request: &GatewayRefundRequest) -> AppResult<GatewayRefund> {
+        let transaction = Some(request.transaction_id.clone());
+        let (invoice_id, payment_request_id) = if request.method == INVOICE_METHOD {
+            (transaction, None)
+        } else if matches!(PaymentChannel::parse(&request.method, None), Ok(PaymentChannel::EWallet(_))) {
+            (None, transaction)
+        } else {
+            return Err(AppError::Payment(format!(
+                "Xendit cannot refund {} payments, refund the customer another way",
+                request.method
+            )));
+        };
+
+        let (refund, _) = self
+            .create_refund(&XenditRefundRequest {
+                invoice_id,
+                payment_request_id,
+                reference_id: request.reference.clone(),
+                amount: request.amount.rupiah(),
+                currency: "IDR".to_string(),
+                reason: "REQUESTED_BY_CUSTOMER".to_string(),
+                metadata: json!({ "reason": request.reason }),
+            })
+            .await?;
+        if matches!(refund.status.as_str(), "FAILED" | "CANCELLED") {
+            return Err(AppError::ExternalService(format!("Xendit refund {} {}", refund.id, refund.status)));
+        }
+        Ok(GatewayRefund { refund_key: refund.id })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::payment_gateway::GatewayCustomer;
+    use wiremock::matchers::{body_partial_json, header, header_exists, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn charge_request(hosted: bool, channel: Option<PaymentChannel>) -> GatewayChargeRequest {
+        GatewayChargeRequest {
+            reference: "PAY-1".to_string(),
+            amount: Money::from_rupiah(50_000),
+            hosted,
+            channel,
+            customer: GatewayCustomer {
+                name: "Budi".to_string(),
+                email: None,
+                phone: Some("081234567890".to_string()),
+            },
+            expiry_minutes: 30,
+        }
+    }
+
+    #[test]
+    fn test_payment_status() {
+        assert_eq!(payment_status("PAID"), Some("settlement"));
+        assert_eq!(payment_status("SUCCEEDED"), Some("settlement"));
+        assert_eq!(payment_status("pending"), Some("pending"));
+        assert_eq!(payment_status("EXPIRED"), Some("expire"));
+        assert_eq!(payment_status("FAILED"), Some("deny"));
+        assert_eq!(payment_status("VOIDED"), Some("cancel"));
+        assert_eq!(payment_status("SOMETHING_NEW"), None);
+    }
+
+    #[test]
+    fn test_callback_token() {
+        assert!(verify_callback_token("token-1", "token-1"));
+        assert!(!verify_callback_token("token-1", "token-2"));
+        assert!(!verify_callback_token("", ""));
+    }
+
+    #[test]
+    fn test_parse_callback() {
+        let invoice = parse_callback(&json!({
+            "id": "inv-1",
+            "external_id": "PAY-1",
+            "status": "PAID",
+            "amount": 50000,
+            "paid_amount": 50000,
+            "payment_channel": "OVO",
+            "paid_at": "2024-03-10T03:05:00.000Z"
+        }))
+        .unwrap();
+        assert_eq!(invoice.reference, "PAY-1");
+        assert_eq!(invoice.amount, Some(Money::from_rupiah(50_000)));
+        assert_eq!(invoice.payment_method.as_deref(), Some("OVO"));
+
+        let qr = parse_callback(&json!({
+            "event": "qr.payment",
+            "data": { "id": "qrpy-1", "reference_id": "PAY-2", "status": "SUCCEEDED", "amount": 42000 }
+        }))
+        .unwrap();
+        assert_eq!(qr.reference, "PAY-2");
+        assert_eq!(qr.payment_method.as_deref(), Some("QRIS"));
+
+        let ewallet = parse_callback(&json!({
+            "event": "ewallet.capture",
+            "data": {
+                "reference_id": "PAY-3",
+                "status": "SUCCEEDED",
+                "channel_code": "ID_DANA",
+                "capture_amount": 15000
+            }
+        }))
+        .unwrap();
+        assert_eq!(ewallet.amount, Some(Money::from_rupiah(15_000)));
+
+        assert!(parse_callback(&json!({ "status": "PAID" })).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_invoice_charge() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v2/invoices"))
+            .and(header_exists("authorization"))
+            .and(body_partial_json(json!({
+                "external_id": "PAY-1",
+                "amount": 50000,
+                "invoice_duration": 1800,
+                "payment_methods": ["QRIS"],
+                "customer": { "mobile_number": "+6281234567890" }
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "inv-1",
+                "external_id": "PAY-1",
+                "status": "PENDING",
+                "amount": 50000,
+                "invoice_url": "https://checkout-staging.xendit.co/web/inv-1",
+                "expiry_date": "2024-03-10T03:30:00.000Z"
+            })))
+            .mount(&server)
+            .await;
+
+        let client = XenditClient::with_base_url("secret".to_string(), server.uri());
+        let charge = client.charge(&charge_request(true, Some(PaymentChannel::Qris)))
+            .await
+            .unwrap();
+
+        assert_eq!(charge.method, INVOICE_METHOD);
+        assert_eq!(charge.status, "pending");
+        assert_eq!(charge.transaction_id.as_deref(), Some("inv-1"));
+        assert_eq!(charge.expiry_time.as_deref(), Some("2024-03-10 10:30:00"));
+    }
+
+    #[tokio::test]
+    async fn test_qr_code_charge() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/qr_codes"))
+            .and(header("api-version", QR_API_VERSION))
+            .and(body_partial_json(json!({ "reference_id": "PAY-1", "type": "DYNAMIC", "amount": 50000 })))
+            .respond_with(ResponseTemplate::new(201).set_body_json(json!({
+                "id": "qr-1",
+                "reference_id": "PAY-1",
+                "status": "ACTIVE",
+                "qr_string": "00020101021226",
+                "expires_at": "2024-03-10T03:30:00.000Z"
+            })))
+            .mount(&server)
+            .await;
+
+        let client = XenditClient::with_base_url("secret".to_string(), server.uri());
+        let charge = client.charge(&charge_request(false, Some(PaymentChannel::Qris)))
+            .await
+            .unwrap();
+
+        assert_eq!(charge.method, "qris");
+        assert_eq!(charge.status, "pending");
+        assert_eq!(charge.raw["qr_string"], "00020101021226");
+    }
+
+    #[tokio::test]
+    async fn test_ewallet_charge() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/ewallets/charges"))
+            .and(body_partial_json(json!({
+                "channel_code": "ID_OVO",
+                "channel_properties": { "mobile_number": "+6281234567890" }
+            })))
+            .respond_with(ResponseTemplate::new(202).set_body_json(json!({
+                "id": "ewc-1",
+                "reference_id": "PAY-1",
+                "status": "PENDING",
+                "actions": { "mobile_web_checkout_url": "https://ewallet.xendit.co/ewc-1" }
+            })))
+            .mount(&server)
+            .await;
+
+        let client = XenditClient::with_base_url("secret".to_string(), server.uri());
+        let channel = PaymentChannel::EWallet(EWallet::Ovo);
+        let charge = client.charge(&charge_request(false, Some(channel)))
+            .await
+            .unwrap();
+
+        assert_eq!(charge.method, "ovo");
+        assert_eq!(charge.actions.len(), 1);
+        assert_eq!(charge.actions[0].name, "mobile-web-checkout");
+    }
+
+    #[tokio::test]
+    async fn test_charge_rejected() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/ewallets/charges"))
+            .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+                "error_code": "API_VALIDATION_ERROR",
+                "message": "channel_properties.success_redirect_url is required"
+            })))
+            .mount(&server)
+            .await;
+
+        let client = XenditClient::with_base_url("secret".to_string(), server.uri());
+        let channel = PaymentChannel::EWallet(EWallet::Dana);
+        let result = client.charge(&charge_request(false, Some(channel))).await;
+
+        assert!(matches!(result, Err(AppError::ExternalService(_))));
+        let va = PaymentChannel::BankTransfer(VaBank::Bca);
+        let result = client.charge(&charge_request(false, Some(va))).await;
+        assert!(matches!(result, Err(AppError::Validation(_))));
+    }
+
+    fn refund_request(method: &str) -> GatewayRefundRequest {
+        GatewayRefundRequest {
+            reference: "REF-1".to_string(),
+            transaction_id: "inv-1".to_string(),
+            method: method.to_string(),
+            amount: Money::from_rupiah(20_000),
+            reason: "Wrong item".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_refund() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/refunds"))
+            .and(body_partial_json(json!({
+                "invoice_id": "inv-1",
+                "reference_id": "REF-1",
+                "amount": 20000,
+                "currency": "IDR"
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "rfd-1",
+                "status": "PENDING"
+            })))
+            .mount(&server)
+            .await;
+
+        let client = XenditClient::with_base_url("secret".to_string(), server.uri());
+        let refund = client.refund(&refund_request(INVOICE_METHOD)).await.unwrap();
+        assert_eq!(refund.refund_key, "rfd-1");
+
+        // QRIS payments cannot be refunded, and nothing is sent
+        let result = client.refund(&refund_request("qris")).await;
+        assert!(matches!(result, Err(AppError::Payment(_))));
+        assert_eq!(server.received_requests().await.unwrap().len(), 1);
+    }
+}
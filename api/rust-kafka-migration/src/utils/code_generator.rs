@@ -1,188 +1,97 @@
-use bson::{doc, DateTime as BsonDateTime, Document};
-use chrono::{Datelike, Duration, TimeZone, Utc};
-use mongodb::{options::FindOneAndUpdateOptions, ClientSession, Collection};
+use chrono::{DateTime, Utc};
+use chrono_tz::Asia::Jakarta;
+use tracing::warn;
 
-use crate::error::AppError;
+use crate::db::repositories::CounterRepository;
 
-/// Get start of day (00:00:00) for a given DateTime
-fn start_of_day(dt: chrono::DateTime<Utc>) -> chrono::DateTime<Utc> {
-    Utc.with_ymd_and_hms(dt.year(), dt.month(), dt.day(), 0, 0, 0)
-        .single()
-        .unwrap_or(dt)
+/// Business date in WIB as `YYYYMMDD`; counters reset when it changes
+pub fn business_date_wib(now: DateTime<Utc>) -> String {
+    now.with_timezone(&Jakarta).format("%Y%m%d").to_string()
 }
 
-/// Get end of day (23:59:59) for a given DateTime
-fn end_of_day(dt: chrono::DateTime<Utc>) -> chrono::DateTime<Utc> {
-    Utc.with_ymd_and_hms(dt.year(), dt.month(), dt.day(), 23, 59, 59)
-        .single()
-        .unwrap_or(dt + Duration::days(1))
+/// Suffix used when the counter is unavailable.
+///
+/// Prefixed with `X` so it can never equal a counter value, and random
+/// enough that two fallbacks on the same day do not collide.
+fn fallback_suffix(now: DateTime<Utc>) -> String {
+    let random = uuid::Uuid::new_v4().simple().to_string();
+    format!(
+        "X{}{}",
+        now.with_timezone(&Jakarta).format("%H%M%S"),
+        random[..6].to_uppercase()
+    )
 }
 
-/// Generate unique reservation code in format: RSV-YYYYMMDD-XXX
-pub async fn generate_reservation_code(
-    reservation_collection: &Collection<Document>,
-    session: &mut ClientSession,
-) -> Result<String, AppError> {
-    let now = Utc::now();
-    let date_string = now.format("%Y%m%d").to_string();
-
-    // Use atomic counter for sequence
-    let start = start_of_day(now);
-    let end = end_of_day(now);
-
-    let filter = doc! {
-        "createdAt": {
-            "$gte": BsonDateTime::from_chrono(start),
-            "$lt": BsonDateTime::from_chrono(end)
-        }
-    };
-
-    let update = doc! {
-        "$inc": { "_tempSequence": 1 }
-    };
-
-    let options = FindOneAndUpdateOptions::builder()
-        .upsert(true)
-        .return_document(mongodb::options::ReturnDocument::After)
-        .build();
-
-    match reservation_collection
-        .find_one_and_update_with_session(filter, update, options, session)
-        .await
-    {
-        Ok(Some(doc)) => {
-            if let Ok(sequence) = doc.get_i32("_tempSequence") {
-                Ok(format!("RSV-{}-{:03}", date_string, sequence))
-            } else {
-                // Fallback to timestamp-based
-                let fallback = now.timestamp_millis() % 1000;
-                Ok(format!("RSV-{}-{:03}", date_string, fallback))
-            }
-        }
-        Ok(None) => {
-            // Fallback
-            Ok(format!("RSV-{}-001", date_string))
-        }
-        Err(_) => {
-            // Fallback to timestamp-based
-            let fallback = now.timestamp_millis() % 1000;
-            Ok(format!("RSV-{}-{:03}", date_string, fallback))
+/// Next counter value for the prefix, zero-padded to `width`
+async fn sequence_suffix(
+    counters: &CounterRepository,
+    prefix: &str,
+    width: usize,
+    now: DateTime<Utc>,
+) -> String {
+    match counters.next_sequence(prefix, &business_date_wib(now)).await {
+        Ok(seq) => format!("{:0width$}", seq, width = width),
+        Err(e) => {
+            warn!("Counter for {} unavailable, using fallback code: {}", prefix, e);
+            fallback_suffix(now)
         }
     }
 }
 
-/// Generate order sequence number for the day
-pub async fn get_next_order_sequence(
-    order_collection: &Collection<Document>,
-    session: &mut ClientSession,
-) -> Result<i32, AppError> {
-    let now = Utc::now();
-    let start = start_of_day(now);
-    let end = end_of_day(now);
-
-    let filter = doc! {
-        "createdAt": {
-            "$gte": BsonDateTime::from_chrono(start),
-            "$lt": BsonDateTime::from_chrono(end)
-        }
-    };
-
-    let update = doc! {
-        "$inc": { "_tempSequence": 1 }
-    };
-
-    let options = FindOneAndUpdateOptions::builder()
-        .upsert(true)
-        .return_document(mongodb::options::ReturnDocument::After)
-        .build();
-
-    match order_collection
-        .find_one_and_update_with_session(filter, update, options, session)
-        .await
-    {
-        Ok(Some(doc)) => Ok(doc.get_i32("_tempSequence").unwrap_or(1)),
-        Ok(None) => Ok(1),
-        Err(_) => {
-            // Fallback to random
-            Ok((now.timestamp_millis() % 100) as i32 + 1)
-        }
-    }
+/// Generate order ID in format: ORD-DDMMTableCode-GGG-N
+pub async fn generate_order_id(
+    counters: &CounterRepository,
+    table_code: &str,
+    guest_number: Option<i32>,
+    now: DateTime<Utc>,
+) -> String {
+    let date_part = now.with_timezone(&Jakarta).format("%d%m");
+    let prefix = format!("ORD-{}", table_code);
+    let seq = sequence_suffix(counters, &prefix, 1, now).await;
+
+    format!(
+        "ORD-{}{}-{:03}-{}",
+        date_part,
+        table_code,
+        guest_number.unwrap_or(1),
+        seq
+    )
 }
 
-/// Generate payment sequence number for the day
-pub async fn get_next_payment_sequence(
-    payment_collection: &Collection<Document>,
-    session: &mut ClientSession,
-) -> Result<i32, AppError> {
-    let now = Utc::now();
-    let start = start_of_day(now);
-    let end = end_of_day(now);
-
-    let filter = doc! {
-        "createdAt": {
-            "$gte": BsonDateTime::from_chrono(start),
-            "$lt": BsonDateTime::from_chrono(end)
-        }
-    };
-
-    let update = doc! {
-        "$inc": { "_tempSequence": 1 }
-    };
-
-    let options = FindOneAndUpdateOptions::builder()
-        .upsert(true)
-        .return_document(mongodb::options::ReturnDocument::After)
-        .build();
+/// Generate payment code in format: PAY-YYYYMMDD-XXXX
+pub async fn generate_payment_code(counters: &CounterRepository, now: DateTime<Utc>) -> String {
+    let seq = sequence_suffix(counters, "PAY", 4, now).await;
+    format!("PAY-{}-{}", business_date_wib(now), seq)
+}
 
-    match payment_collection
-        .find_one_and_update_with_session(filter, update, options, session)
-        .await
-    {
-        Ok(Some(doc)) => Ok(doc.get_i32("_tempSequence").unwrap_or(1)),
-        Ok(None) => Ok(1),
-        Err(_) => {
-            // Fallback to random
-            Ok((now.timestamp_millis() % 100) as i32 + 1)
-        }
-    }
+/// Generate reservation code in format: RSV-YYYYMMDD-XXX
+pub async fn generate_reservation_code(counters: &CounterRepository, now: DateTime<Utc>) -> String {
+    let seq = sequence_suffix(counters, "RSV", 3, now).await;
+    format!("RSV-{}-{}", business_date_wib(now), seq)
 }
 
-/// Generate order ID in format: ORD-DDTableNumber-XXX or ORD-DDCODE-XXX
-pub async fn generate_order_id(
-    table_number: Option<&str>,
-    counters_collection: &Collection<Document>,
-) -> Result<String, AppError> {
-    let now = Utc::now();
-    let day = now.format("%d").to_string();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
 
-    let table_or_day_code = if let Some(table_num) = table_number {
-        table_num.to_string()
-    } else {
-        // Use day code if no table number
-        let days = ["MD", "TU", "WD", "TH", "FR", "ST", "SN"];
-        let day_code = days[now.weekday().num_days_from_monday() as usize];
-        format!("{}{}", day_code, day)
-    };
+    #[test]
+    fn test_business_date_rolls_over_at_wib_midnight() {
+        // 16:59 UTC is 23:59 WIB, 17:00 UTC is already the next WIB day
+        let before = Utc.with_ymd_and_hms(2024, 3, 9, 16, 59, 0).unwrap();
+        let after = Utc.with_ymd_and_hms(2024, 3, 9, 17, 0, 0).unwrap();
 
-    let date_str = now.format("%Y%m%d").to_string();
-    let key = format!("order_seq_{}_{}", table_or_day_code, date_str);
+        assert_eq!(business_date_wib(before), "20240309");
+        assert_eq!(business_date_wib(after), "20240310");
+    }
 
-    let filter = doc! { "_id": &key };
-    let update = doc! { "$inc": { "seq": 1 } };
-    let options = FindOneAndUpdateOptions::builder()
-        .upsert(true)
-        .return_document(mongodb::options::ReturnDocument::After)
-        .build();
+    #[test]
+    fn test_fallback_suffix_is_distinct() {
+        let now = Utc::now();
+        let a = fallback_suffix(now);
+        let b = fallback_suffix(now);
 
-    match counters_collection
-        .find_one_and_update(filter, update, options)
-        .await
-    {
-        Ok(Some(doc)) => {
-            let seq = doc.get_i32("seq").unwrap_or(1);
-            Ok(format!("ORD-{}{}-{:03}", day, table_or_day_code, seq))
-        }
-        Ok(None) => Ok(format!("ORD-{}{}-001", day, table_or_day_code)),
-        Err(e) => Err(AppError::Internal(e.to_string())),
+        assert!(a.starts_with('X'));
+        assert_ne!(a, b);
     }
 }
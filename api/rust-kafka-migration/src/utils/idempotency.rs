@@ -0,0 +1,134 @@
+use crate::error::{AppError, AppResult};
+use crate::utils::LockUtil;
+use redis::{AsyncCommands, Client};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::future::Future;
+use tracing::info;
+
+/// How long a stored response is replayed for
+const IDEMPOTENCY_TTL_SECS: u64 = 24 * 60 * 60;
+
+/// Longest accepted `Idempotency-Key` header value
+const MAX_KEY_LENGTH: usize = 255;
+
+/// Result of an idempotent call
+pub enum Idempotent {
+    /// The task ran for the first time
+    Fresh(Value),
+    /// A stored response from an earlier call with the same key
+    Replayed(Value),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct StoredResponse {
+    fingerprint: String,
+    response: Value,
+}
+
+/// Copy of a JSON value with object keys sorted, so key order never matters
+fn canonical(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let sorted: BTreeMap<&String, Value> =
+                map.iter().map(|(k, v)| (k, canonical(v))).collect();
+            Value::Object(sorted.into_iter().map(|(k, v)| (k.clone(), v)).collect())
+        }
+        Value::Array(items) => Value::Array(items.iter().map(canonical).collect()),
+        other => other.clone(),
+    }
+}
+
+/// Stable hash of a request body, used to detect reused keys
+pub fn fingerprint(body: &Value) -> String {
+    hex::encode(Sha256::digest(canonical(body).to_string().as_bytes()))
+}
+
+/// Stores the first successful response per `Idempotency-Key` in Redis
+#[derive(Clone)]
+pub struct IdempotencyUtil {
+    client: Client,
+    lock: LockUtil,
+}
+
+impl IdempotencyUtil {
+    pub fn new(client: Client) -> Self {
+        Self {
+            lock: LockUtil::new(client.clone()),
+            client,
+        }
+    }
+
+    /// Run `task` once per `scope`/`key` pair.
+    ///
+    /// Concurrent calls with the same key are serialised through a lock. A
+    /// retry with the same body gets the stored response back; reusing the
+    /// key with a different body is a conflict. Failed tasks are not stored,
+    /// so the client may retry them with the same key.
+    pub async fn run<F, Fut>(&self, scope: &str, key: &str, body: &Value, task: F) -> AppResult<Idempotent>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = AppResult<Value>>,
+    {
+        let key = key.trim();
+        if key.is_empty() || key.len() > MAX_KEY_LENGTH {
+            return Err(AppError::Validation(format!(
+                "Idempotency-Key must be between 1 and {} characters",
+                MAX_KEY_LENGTH
+            )));
+        }
+
+        let redis_key = format!("idempotency:{}:{}", scope, key);
+        let request_fingerprint = fingerprint(body);
+        let owner = uuid::Uuid::new_v4().to_string();
+
+        self.lock
+            .with_lock(&redis_key, &owner, 30000, 50, 200, || async {
+                let mut con = self.client.get_multiplexed_async_connection().await?;
+
+                let stored: Option<String> = con.get(&redis_key).await?;
+                if let Some(stored) = stored {
+                    let stored: StoredResponse = serde_json::from_str(&stored)?;
+                    if stored.fingerprint != request_fingerprint {
+                        return Err(AppError::Conflict(
+                            "Idempotency-Key was already used with a different request".to_string(),
+                        ));
+                    }
+
+                    info!("🔁 Replaying stored response for {}", redis_key);
+                    return Ok(Idempotent::Replayed(stored.response));
+                }
+
+                let response = task().await?;
+
+                let stored = StoredResponse {
+                    fingerprint: request_fingerprint.clone(),
+                    response: response.clone(),
+                };
+                con.set_ex::<_, _, ()>(&redis_key, serde_json::to_string(&stored)?, IDEMPOTENCY_TTL_SECS)
+                    .await?;
+
+                Ok(Idempotent::Fresh(response))
+            })
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_fingerprint_ignores_key_order() {
+        let a = json!({ "outletId": "1", "items": [{ "id": "x", "quantity": 2 }] });
+        let b: Value =
+            serde_json::from_str(r#"{"items":[{"quantity":2,"id":"x"}],"outletId":"1"}"#).unwrap();
+        let c = json!({ "outletId": "1", "items": [{ "id": "x", "quantity": 3 }] });
+
+        assert_eq!(fingerprint(&a), fingerprint(&b));
+        assert_ne!(fingerprint(&a), fingerprint(&c));
+    }
+}
@@ -1,5 +1,5 @@
 use crate::error::{AppError, AppResult};
-use redis::{Client, AsyncCommands};
+use redis::Client;
 use std::time::Duration;
 use tokio::time::sleep;
 use tracing::{info, warn};
@@ -82,6 +82,33 @@ impl LockUtil {
         Ok(())
     }
 
+    /// Take the lock, or keep it for another `ttl_ms` if `owner` already holds it.
+    /// Returns false while someone else holds it.
+    pub async fn hold_lock(&self, key: &str, owner: &str, ttl_ms: u64) -> AppResult<bool> {
+        let mut con = self.client.get_multiplexed_async_connection().await
+            .map_err(AppError::Redis)?;
+
+        let script = redis::Script::new(r"
+            if redis.call('get', KEYS[1]) == ARGV[1] then
+                return redis.call('pexpire', KEYS[1], ARGV[2])
+            elseif redis.call('set', KEYS[1], ARGV[1], 'NX', 'PX', ARGV[2]) then
+                return 1
+            else
+                return 0
+            end
+        ");
+
+        let held: i32 = script
+            .key(key)
+            .arg(owner)
+            .arg(ttl_ms)
+            .invoke_async(&mut con)
+            .await
+            .map_err(AppError::Redis)?;
+
+        Ok(held == 1)
+    }
+
     /// Execute a closure with a locl
     pub async fn with_lock<F, Fut, T>(
         &self,
@@ -1,14 +1,18 @@
+// Re-exports make up the module's public surface; not all are used by the binary yet
+#![allow(unused_imports)]
+
 pub mod code_generator;
 pub mod date_utils;
+pub mod idempotency;
 pub mod jwt;
 pub mod lock;
 pub mod serde_utils;
 pub mod timezone;
 
 pub use code_generator::{
-    generate_order_id, generate_reservation_code, get_next_order_sequence,
-    get_next_payment_sequence,
+    business_date_wib, generate_order_id, generate_payment_code, generate_reservation_code,
 };
+pub use idempotency::{Idempotent, IdempotencyUtil};
 pub use jwt::{generate_token, verify_token};
 pub use lock::LockUtil;
 pub use timezone::{format_wib, get_today_wib_range, get_wib_now, parse_food_serving_time};
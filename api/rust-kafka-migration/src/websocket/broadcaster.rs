@@ -69,6 +69,23 @@ impl WebSocketBroadcaster {
         }
     }
 
+    pub fn emit_table_status_update(&self, data: TableStatusData) {
+        let message = WebSocketMessage::TableStatusUpdate(data.clone());
+
+        if let Ok(json) = serde_json::to_string(&message) {
+            self.manager.broadcast_to_room(&"cashier_room".to_string(), json.clone());
+
+            if let Some(area_code) = &data.area_code {
+                self.manager.broadcast_to_room(
+                    &format!("area_{}", area_code),
+                    json
+                );
+            }
+
+            info!("Broadcasted table {} status: {}", data.table_number, data.status);
+        }
+    }
+
     pub fn get_room_size(&self, room: &str) -> usize {
         self.manager.get_room_size(&room.to_string())
     }
@@ -56,6 +56,21 @@ pub struct StatusUpdate {
     pub cashier: Option<CashierData>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TableStatusData {
+    pub table_id: String,
+    pub table_number: String,
+    pub area_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub area_code: Option<String>,
+    pub status: String,
+    pub is_available: bool,
+    pub updated_by: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    pub timestamp: DateTime<Utc>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CashierData {
     pub id: String,
@@ -91,6 +106,9 @@ pub enum WebSocketMessage {
     
     #[serde(rename = "order_confirmed")]
     OrderConfirmed(StatusUpdate),
+
+    #[serde(rename = "table_status_update")]
+    TableStatusUpdate(TableStatusData),
     
     #[serde(rename = "device_registered")]
     DeviceRegistered { device_id: String, success: bool },
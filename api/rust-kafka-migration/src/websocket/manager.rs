@@ -47,7 +47,7 @@ impl ConnectionManager {
     pub fn join_room(&self, client_id: ClientId, room: RoomName) {
         self.rooms
             .entry(room.clone())
-            .or_insert_with(Vec::new)
+            .or_default()
             .push(client_id.clone());
         
         info!("Client {} joined room: {}", client_id, room);